@@ -0,0 +1,278 @@
+use std::collections::HashMap;
+
+use bevy_math::Vec3;
+
+use crate::AssignmentSolver;
+
+// An agent that can be allocated to a target
+#[derive(Clone, Debug)]
+pub struct AllocationAgent {
+    pub position: Vec3,
+    pub max_speed: f32,
+}
+
+// A target agents can be allocated to, e.g. an attack target or a docking port
+#[derive(Clone, Debug)]
+pub struct AllocationTarget {
+    pub position: Vec3,
+    pub velocity: Vec3,
+    // The maximum number of agents the target can take
+    pub capacity: usize,
+}
+
+// The cost of sending an agent to a target
+// Returning a non-finite cost means the agent can't reach the target.
+pub trait AllocationCost {
+    fn get_cost(&self, agent: &AllocationAgent, target: &AllocationTarget) -> f32;
+}
+
+impl<F> AllocationCost for F
+where
+    F: Fn(&AllocationAgent, &AllocationTarget) -> f32,
+{
+    fn get_cost(&self, agent: &AllocationAgent, target: &AllocationTarget) -> f32 {
+        self(agent, target)
+    }
+}
+
+// The distance between the agent and the current position of the target
+pub struct DistanceCost;
+
+impl AllocationCost for DistanceCost {
+    fn get_cost(&self, agent: &AllocationAgent, target: &AllocationTarget) -> f32 {
+        agent.position.distance(target.position)
+    }
+}
+
+// The time it takes the agent to intercept the moving target at its maximum speed
+// Targets faster than the agent that move away from it can't be intercepted.
+pub struct TimeToInterceptCost;
+
+impl AllocationCost for TimeToInterceptCost {
+    fn get_cost(&self, agent: &AllocationAgent, target: &AllocationTarget) -> f32 {
+        time_to_intercept(
+            target.position - agent.position,
+            target.velocity,
+            agent.max_speed,
+        )
+        .unwrap_or(f32::INFINITY)
+    }
+}
+
+// The result of an allocation
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Allocation {
+    // Agent indexes mapped to the indexes of the targets they were allocated to
+    pub targets: HashMap<usize, usize>,
+    // Agents that weren't allocated because all targets are full or can't be reached
+    pub unallocated_agents: Vec<usize>,
+    // The sum of the costs of all allocated agents
+    pub total_cost: f32,
+}
+
+// Allocates agents to targets so the total cost is minimal.
+// Every target is split into as many slots as its capacity, so the problem can be solved
+// by the same assignment solvers that are used for formation slots.
+//
+// agents: The agents that should be allocated
+// targets: The targets the agents can be allocated to
+// cost: The cost of sending an agent to a target
+// solver: The algorithm used to solve the assignment
+pub fn allocate(
+    agents: &[AllocationAgent],
+    targets: &[AllocationTarget],
+    cost: &impl AllocationCost,
+    solver: AssignmentSolver,
+) -> Allocation {
+    let slots = targets
+        .iter()
+        .enumerate()
+        .flat_map(|(index, target)| std::iter::repeat_n(index, target.capacity))
+        .collect::<Vec<_>>();
+
+    let costs = agents
+        .iter()
+        .map(|agent| {
+            targets
+                .iter()
+                .map(|target| cost.get_cost(agent, target))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    // Unreachable targets get a cost higher than any allocation of reachable ones
+    let unreachable_cost = 1.0
+        + costs
+            .iter()
+            .flatten()
+            .filter(|cost| cost.is_finite())
+            .map(|cost| cost.abs())
+            .sum::<f32>()
+            * 2.0;
+
+    let matrix = costs
+        .iter()
+        .map(|row| {
+            slots
+                .iter()
+                .map(|&target| {
+                    if row[target].is_finite() {
+                        row[target]
+                    } else {
+                        unreachable_cost
+                    }
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    let refs = matrix.iter().map(|e| e.as_slice()).collect::<Vec<&[f32]>>();
+    let assignment = solver.assign(&refs);
+
+    let mut allocation = Allocation::default();
+
+    for (agent, agent_costs) in costs.iter().enumerate() {
+        let target = assignment
+            .pairs
+            .get(&agent)
+            .map(|&slot| slots[slot])
+            .filter(|&target| agent_costs[target].is_finite());
+
+        match target {
+            Some(target) => {
+                allocation.targets.insert(agent, target);
+                allocation.total_cost += agent_costs[target];
+            }
+            None => allocation.unallocated_agents.push(agent),
+        }
+    }
+
+    allocation
+}
+
+// Returns the earliest time at which an agent moving at the given speed can meet a target
+// at the relative position moving with the given velocity
+fn time_to_intercept(relative_position: Vec3, target_velocity: Vec3, speed: f32) -> Option<f32> {
+    // |p + v * t| = s * t  =>  (v.v - s^2) t^2 + 2 (p.v) t + p.p = 0
+    let a = target_velocity.length_squared() - speed * speed;
+    let b = 2.0 * relative_position.dot(target_velocity);
+    let c = relative_position.length_squared();
+
+    if c < f32::EPSILON {
+        return Some(0.0);
+    }
+
+    if a.abs() < f32::EPSILON {
+        let t = -c / b;
+        return (t > 0.0).then_some(t);
+    }
+
+    let discriminant = b * b - 4.0 * a * c;
+
+    if discriminant < 0.0 {
+        return None;
+    }
+
+    let sqrt_discriminant = discriminant.sqrt();
+    let t1 = (-b - sqrt_discriminant) / (2.0 * a);
+    let t2 = (-b + sqrt_discriminant) / (2.0 * a);
+
+    [t1, t2]
+        .into_iter()
+        .filter(|t| *t > 0.0)
+        .min_by(|a, b| a.total_cmp(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allocate_with_capacity() {
+        let agents = [
+            AllocationAgent {
+                position: Vec3::new(0.0, 0.0, 0.0),
+                max_speed: 1.0,
+            },
+            AllocationAgent {
+                position: Vec3::new(1.0, 0.0, 0.0),
+                max_speed: 1.0,
+            },
+            AllocationAgent {
+                position: Vec3::new(100.0, 0.0, 0.0),
+                max_speed: 1.0,
+            },
+        ];
+
+        let targets = [
+            AllocationTarget {
+                position: Vec3::new(0.0, 10.0, 0.0),
+                velocity: Vec3::ZERO,
+                capacity: 1,
+            },
+            AllocationTarget {
+                position: Vec3::new(100.0, 10.0, 0.0),
+                velocity: Vec3::ZERO,
+                capacity: 2,
+            },
+        ];
+
+        let allocation = allocate(
+            &agents,
+            &targets,
+            &DistanceCost,
+            AssignmentSolver::default(),
+        );
+
+        assert_eq!(allocation.targets[&2], 1);
+        assert_eq!(allocation.targets.len(), 3);
+        assert!(allocation.unallocated_agents.is_empty());
+        assert_eq!(
+            allocation
+                .targets
+                .values()
+                .filter(|target| **target == 1)
+                .count(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_time_to_intercept() {
+        let agents = [AllocationAgent {
+            position: Vec3::ZERO,
+            max_speed: 2.0,
+        }];
+
+        let targets = [
+            AllocationTarget {
+                position: Vec3::new(10.0, 0.0, 0.0),
+                velocity: Vec3::new(1.0, 0.0, 0.0),
+                capacity: 1,
+            },
+            AllocationTarget {
+                position: Vec3::new(5.0, 0.0, 0.0),
+                velocity: Vec3::new(5.0, 0.0, 0.0),
+                capacity: 1,
+            },
+        ];
+
+        approx::assert_relative_eq!(
+            TimeToInterceptCost.get_cost(&agents[0], &targets[0]),
+            10.0,
+            epsilon = 1e-4
+        );
+        assert!(!TimeToInterceptCost
+            .get_cost(&agents[0], &targets[1])
+            .is_finite());
+
+        let allocation = allocate(
+            &agents,
+            &targets[1..],
+            &TimeToInterceptCost,
+            AssignmentSolver::default(),
+        );
+
+        assert_eq!(allocation.unallocated_agents, vec![0]);
+    }
+}
@@ -0,0 +1,504 @@
+use std::collections::{HashMap, HashSet};
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+use crate::{
+    greedy_assignment::greedy_assignment, hungarian::hungarian, jonker_volgenant::jonker_volgenant,
+    CoordinationError,
+};
+
+// The result of an assignment between the rows and columns of a cost matrix
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Assignment {
+    // Row indexes mapped to the column indexes they were assigned to
+    pub pairs: HashMap<usize, usize>,
+    // Rows left without a column, only when there are more rows than columns
+    pub unassigned_rows: Vec<usize>,
+    // Columns left without a row, only when there are more columns than rows
+    pub unassigned_columns: Vec<usize>,
+    // The sum of the costs of all assigned pairs
+    pub total_cost: f32,
+}
+
+impl Assignment {
+    // Returns the mean cost of the assigned pairs, zero if nothing was assigned
+    pub fn mean_cost(&self) -> f32 {
+        if self.pairs.is_empty() {
+            0.0
+        } else {
+            self.total_cost / self.pairs.len() as f32
+        }
+    }
+}
+
+// Restricts which columns a row may be assigned to and adjusts the costs of individual pairs.
+// In a formation the rows are agents and the columns are slots, which allows pinning
+// specific agents to specific roles, e.g. the leader to slot 0.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AssignmentConstraints {
+    // Rows mapped to the only columns they may be assigned to, rows without an entry may take any column
+    pub allowed_columns: HashMap<usize, HashSet<usize>>,
+    // Costs added to individual (row, column) pairs, negative values make a pair more attractive
+    pub cost_modifiers: HashMap<(usize, usize), f32>,
+}
+
+impl AssignmentConstraints {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Allows the row to be assigned only to the given columns
+    pub fn allow(mut self, row: usize, columns: impl IntoIterator<Item = usize>) -> Self {
+        self.allowed_columns.entry(row).or_default().extend(columns);
+        self
+    }
+
+    // Forces the row to be assigned to exactly the given column
+    pub fn pin(mut self, row: usize, column: usize) -> Self {
+        self.allowed_columns.insert(row, HashSet::from([column]));
+        self
+    }
+
+    // Adds the modifier to the cost of assigning the row to the column
+    pub fn modify_cost(mut self, row: usize, column: usize, modifier: f32) -> Self {
+        *self.cost_modifiers.entry((row, column)).or_default() += modifier;
+        self
+    }
+
+    pub fn is_allowed(&self, row: usize, column: usize) -> bool {
+        self.allowed_columns
+            .get(&row)
+            .is_none_or(|columns| columns.contains(&column))
+    }
+
+    // Applies the constraints to the cost matrix
+    // Forbidden pairs get a cost higher than any assignment made only of allowed pairs,
+    // so the solver only picks them when the constraints can't be satisfied.
+    // Set the costs of the forbidden pairs to f32::INFINITY instead and use try_assign
+    // to get an error in that case.
+    pub fn apply(&self, cost: &mut [Vec<f32>]) {
+        for (&(row, column), modifier) in &self.cost_modifiers {
+            if let Some(value) = cost.get_mut(row).and_then(|row| row.get_mut(column)) {
+                *value += modifier;
+            }
+        }
+
+        let forbidden_cost = 1.0
+            + cost
+                .iter()
+                .map(|row| row.iter().fold(0.0_f32, |max, value| max.max(value.abs())))
+                .sum::<f32>()
+                * 2.0;
+
+        for (row, values) in cost.iter_mut().enumerate() {
+            for (column, value) in values.iter_mut().enumerate() {
+                if !self.is_allowed(row, column) {
+                    *value = forbidden_cost;
+                }
+            }
+        }
+    }
+}
+
+// The costs of assigning rows to columns that the solvers read from
+// Implemented by nested vectors and slices, CostMatrix and CostFn.
+pub trait AssignmentCost {
+    fn rows(&self) -> usize;
+    fn columns(&self) -> usize;
+    fn cost(&self, row: usize, column: usize) -> f32;
+}
+
+impl AssignmentCost for [&[f32]] {
+    fn rows(&self) -> usize {
+        self.len()
+    }
+
+    fn columns(&self) -> usize {
+        self.first().map_or(0, |row| row.len())
+    }
+
+    fn cost(&self, row: usize, column: usize) -> f32 {
+        self[row][column]
+    }
+}
+
+impl AssignmentCost for [Vec<f32>] {
+    fn rows(&self) -> usize {
+        self.len()
+    }
+
+    fn columns(&self) -> usize {
+        self.first().map_or(0, |row| row.len())
+    }
+
+    fn cost(&self, row: usize, column: usize) -> f32 {
+        self[row][column]
+    }
+}
+
+impl AssignmentCost for Vec<&[f32]> {
+    fn rows(&self) -> usize {
+        self.as_slice().rows()
+    }
+
+    fn columns(&self) -> usize {
+        self.as_slice().columns()
+    }
+
+    fn cost(&self, row: usize, column: usize) -> f32 {
+        self[row][column]
+    }
+}
+
+impl AssignmentCost for Vec<Vec<f32>> {
+    fn rows(&self) -> usize {
+        self.as_slice().rows()
+    }
+
+    fn columns(&self) -> usize {
+        self.as_slice().columns()
+    }
+
+    fn cost(&self, row: usize, column: usize) -> f32 {
+        self[row][column]
+    }
+}
+
+// A cost matrix stored in a single buffer, row by row
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CostMatrix {
+    rows: usize,
+    columns: usize,
+    values: Vec<f32>,
+}
+
+impl CostMatrix {
+    // Evaluates the cost of every pair, in parallel with the rayon feature
+    pub fn from_fn(rows: usize, columns: usize, cost: impl Fn(usize, usize) -> f32 + Sync) -> Self {
+        let cost_at = |index: usize| cost(index / columns, index % columns);
+
+        #[cfg(feature = "rayon")]
+        let values = (0..rows * columns)
+            .into_par_iter()
+            .map(cost_at)
+            .collect::<Vec<_>>();
+
+        #[cfg(not(feature = "rayon"))]
+        let values = (0..rows * columns).map(cost_at).collect::<Vec<_>>();
+
+        Self {
+            rows,
+            columns,
+            values,
+        }
+    }
+}
+
+impl AssignmentCost for CostMatrix {
+    fn rows(&self) -> usize {
+        self.rows
+    }
+
+    fn columns(&self) -> usize {
+        self.columns
+    }
+
+    fn cost(&self, row: usize, column: usize) -> f32 {
+        self.values[row * self.columns + column]
+    }
+}
+
+// Costs computed on demand by a callback instead of being stored
+// The exact solvers read every cost many times, so an expensive callback should be
+// evaluated into a CostMatrix first, see AssignmentSolver::assign_with.
+pub struct CostFn<F> {
+    rows: usize,
+    columns: usize,
+    cost: F,
+}
+
+impl<F: Fn(usize, usize) -> f32> CostFn<F> {
+    pub fn new(rows: usize, columns: usize, cost: F) -> Self {
+        Self {
+            rows,
+            columns,
+            cost,
+        }
+    }
+}
+
+impl<F: Fn(usize, usize) -> f32> AssignmentCost for CostFn<F> {
+    fn rows(&self) -> usize {
+        self.rows
+    }
+
+    fn columns(&self) -> usize {
+        self.columns
+    }
+
+    fn cost(&self, row: usize, column: usize) -> f32 {
+        (self.cost)(row, column)
+    }
+}
+
+// Swaps the rows and the columns of the costs without copying them
+struct Transposed<'a, C: ?Sized>(&'a C);
+
+impl<C: AssignmentCost + ?Sized> AssignmentCost for Transposed<'_, C> {
+    fn rows(&self) -> usize {
+        self.0.columns()
+    }
+
+    fn columns(&self) -> usize {
+        self.0.rows()
+    }
+
+    fn cost(&self, row: usize, column: usize) -> f32 {
+        self.0.cost(column, row)
+    }
+}
+
+// The algorithm used to assign agents to formation slots
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AssignmentSolver {
+    // Exact, O(n^3), kept as the reference implementation
+    Hungarian,
+    // Exact, O(n^3), considerably faster than Hungarian for large formations
+    #[default]
+    JonkerVolgenant,
+    // Approximate, O(n^2 log n), for very large groups where an exact solution is too slow
+    Greedy,
+}
+
+impl AssignmentSolver {
+    // Assigns rows to columns of the cost matrix
+    // The number of rows must be less than or equal to the number of columns.
+    // Panics when the forbidden pairs leave some row without a column, see try_solve.
+    //
+    // cost: The cost matrix, cost[row][column]
+    // Returns: A vector of (row, column, cost) tuples
+    pub fn solve<C: AssignmentCost + ?Sized>(&self, cost: &C) -> Vec<(usize, usize, f32)> {
+        self.try_solve(cost)
+            .expect("The forbidden pairs leave some row without a column")
+    }
+
+    // Assigns rows to columns of the cost matrix, pairs with a cost of f32::INFINITY are forbidden
+    // The number of rows must be less than or equal to the number of columns.
+    //
+    // cost: The cost matrix, cost[row][column]
+    // Returns: A vector of (row, column, cost) tuples, or NoPerfectMatching when the forbidden
+    //          pairs leave some row without a column
+    pub fn try_solve<C: AssignmentCost + ?Sized>(
+        &self,
+        cost: &C,
+    ) -> Result<Vec<(usize, usize, f32)>, CoordinationError> {
+        match self {
+            AssignmentSolver::Hungarian => hungarian(cost),
+            AssignmentSolver::JonkerVolgenant => jonker_volgenant(cost),
+            AssignmentSolver::Greedy => greedy_assignment(cost),
+        }
+    }
+
+    // Assigns rows to columns of a cost matrix of any shape
+    // When there are more rows than columns the problem is solved on the transposed matrix,
+    // which is the same as padding the matrix with dummy columns of equal cost.
+    // Every row or column that doesn't fit is reported as unassigned.
+    // Panics when the forbidden pairs make a full assignment impossible, see try_assign.
+    //
+    // cost: The cost matrix, cost[row][column]
+    // Returns: The assignment together with its total cost
+    pub fn assign<C: AssignmentCost + ?Sized>(&self, cost: &C) -> Assignment {
+        self.try_assign(cost)
+            .expect("The forbidden pairs make a full assignment impossible")
+    }
+
+    // Same as assign, but pairs with a cost of f32::INFINITY are forbidden
+    //
+    // cost: The cost matrix, cost[row][column]
+    // Returns: The assignment together with its total cost, or NoPerfectMatching when the
+    //          forbidden pairs leave some row (or column if there are fewer columns) unassigned
+    pub fn try_assign<C: AssignmentCost + ?Sized>(
+        &self,
+        cost: &C,
+    ) -> Result<Assignment, CoordinationError> {
+        let rows = cost.rows();
+        let cols = cost.columns();
+
+        if rows == 0 || cols == 0 {
+            return Ok(Assignment {
+                unassigned_rows: (0..rows).collect(),
+                unassigned_columns: (0..cols).collect(),
+                ..Default::default()
+            });
+        }
+
+        let result = if rows <= cols {
+            self.try_solve(cost)?
+        } else {
+            self.try_solve(&Transposed(cost))?
+                .into_iter()
+                .map(|(col, row, cost)| (row, col, cost))
+                .collect()
+        };
+
+        let mut pairs = HashMap::new();
+        let mut total_cost = 0.0;
+
+        for (row, col, cost) in result {
+            pairs.insert(row, col);
+            total_cost += cost;
+        }
+
+        let mut is_col_assigned = vec![false; cols];
+        for col in pairs.values() {
+            is_col_assigned[*col] = true;
+        }
+
+        Ok(Assignment {
+            unassigned_rows: (0..rows).filter(|row| !pairs.contains_key(row)).collect(),
+            unassigned_columns: (0..cols).filter(|col| !is_col_assigned[*col]).collect(),
+            pairs,
+            total_cost,
+        })
+    }
+
+    // Same as assign, but the costs are computed by a callback
+    // The greedy solver reads every cost once, so it calls the callback directly. The exact
+    // solvers read the costs many times, so they are evaluated into a CostMatrix first,
+    // in parallel with the rayon feature.
+    //
+    // cost: The cost of assigning a row to a column
+    // Returns: The assignment together with its total cost
+    pub fn assign_with(
+        &self,
+        rows: usize,
+        columns: usize,
+        cost: impl Fn(usize, usize) -> f32 + Sync,
+    ) -> Assignment {
+        match self {
+            AssignmentSolver::Greedy => self.assign(&CostFn::new(rows, columns, cost)),
+            AssignmentSolver::Hungarian | AssignmentSolver::JonkerVolgenant => {
+                self.assign(&CostMatrix::from_fn(rows, columns, cost))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_constraints() {
+        let mut cost = vec![
+            vec![8.0, 5.0, 9.0],
+            vec![4.0, 2.0, 4.0],
+            vec![7.0, 3.0, 8.0],
+        ];
+
+        AssignmentConstraints::new()
+            .pin(2, 0)
+            .modify_cost(0, 1, -10.0)
+            .apply(&mut cost);
+
+        let result = AssignmentSolver::default().assign(&cost);
+
+        assert_eq!(result.pairs, HashMap::from([(0, 1), (1, 2), (2, 0)]));
+    }
+
+    #[test]
+    fn test_assign_more_rows_than_columns() {
+        let cost = vec![vec![8.0, 4.0], vec![5.0, 2.0], vec![9.0, 3.0]];
+
+        let result = AssignmentSolver::default().assign(&cost);
+
+        assert_eq!(result.pairs, HashMap::from([(1, 0), (2, 1)]));
+        assert_eq!(result.unassigned_rows, vec![0]);
+        assert!(result.unassigned_columns.is_empty());
+        assert_eq!(result.total_cost, 8.0);
+        assert_eq!(result.mean_cost(), 4.0);
+    }
+
+    #[test]
+    fn test_assign_more_columns_than_rows() {
+        let cost = vec![vec![8.0, 5.0, 9.0], vec![4.0, 2.0, 6.0]];
+
+        let result = AssignmentSolver::default().assign(&cost);
+
+        assert_eq!(result.pairs, HashMap::from([(0, 1), (1, 0)]));
+        assert!(result.unassigned_rows.is_empty());
+        assert_eq!(result.unassigned_columns, vec![2]);
+        assert_eq!(result.total_cost, 9.0);
+    }
+
+    #[test]
+    fn test_try_assign_forbidden_pairs() {
+        let cost = vec![
+            vec![1.0, f32::INFINITY],
+            vec![2.0, f32::INFINITY],
+            vec![3.0, 4.0],
+        ];
+
+        for solver in [
+            AssignmentSolver::Hungarian,
+            AssignmentSolver::JonkerVolgenant,
+            AssignmentSolver::Greedy,
+        ] {
+            let result = solver.try_assign(&cost).unwrap();
+
+            assert_eq!(result.pairs, HashMap::from([(0, 0), (2, 1)]));
+            assert_eq!(result.unassigned_rows, vec![1]);
+            assert_eq!(result.total_cost, 5.0);
+        }
+
+        let cost = vec![vec![1.0, f32::INFINITY], vec![2.0, f32::INFINITY]];
+
+        assert_eq!(
+            AssignmentSolver::default().try_assign(&cost),
+            Err(CoordinationError::NoPerfectMatching)
+        );
+    }
+
+    #[test]
+    fn test_cost_sources() {
+        let positions: [f32; 3] = [0.0, 3.0, 5.0];
+        let slots: [f32; 4] = [4.0, 1.0, 7.0, 2.0];
+        let distance = |row: usize, column: usize| (positions[row] - slots[column]).abs();
+
+        let nested = (0..positions.len())
+            .map(|row| {
+                (0..slots.len())
+                    .map(|column| distance(row, column))
+                    .collect()
+            })
+            .collect::<Vec<Vec<f32>>>();
+        let expected = AssignmentSolver::default().assign(&nested);
+
+        assert_eq!(expected.pairs, HashMap::from([(0, 1), (1, 3), (2, 0)]));
+        assert_eq!(expected.unassigned_columns, vec![2]);
+
+        let matrix = CostMatrix::from_fn(positions.len(), slots.len(), distance);
+        let transposed = CostMatrix::from_fn(slots.len(), positions.len(), |row, column| {
+            distance(column, row)
+        });
+
+        for solver in [
+            AssignmentSolver::Hungarian,
+            AssignmentSolver::JonkerVolgenant,
+        ] {
+            assert_eq!(solver.assign(&matrix), expected);
+            assert_eq!(
+                solver.assign_with(positions.len(), slots.len(), distance),
+                expected
+            );
+            assert_eq!(solver.assign(&transposed).pairs.len(), 3);
+            assert_eq!(solver.assign(&transposed).total_cost, expected.total_cost);
+        }
+
+        assert_eq!(
+            AssignmentSolver::Greedy.assign(&CostFn::new(positions.len(), slots.len(), distance)),
+            AssignmentSolver::Greedy.assign(&nested)
+        );
+    }
+}
@@ -0,0 +1,170 @@
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    sync::{PoisonError, RwLock},
+};
+
+use bevy_math::{Quat, Vec3};
+use geometry::Aabb;
+use orca::Agent3D;
+
+use crate::{Formation, FormationTemplate, PriorityContext};
+
+// Wraps a template and remembers the formations and bounding boxes it created for each number
+// of agents, e.g. for templates combined from other templates or resampled custom formations.
+// The template is asked only once per number of agents, so it has to create the same formation
+// for the same number of agents. The cache is shared by parallel evaluations and is never
+// shrunk, call clear when the wrapped template would create different formations.
+pub struct CachedFormation<T> {
+    template: T,
+    formations: RwLock<HashMap<usize, Formation>>,
+    aabbs: RwLock<HashMap<usize, Aabb>>,
+}
+
+impl<T: FormationTemplate> CachedFormation<T> {
+    pub fn new(template: T) -> Self {
+        Self {
+            template,
+            formations: RwLock::default(),
+            aabbs: RwLock::default(),
+        }
+    }
+
+    pub fn template(&self) -> &T {
+        &self.template
+    }
+
+    pub fn into_inner(self) -> T {
+        self.template
+    }
+
+    // Forgets all cached formations and bounding boxes
+    pub fn clear(&self) {
+        self.formations
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+        self.aabbs
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+    }
+}
+
+// A panic while holding the lock can't leave the map half updated, so poisoning is ignored
+fn get_or_insert_with<K: Eq + Hash, V: Clone>(
+    cache: &RwLock<HashMap<K, V>>,
+    key: K,
+    create: impl FnOnce() -> V,
+) -> V {
+    if let Some(value) = cache
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get(&key)
+    {
+        return value.clone();
+    }
+
+    // Created outside of the lock, parallel evaluations may create the same value twice
+    // but they don't wait for each other
+    let value = create();
+
+    cache
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .entry(key)
+        .or_insert(value)
+        .clone()
+}
+
+impl<T: FormationTemplate> FormationTemplate for CachedFormation<T> {
+    fn create_formation(&self, n_agents: usize) -> Formation {
+        get_or_insert_with(&self.formations, n_agents, || {
+            self.template.create_formation(n_agents)
+        })
+    }
+
+    fn get_priority(&self) -> f32 {
+        self.template.get_priority()
+    }
+
+    fn get_priority_in_context(&self, context: &PriorityContext) -> f32 {
+        self.template.get_priority_in_context(context)
+    }
+
+    fn get_aabb(&self, n_agents: usize) -> Aabb {
+        get_or_insert_with(&self.aabbs, n_agents, || self.template.get_aabb(n_agents))
+    }
+
+    // The radii and the obstacles change too often to be cached, the wrapped template is asked
+    // every time
+    fn create_formation_with_radii(&self, radii: &[f32]) -> Formation {
+        self.template.create_formation_with_radii(radii)
+    }
+
+    fn create_formation_near_obstacles(
+        &self,
+        n_agents: usize,
+        position: Vec3,
+        rotation: Quat,
+        obstacles: &[Agent3D],
+        agent_radius: f32,
+    ) -> Formation {
+        self.template.create_formation_near_obstacles(
+            n_agents,
+            position,
+            rotation,
+            obstacles,
+            agent_radius,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    struct CountingFormation {
+        created: AtomicUsize,
+    }
+
+    impl FormationTemplate for CountingFormation {
+        fn create_formation(&self, n_agents: usize) -> Formation {
+            self.created.fetch_add(1, Ordering::Relaxed);
+
+            Formation::new((0..n_agents).map(|i| Vec3::X * i as f32).collect())
+        }
+
+        fn get_priority(&self) -> f32 {
+            1.0
+        }
+
+        fn get_aabb(&self, n_agents: usize) -> Aabb {
+            self.create_formation(n_agents).get_bounds(0.5)
+        }
+    }
+
+    #[test]
+    fn test_formations_are_created_once_per_agent_count() {
+        let template = CachedFormation::new(CountingFormation {
+            created: AtomicUsize::new(0),
+        });
+
+        let formation = template.create_formation(3);
+        template.create_formation(3);
+        template.get_aabb(3);
+        template.get_aabb(3);
+
+        assert_eq!(formation.get_positions().len(), 3);
+        assert_eq!(template.template().created.load(Ordering::Relaxed), 2);
+
+        template.create_formation(4);
+        assert_eq!(template.template().created.load(Ordering::Relaxed), 3);
+
+        template.clear();
+        template.create_formation(3);
+        assert_eq!(template.template().created.load(Ordering::Relaxed), 4);
+    }
+}
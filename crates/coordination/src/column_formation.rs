@@ -0,0 +1,114 @@
+use bevy_math::Vec3;
+use geometry::Aabb;
+
+use crate::{Formation, FormationTemplate};
+
+// Rows of agents_per_row agents side by side, stacked one behind another
+// along the travel axis (z).
+pub struct ColumnFormation {
+    priority: f32,
+    spacing: f32,
+    agent_radius: f32,
+    agents_per_row: usize,
+}
+
+impl ColumnFormation {
+    pub fn new(agent_radius: f32, spacing: f32, agents_per_row: usize, priority: f32) -> Self {
+        Self {
+            priority,
+            spacing: spacing.max(0.0),
+            agent_radius,
+            agents_per_row: agents_per_row.max(1),
+        }
+    }
+
+    fn get_separation(&self) -> f32 {
+        self.spacing + 2.0 * self.agent_radius
+    }
+}
+
+impl FormationTemplate for ColumnFormation {
+    fn get_priority(&self) -> f32 {
+        self.priority
+    }
+
+    fn create_formation(&self, n_agents: usize) -> Formation {
+        if n_agents == 0 {
+            return Formation::new(Vec::new());
+        }
+
+        let separation = self.get_separation();
+        let rows = n_agents.div_ceil(self.agents_per_row);
+        let center_z = (rows - 1) as f32 / 2.0;
+
+        let positions = (0..n_agents)
+            .map(|i| {
+                let row = i / self.agents_per_row;
+                let agents_in_row = self
+                    .agents_per_row
+                    .min(n_agents - row * self.agents_per_row);
+                let column = i % self.agents_per_row;
+
+                // Partially filled rows are centered as well
+                let x = (column as f32 - (agents_in_row - 1) as f32 / 2.0) * separation;
+                let z = (center_z - row as f32) * separation;
+
+                Vec3::new(x, 0.0, z)
+            })
+            .collect();
+
+        Formation::new(positions)
+    }
+
+    fn get_aabb(&self, n_agents: usize) -> Aabb {
+        if n_agents == 0 {
+            return Aabb::new(Vec3::ZERO, Vec3::ZERO);
+        }
+
+        let separation = self.get_separation();
+        let rows = n_agents.div_ceil(self.agents_per_row);
+        let widest_row = self.agents_per_row.min(n_agents);
+
+        let half_sizes = Vec3::new(
+            (widest_row - 1) as f32 * separation / 2.0,
+            0.0,
+            (rows - 1) as f32 * separation / 2.0,
+        ) + Vec3::splat(self.agent_radius);
+
+        Aabb::new(Vec3::ZERO, half_sizes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_partial_row_is_centered() {
+        let positions = ColumnFormation::new(0.5, 1.0, 3, 1.0)
+            .create_formation(4)
+            .get_positions()
+            .to_vec();
+
+        assert_eq!(positions[1].x, 0.0);
+        assert_eq!(positions[3].x, 0.0);
+        assert!((positions[0].z - positions[3].z - 2.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_rows_are_one_separation_apart() {
+        let positions = ColumnFormation::new(0.5, 1.0, 3, 1.0)
+            .create_formation(9)
+            .get_positions()
+            .to_vec();
+
+        for row in positions.chunks(3) {
+            assert!((row[1] - row[0]).distance(Vec3::X * 2.0) < 1e-5);
+            assert!((row[2] - row[1]).distance(Vec3::X * 2.0) < 1e-5);
+        }
+
+        for (front, back) in positions.iter().zip(&positions[3..]) {
+            assert!((*front - *back).distance(Vec3::Z * 2.0) < 1e-5);
+        }
+    }
+}
@@ -0,0 +1,229 @@
+use bevy_math::Vec3;
+use geometry::Aabb;
+
+use crate::{Formation, FormationTemplate};
+
+// Describes how the offsets of a custom formation are adapted when the number
+// of agents differs from the number of offsets.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum OffsetResampling {
+    // Evenly distributed offsets are picked when there are fewer agents than offsets.
+    // When there are more agents, copies of the whole formation are appended behind it.
+    #[default]
+    Subsample,
+    // The offsets are treated as an ordered polyline and the agents are distributed
+    // evenly along its length.
+    Interpolate,
+}
+
+// A formation authored as a list of offsets, e.g. in an editor.
+// The offsets are always re-centered so the center of mass of the formation lies in the origin.
+pub struct CustomFormation {
+    priority: f32,
+    agent_radius: f32,
+    offsets: Vec<Vec3>,
+    resampling: OffsetResampling,
+}
+
+impl CustomFormation {
+    // Without any offsets the formation is a single slot in the origin
+    pub fn from_offsets(offsets: Vec<Vec3>, agent_radius: f32, priority: f32) -> Self {
+        let offsets = if offsets.is_empty() {
+            vec![Vec3::ZERO]
+        } else {
+            offsets
+        };
+
+        Self {
+            priority,
+            agent_radius,
+            offsets: center_of_mass_normalized(offsets),
+            resampling: OffsetResampling::default(),
+        }
+    }
+
+    #[must_use]
+    pub fn with_resampling(mut self, resampling: OffsetResampling) -> Self {
+        self.resampling = resampling;
+        self
+    }
+
+    pub fn get_offsets(&self) -> &[Vec3] {
+        &self.offsets
+    }
+
+    fn subsample(&self, n_agents: usize) -> Vec<Vec3> {
+        let n_offsets = self.offsets.len();
+
+        if n_agents <= n_offsets {
+            return (0..n_agents)
+                .map(|i| self.offsets[i * n_offsets / n_agents])
+                .collect();
+        }
+
+        // Copies of the formation are placed behind each other along the z axis
+        let (min_z, max_z) = self
+            .offsets
+            .iter()
+            .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), offset| {
+                (min.min(offset.z), max.max(offset.z))
+            });
+
+        let copy_separation = max_z - min_z + 2.0 * self.agent_radius;
+
+        (0..n_agents)
+            .map(|i| {
+                let copy = (i / n_offsets) as f32;
+                self.offsets[i % n_offsets] - Vec3::Z * copy * copy_separation
+            })
+            .collect()
+    }
+
+    fn interpolate(&self, n_agents: usize) -> Vec<Vec3> {
+        if n_agents == 1 || self.offsets.len() == 1 {
+            return vec![self.offsets[0]; n_agents];
+        }
+
+        let lengths = self
+            .offsets
+            .windows(2)
+            .map(|w| w[0].distance(w[1]))
+            .collect::<Vec<_>>();
+
+        let total_length = lengths.iter().sum::<f32>();
+        let step = total_length / (n_agents - 1) as f32;
+
+        let mut positions = Vec::with_capacity(n_agents);
+        let mut segment = 0;
+        let mut segment_start = 0.0;
+
+        for i in 0..n_agents {
+            let distance = step * i as f32;
+
+            while segment < lengths.len() - 1 && distance > segment_start + lengths[segment] {
+                segment_start += lengths[segment];
+                segment += 1;
+            }
+
+            let t = if lengths[segment] > f32::EPSILON {
+                ((distance - segment_start) / lengths[segment]).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+
+            positions.push(self.offsets[segment].lerp(self.offsets[segment + 1], t));
+        }
+
+        positions
+    }
+}
+
+fn center_of_mass_normalized(positions: Vec<Vec3>) -> Vec<Vec3> {
+    let center_of_mass = positions.iter().sum::<Vec3>() / positions.len() as f32;
+
+    positions
+        .into_iter()
+        .map(|position| position - center_of_mass)
+        .collect()
+}
+
+impl FormationTemplate for CustomFormation {
+    fn get_priority(&self) -> f32 {
+        self.priority
+    }
+
+    fn create_formation(&self, n_agents: usize) -> Formation {
+        if n_agents == 0 {
+            return Formation::new(Vec::new());
+        }
+
+        let positions = match self.resampling {
+            OffsetResampling::Subsample => self.subsample(n_agents),
+            OffsetResampling::Interpolate => self.interpolate(n_agents),
+        };
+
+        Formation::new(center_of_mass_normalized(positions))
+    }
+
+    fn get_aabb(&self, n_agents: usize) -> Aabb {
+        if n_agents == 0 {
+            return Aabb::new(Vec3::ZERO, Vec3::ZERO);
+        }
+
+        self.create_formation(n_agents)
+            .get_bounds(self.agent_radius)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn offsets() -> Vec<Vec3> {
+        vec![
+            Vec3::new(0.0, 0.0, 4.0),
+            Vec3::new(-3.0, 0.0, 0.0),
+            Vec3::new(3.0, 0.0, 0.0),
+            Vec3::new(0.0, 2.0, -1.0),
+        ]
+    }
+
+    #[test]
+    fn test_offsets_are_centered() {
+        let formation = CustomFormation::from_offsets(offsets(), 0.5, 1.0);
+
+        assert!(formation.get_offsets().iter().sum::<Vec3>().length() < 1e-5);
+
+        for n_agents in 1..20 {
+            let positions = formation.create_formation(n_agents);
+            let positions = positions.get_positions();
+
+            assert!(positions.iter().sum::<Vec3>().length() / (n_agents as f32) < 1e-4);
+        }
+    }
+
+    #[test]
+    fn test_without_offsets() {
+        let formation = CustomFormation::from_offsets(Vec::new(), 0.5, 1.0);
+
+        assert_eq!(formation.get_offsets(), [Vec3::ZERO]);
+        assert_eq!(formation.create_formation(1).get_positions(), [Vec3::ZERO]);
+        assert_eq!(formation.create_formation(3).get_positions().len(), 3);
+    }
+
+    #[test]
+    fn test_subsampled_copies_are_placed_behind() {
+        let positions = CustomFormation::from_offsets(offsets(), 0.5, 1.0)
+            .create_formation(8)
+            .get_positions()
+            .to_vec();
+
+        // The offsets are 5 deep, the copy is one agent diameter behind them
+        for (front, back) in positions.iter().zip(&positions[4..]) {
+            assert!((*front - *back).distance(Vec3::Z * 6.0) < 1e-4);
+        }
+    }
+
+    #[test]
+    fn test_interpolated_agents_are_evenly_spaced() {
+        let offsets = vec![
+            Vec3::ZERO,
+            Vec3::new(4.0, 0.0, 0.0),
+            Vec3::new(4.0, 0.0, 4.0),
+        ];
+
+        let positions = CustomFormation::from_offsets(offsets, 0.5, 1.0)
+            .with_resampling(OffsetResampling::Interpolate)
+            .create_formation(5)
+            .get_positions()
+            .to_vec();
+
+        // The middle agent ends up in the corner of the polyline
+        assert!((positions[2] - positions[0]).distance(Vec3::X * 4.0) < 1e-4);
+
+        for pair in positions.windows(2) {
+            assert!((pair[0].distance(pair[1]) - 2.0).abs() < 1e-4);
+        }
+    }
+}
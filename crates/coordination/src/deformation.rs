@@ -0,0 +1,96 @@
+use bevy_math::Vec3;
+
+use crate::{best_matching, AssignmentSolver, Formation};
+
+// How far the agents of a formation are from the slots of a template
+#[derive(Clone, Debug, PartialEq)]
+pub struct DeformationReport {
+    // The distance of each agent from its slot, agents without a slot have no error
+    pub agent_errors: Vec<Option<f32>>,
+    // The root mean square of the errors of the agents with a slot
+    pub rms: f32,
+    // The largest error of an agent with a slot
+    pub max: f32,
+}
+
+// Measures how much the current positions of the agents deviate from the template formation.
+// The template is moved so its center of mass matches the center of mass of the agents, it isn't
+// rotated, so it's expected to be in the same orientation as the agents. Each agent is then
+// compared to the slot it would be assigned to.
+//
+// current_positions: The positions of the agents
+// template: The formation the agents should be in
+// Returns: The error of each agent together with their RMS and maximum
+pub fn formation_deviation(current_positions: &[Vec3], template: &Formation) -> DeformationReport {
+    let template_positions = template.get_positions();
+
+    if current_positions.is_empty() || template_positions.is_empty() {
+        return DeformationReport {
+            agent_errors: vec![None; current_positions.len()],
+            rms: 0.0,
+            max: 0.0,
+        };
+    }
+
+    let current_center = current_positions.iter().sum::<Vec3>() / current_positions.len() as f32;
+    let template_center = template_positions.iter().sum::<Vec3>() / template_positions.len() as f32;
+
+    let slots = template_positions
+        .iter()
+        .map(|position| *position - template_center + current_center)
+        .collect::<Vec<_>>();
+
+    let assignment = best_matching(current_positions, &slots, AssignmentSolver::default());
+
+    let agent_errors = (0..current_positions.len())
+        .map(|i| {
+            assignment
+                .pairs
+                .get(&i)
+                .map(|&j| current_positions[i].distance(slots[j]))
+        })
+        .collect::<Vec<_>>();
+
+    let rms = (assignment.total_cost / assignment.pairs.len() as f32).sqrt();
+    let max = agent_errors
+        .iter()
+        .flatten()
+        .fold(0.0_f32, |a, b| a.max(*b));
+
+    DeformationReport {
+        agent_errors,
+        rms,
+        max,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_formation_deviation() {
+        let template = Formation::new(vec![
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(10.0, 0.0, 0.0),
+            Vec3::new(20.0, 0.0, 0.0),
+        ]);
+
+        // The same formation, shifted and with the middle agent out of place
+        let current_positions = [
+            Vec3::new(100.0, 0.0, 0.0),
+            Vec3::new(110.0, 3.0, 0.0),
+            Vec3::new(120.0, 0.0, 0.0),
+        ];
+
+        let report = formation_deviation(&current_positions, &template);
+
+        assert_eq!(report.agent_errors.len(), 3);
+        approx::assert_relative_eq!(report.max, 2.0, epsilon = 1e-4);
+        approx::assert_relative_eq!(
+            report.rms,
+            ((1.0 + 4.0 + 1.0) / 3.0_f32).sqrt(),
+            epsilon = 1e-4
+        );
+    }
+}
@@ -0,0 +1,35 @@
+use std::fmt;
+
+// Errors of the fallible coordination APIs
+// They are caused by degenerate input, e.g. an empty group of agents, so the caller can skip
+// the evaluation for a frame instead of crashing.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CoordinationError {
+    // There are no agents to evaluate the formation of
+    EmptyFormation,
+    // The agent weights don't match the agents or are all zero
+    InvalidAgentWeights { agents: usize, weights: usize },
+    // None of the evaluated candidates could be selected
+    NoFormationFound,
+    // A matrix couldn't be decomposed or the matrix dimensions don't match
+    MatrixDecomposition,
+    // The forbidden pairs of an assignment leave some row without a column
+    NoPerfectMatching,
+}
+
+impl fmt::Display for CoordinationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::EmptyFormation => write!(f, "the formation has no agents"),
+            Self::InvalidAgentWeights { agents, weights } => write!(
+                f,
+                "expected {agents} non-negative agent weights with a positive sum, got {weights}"
+            ),
+            Self::NoFormationFound => write!(f, "no formation could be selected"),
+            Self::MatrixDecomposition => write!(f, "the matrix couldn't be decomposed"),
+            Self::NoPerfectMatching => write!(f, "no assignment avoids all forbidden pairs"),
+        }
+    }
+}
+
+impl std::error::Error for CoordinationError {}
@@ -1,9 +1,9 @@
 use std::{collections::HashMap, f32::consts::TAU};
 
-use bevy_math::Vec3;
+use bevy_math::{Mat3, Vec3};
 use geometry::Ray3D;
 
-use crate::hungarian::hungarian;
+use crate::{Assignment, AssignmentConstraints, AssignmentSolver};
 
 fn probability_density_function_of_formation(
     value: Vec3,
@@ -35,40 +35,263 @@ fn combine(values: &[&[Vec3]], coefficients: &[f32]) -> Vec<Vec3> {
 }
 
 pub fn best_matching_indexes(a: &[Vec3], b: &[Vec3]) -> HashMap<usize, usize> {
-    let matrix = a
+    best_matching_indexes_with_solver(a, b, AssignmentSolver::default())
+}
+
+pub fn best_matching_indexes_with_solver(
+    a: &[Vec3],
+    b: &[Vec3],
+    solver: AssignmentSolver,
+) -> HashMap<usize, usize> {
+    best_matching(a, b, solver).pairs
+}
+
+// Matches the positions in a to the positions in b minimizing the sum of squared distances.
+// The sets may differ in size, the positions that weren't matched are reported as unassigned.
+// The total and mean cost of the matching measure how coherent the formation is.
+pub fn best_matching(a: &[Vec3], b: &[Vec3], solver: AssignmentSolver) -> Assignment {
+    solver.assign_with(a.len(), b.len(), |i, j| a[i].distance_squared(b[j]))
+}
+
+// Same as best_matching, but agents may be restricted to specific slots and individual pairs
+// may be made more or less attractive, see AssignmentConstraints.
+//
+// Returns: None if the hard constraints can't be satisfied, otherwise the assignment
+// with its total cost measured without the cost modifiers
+pub fn constrained_matching(
+    a: &[Vec3],
+    b: &[Vec3],
+    constraints: &AssignmentConstraints,
+    solver: AssignmentSolver,
+) -> Option<Assignment> {
+    let distances = distance_cost_matrix(a, b);
+
+    let mut matrix = distances.clone();
+    constraints.apply(&mut matrix);
+
+    let mut assignment = solver.assign(&matrix);
+
+    if assignment
+        .pairs
         .iter()
+        .any(|(&i, &j)| !constraints.is_allowed(i, j))
+    {
+        return None;
+    }
+
+    assignment.total_cost = assignment
+        .pairs
+        .iter()
+        .map(|(&i, &j)| distances[i][j])
+        .sum();
+
+    Some(assignment)
+}
+
+// Same as best_matching_indexes, but keeps the assignment stable between frames.
+// Every pair that differs from the previous assignment is penalized by switching_penalty,
+// so an agent only changes its slot if it saves more than that.
+//
+// previous: The assignment returned in the previous frame, indexes of a mapped to indexes of b
+// switching_penalty: The cost added to every pair that wasn't part of the previous assignment
+pub fn sticky_matching_indexes(
+    a: &[Vec3],
+    b: &[Vec3],
+    previous: &HashMap<usize, usize>,
+    switching_penalty: f32,
+) -> HashMap<usize, usize> {
+    let mut matrix = distance_cost_matrix(a, b);
+
+    for (i, row) in matrix.iter_mut().enumerate() {
+        for (j, cost) in row.iter_mut().enumerate() {
+            if previous.get(&i) != Some(&j) {
+                *cost += switching_penalty;
+            }
+        }
+    }
+
+    AssignmentSolver::default().assign(&matrix).pairs
+}
+
+fn distance_cost_matrix(a: &[Vec3], b: &[Vec3]) -> Vec<Vec<f32>> {
+    a.iter()
         .map(|&a| {
             b.iter()
                 .map(|&b| a.distance_squared(b))
                 .collect::<Vec<f32>>()
         })
-        .collect::<Vec<Vec<f32>>>();
+        .collect::<Vec<Vec<f32>>>()
+}
 
-    let refs = matrix.iter().map(|e| e.as_slice()).collect::<Vec<&[f32]>>();
+// The state of the expectation maximization after a single iteration
+#[derive(Clone, Debug)]
+pub struct ExpectationMaximizationStep<'a> {
+    pub step: usize,
+    pub coefficients: &'a [f32],
+    pub std_deviation: f32,
+}
 
-    let result = hungarian(&refs);
+#[derive(Clone, Debug)]
+pub struct ExpectationMaximizationResult {
+    // The weight of each template in the fitted mixture, they sum up to 1
+    pub coefficients: Vec<f32>,
+    // The standard deviation of the values from the fitted mixture
+    pub std_deviation: f32,
+    // The covariance of the deviations of the values from the fitted mixture, its trace is the
+    // variance
+    pub covariance: Mat3,
+    // The number of iterations that were run
+    pub iterations: usize,
+    // False if the maximum number of steps was reached before the standard deviation settled
+    pub converged: bool,
+}
+
+// The smallest standard deviation a warm start begins with
+const MIN_WARM_START_STD_DEVIATION: f32 = 1e-3;
+
+// The state of a previous expectation maximization to continue from
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "reflect", derive(bevy_reflect::Reflect))]
+pub struct ExpectationMaximizationWarmStart {
+    pub coefficients: Vec<f32>,
+    pub std_deviation: f32,
+}
 
-    let mut map = HashMap::new();
-    for (i, j, _) in result {
-        map.insert(i, j);
+impl From<&ExpectationMaximizationResult> for ExpectationMaximizationWarmStart {
+    fn from(result: &ExpectationMaximizationResult) -> Self {
+        Self {
+            coefficients: result.coefficients.clone(),
+            std_deviation: result.std_deviation,
+        }
     }
+}
 
-    map
+impl ExpectationMaximizationResult {
+    // Returns the standard deviation of the values along the axis, e.g. the direction of travel
+    // to tell a formation stretched along its path from one scattered to the sides
+    #[must_use]
+    pub fn std_deviation_along(&self, axis: Vec3) -> f32 {
+        let axis = axis.normalize_or_zero();
+
+        axis.dot(self.covariance * axis).max(0.0).sqrt()
+    }
 }
 
 pub fn expectation_maximization(
     values: &[Vec3],
     formation_templates: &[&[Vec3]],
     max_steps: usize,
-) -> (Vec<f32>, f32) {
+) -> ExpectationMaximizationResult {
+    expectation_maximization_with_trace(values, formation_templates, max_steps, |_| {})
+}
+
+// Same as expectation_maximization, but calls trace after every iteration
+pub fn expectation_maximization_with_trace(
+    values: &[Vec3],
+    formation_templates: &[&[Vec3]],
+    max_steps: usize,
+    trace: impl FnMut(&ExpectationMaximizationStep),
+) -> ExpectationMaximizationResult {
+    weighted_expectation_maximization(
+        values,
+        &vec![1.0; values.len()],
+        formation_templates,
+        max_steps,
+        trace,
+    )
+}
+
+// Same as expectation_maximization_with_trace, but every value has a weight
+// Values with a higher weight contribute more to the fitted coefficients and to the standard
+// deviation, so the shape of the formation is dominated by its most important agents.
+//
+// weights: A non-negative weight for each value, at least one of them has to be positive
+pub fn weighted_expectation_maximization(
+    values: &[Vec3],
+    weights: &[f32],
+    formation_templates: &[&[Vec3]],
+    max_steps: usize,
+    trace: impl FnMut(&ExpectationMaximizationStep),
+) -> ExpectationMaximizationResult {
+    run_expectation_maximization(values, weights, formation_templates, None, max_steps, trace)
+}
+
+// Same as weighted_expectation_maximization, but starts from the result of a previous run
+// instead of uniform coefficients. When the formation changes slowly the previous frame is
+// already close to the fit, so a couple of steps refine it as well as a full run from scratch.
+// A warm start that doesn't match the templates, e.g. after a template was added, is ignored.
+pub fn warm_started_expectation_maximization(
+    values: &[Vec3],
+    weights: &[f32],
+    formation_templates: &[&[Vec3]],
+    warm_start: &ExpectationMaximizationWarmStart,
+    max_steps: usize,
+    trace: impl FnMut(&ExpectationMaximizationStep),
+) -> ExpectationMaximizationResult {
+    let is_valid = warm_start.coefficients.len() == formation_templates.len()
+        && warm_start
+            .coefficients
+            .iter()
+            .all(|c| c.is_finite() && *c >= 0.0)
+        && warm_start.coefficients.iter().sum::<f32>() > 0.0
+        && warm_start.std_deviation.is_finite();
+
+    run_expectation_maximization(
+        values,
+        weights,
+        formation_templates,
+        is_valid.then_some(warm_start),
+        max_steps,
+        trace,
+    )
+}
+
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "trace",
+        skip_all,
+        fields(
+            values = values.len(),
+            templates = formation_templates.len(),
+            warm_start = warm_start.is_some()
+        )
+    )
+)]
+fn run_expectation_maximization(
+    values: &[Vec3],
+    weights: &[f32],
+    formation_templates: &[&[Vec3]],
+    warm_start: Option<&ExpectationMaximizationWarmStart>,
+    max_steps: usize,
+    mut trace: impl FnMut(&ExpectationMaximizationStep),
+) -> ExpectationMaximizationResult {
+    assert_eq!(
+        values.len(),
+        weights.len(),
+        "Every value needs exactly one weight"
+    );
+    assert!(weights.iter().all(|w| *w >= 0.0));
+
     let n_templates = formation_templates.len();
     let n_values = values.len();
+    let total_weight = weights.iter().sum::<f32>();
+
+    assert!(total_weight > 0.0, "At least one weight has to be positive");
 
     // Initialization step
-    let mut coefficients = vec![1.0 / n_templates as f32; n_templates];
-    let mut std_deviation = 1.0_f32;
+    // A perfectly fitting previous frame has no deviation, which the densities can't work with
+    let (mut coefficients, mut std_deviation) = match warm_start {
+        Some(warm_start) => (
+            warm_start.coefficients.clone(),
+            warm_start.std_deviation.max(MIN_WARM_START_STD_DEVIATION),
+        ),
+        None => (vec![1.0 / n_templates as f32; n_templates], 1.0_f32),
+    };
 
     let mut steps = 0;
+    let mut converged = false;
     loop {
         // Calculate probabilities of each value belonging to each Gaussian
         let mut probabilities = Vec::new();
@@ -121,8 +344,8 @@ pub fn expectation_maximization(
                         line.parameter_at_point(values[i])
                     };
 
-                coefficient += ideal_parameter * probabilities[i * n_templates + k];
-                denominator += probabilities[i * n_templates + k];
+                coefficient += weights[i] * ideal_parameter * probabilities[i * n_templates + k];
+                denominator += weights[i] * probabilities[i * n_templates + k];
             }
 
             coefficients[k] = coefficient / denominator;
@@ -132,8 +355,15 @@ pub fn expectation_maximization(
         coefficients.iter_mut().for_each(|c| *c = c.abs());
 
         // Normalize coefficients
+        // All of them are zero when the values lie perpendicular to every template, no template
+        // fits better than the others then and they get the same weight
         let sum: f32 = coefficients.iter().sum();
-        coefficients.iter_mut().for_each(|c| *c /= sum);
+        if sum > f32::EPSILON {
+            coefficients.iter_mut().for_each(|c| *c /= sum);
+        } else {
+            let uniform = 1.0 / n_templates as f32;
+            coefficients.iter_mut().for_each(|c| *c = uniform);
+        }
 
         // Update standard deviation
         let combined_values = combine(formation_templates, &coefficients);
@@ -142,30 +372,62 @@ pub fn expectation_maximization(
         std_deviation = (combined_values
             .iter()
             .zip(values.iter())
-            .map(|(a, b)| a.distance_squared(*b))
+            .zip(weights.iter())
+            .map(|((a, b), w)| w * a.distance_squared(*b))
             .sum::<f32>()
-            / values.len() as f32)
+            / total_weight)
             .sqrt();
 
-        if std_deviation < f32::EPSILON {
-            break;
-        }
+        trace(&ExpectationMaximizationStep {
+            step: steps,
+            coefficients: &coefficients,
+            std_deviation,
+        });
 
-        if (std_deviation - previous_std_deviation).abs() < 10e-6 {
+        steps += 1;
+
+        if std_deviation < f32::EPSILON || (std_deviation - previous_std_deviation).abs() < 10e-6 {
+            converged = true;
             break;
         }
 
-        steps += 1;
-
         if steps >= max_steps {
             break;
         }
     }
 
-    (coefficients, std_deviation)
+    let covariance = combine(formation_templates, &coefficients)
+        .iter()
+        .zip(values.iter())
+        .zip(weights.iter())
+        .map(|((a, b), w)| {
+            let deviation = *b - *a;
+
+            Mat3::from_cols(
+                deviation * deviation.x,
+                deviation * deviation.y,
+                deviation * deviation.z,
+            ) * *w
+        })
+        .fold(Mat3::ZERO, |sum, covariance| sum + covariance)
+        * total_weight.recip();
+
+    ExpectationMaximizationResult {
+        coefficients,
+        std_deviation,
+        covariance,
+        iterations: steps,
+        converged,
+    }
 }
 
 #[cfg(test)]
+#[allow(
+    unused_imports,
+    dead_code,
+    clippy::needless_range_loop,
+    clippy::manual_range_contains
+)]
 mod tests {
     use rand::Rng;
 
@@ -295,7 +557,7 @@ mod tests {
                         .collect::<Vec<&[Vec3]>>(),
                     200,
                 )
-                .0
+                .coefficients
             })
             .collect::<Vec<Vec<f32>>>();
 
@@ -330,4 +592,151 @@ mod tests {
         assert!(third_result[2] > third_result[0]);
         assert!(third_result[2] > third_result[1]);
     }
+
+    #[test]
+    fn test_sticky_matching_indexes() {
+        let a = [Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0)];
+        let b = [Vec3::new(0.6, 0.0, 0.0), Vec3::new(0.4, 0.0, 0.0)];
+
+        let previous = HashMap::from([(0, 0), (1, 1)]);
+
+        assert_eq!(
+            best_matching_indexes(&a, &b),
+            HashMap::from([(0, 1), (1, 0)])
+        );
+        assert_eq!(sticky_matching_indexes(&a, &b, &previous, 1.0), previous);
+        assert_eq!(
+            sticky_matching_indexes(&a, &b, &previous, 0.0),
+            HashMap::from([(0, 1), (1, 0)])
+        );
+    }
+
+    #[test]
+    fn test_expectation_maximization_trace() {
+        let formation_templates = [
+            [Vec3::new(-10.0, 0.0, 0.0), Vec3::new(10.0, 0.0, 0.0)],
+            [Vec3::new(0.0, 0.0, -10.0), Vec3::new(0.0, 0.0, 10.0)],
+        ];
+        let templates = formation_templates
+            .iter()
+            .map(|e| e.as_slice())
+            .collect::<Vec<&[Vec3]>>();
+
+        let values = [Vec3::new(-10.0, 0.0, 0.0), Vec3::new(10.0, 0.0, 0.0)];
+
+        let mut traced_steps = Vec::new();
+        let result = expectation_maximization_with_trace(&values, &templates, 50, |step| {
+            traced_steps.push(step.step);
+        });
+
+        assert!(result.converged);
+        assert_eq!(result.iterations, traced_steps.len());
+        assert_eq!(traced_steps, (0..result.iterations).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_weighted_expectation_maximization() {
+        let formation_templates = [
+            [Vec3::new(-10.0, 0.0, 0.0), Vec3::new(10.0, 0.0, 0.0)],
+            [Vec3::new(0.0, 0.0, -10.0), Vec3::new(0.0, 0.0, 10.0)],
+        ];
+        let templates = formation_templates
+            .iter()
+            .map(|e| e.as_slice())
+            .collect::<Vec<&[Vec3]>>();
+
+        // The first agent is in the first template, the second one in the second template
+        let values = [Vec3::new(-10.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 10.0)];
+
+        let first_heavy =
+            weighted_expectation_maximization(&values, &[10.0, 1.0], &templates, 50, |_| {});
+        let second_heavy =
+            weighted_expectation_maximization(&values, &[1.0, 10.0], &templates, 50, |_| {});
+
+        assert!(first_heavy.coefficients[0] > first_heavy.coefficients[1]);
+        assert!(second_heavy.coefficients[1] > second_heavy.coefficients[0]);
+    }
+
+    #[test]
+    fn test_covariance_of_deviations() {
+        let template = [Vec3::new(-10.0, 0.0, 0.0), Vec3::new(10.0, 0.0, 0.0)];
+
+        // The formation is stretched along x only
+        let values = [Vec3::new(-12.0, 0.0, 0.0), Vec3::new(12.0, 0.0, 0.0)];
+
+        let result = expectation_maximization(&values, &[&template], 50);
+
+        assert!((result.std_deviation - 2.0).abs() < 1e-4);
+        assert!((result.std_deviation_along(Vec3::X) - 2.0).abs() < 1e-4);
+        assert!(result.std_deviation_along(Vec3::Z) < 1e-4);
+        assert!(
+            (result.covariance.x_axis.x + result.covariance.y_axis.y + result.covariance.z_axis.z
+                - result.std_deviation.powi(2))
+            .abs()
+                < 1e-3
+        );
+    }
+
+    #[test]
+    fn test_warm_started_expectation_maximization() {
+        let formation_templates = [
+            [Vec3::new(-10.0, 0.0, 0.0), Vec3::new(10.0, 0.0, 0.0)],
+            [Vec3::new(0.0, 0.0, -10.0), Vec3::new(0.0, 0.0, 10.0)],
+        ];
+        let templates = formation_templates
+            .iter()
+            .map(|e| e.as_slice())
+            .collect::<Vec<&[Vec3]>>();
+        let values = [Vec3::new(-10.0, 0.0, 1.0), Vec3::new(10.0, 0.0, -1.0)];
+        let weights = [1.0, 1.0];
+
+        let cold = weighted_expectation_maximization(&values, &weights, &templates, 100, |_| {});
+        let warm = warm_started_expectation_maximization(
+            &values,
+            &weights,
+            &templates,
+            &ExpectationMaximizationWarmStart::from(&cold),
+            2,
+            |_| {},
+        );
+
+        assert!(warm.iterations <= 2);
+        for (warm, cold) in warm.coefficients.iter().zip(&cold.coefficients) {
+            assert!((warm - cold).abs() < 1e-2);
+        }
+        assert!((warm.std_deviation - cold.std_deviation).abs() < 1e-2);
+    }
+
+    #[test]
+    fn test_values_perpendicular_to_every_template() {
+        let line = [Vec3::new(-10.0, 0.0, 0.0), Vec3::new(10.0, 0.0, 0.0)];
+        let column = [Vec3::new(0.0, -10.0, 0.0), Vec3::new(0.0, 10.0, 0.0)];
+        let values = [Vec3::new(0.0, 0.0, -10.0), Vec3::new(0.0, 0.0, 10.0)];
+
+        let result = expectation_maximization(&values, &[&line, &column], 50);
+
+        assert_eq!(result.coefficients, vec![0.5, 0.5]);
+        assert!(result.std_deviation.is_finite());
+    }
+
+    #[test]
+    fn test_mismatched_warm_start_is_ignored() {
+        let template = [Vec3::new(-10.0, 0.0, 0.0), Vec3::new(10.0, 0.0, 0.0)];
+        let values = [Vec3::new(-10.0, 0.0, 0.0), Vec3::new(10.0, 0.0, 0.0)];
+        let warm_start = ExpectationMaximizationWarmStart {
+            coefficients: vec![0.5, 0.5],
+            std_deviation: 0.0,
+        };
+
+        let result = warm_started_expectation_maximization(
+            &values,
+            &[1.0, 1.0],
+            &[&template],
+            &warm_start,
+            10,
+            |_| {},
+        );
+
+        assert_eq!(result.coefficients, vec![1.0]);
+    }
 }
@@ -1,5 +1,9 @@
-use bevy_math::Vec3;
-use geometry::Aabb;
+use bevy_math::{Quat, Vec3};
+use geometry::{colliders::Collider, Aabb, Vec3Operations};
+use orca::Agent3D;
+
+const MAX_RADII_RELAXATION_STEPS: usize = 100;
+const EPSILON: f32 = 0.001;
 
 #[derive(Clone, Debug)]
 pub struct Formation {
@@ -33,9 +37,209 @@ impl Formation {
         Aabb::new(center, half_sizes)
     }
 
+    // Returns an agent covering the whole formation, so other formations can avoid it
+    // The formation is expected to be in world space
+    pub fn get_agent(&self, agent_radius: f32, velocity: Vec3) -> Agent3D {
+        let bounds = self.get_bounds(agent_radius);
+
+        Agent3D::new(
+            bounds.center,
+            velocity,
+            Collider::new_aabb(Vec3::ZERO, bounds.half_sizes),
+        )
+    }
+
+    // Adapts the formation to agents of different sizes
+    // The largest agents are moved to the slots with the most free space around them, the zeroth
+    // agent keeps the zeroth slot. Then all overlapping agents are pushed apart until the gap
+    // between any two of them is at least min_spacing, the zeroth agent is never moved.
+    //
+    // radii: The radius of each agent, there has to be one for every position
+    // min_spacing: The minimum gap between two agents
+    // Returns: The formation with the position of the i-th agent at the i-th index
+    pub fn fit_to_radii(&self, radii: &[f32], min_spacing: f32) -> Self {
+        assert_eq!(
+            radii.len(),
+            self.positions.len(),
+            "Every agent needs exactly one radius"
+        );
+
+        if self.positions.len() < 2 {
+            return self.clone();
+        }
+
+        let clearance = |slot: usize| {
+            self.positions
+                .iter()
+                .enumerate()
+                .filter(|(other, _)| *other != slot)
+                .map(|(_, position)| position.distance(self.positions[slot]))
+                .fold(f32::INFINITY, f32::min)
+        };
+
+        let mut slots = (1..self.positions.len()).collect::<Vec<_>>();
+        slots.sort_by(|a, b| clearance(*b).total_cmp(&clearance(*a)));
+
+        let mut agents = (1..self.positions.len()).collect::<Vec<_>>();
+        agents.sort_by(|a, b| radii[*b].total_cmp(&radii[*a]));
+
+        let mut positions = self.positions.clone();
+        for (agent, slot) in agents.into_iter().zip(slots) {
+            positions[agent] = self.positions[slot];
+        }
+
+        for _ in 0..MAX_RADII_RELAXATION_STEPS {
+            let mut has_overlap = false;
+
+            for i in 0..positions.len() {
+                for j in (i + 1)..positions.len() {
+                    let min_distance = radii[i] + radii[j] + min_spacing;
+                    let offset = positions[j] - positions[i];
+                    let distance = offset.length();
+
+                    if distance + f32::EPSILON >= min_distance {
+                        continue;
+                    }
+
+                    has_overlap = true;
+
+                    let direction = offset.try_normalize().unwrap_or(Vec3::X);
+                    let correction = direction * (min_distance - distance);
+
+                    if i == 0 {
+                        positions[j] += correction;
+                    } else {
+                        positions[i] -= correction / 2.0;
+                        positions[j] += correction / 2.0;
+                    }
+                }
+            }
+
+            if !has_overlap {
+                break;
+            }
+        }
+
+        Self::new(positions)
+    }
+
+    // Moves every position that overlaps an obstacle to the closest point on the obstacle's
+    // surface, grown by the agent radius. Positions are displaced independently, so the rest of the
+    // formation keeps its shape. Both the formation and the obstacles are expected to be in world space.
+    //
+    // obstacles: The obstacles near the formation
+    // agent_radius: The radius of the agents
+    pub fn displace_from_obstacles(&mut self, obstacles: &[Agent3D], agent_radius: f32) {
+        let agent_collider = Collider::new_sphere(agent_radius);
+        let colliders = obstacles
+            .iter()
+            .map(|obstacle| {
+                (
+                    obstacle.position,
+                    obstacle.shape.minkowski_sum(&agent_collider),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        for position in self.positions.iter_mut() {
+            // Moving out of one obstacle may move the position into another one
+            for _ in 0..colliders.len() {
+                let mut displaced = false;
+
+                for (obstacle_position, collider) in &colliders {
+                    let local_position = *position - *obstacle_position;
+
+                    if !collider.contains(local_position) {
+                        continue;
+                    }
+
+                    let (point, normal) = match collider {
+                        Collider::Sphere(sphere) => {
+                            let normal = (local_position - sphere.origin)
+                                .try_normalize()
+                                .unwrap_or(Vec3::Y);
+
+                            (sphere.origin + normal * sphere.radius, normal)
+                        }
+                        Collider::Aabb(aabb) => aabb.closest_point_and_normal(local_position),
+                    };
+
+                    *position = *obstacle_position + point + normal * EPSILON;
+                    displaced = true;
+                }
+
+                if !displaced {
+                    break;
+                }
+            }
+        }
+    }
+
     pub fn scale(&mut self, scale: f32) {
         for position in self.positions.iter_mut() {
             *position *= scale;
         }
     }
+
+    pub fn translate(&mut self, offset: Vec3) {
+        for position in self.positions.iter_mut() {
+            *position += offset;
+        }
+    }
+
+    pub fn rotate(&mut self, rotation: Quat) {
+        for position in self.positions.iter_mut() {
+            *position = rotation * *position;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_displace_from_obstacles() {
+        let mut formation = Formation::new(vec![
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(10.0, 0.0, 0.0),
+            Vec3::new(20.0, 0.0, 0.0),
+        ]);
+
+        let obstacles = [Agent3D::new(
+            Vec3::new(10.0, -1.0, 0.0),
+            Vec3::ZERO,
+            Collider::new_sphere(2.0),
+        )];
+
+        formation.displace_from_obstacles(&obstacles, 1.0);
+
+        let positions = formation.get_positions();
+
+        assert_eq!(positions[0], Vec3::new(0.0, 0.0, 0.0));
+        assert_eq!(positions[2], Vec3::new(20.0, 0.0, 0.0));
+        assert!(positions[1].distance(obstacles[0].position) >= 3.0);
+    }
+
+    #[test]
+    fn test_fit_to_radii() {
+        let formation = Formation::new(vec![
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(2.0, 0.0, 0.0),
+            Vec3::new(4.0, 0.0, 0.0),
+            Vec3::new(-2.0, 0.0, 0.0),
+        ]);
+
+        let radii = [1.0, 1.0, 3.0, 1.0];
+        let fitted = formation.fit_to_radii(&radii, 0.5);
+        let positions = fitted.get_positions();
+
+        assert_eq!(positions[0], Vec3::ZERO);
+
+        for i in 0..positions.len() {
+            for j in (i + 1)..positions.len() {
+                assert!(positions[i].distance(positions[j]) + 1e-3 >= radii[i] + radii[j] + 0.5);
+            }
+        }
+    }
 }
@@ -0,0 +1,225 @@
+use bevy_math::Vec3;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    formations::{
+        CircleFormation, ColumnFormation, CustomFormation, GridFormation, HelixFormation,
+        LineFormation, OffsetResampling, QueueFormation, SphereFormation, VFormation,
+        WedgeFormation,
+    },
+    FormationTemplate,
+};
+
+// Data-driven description of a formation template, e.g.
+//
+// (type: "Circle", agent_radius: 5.0, spacing: 2.0, priority: 1.0)
+//
+// The parameters are validated by the template constructors, so invalid values
+// panic the same way constructing the template in code would.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum FormationTemplateDescription {
+    Line {
+        agent_radius: f32,
+        spacing: f32,
+        priority: f32,
+    },
+    Queue {
+        agent_radius: f32,
+        spacing: f32,
+        priority: f32,
+    },
+    Circle {
+        agent_radius: f32,
+        spacing: f32,
+        priority: f32,
+    },
+    V {
+        agent_radius: f32,
+        spacing: f32,
+        priority: f32,
+    },
+    Wedge {
+        agent_radius: f32,
+        spacing: f32,
+        priority: f32,
+    },
+    Sphere {
+        agent_radius: f32,
+        spacing: f32,
+        radius_growth: f32,
+        priority: f32,
+    },
+    Grid {
+        agent_radius: f32,
+        spacing: [f32; 3],
+        priority: f32,
+    },
+    Helix {
+        agent_radius: f32,
+        spacing: f32,
+        agents_per_turn: usize,
+        priority: f32,
+    },
+    Column {
+        agent_radius: f32,
+        spacing: f32,
+        agents_per_row: usize,
+        priority: f32,
+    },
+    Custom {
+        agent_radius: f32,
+        offsets: Vec<[f32; 3]>,
+        #[serde(default)]
+        resampling: OffsetResampling,
+        priority: f32,
+    },
+}
+
+impl FormationTemplateDescription {
+    pub fn into_template(self) -> Box<dyn FormationTemplate> {
+        match self {
+            Self::Line {
+                agent_radius,
+                spacing,
+                priority,
+            } => Box::new(LineFormation::new(agent_radius, spacing, priority)),
+            Self::Queue {
+                agent_radius,
+                spacing,
+                priority,
+            } => Box::new(QueueFormation::new(agent_radius, spacing, priority)),
+            Self::Circle {
+                agent_radius,
+                spacing,
+                priority,
+            } => Box::new(CircleFormation::new(agent_radius, spacing, priority)),
+            Self::V {
+                agent_radius,
+                spacing,
+                priority,
+            } => Box::new(VFormation::new(agent_radius, spacing, priority)),
+            Self::Wedge {
+                agent_radius,
+                spacing,
+                priority,
+            } => Box::new(WedgeFormation::new(agent_radius, spacing, priority)),
+            Self::Sphere {
+                agent_radius,
+                spacing,
+                radius_growth,
+                priority,
+            } => Box::new(SphereFormation::new(
+                agent_radius,
+                spacing,
+                radius_growth,
+                priority,
+            )),
+            Self::Grid {
+                agent_radius,
+                spacing,
+                priority,
+            } => Box::new(GridFormation::new(
+                agent_radius,
+                Vec3::from_array(spacing),
+                priority,
+            )),
+            Self::Helix {
+                agent_radius,
+                spacing,
+                agents_per_turn,
+                priority,
+            } => Box::new(HelixFormation::new(
+                agent_radius,
+                spacing,
+                agents_per_turn,
+                priority,
+            )),
+            Self::Column {
+                agent_radius,
+                spacing,
+                agents_per_row,
+                priority,
+            } => Box::new(ColumnFormation::new(
+                agent_radius,
+                spacing,
+                agents_per_row,
+                priority,
+            )),
+            Self::Custom {
+                agent_radius,
+                offsets,
+                resampling,
+                priority,
+            } => Box::new(
+                CustomFormation::from_offsets(
+                    offsets.into_iter().map(Vec3::from_array).collect(),
+                    agent_radius,
+                    priority,
+                )
+                .with_resampling(resampling),
+            ),
+        }
+    }
+}
+
+// Loads a list of formation templates from a RON document
+#[cfg(feature = "ron")]
+pub fn formation_templates_from_ron(
+    source: &str,
+) -> Result<Vec<Box<dyn FormationTemplate>>, ron::error::SpannedError> {
+    let descriptions: Vec<FormationTemplateDescription> = ron::from_str(source)?;
+
+    Ok(descriptions
+        .into_iter()
+        .map(FormationTemplateDescription::into_template)
+        .collect())
+}
+
+// Loads a list of formation templates from a JSON document
+#[cfg(feature = "json")]
+pub fn formation_templates_from_json(
+    source: &str,
+) -> Result<Vec<Box<dyn FormationTemplate>>, serde_json::Error> {
+    let descriptions: Vec<FormationTemplateDescription> = serde_json::from_str(source)?;
+
+    Ok(descriptions
+        .into_iter()
+        .map(FormationTemplateDescription::into_template)
+        .collect())
+}
+
+#[cfg(all(test, feature = "ron", feature = "json"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ron_and_json_describe_the_same_templates() {
+        let from_ron = formation_templates_from_ron(
+            r#"[
+                (type: "Line", agent_radius: 5.0, spacing: 2.0, priority: 1.0),
+                (type: "Custom", agent_radius: 5.0, offsets: [(0.0, 0.0, 0.0), (20.0, 0.0, 0.0)], priority: 2.0),
+            ]"#,
+        )
+        .unwrap();
+
+        let from_json = formation_templates_from_json(
+            r#"[
+                {"type": "Line", "agent_radius": 5.0, "spacing": 2.0, "priority": 1.0},
+                {"type": "Custom", "agent_radius": 5.0, "offsets": [[0.0, 0.0, 0.0], [20.0, 0.0, 0.0]], "priority": 2.0}
+            ]"#,
+        )
+        .unwrap();
+
+        assert_eq!(from_ron.len(), 2);
+        assert_eq!(from_json.len(), 2);
+
+        for (a, b) in from_ron.iter().zip(from_json.iter()) {
+            assert_eq!(a.get_priority(), b.get_priority());
+            assert_eq!(
+                a.create_formation(3).get_positions(),
+                b.create_formation(3).get_positions()
+            );
+        }
+    }
+}
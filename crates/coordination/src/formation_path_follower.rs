@@ -0,0 +1,274 @@
+use bevy_math::Vec3;
+
+// Moves the frame of a formation along a path and produces the preferred velocity of the formation,
+// which is then passed to FormationTemplateSet::get_best_formation.
+//
+// The path is parametrized by its arc length. The speed along it is limited by the slowest member
+// of the formation and by the turns ahead, the formation starts braking early enough to take
+// every turn at a speed its turn rate allows and to stop at the end of the path.
+#[derive(Clone, Debug)]
+pub struct FormationPathFollower {
+    points: Vec<Vec3>,
+    // Arc length at every point of the path
+    distances: Vec<f32>,
+    // The highest speed at which the turn at every point can be taken
+    point_speeds: Vec<f32>,
+    max_speed: f32,
+    max_deceleration: f32,
+    distance: f32,
+}
+
+impl FormationPathFollower {
+    // path: The points of the path
+    // member_max_speeds: The maximum speed of every member of the formation
+    // max_turn_rate: The maximum turning speed of the formation in radians per second
+    // max_deceleration: The maximum deceleration of the formation
+    pub fn new(
+        path: Vec<Vec3>,
+        member_max_speeds: &[f32],
+        max_turn_rate: f32,
+        max_deceleration: f32,
+    ) -> Self {
+        assert!(path.len() >= 2, "The path needs at least two points");
+        assert!(!member_max_speeds.is_empty());
+        assert!(max_turn_rate > 0.0);
+        assert!(max_deceleration > 0.0);
+
+        let max_speed = member_max_speeds
+            .iter()
+            .copied()
+            .fold(f32::INFINITY, f32::min);
+
+        let mut distances = Vec::with_capacity(path.len());
+        let mut distance = 0.0;
+
+        for (i, point) in path.iter().enumerate() {
+            if i > 0 {
+                distance += point.distance(path[i - 1]);
+            }
+
+            distances.push(distance);
+        }
+
+        let point_speeds = (0..path.len())
+            .map(|i| {
+                if i == 0 {
+                    return max_speed;
+                }
+
+                if i == path.len() - 1 {
+                    return 0.0;
+                }
+
+                let incoming = path[i] - path[i - 1];
+                let outgoing = path[i + 1] - path[i];
+                let angle = incoming.angle_between(outgoing);
+
+                if !angle.is_finite() || angle < f32::EPSILON {
+                    return max_speed;
+                }
+
+                // The radius of the largest arc that fits between the middles of both segments
+                let radius = 0.5 * incoming.length().min(outgoing.length()) / (angle / 2.0).tan();
+
+                (radius * max_turn_rate).min(max_speed)
+            })
+            .collect();
+
+        Self {
+            points: path,
+            distances,
+            point_speeds,
+            max_speed,
+            max_deceleration,
+            distance: 0.0,
+        }
+    }
+
+    // Creates a follower along a Catmull-Rom spline through the control points
+    // samples_per_segment: The number of points each segment between two control points is sampled with
+    pub fn from_spline(
+        control_points: &[Vec3],
+        samples_per_segment: usize,
+        member_max_speeds: &[f32],
+        max_turn_rate: f32,
+        max_deceleration: f32,
+    ) -> Self {
+        assert!(control_points.len() >= 2);
+        assert!(samples_per_segment > 0);
+
+        let mut path = Vec::with_capacity((control_points.len() - 1) * samples_per_segment + 1);
+
+        for i in 0..control_points.len() - 1 {
+            let p0 = control_points[i.saturating_sub(1)];
+            let p1 = control_points[i];
+            let p2 = control_points[i + 1];
+            let p3 = control_points[(i + 2).min(control_points.len() - 1)];
+
+            for sample in 0..samples_per_segment {
+                let t = sample as f32 / samples_per_segment as f32;
+                let t2 = t * t;
+                let t3 = t2 * t;
+
+                path.push(
+                    0.5 * (2.0 * p1
+                        + (p2 - p0) * t
+                        + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t2
+                        + (3.0 * p1 - p0 - 3.0 * p2 + p3) * t3),
+                );
+            }
+        }
+
+        path.extend(control_points.last());
+
+        Self::new(path, member_max_speeds, max_turn_rate, max_deceleration)
+    }
+
+    pub fn length(&self) -> f32 {
+        *self.distances.last().expect("The path can't be empty")
+    }
+
+    // The arc length the formation frame has travelled
+    pub fn distance(&self) -> f32 {
+        self.distance
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.distance >= self.length() - f32::EPSILON
+    }
+
+    // Returns the point of the path at the given arc length
+    pub fn position_at(&self, distance: f32) -> Vec3 {
+        let (segment, t) = self.segment_at(distance);
+
+        self.points[segment].lerp(self.points[segment + 1], t)
+    }
+
+    // Returns the direction of the path at the given arc length
+    pub fn direction_at(&self, distance: f32) -> Vec3 {
+        let (segment, _) = self.segment_at(distance);
+
+        (self.points[segment + 1] - self.points[segment]).normalize_or_zero()
+    }
+
+    // Returns the highest speed at the given arc length that still allows the formation
+    // to slow down for all turns ahead and to stop at the end of the path
+    pub fn speed_limit_at(&self, distance: f32) -> f32 {
+        self.distances
+            .iter()
+            .zip(self.point_speeds.iter())
+            .filter(|(point_distance, _)| **point_distance >= distance)
+            .map(|(point_distance, speed)| {
+                (speed * speed + 2.0 * self.max_deceleration * (point_distance - distance)).sqrt()
+            })
+            .fold(self.max_speed, f32::min)
+    }
+
+    // Advances the formation frame along the path
+    //
+    // formation_center: The current center of the formation
+    // delta_time: The time step
+    // Returns: The preferred velocity of the formation
+    pub fn update(&mut self, formation_center: Vec3, delta_time: f32) -> Vec3 {
+        if delta_time <= 0.0 {
+            return Vec3::ZERO;
+        }
+
+        // The frame only advances as far as the formation got, so it doesn't run away from
+        // a formation slowed down by obstacles
+        self.distance = self.distance.max(self.project(formation_center));
+
+        let speed = self.speed_limit_at(self.distance);
+        let target = self.position_at(self.distance + speed * delta_time);
+
+        ((target - formation_center) / delta_time).clamp_length_max(speed)
+    }
+
+    fn segment_at(&self, distance: f32) -> (usize, f32) {
+        let distance = distance.clamp(0.0, self.length());
+        let segment = self
+            .distances
+            .windows(2)
+            .position(|window| distance <= window[1])
+            .unwrap_or(self.points.len() - 2);
+
+        let segment_length = self.distances[segment + 1] - self.distances[segment];
+        let t = if segment_length > f32::EPSILON {
+            (distance - self.distances[segment]) / segment_length
+        } else {
+            0.0
+        };
+
+        (segment, t)
+    }
+
+    // Returns the arc length of the point of the path closest to the given position
+    fn project(&self, position: Vec3) -> f32 {
+        let mut best_distance = f32::INFINITY;
+        let mut best_arc_length = 0.0;
+
+        for i in 0..self.points.len() - 1 {
+            let start = self.points[i];
+            let segment = self.points[i + 1] - start;
+            let t = if segment.length_squared() > f32::EPSILON {
+                ((position - start).dot(segment) / segment.length_squared()).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+
+            let distance = position.distance_squared(start + segment * t);
+
+            if distance < best_distance {
+                best_distance = distance;
+                best_arc_length = self.distances[i] + t * segment.length();
+            }
+        }
+
+        best_arc_length
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_speed_is_limited_by_slowest_member_and_turns() {
+        let follower = FormationPathFollower::new(
+            vec![
+                Vec3::new(0.0, 0.0, 0.0),
+                Vec3::new(1000.0, 0.0, 0.0),
+                Vec3::new(1000.0, 0.0, 1000.0),
+            ],
+            &[20.0, 10.0, 30.0],
+            0.01,
+            1.0,
+        );
+
+        // The slowest member limits the speed on the straight part
+        assert_eq!(follower.speed_limit_at(0.0), 10.0);
+
+        // The right angle turn has a radius of 500 and can be taken at 5
+        approx::assert_relative_eq!(follower.speed_limit_at(1000.0), 5.0, epsilon = 1e-3);
+
+        // The formation stops at the end of the path
+        assert_eq!(follower.speed_limit_at(2000.0), 0.0);
+    }
+
+    #[test]
+    fn test_update_follows_path() {
+        let mut follower = FormationPathFollower::from_spline(
+            &[Vec3::new(0.0, 0.0, 0.0), Vec3::new(100.0, 0.0, 0.0)],
+            4,
+            &[10.0],
+            1.0,
+            1.0,
+        );
+
+        let velocity = follower.update(Vec3::ZERO, 0.1);
+
+        approx::assert_relative_eq!(velocity.x, 10.0, epsilon = 1e-3);
+        assert!(velocity.y.abs() < 1e-3 && velocity.z.abs() < 1e-3);
+        assert!(!follower.is_finished());
+    }
+}
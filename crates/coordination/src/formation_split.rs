@@ -0,0 +1,199 @@
+use std::collections::HashMap;
+
+use bevy_math::Vec3;
+
+use crate::{best_matching_indexes, Formation};
+
+// A group of agents split off from a formation
+#[derive(Clone, Debug)]
+pub struct FormationGroup {
+    // Indexes of the agents in the original formation
+    pub agents: Vec<usize>,
+    // The center of mass of the group
+    pub center: Vec3,
+    // The positions of the agents relative to the center, in the same order as agents
+    pub formation: Formation,
+}
+
+// Splits a formation into at most k groups of nearby agents using k-means clustering.
+// The clusters are seeded by repeatedly taking the agent farthest from all already chosen seeds,
+// which makes the split deterministic. Every group can then pick its own template, for example
+// with FormationTemplateSet::get_closest_template.
+//
+// formation: The formation that should be split, in world space
+// k: The number of groups
+// max_steps: The maximum number of k-means iterations
+// Returns: The non-empty groups
+pub fn split_formation(formation: &Formation, k: usize, max_steps: usize) -> Vec<FormationGroup> {
+    assert!(k > 0);
+
+    let positions = formation.get_positions();
+
+    if positions.is_empty() {
+        return Vec::new();
+    }
+
+    let mut centers = vec![positions[0]];
+
+    while centers.len() < k.min(positions.len()) {
+        let farthest = positions
+            .iter()
+            .max_by(|a, b| {
+                let a = distance_to_closest(**a, &centers);
+                let b = distance_to_closest(**b, &centers);
+
+                a.total_cmp(&b)
+            })
+            .expect("Positions can't be empty");
+
+        centers.push(*farthest);
+    }
+
+    let mut labels = vec![0; positions.len()];
+
+    for _ in 0..max_steps {
+        let mut changed = false;
+
+        for (i, position) in positions.iter().enumerate() {
+            let label = closest(*position, &centers);
+
+            if label != labels[i] {
+                labels[i] = label;
+                changed = true;
+            }
+        }
+
+        for (label, center) in centers.iter_mut().enumerate() {
+            let (sum, count) = positions
+                .iter()
+                .zip(labels.iter())
+                .filter(|(_, l)| **l == label)
+                .fold((Vec3::ZERO, 0), |(sum, count), (p, _)| {
+                    (sum + *p, count + 1)
+                });
+
+            if count > 0 {
+                *center = sum / count as f32;
+            }
+        }
+
+        if !changed {
+            break;
+        }
+    }
+
+    (0..centers.len())
+        .filter_map(|label| {
+            let agents = (0..positions.len())
+                .filter(|i| labels[*i] == label)
+                .collect::<Vec<_>>();
+
+            if agents.is_empty() {
+                return None;
+            }
+
+            let center = agents.iter().map(|i| positions[*i]).sum::<Vec3>() / agents.len() as f32;
+            let formation = Formation::new(
+                agents
+                    .iter()
+                    .map(|i| positions[*i] - center)
+                    .collect::<Vec<_>>(),
+            );
+
+            Some(FormationGroup {
+                agents,
+                center,
+                formation,
+            })
+        })
+        .collect()
+}
+
+// Merges several formations into one.
+// The agents of all formations are numbered in the order the formations are given and assigned
+// to the slots of the destination formation placed at their common center of mass.
+//
+// formations: The formations that should be merged, in world space
+// destination: The formation the merged agents should take, relative to its center
+// Returns: The destination formation in world space and the agents mapped to its slots
+pub fn merge_formations(
+    formations: &[&Formation],
+    destination: &Formation,
+) -> (Formation, HashMap<usize, usize>) {
+    let positions = formations
+        .iter()
+        .flat_map(|formation| formation.get_positions().iter().copied())
+        .collect::<Vec<_>>();
+
+    if positions.is_empty() {
+        return (Formation::new(Vec::new()), HashMap::new());
+    }
+
+    let center = positions.iter().sum::<Vec3>() / positions.len() as f32;
+    let slots = destination
+        .get_positions()
+        .iter()
+        .map(|position| *position + center)
+        .collect::<Vec<_>>();
+
+    let assignment = best_matching_indexes(&positions, &slots);
+
+    (Formation::new(slots), assignment)
+}
+
+fn distance_to_closest(position: Vec3, centers: &[Vec3]) -> f32 {
+    centers
+        .iter()
+        .map(|center| position.distance_squared(*center))
+        .fold(f32::INFINITY, f32::min)
+}
+
+fn closest(position: Vec3, centers: &[Vec3]) -> usize {
+    centers
+        .iter()
+        .enumerate()
+        .min_by(|(_, a), (_, b)| {
+            position
+                .distance_squared(**a)
+                .total_cmp(&position.distance_squared(**b))
+        })
+        .map(|(i, _)| i)
+        .expect("Centers can't be empty")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_and_merge() {
+        let formation = Formation::new(vec![
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(100.0, 0.0, 0.0),
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(101.0, 0.0, 0.0),
+        ]);
+
+        let groups = split_formation(&formation, 2, 10);
+
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].agents, vec![0, 2]);
+        assert_eq!(groups[1].agents, vec![1, 3]);
+        assert_eq!(groups[1].center, Vec3::new(100.5, 0.0, 0.0));
+
+        let destination = Formation::new(vec![
+            Vec3::new(-1.5, 0.0, 0.0),
+            Vec3::new(-0.5, 0.0, 0.0),
+            Vec3::new(0.5, 0.0, 0.0),
+            Vec3::new(1.5, 0.0, 0.0),
+        ]);
+
+        let first = Formation::new(vec![Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0)]);
+        let second = Formation::new(vec![Vec3::new(2.0, 0.0, 0.0), Vec3::new(3.0, 0.0, 0.0)]);
+
+        let (merged, assignment) = merge_formations(&[&first, &second], &destination);
+
+        assert_eq!(merged.get_positions()[0], Vec3::new(0.0, 0.0, 0.0));
+        assert_eq!(assignment, HashMap::from([(0, 0), (1, 1), (2, 2), (3, 3)]));
+    }
+}
@@ -1,12 +1,23 @@
+use std::{
+    borrow::Cow,
+    f32::consts::{FRAC_PI_2, PI},
+};
+
 use bevy_gizmos::gizmos::Gizmos;
-use bevy_math::Vec3;
-use bevy_render::color::Color;
+use bevy_math::{Mat3, Quat, Vec3};
 use geometry::{colliders::Collider, Aabb};
-use orca::{optimize_velocity_3d, Agent3D, FormationVelocityObstacle3D};
+use orca::{cluster_obstacles, optimize_velocity_3d, Agent3D, FormationVelocityObstacle3D};
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
 
-use crate::{expectation_maximization::expectation_maximization, Formation};
+use crate::{
+    expectation_maximization, warm_started_expectation_maximization,
+    weighted_expectation_maximization, CoordinationError, ExpectationMaximizationResult,
+    ExpectationMaximizationWarmStart, Formation, PriorityContext,
+};
 
-pub trait FormationTemplate {
+// Templates are shared by parallel evaluations and ECS systems, so they have to be Send and Sync
+pub trait FormationTemplate: Send + Sync {
     // Get the positions of the agents in the formation
     // The zeroth position is always the center of the formation
     //
@@ -18,10 +29,317 @@ pub trait FormationTemplate {
     // Returns: A float representing the priority of the formation
     fn get_priority(&self) -> f32;
 
+    // Get the priority of the formation in the situation it's evaluated in
+    // context: The situation the formation is evaluated in
+    // Returns: A float representing the priority of the formation, get_priority by default
+    fn get_priority_in_context(&self, _context: &PriorityContext) -> f32 {
+        self.get_priority()
+    }
+
     // Gets the AABB bounding box for the formation
     // n_agents: The number of agents in the formations
     // Returns: The AABB bounding box of the formation
     fn get_aabb(&self, n_agents: usize) -> Aabb;
+
+    // Get the positions of agents of different sizes in the formation
+    // The formation is created for the number of agents and then fitted to their radii,
+    // see Formation::fit_to_radii
+    //
+    // radii: The radius of each agent
+    // Returns: A formation with the position of the i-th agent at the i-th index
+    fn create_formation_with_radii(&self, radii: &[f32]) -> Formation {
+        self.create_formation(radii.len()).fit_to_radii(radii, 0.0)
+    }
+
+    // Get the positions of the agents in the formation with the slots that would overlap
+    // the obstacles moved out of them, see Formation::displace_from_obstacles
+    //
+    // n_agents: The number of agents in the formation
+    // position, rotation: Where the formation is placed in the world
+    // obstacles: The obstacles near the formation, in world space
+    // agent_radius: The radius of the agents
+    // Returns: The positions relative to the formation, like create_formation
+    fn create_formation_near_obstacles(
+        &self,
+        n_agents: usize,
+        position: Vec3,
+        rotation: Quat,
+        obstacles: &[Agent3D],
+        agent_radius: f32,
+    ) -> Formation {
+        let mut formation = self.create_formation(n_agents);
+
+        formation.rotate(rotation);
+        formation.translate(position);
+        formation.displace_from_obstacles(obstacles, agent_radius);
+        formation.translate(-position);
+        formation.rotate(rotation.inverse());
+
+        formation
+    }
+}
+
+// Describes how template formations may be contracted to squeeze through gaps.
+// Each template is evaluated at number_of_samples scale factors evenly spread between
+// 1.0 and the smallest scale at which the agents still don't overlap.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "reflect", derive(bevy_reflect::Reflect))]
+pub struct FormationScaling {
+    pub agent_radius: f32,
+    pub number_of_samples: usize,
+    pub compression_penalty_multiplier: f32,
+}
+
+impl FormationScaling {
+    pub fn new(
+        agent_radius: f32,
+        number_of_samples: usize,
+        compression_penalty_multiplier: f32,
+    ) -> Self {
+        assert!(agent_radius >= 0.0);
+        assert!(number_of_samples > 0);
+
+        Self {
+            agent_radius,
+            number_of_samples,
+            compression_penalty_multiplier,
+        }
+    }
+
+    // Templates are only evaluated at their original scale
+    pub fn disabled() -> Self {
+        Self::new(0.0, 1, 0.0)
+    }
+
+    // Returns the scale factors at which the formation should be evaluated, starting with 1.0
+    pub fn get_scales(&self, formation: &Formation) -> Vec<f32> {
+        if self.number_of_samples <= 1 {
+            return vec![1.0];
+        }
+
+        let positions = formation.get_positions();
+        let mut min_distance = f32::INFINITY;
+
+        for (i, a) in positions.iter().enumerate() {
+            for b in &positions[i + 1..] {
+                min_distance = min_distance.min(a.distance(*b));
+            }
+        }
+
+        if !min_distance.is_finite() || min_distance <= f32::EPSILON {
+            return vec![1.0];
+        }
+
+        let min_scale = (2.0 * self.agent_radius / min_distance).min(1.0);
+
+        (0..self.number_of_samples)
+            .map(|i| 1.0 - (1.0 - min_scale) * i as f32 / (self.number_of_samples - 1) as f32)
+            .collect()
+    }
+
+    // The agents keep their size when the formation is contracted,
+    // only the distances between them get smaller
+    pub fn scale_aabb(&self, aabb: &Aabb, scale: f32) -> Aabb {
+        let agent_half_sizes = Vec3::splat(self.agent_radius).min(aabb.half_sizes);
+
+        Aabb::new(
+            aabb.center * scale,
+            (aabb.half_sizes - agent_half_sizes) * scale + agent_half_sizes,
+        )
+    }
+}
+
+// Parameters of the formation evaluation done by FormationTemplateSet::get_best_formation
+//
+// maximum_velocity: The maximum speed of the formation
+// deformation_penalty_multiplier: How much the priority of a deformed formation is reduced
+//                                 by its standard deviation from the templates
+// travel_deformation_penalty_multiplier: Replaces deformation_penalty_multiplier for the part of
+//                                        the deviation along the preferred velocity, so a formation
+//                                        stretched along its path can be penalized less than one
+//                                        scattered to the sides. The whole deviation is penalized
+//                                        the same if not set
+// obstacle_avoidance_time_horizon: How far into the future collisions with obstacles are avoided
+// number_of_yaw_samples, number_of_pitch_samples: The resolution of the sampled velocity obstacles
+// number_of_heading_samples: The number of headings every template is evaluated in
+// max_steps_for_em: The maximum number of steps of the expectation maximization
+// em_warm_start: The result of the expectation maximization of the previous evaluation, see
+//                FormationDecision::em_warm_start. The fit starts from it and only runs for
+//                em_refinement_steps instead of max_steps_for_em. It starts from scratch if not set
+// scaling: The scales every template is evaluated at
+// threat_level: A game specific measure of danger passed to the priority providers
+// agent_weights: The importance of each agent when fitting the current formation to the templates,
+//                all agents are equally important if not set
+// obstacle_cluster_distance: Obstacles closer to each other than this distance (after being grown
+//                            by the formation) are avoided as one, see orca::cluster_obstacles.
+//                            The obstacles are avoided one by one if not set
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "reflect", derive(bevy_reflect::Reflect))]
+pub struct FormationEvaluationParams {
+    pub maximum_velocity: f32,
+    pub deformation_penalty_multiplier: f32,
+    pub travel_deformation_penalty_multiplier: Option<f32>,
+    pub obstacle_avoidance_time_horizon: f32,
+    pub number_of_yaw_samples: u16,
+    pub number_of_pitch_samples: u16,
+    pub number_of_heading_samples: u16,
+    pub max_steps_for_em: usize,
+    pub em_warm_start: Option<ExpectationMaximizationWarmStart>,
+    pub em_refinement_steps: usize,
+    pub scaling: FormationScaling,
+    pub threat_level: f32,
+    pub agent_weights: Option<Vec<f32>>,
+    pub obstacle_cluster_distance: Option<f32>,
+}
+
+impl FormationEvaluationParams {
+    pub fn new(maximum_velocity: f32) -> Self {
+        assert!(maximum_velocity >= 0.0);
+
+        Self {
+            maximum_velocity,
+            deformation_penalty_multiplier: 0.0,
+            travel_deformation_penalty_multiplier: None,
+            obstacle_avoidance_time_horizon: 5.0,
+            number_of_yaw_samples: 20,
+            number_of_pitch_samples: 20,
+            number_of_heading_samples: 1,
+            max_steps_for_em: 100,
+            em_warm_start: None,
+            em_refinement_steps: 3,
+            scaling: FormationScaling::disabled(),
+            threat_level: 0.0,
+            agent_weights: None,
+            obstacle_cluster_distance: None,
+        }
+    }
+
+    pub fn with_deformation_penalty_multiplier(mut self, multiplier: f32) -> Self {
+        self.deformation_penalty_multiplier = multiplier;
+        self
+    }
+
+    pub fn with_travel_deformation_penalty_multiplier(mut self, multiplier: f32) -> Self {
+        self.travel_deformation_penalty_multiplier = Some(multiplier);
+        self
+    }
+
+    pub fn with_obstacle_avoidance_time_horizon(mut self, time_horizon: f32) -> Self {
+        self.obstacle_avoidance_time_horizon = time_horizon;
+        self
+    }
+
+    pub fn with_velocity_obstacle_samples(
+        mut self,
+        number_of_yaw_samples: u16,
+        number_of_pitch_samples: u16,
+    ) -> Self {
+        self.number_of_yaw_samples = number_of_yaw_samples;
+        self.number_of_pitch_samples = number_of_pitch_samples;
+        self
+    }
+
+    pub fn with_heading_samples(mut self, number_of_heading_samples: u16) -> Self {
+        self.number_of_heading_samples = number_of_heading_samples;
+        self
+    }
+
+    pub fn with_max_steps_for_em(mut self, max_steps_for_em: usize) -> Self {
+        self.max_steps_for_em = max_steps_for_em;
+        self
+    }
+
+    pub fn with_em_warm_start(
+        mut self,
+        warm_start: ExpectationMaximizationWarmStart,
+        refinement_steps: usize,
+    ) -> Self {
+        self.em_warm_start = Some(warm_start);
+        self.em_refinement_steps = refinement_steps;
+        self
+    }
+
+    pub fn with_scaling(mut self, scaling: FormationScaling) -> Self {
+        self.scaling = scaling;
+        self
+    }
+
+    pub fn with_threat_level(mut self, threat_level: f32) -> Self {
+        self.threat_level = threat_level;
+        self
+    }
+
+    pub fn with_agent_weights(mut self, agent_weights: Vec<f32>) -> Self {
+        self.agent_weights = Some(agent_weights);
+        self
+    }
+
+    pub fn with_obstacle_clustering(mut self, cluster_distance: f32) -> Self {
+        assert!(cluster_distance >= 0.0);
+
+        self.obstacle_cluster_distance = Some(cluster_distance);
+        self
+    }
+}
+
+// Which formation a candidate of the evaluation stands for
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "reflect", derive(bevy_reflect::Reflect))]
+pub enum FormationChoice {
+    // The template at the given index of the FormationTemplateSet
+    Template(usize),
+    // The current formation, kept as it is. Its positions are relative to the center of their
+    // bounds and aren't rotated.
+    Current,
+}
+
+// A single formation evaluated by FormationTemplateSet::get_best_formation
+#[derive(Clone, Debug)]
+pub struct FormationCandidate {
+    pub choice: FormationChoice,
+    // The scale the template was evaluated at, always 1.0 for the current formation
+    pub scale: f32,
+    pub rotation: Quat,
+    // The collision-free velocity of the formation
+    pub velocity: Vec3,
+    pub fitness: f32,
+    // The number of ORCA planes the velocity was constrained by
+    pub number_of_orca_planes: usize,
+}
+
+// The result of FormationTemplateSet::get_best_formation
+#[derive(Clone, Debug)]
+pub struct FormationDecision {
+    // The chosen formation in its own frame
+    pub formation: Formation,
+    // The rotation that places the chosen formation in the world around the center of the bounds
+    // of the current formation
+    pub rotation: Quat,
+    pub velocity: Vec3,
+    pub choice: FormationChoice,
+    pub fitness: f32,
+    // All evaluated candidates, templates first in the order they were evaluated,
+    // the current formation last
+    pub candidates: Vec<FormationCandidate>,
+    // The weights of the templates in the current formation found by expectation maximization
+    pub em_coefficients: Vec<f32>,
+    // The standard deviation of the current formation from the weighted templates
+    pub std_deviation: f32,
+    // The covariance of the deviations of the current formation from the weighted templates
+    pub covariance: Mat3,
+}
+
+impl FormationDecision {
+    // The state of the expectation maximization to continue from in the next evaluation,
+    // see FormationEvaluationParams::with_em_warm_start
+    pub fn em_warm_start(&self) -> ExpectationMaximizationWarmStart {
+        ExpectationMaximizationWarmStart {
+            coefficients: self.em_coefficients.clone(),
+            std_deviation: self.std_deviation,
+        }
+    }
 }
 
 pub struct FormationTemplateSet<'a>(Vec<&'a dyn FormationTemplate>);
@@ -52,8 +370,424 @@ impl<'a> FormationTemplateSet<'a> {
     //        formations)
     //        n is the number of formation templates
     //
-    // The formation with the highest fitness function is selected
-    #[allow(clippy::too_many_arguments)]
+    // Template formations can also be contracted by a scale factor s, in that case their priority
+    // is reduced to p_f - delta * (1 - s), where delta is the compression penalty multiplier.
+    //
+    // Every template is also evaluated in several headings. The formation is first aligned with the
+    // preferred velocity (its Z axis pointing along it) and then yawed around its local Y axis by
+    // offsets spread evenly over [-pi/2, pi/2]. The unrotated heading is always evaluated.
+    //
+    // Other formations are avoided the same way as obstacles, but reciprocally. Both formations
+    // take their share of the avoidance given by their responsibility, see Formation::get_agent.
+    //
+    // The formation with the highest fitness function is selected. It's returned in its own frame
+    // together with the rotation that places it in the world. The decision also contains every
+    // evaluated candidate, which helps to find out why the formations keep changing.
+    //
+    // Fails if there are no agents or the agent weights don't fit the agents.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            level = "debug",
+            skip_all,
+            fields(
+                templates = self.0.len(),
+                agents = current_formation.len(),
+                obstacles = obtacles.len()
+            )
+        )
+    )]
+    pub fn get_best_formation(
+        &self,
+        current_formation: &[Vec3],
+        preffered_velocity: Vec3,
+        obtacles: &[Agent3D],
+        other_formations: &[Agent3D],
+        params: &FormationEvaluationParams,
+    ) -> Result<FormationDecision, CoordinationError> {
+        let FormationEvaluationParams {
+            maximum_velocity,
+            deformation_penalty_multiplier,
+            travel_deformation_penalty_multiplier,
+            obstacle_avoidance_time_horizon,
+            number_of_yaw_samples,
+            number_of_pitch_samples,
+            number_of_heading_samples,
+            max_steps_for_em,
+            ref em_warm_start,
+            em_refinement_steps,
+            ref scaling,
+            threat_level,
+            ref agent_weights,
+            obstacle_cluster_distance,
+        } = *params;
+
+        if current_formation.is_empty() {
+            return Err(CoordinationError::EmptyFormation);
+        }
+
+        if let Some(weights) = agent_weights {
+            if weights.len() != current_formation.len()
+                || !weights.iter().all(|weight| *weight >= 0.0)
+                || weights.iter().sum::<f32>() <= 0.0
+            {
+                return Err(CoordinationError::InvalidAgentWeights {
+                    agents: current_formation.len(),
+                    weights: weights.len(),
+                });
+            }
+        }
+
+        let heading = preffered_velocity
+            .try_normalize()
+            .map_or(Quat::IDENTITY, |direction| {
+                Quat::from_rotation_arc(Vec3::Z, direction)
+            });
+        let rotations = get_heading_rotations(heading, number_of_heading_samples);
+
+        let (formation_aabb, center) = {
+            let mut min = Vec3::splat(f32::INFINITY);
+            let mut max = Vec3::splat(f32::NEG_INFINITY);
+
+            for position in current_formation {
+                min = min.min(*position);
+                max = max.max(*position);
+            }
+
+            // The positions are already in world space, unlike the templates the bounds
+            // must not be rotated by the heading
+            (
+                Collider::new_aabb(Vec3::ZERO, (max - min) / 2.0),
+                (min + max) / 2.0,
+            )
+        };
+
+        let priority_context = PriorityContext {
+            velocity: preffered_velocity,
+            threat_level,
+            obstacle_density: get_obstacle_density(
+                center,
+                formation_aabb.bounding_sphere().radius
+                    + preffered_velocity.length() * obstacle_avoidance_time_horizon,
+                obtacles,
+            ),
+        };
+
+        // First evaluate the fitness of each template formation
+        // Every template is evaluated at all of its scales and headings, these evaluations
+        // are independent of each other and run in parallel with the rayon feature
+        let templates = self
+            .0
+            .iter()
+            .map(|template| {
+                let template_formation = template.create_formation(current_formation.len());
+                let template_aabb = template.get_aabb(current_formation.len());
+
+                (
+                    template_formation,
+                    template_aabb,
+                    template.get_priority_in_context(&priority_context),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let rotations = &rotations;
+        let template_candidates = templates
+            .iter()
+            .enumerate()
+            .flat_map(|(index, (template_formation, _, _))| {
+                scaling
+                    .get_scales(template_formation)
+                    .into_iter()
+                    .flat_map(move |scale| {
+                        rotations
+                            .iter()
+                            .map(move |rotation| (index, scale, *rotation))
+                    })
+            })
+            .collect::<Vec<_>>();
+
+        let evaluate_candidate = |&(index, scale, rotation): &(usize, f32, Quat)| {
+            let (_, template_aabb, template_priority) = &templates[index];
+            let scaled_aabb = scaling.scale_aabb(template_aabb, scale).rotated(rotation);
+
+            let formation_agent = Agent3D::new(
+                center,
+                preffered_velocity,
+                Collider::new_aabb(Vec3::ZERO, scaled_aabb.half_sizes),
+            );
+
+            let obstacles = get_clustered_obstacles(
+                obtacles,
+                &formation_agent.shape,
+                obstacle_avoidance_time_horizon,
+                obstacle_cluster_distance,
+            );
+
+            let orca_planes = obstacles
+                .iter()
+                .filter_map(|obstacle| {
+                    let vo = FormationVelocityObstacle3D::new(
+                        &formation_agent,
+                        obstacle,
+                        obstacle_avoidance_time_horizon,
+                    );
+
+                    //let triangles =
+                    //    vo.construct_vo_mesh(number_of_yaw_samples, number_of_pitch_samples, 0.0);
+
+                    //for triangle in triangles {
+                    //    gizmos.line(triangle[0], triangle[1], Color::RED);
+                    //    gizmos.line(triangle[1], triangle[2], Color::RED);
+                    //    gizmos.line(triangle[2], triangle[0], Color::RED);
+                    //}
+
+                    vo.orca_plane(number_of_yaw_samples, number_of_pitch_samples, 0.0)
+                })
+                .chain(other_formations.iter().filter_map(|other_formation| {
+                    FormationVelocityObstacle3D::new_reciprocal(
+                        &formation_agent,
+                        other_formation,
+                        obstacle_avoidance_time_horizon,
+                    )
+                    .orca_plane(
+                        number_of_yaw_samples,
+                        number_of_pitch_samples,
+                        0.0,
+                    )
+                }))
+                .collect::<Vec<_>>();
+
+            let optimal_velocity = if orca_planes.is_empty() {
+                preffered_velocity
+            } else {
+                optimize_velocity_3d(preffered_velocity, maximum_velocity, &orca_planes)
+            };
+
+            let priority =
+                template_priority - scaling.compression_penalty_multiplier * (1.0 - scale);
+            let fitness = priority * optimal_velocity.dot(preffered_velocity);
+
+            FormationCandidate {
+                choice: FormationChoice::Template(index),
+                scale,
+                rotation,
+                velocity: optimal_velocity,
+                fitness,
+                number_of_orca_planes: orca_planes.len(),
+            }
+        };
+
+        #[cfg(feature = "rayon")]
+        let results = template_candidates
+            .par_iter()
+            .map(evaluate_candidate)
+            .collect::<Vec<_>>();
+
+        #[cfg(not(feature = "rayon"))]
+        let results = template_candidates
+            .iter()
+            .map(evaluate_candidate)
+            .collect::<Vec<_>>();
+
+        // The results keep the order of the candidates, so ties are resolved the same way
+        // whether they were evaluated in parallel or not
+        let mut best_index = None;
+        let mut best_fitness = f32::NEG_INFINITY;
+
+        for (index, candidate) in results.iter().enumerate() {
+            if candidate.fitness > best_fitness {
+                best_fitness = candidate.fitness;
+                best_index = Some(index);
+            }
+        }
+
+        // Now evaluate the fitness of the current formation
+        let formation_agent = Agent3D::new(center, preffered_velocity, formation_aabb);
+
+        let obstacles = get_clustered_obstacles(
+            obtacles,
+            &formation_agent.shape,
+            obstacle_avoidance_time_horizon,
+            obstacle_cluster_distance,
+        );
+
+        let orca_planes = obstacles
+            .iter()
+            .filter_map(|obstacle| {
+                FormationVelocityObstacle3D::new(
+                    &formation_agent,
+                    obstacle,
+                    obstacle_avoidance_time_horizon,
+                )
+                .orca_plane(number_of_yaw_samples, number_of_pitch_samples, 0.0)
+            })
+            .chain(other_formations.iter().filter_map(|other_formation| {
+                FormationVelocityObstacle3D::new_reciprocal(
+                    &formation_agent,
+                    other_formation,
+                    obstacle_avoidance_time_horizon,
+                )
+                .orca_plane(number_of_yaw_samples, number_of_pitch_samples, 0.0)
+            }))
+            .collect::<Vec<_>>();
+
+        let optimal_velocity =
+            optimize_velocity_3d(preffered_velocity, maximum_velocity, &orca_planes);
+
+        let formation_templates_ref = templates
+            .iter()
+            .map(|(formation, _, _)| formation.get_positions())
+            .collect::<Vec<_>>();
+
+        let uniform_weights;
+        let weights = match agent_weights {
+            Some(weights) => weights.as_slice(),
+            None => {
+                uniform_weights = vec![1.0; current_formation.len()];
+                uniform_weights.as_slice()
+            }
+        };
+
+        let em_result = match em_warm_start {
+            Some(warm_start) => warm_started_expectation_maximization(
+                current_formation,
+                weights,
+                &formation_templates_ref,
+                warm_start,
+                em_refinement_steps,
+                |_| {},
+            ),
+            None => weighted_expectation_maximization(
+                current_formation,
+                weights,
+                &formation_templates_ref,
+                max_steps_for_em,
+                |_| {},
+            ),
+        };
+
+        let deformation_penalty = match (
+            travel_deformation_penalty_multiplier,
+            preffered_velocity.try_normalize(),
+        ) {
+            (Some(travel_multiplier), Some(direction)) => {
+                let travel_std_dev = em_result.std_deviation_along(direction);
+                let lateral_std_dev = (em_result.std_deviation.powi(2) - travel_std_dev.powi(2))
+                    .max(0.0)
+                    .sqrt();
+
+                travel_multiplier * travel_std_dev
+                    + deformation_penalty_multiplier * lateral_std_dev
+            }
+            _ => deformation_penalty_multiplier * em_result.std_deviation,
+        };
+
+        let ExpectationMaximizationResult {
+            coefficients,
+            std_deviation: std_dev,
+            covariance,
+            ..
+        } = em_result;
+
+        let priority = coefficients
+            .iter()
+            .zip(templates.iter())
+            .map(|(c, (_, _, template_priority))| c * template_priority)
+            .sum::<f32>()
+            - deformation_penalty;
+
+        let fitness = priority * optimal_velocity.dot(preffered_velocity);
+
+        let mut candidates = results;
+        candidates.push(FormationCandidate {
+            choice: FormationChoice::Current,
+            scale: 1.0,
+            // The current formation is already placed in the world, it's only moved to the center
+            rotation: Quat::IDENTITY,
+            velocity: optimal_velocity,
+            fitness,
+            number_of_orca_planes: orca_planes.len(),
+        });
+
+        if best_index.is_none() || fitness > best_fitness + 1e-3 {
+            best_index = Some(candidates.len() - 1);
+        }
+
+        let best = &candidates[best_index.ok_or(CoordinationError::NoFormationFound)?];
+
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            monotonic_counter.formation_candidates = candidates.len() as u64,
+            choice = ?best.choice,
+            fitness = best.fitness,
+            "formation chosen"
+        );
+
+        let formation = match best.choice {
+            FormationChoice::Template(index) => {
+                let mut formation = templates[index].0.clone();
+                formation.scale(best.scale);
+                formation
+            }
+            FormationChoice::Current => Formation::new(
+                current_formation
+                    .iter()
+                    .map(|position| *position - center)
+                    .collect(),
+            ),
+        };
+
+        Ok(FormationDecision {
+            formation,
+            rotation: best.rotation,
+            velocity: best.velocity,
+            choice: best.choice,
+            fitness: best.fitness,
+            em_coefficients: coefficients,
+            std_deviation: std_dev,
+            covariance,
+            candidates,
+        })
+    }
+
+    // Returns the index of the template the positions resemble the most, according to the
+    // coefficients found by expectation maximization. The positions are expected to be relative
+    // to the center of the formation, like the positions of a FormationGroup.
+    pub fn get_closest_template(
+        &self,
+        positions: &[Vec3],
+        max_steps_for_em: usize,
+    ) -> Option<usize> {
+        if self.0.is_empty() || positions.is_empty() {
+            return None;
+        }
+
+        let formation_templates = self
+            .0
+            .iter()
+            .map(|template| template.create_formation(positions.len()))
+            .collect::<Vec<_>>();
+
+        let formation_templates_ref = formation_templates
+            .iter()
+            .map(|e| e.get_positions())
+            .collect::<Vec<_>>();
+
+        expectation_maximization(positions, &formation_templates_ref, max_steps_for_em)
+            .coefficients
+            .iter()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(index, _)| index)
+    }
+
+    // The evaluation as it was before FormationEvaluationParams, it's kept unchanged for
+    // existing callers. Unlike get_best_formation, templates are evaluated in their own frame
+    // only, the current formation is returned in world space and it panics when no formation
+    // is found.
+    #[deprecated(note = "use get_best_formation with FormationEvaluationParams instead")]
+    #[allow(clippy::too_many_arguments, unused_variables)]
     pub fn get_best_formation_and_velocity(
         &self,
         current_formation: &[Vec3],
@@ -167,7 +901,11 @@ impl<'a> FormationTemplateSet<'a> {
                 .map(|e| e.get_positions())
                 .collect::<Vec<_>>();
 
-            let (coefficients, std_dev) = expectation_maximization(
+            let ExpectationMaximizationResult {
+                coefficients,
+                std_deviation: std_dev,
+                ..
+            } = expectation_maximization(
                 current_formation,
                 &formation_templates_ref,
                 max_steps_for_em,
@@ -194,3 +932,314 @@ impl<'a> FormationTemplateSet<'a> {
         (best_form, best_vel)
     }
 }
+
+// The obstacles are only cloned when clustering is enabled
+fn get_clustered_obstacles<'a>(
+    obstacles: &'a [Agent3D],
+    formation_collider: &Collider,
+    time_horizon: f32,
+    cluster_distance: Option<f32>,
+) -> Cow<'a, [Agent3D]> {
+    match cluster_distance {
+        Some(cluster_distance) => Cow::Owned(cluster_obstacles(
+            obstacles,
+            formation_collider,
+            time_horizon,
+            cluster_distance,
+        )),
+        None => Cow::Borrowed(obstacles),
+    }
+}
+
+// Returns the number of obstacles per unit of volume in the sphere the formation can reach
+fn get_obstacle_density(center: Vec3, reach: f32, obstacles: &[Agent3D]) -> f32 {
+    if reach <= 0.0 {
+        return 0.0;
+    }
+
+    let number_of_obstacles = obstacles
+        .iter()
+        .filter(|obstacle| obstacle.position.distance(center) <= reach)
+        .count();
+
+    number_of_obstacles as f32 / (4.0 / 3.0 * PI * reach.powi(3))
+}
+
+// Returns the heading rotation followed by the yaw offsets that should be evaluated,
+// the offsets are spread symmetrically around the heading
+fn get_heading_rotations(heading: Quat, number_of_heading_samples: u16) -> Vec<Quat> {
+    let half = number_of_heading_samples.saturating_sub(1) / 2;
+    let mut rotations = vec![heading];
+
+    for i in 1..=half {
+        let yaw = FRAC_PI_2 * f32::from(i) / f32::from(half);
+
+        rotations.push(heading * Quat::from_rotation_y(yaw));
+        rotations.push(heading * Quat::from_rotation_y(-yaw));
+    }
+
+    rotations
+}
+
+#[cfg(test)]
+mod tests {
+    use geometry::Vec3Operations;
+
+    use crate::formations::{
+        CircleFormation, ColumnFormation, CustomFormation, GridFormation, HelixFormation,
+        LineFormation, OffsetResampling, QueueFormation, SphereFormation, VFormation,
+        WedgeFormation,
+    };
+
+    use super::*;
+
+    // Every template has to create exactly one slot per agent and its AABB has to contain
+    // the agents standing in those slots
+    fn assert_slots_fit_in_aabb(
+        name: &str,
+        template: &dyn FormationTemplate,
+        agent_radius: f32,
+        min_agents: usize,
+    ) {
+        for n_agents in min_agents..40 {
+            let formation = template.create_formation(n_agents);
+
+            assert_eq!(
+                formation.get_positions().len(),
+                n_agents,
+                "{name} has to create a slot for each of {n_agents} agents"
+            );
+
+            if n_agents == 0 {
+                continue;
+            }
+
+            let aabb = template.get_aabb(n_agents);
+            let aabb = Aabb::new(aabb.center, aabb.half_sizes + Vec3::splat(1e-4));
+
+            for position in formation.get_positions() {
+                assert!(
+                    aabb.contains(*position + Vec3::splat(agent_radius))
+                        && aabb.contains(*position - Vec3::splat(agent_radius)),
+                    "The AABB of {name} with {n_agents} agents doesn't contain {position}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_templates_fill_their_slots_inside_their_aabb() {
+        let custom_offsets = vec![
+            Vec3::new(0.0, 0.0, 4.0),
+            Vec3::new(-3.0, 0.0, 0.0),
+            Vec3::new(3.0, 0.0, 0.0),
+            Vec3::new(0.0, 2.0, -1.0),
+        ];
+
+        // The line, circle and queue formations need at least one agent
+        let templates: Vec<(&str, usize, Box<dyn FormationTemplate>)> = vec![
+            ("line", 1, Box::new(LineFormation::new(0.5, 1.0, 1.0))),
+            ("circle", 1, Box::new(CircleFormation::new(0.5, 1.0, 1.0))),
+            ("queue", 1, Box::new(QueueFormation::new(0.5, 1.0, 1.0))),
+            ("v", 0, Box::new(VFormation::new(0.5, 1.0, 1.0))),
+            (
+                "sphere",
+                0,
+                Box::new(SphereFormation::new(0.5, 1.0, 1.2, 1.0)),
+            ),
+            ("wedge", 0, Box::new(WedgeFormation::new(0.5, 1.0, 1.0))),
+            (
+                "grid",
+                0,
+                Box::new(GridFormation::new(0.5, Vec3::new(1.0, 2.0, 0.5), 1.0)),
+            ),
+            ("helix", 0, Box::new(HelixFormation::new(0.5, 1.0, 6, 1.0))),
+            (
+                "column",
+                0,
+                Box::new(ColumnFormation::new(0.5, 1.0, 3, 1.0)),
+            ),
+            (
+                "subsampled custom",
+                0,
+                Box::new(
+                    CustomFormation::from_offsets(custom_offsets.clone(), 0.5, 1.0)
+                        .with_resampling(OffsetResampling::Subsample),
+                ),
+            ),
+            (
+                "interpolated custom",
+                0,
+                Box::new(
+                    CustomFormation::from_offsets(custom_offsets, 0.5, 1.0)
+                        .with_resampling(OffsetResampling::Interpolate),
+                ),
+            ),
+        ];
+
+        for (name, min_agents, template) in &templates {
+            assert_slots_fit_in_aabb(name, template.as_ref(), 0.5, *min_agents);
+        }
+    }
+
+    #[test]
+    fn test_empty_formation_is_an_error() {
+        let template = LineFormation::new(1.0, 1.0, 1.0);
+        let template_set = FormationTemplateSet::from_slice(&[&template as &dyn FormationTemplate]);
+
+        let result = template_set.get_best_formation(
+            &[],
+            Vec3::Z,
+            &[],
+            &[],
+            &FormationEvaluationParams::new(10.0),
+        );
+
+        assert_eq!(result.err(), Some(CoordinationError::EmptyFormation));
+    }
+
+    #[test]
+    fn test_agent_weights_have_to_match_the_agents() {
+        let template = LineFormation::new(1.0, 1.0, 1.0);
+        let template_set = FormationTemplateSet::from_slice(&[&template as &dyn FormationTemplate]);
+        let positions = [Vec3::ZERO, Vec3::X * 3.0];
+
+        let result = template_set.get_best_formation(
+            &positions,
+            Vec3::Z,
+            &[],
+            &[],
+            &FormationEvaluationParams::new(10.0).with_agent_weights(vec![1.0]),
+        );
+
+        assert_eq!(
+            result.err(),
+            Some(CoordinationError::InvalidAgentWeights {
+                agents: 2,
+                weights: 1
+            })
+        );
+
+        let decision = template_set
+            .get_best_formation(
+                &positions,
+                Vec3::Z,
+                &[],
+                &[],
+                &FormationEvaluationParams::new(10.0).with_agent_weights(vec![1.0, 2.0]),
+            )
+            .unwrap();
+
+        assert_eq!(decision.formation.get_positions().len(), 2);
+    }
+
+    #[test]
+    fn test_travel_deformation_penalty() {
+        let template = LineFormation::new(1.0, 1.0, 1.0);
+        let template_set = FormationTemplateSet::from_slice(&[&template as &dyn FormationTemplate]);
+        let positions = [Vec3::Z * -4.0, Vec3::ZERO, Vec3::Z * 4.0];
+        let params = FormationEvaluationParams::new(10.0).with_deformation_penalty_multiplier(10.0);
+
+        let isotropic = template_set
+            .get_best_formation(&positions, Vec3::Z, &[], &[], &params)
+            .unwrap();
+        let lenient = template_set
+            .get_best_formation(
+                &positions,
+                Vec3::Z,
+                &[],
+                &[],
+                &params
+                    .clone()
+                    .with_travel_deformation_penalty_multiplier(0.0),
+            )
+            .unwrap();
+
+        let covariance = isotropic.covariance;
+        let variance = covariance.x_axis.x + covariance.y_axis.y + covariance.z_axis.z;
+        assert!((variance - isotropic.std_deviation.powi(2)).abs() < 1e-3);
+
+        // Ignoring the deviation along the path can only make the current formation better
+        let current_fitness = |decision: &FormationDecision| {
+            decision
+                .candidates
+                .iter()
+                .find(|candidate| candidate.choice == FormationChoice::Current)
+                .unwrap()
+                .fitness
+        };
+        assert!(current_fitness(&lenient) >= current_fitness(&isotropic) - 1e-4);
+    }
+
+    #[test]
+    fn test_current_formation_bounds_are_not_rotated() {
+        let template = LineFormation::new(1.0, 1.0, 1.0);
+        let template_set = FormationTemplateSet::from_slice(&[&template as &dyn FormationTemplate]);
+        let positions = [Vec3::X * -10.0, Vec3::ZERO, Vec3::X * 10.0];
+
+        // The obstacle is next to the formation flying along its length, it would be inside
+        // of the bounds if they were turned towards the heading
+        let obstacle = Agent3D::new(Vec3::Z * 6.0, Vec3::ZERO, Collider::new_sphere(1.0));
+
+        let decision = template_set
+            .get_best_formation(
+                &positions,
+                Vec3::X * 5.0,
+                &[obstacle],
+                &[],
+                &FormationEvaluationParams::new(10.0),
+            )
+            .unwrap();
+
+        let current = decision
+            .candidates
+            .iter()
+            .find(|candidate| candidate.choice == FormationChoice::Current)
+            .unwrap();
+
+        assert!((current.velocity - Vec3::X * 5.0).length() < 1e-3);
+    }
+
+    #[test]
+    fn test_current_formation_keeps_the_agents_in_place() {
+        let template = LineFormation::new(1.0, 10.0, 1.0);
+        let template_set = FormationTemplateSet::from_slice(&[&template as &dyn FormationTemplate]);
+        let positions = [
+            Vec3::new(-1.0, 0.0, 0.0),
+            Vec3::new(1.0, 0.0, 0.5),
+            Vec3::new(0.0, 1.0, -0.5),
+        ];
+
+        // The obstacle is next to the compact current formation, but inside of the wide line
+        let obstacle = Agent3D::new(
+            Vec3::new(6.0, 0.5, 1.5),
+            Vec3::ZERO,
+            Collider::new_sphere(1.0),
+        );
+        let params = FormationEvaluationParams::new(10.0)
+            .with_deformation_penalty_multiplier(0.0)
+            .with_obstacle_avoidance_time_horizon(5.0);
+
+        let decision = template_set
+            .get_best_formation(&positions, Vec3::Z * 5.0, &[obstacle], &[], &params)
+            .unwrap();
+
+        assert_eq!(decision.choice, FormationChoice::Current);
+
+        let center = Formation::new(positions.to_vec()).get_bounds(0.0).center;
+        for (slot, position) in decision.formation.get_positions().iter().zip(positions) {
+            assert!((decision.rotation * *slot + center - position).length() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn test_templates_are_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync + ?Sized>() {}
+
+        assert_send_sync::<dyn FormationTemplate>();
+        assert_send_sync::<FormationTemplateSet>();
+        assert_send_sync::<crate::FormationTemplateRegistry>();
+        assert_send_sync::<FormationDecision>();
+        assert_send_sync::<Formation>();
+    }
+}
@@ -0,0 +1,184 @@
+use bevy_math::Vec3;
+use orca::Agent3D;
+
+use crate::{
+    CoordinationError, FormationDecision, FormationEvaluationParams, FormationTemplate,
+    FormationTemplateSet,
+};
+
+// Owns the formation templates under their keys, so they can be stored in an ECS resource or
+// a component without borrowing them for every evaluation like FormationTemplateSet does.
+// The templates keep the order they were added in, which is the order FormationChoice::Template
+// indexes. Removing a template shifts the indexes of the ones after it.
+pub struct FormationTemplateRegistry<K = String> {
+    entries: Vec<(K, Box<dyn FormationTemplate>)>,
+}
+
+impl<K> Default for FormationTemplateRegistry<K> {
+    fn default() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+}
+
+impl<K: PartialEq> FormationTemplateRegistry<K> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_template(mut self, key: K, template: impl FormationTemplate + 'static) -> Self {
+        self.add(key, Box::new(template));
+        self
+    }
+
+    // Adds the template under the key
+    // A template already registered under the key is replaced in place, keeping its index.
+    // Returns: The replaced template
+    pub fn add(
+        &mut self,
+        key: K,
+        template: Box<dyn FormationTemplate>,
+    ) -> Option<Box<dyn FormationTemplate>> {
+        match self.index_of(&key) {
+            Some(index) => Some(std::mem::replace(&mut self.entries[index].1, template)),
+            None => {
+                self.entries.push((key, template));
+                None
+            }
+        }
+    }
+
+    // Removes the template registered under the key
+    // Returns: The removed template
+    pub fn remove(&mut self, key: &K) -> Option<Box<dyn FormationTemplate>> {
+        let index = self.index_of(key)?;
+
+        Some(self.entries.remove(index).1)
+    }
+
+    pub fn get(&self, key: &K) -> Option<&dyn FormationTemplate> {
+        self.index_of(key)
+            .map(|index| self.entries[index].1.as_ref())
+    }
+
+    // The index of the template in FormationChoice::Template
+    pub fn index_of(&self, key: &K) -> Option<usize> {
+        self.entries.iter().position(|(other, _)| other == key)
+    }
+
+    // The key of the template at the index of FormationChoice::Template
+    pub fn key(&self, index: usize) -> Option<&K> {
+        self.entries.get(index).map(|(key, _)| key)
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &K> {
+        self.entries.iter().map(|(key, _)| key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // Borrows the templates for a single evaluation
+    pub fn template_set(&self) -> FormationTemplateSet<'_> {
+        self.entries
+            .iter()
+            .map(|(_, template)| template.as_ref())
+            .collect()
+    }
+
+    // See FormationTemplateSet::get_best_formation
+    pub fn get_best_formation(
+        &self,
+        current_formation: &[Vec3],
+        preffered_velocity: Vec3,
+        obtacles: &[Agent3D],
+        other_formations: &[Agent3D],
+        params: &FormationEvaluationParams,
+    ) -> Result<FormationDecision, CoordinationError> {
+        self.template_set().get_best_formation(
+            current_formation,
+            preffered_velocity,
+            obtacles,
+            other_formations,
+            params,
+        )
+    }
+
+    // See FormationTemplateSet::get_closest_template
+    // Returns: The key of the closest template
+    pub fn get_closest_template(&self, positions: &[Vec3], max_steps_for_em: usize) -> Option<&K> {
+        self.template_set()
+            .get_closest_template(positions, max_steps_for_em)
+            .and_then(|index| self.key(index))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        formations::{CircleFormation, LineFormation},
+        FormationChoice,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_add_and_remove() {
+        let mut registry = FormationTemplateRegistry::new()
+            .with_template("line", LineFormation::new(1.0, 1.0, 1.0))
+            .with_template("circle", CircleFormation::new(1.0, 1.0, 2.0));
+
+        assert_eq!(registry.len(), 2);
+        assert_eq!(registry.index_of(&"circle"), Some(1));
+
+        let replaced = registry.add("line", Box::new(LineFormation::new(1.0, 1.0, 3.0)));
+        assert_eq!(replaced.map(|template| template.get_priority()), Some(1.0));
+        assert_eq!(registry.get(&"line").unwrap().get_priority(), 3.0);
+        assert_eq!(registry.index_of(&"line"), Some(0));
+
+        assert!(registry.remove(&"line").is_some());
+        assert!(registry.remove(&"line").is_none());
+        assert_eq!(registry.keys().collect::<Vec<_>>(), vec![&"circle"]);
+        assert_eq!(registry.key(0), Some(&"circle"));
+    }
+
+    #[test]
+    fn test_evaluation() {
+        let registry = FormationTemplateRegistry::new()
+            .with_template("line".to_string(), LineFormation::new(1.0, 1.0, 1.0));
+        let positions = [Vec3::ZERO, Vec3::X * 3.0];
+
+        let decision = registry
+            .get_best_formation(
+                &positions,
+                Vec3::Z,
+                &[],
+                &[],
+                &FormationEvaluationParams::new(10.0),
+            )
+            .unwrap();
+
+        if let FormationChoice::Template(index) = decision.choice {
+            assert_eq!(registry.key(index).map(String::as_str), Some("line"));
+        }
+
+        assert_eq!(
+            registry
+                .get_best_formation(
+                    &[],
+                    Vec3::Z,
+                    &[],
+                    &[],
+                    &FormationEvaluationParams::new(10.0)
+                )
+                .err(),
+            Some(CoordinationError::EmptyFormation)
+        );
+    }
+}
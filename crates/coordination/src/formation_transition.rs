@@ -0,0 +1,140 @@
+use std::collections::HashMap;
+
+use bevy_math::Vec3;
+
+use crate::Formation;
+
+// Interpolates the target positions of agents switching from one formation to another,
+// so they don't snap to their newly assigned slots.
+//
+// Every agent moves along a straight line with a smooth start and stop. With staggering, agents
+// don't move all at once: agents with the longest way to go start first, agents with the shortest
+// way last, which reduces the number of agents crossing each other at the same time.
+#[derive(Clone, Debug)]
+pub struct FormationTransition {
+    from: Vec<Vec3>,
+    to: Vec<Vec3>,
+    start_times: Vec<f32>,
+    move_duration: f32,
+    duration: f32,
+}
+
+impl FormationTransition {
+    // source: The formation the agents are currently in, indexed by agent
+    // destination: The formation the agents are moving to, indexed by slot
+    // assignment: Agent indexes mapped to the destination slots, agents without a slot stay in place
+    // duration: The time it takes to finish the whole transition
+    // stagger: Portion of the duration over which the starts of the agents are spread, from 0 to 1
+    pub fn new(
+        source: &Formation,
+        destination: &Formation,
+        assignment: &HashMap<usize, usize>,
+        duration: f32,
+        stagger: f32,
+    ) -> Self {
+        assert!(duration >= 0.0);
+        assert!((0.0..1.0).contains(&stagger));
+
+        let from = source.get_positions().to_vec();
+        let to = from
+            .iter()
+            .enumerate()
+            .map(|(i, position)| {
+                assignment
+                    .get(&i)
+                    .map_or(*position, |&slot| destination.get_positions()[slot])
+            })
+            .collect::<Vec<_>>();
+
+        let mut order = (0..from.len()).collect::<Vec<_>>();
+        order.sort_by(|&a, &b| {
+            let a = from[a].distance_squared(to[a]);
+            let b = from[b].distance_squared(to[b]);
+
+            b.total_cmp(&a)
+        });
+
+        let stagger_duration = duration * stagger;
+        let mut start_times = vec![0.0; from.len()];
+
+        for (rank, &agent) in order.iter().enumerate() {
+            start_times[agent] = if from.len() > 1 {
+                stagger_duration * rank as f32 / (from.len() - 1) as f32
+            } else {
+                0.0
+            };
+        }
+
+        Self {
+            from,
+            to,
+            start_times,
+            move_duration: duration - stagger_duration,
+            duration,
+        }
+    }
+
+    pub fn get_duration(&self) -> f32 {
+        self.duration
+    }
+
+    pub fn is_finished(&self, time: f32) -> bool {
+        time >= self.duration
+    }
+
+    // Returns the target position of every agent at the given time since the start of the transition
+    pub fn get_positions(&self, time: f32) -> Vec<Vec3> {
+        self.from
+            .iter()
+            .zip(self.to.iter())
+            .zip(self.start_times.iter())
+            .map(|((from, to), start_time)| {
+                let t = if self.move_duration > 0.0 {
+                    ((time - start_time) / self.move_duration).clamp(0.0, 1.0)
+                } else if time >= *start_time {
+                    1.0
+                } else {
+                    0.0
+                };
+
+                // Smoothstep, the agents start and stop without a jump in velocity
+                from.lerp(*to, t * t * (3.0 - 2.0 * t))
+            })
+            .collect()
+    }
+
+    pub fn get_formation(&self, time: f32) -> Formation {
+        Formation::new(self.get_positions(time))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_formation_transition() {
+        let source = Formation::new(vec![
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(10.0, 0.0, 0.0),
+            Vec3::new(20.0, 0.0, 0.0),
+        ]);
+        let destination = Formation::new(vec![
+            Vec3::new(0.0, 0.0, 10.0),
+            Vec3::new(0.0, 0.0, 20.0),
+            Vec3::new(0.0, 0.0, 30.0),
+        ]);
+        let assignment = HashMap::from([(0, 0), (1, 1), (2, 2)]);
+
+        let transition = FormationTransition::new(&source, &destination, &assignment, 2.0, 0.5);
+
+        assert_eq!(transition.get_positions(0.0), source.get_positions());
+        assert_eq!(transition.get_positions(2.0), destination.get_positions());
+        assert!(transition.is_finished(2.0));
+
+        // The agent with the shortest way to go hasn't started yet
+        let halfway = transition.get_positions(0.5);
+        assert_eq!(halfway[0], source.get_positions()[0]);
+        assert_ne!(halfway[2], source.get_positions()[2]);
+    }
+}
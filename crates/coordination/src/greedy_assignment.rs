@@ -0,0 +1,105 @@
+use crate::{AssignmentCost, CoordinationError};
+
+// Approximates the linear assignment problem by repeatedly taking the cheapest pair
+// whose row and column are both still free.
+//
+// It runs in O(n * m * log(n * m)) and is meant for very large groups where the exact solvers
+// are too slow. The result is not optimal, when the costs are distances in a metric space the
+// total cost is at most O(n^0.585) times the optimum (Reingold & Tarjan, 1981). No such bound holds
+// for squared distances, in practice it stays close to the optimum for formations whose agents are
+// already close to their slots and degrades when agents have to cross the formation.
+// Pairs with a cost of f32::INFINITY are forbidden. Unlike the exact solvers it can fail
+// when the greedy choices use up the only allowed columns of a row, even if a perfect matching exists.
+//
+// cost: The cost of assigning a row to a column, read once per pair so it doesn't have to be
+//       stored in a matrix
+// Returns: A vector of (row, column, cost) tuples, or NoPerfectMatching when some row is left
+//          without a column
+pub fn greedy_assignment<C: AssignmentCost + ?Sized>(
+    cost: &C,
+) -> Result<Vec<(usize, usize, f32)>, CoordinationError> {
+    let rows = cost.rows();
+
+    if rows == 0 {
+        return Ok(Vec::new());
+    }
+
+    let cols = cost.columns();
+
+    assert!(
+        rows <= cols,
+        "The number of rows must be less than or equal to the number of columns"
+    );
+
+    let mut pairs = (0..rows)
+        .flat_map(|row| (0..cols).map(move |col| (row, col, cost.cost(row, col))))
+        .filter(|(_, _, cost)| *cost != f32::INFINITY)
+        .collect::<Vec<_>>();
+
+    pairs.sort_by(|(_, _, a), (_, _, b)| a.total_cmp(b));
+
+    let mut col_of_row = vec![None; rows];
+    let mut is_col_taken = vec![false; cols];
+    let mut assigned = 0;
+
+    for (row, col, cost) in pairs {
+        if col_of_row[row].is_some() || is_col_taken[col] {
+            continue;
+        }
+
+        col_of_row[row] = Some((col, cost));
+        is_col_taken[col] = true;
+        assigned += 1;
+
+        if assigned == rows {
+            break;
+        }
+    }
+
+    if assigned < rows {
+        return Err(CoordinationError::NoPerfectMatching);
+    }
+
+    Ok(col_of_row
+        .iter()
+        .enumerate()
+        .filter_map(|(row, pair)| pair.map(|(col, cost)| (row, col, cost)))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_greedy_assignment() {
+        let cost = vec![
+            vec![8.0, 5.0, 9.0],
+            vec![4.0, 2.0, 4.0],
+            vec![7.0, 3.0, 8.0],
+        ];
+
+        let result = greedy_assignment(&cost).unwrap();
+
+        // The greedy choice of (1, 1) forces a worse assignment than the optimal 15.0
+        assert_eq!(result, vec![(0, 2, 9.0), (1, 1, 2.0), (2, 0, 7.0)]);
+    }
+    #[test]
+    fn test_greedy_assignment_forbidden_pairs() {
+        let cost = vec![vec![1.0, f32::INFINITY], vec![2.0, 3.0]];
+
+        // The cheapest pair (0, 0) is allowed, (1, 1) is the only column left for row 1
+        assert_eq!(
+            greedy_assignment(&cost).unwrap(),
+            vec![(0, 0, 1.0), (1, 1, 3.0)]
+        );
+
+        // Row 1 takes column 0 first and row 0 has no allowed column left
+        let cost = vec![vec![2.0, f32::INFINITY], vec![1.0, 3.0]];
+
+        assert_eq!(
+            greedy_assignment(&cost),
+            Err(CoordinationError::NoPerfectMatching)
+        );
+    }
+}
@@ -0,0 +1,113 @@
+use bevy_math::Vec3;
+use geometry::Aabb;
+
+use crate::{Formation, FormationTemplate};
+
+// An N x M x K lattice of agents. The dimensions are picked automatically
+// so the lattice is as close to a cube as the number of agents allows.
+pub struct GridFormation {
+    priority: f32,
+    spacing: Vec3,
+    agent_radius: f32,
+}
+
+impl GridFormation {
+    pub fn new(agent_radius: f32, spacing: Vec3, priority: f32) -> Self {
+        Self {
+            priority,
+            spacing: spacing.max(Vec3::ZERO),
+            agent_radius,
+        }
+    }
+
+    // Returns the number of agents along the x, y and z axis
+    pub fn get_dimensions(n_agents: usize) -> (usize, usize, usize) {
+        if n_agents == 0 {
+            return (0, 0, 0);
+        }
+
+        let x = (n_agents as f32).cbrt().ceil() as usize;
+        let remaining = n_agents.div_ceil(x);
+        let z = (remaining as f32).sqrt().ceil() as usize;
+        let y = remaining.div_ceil(z);
+
+        (x, y, z)
+    }
+}
+
+impl FormationTemplate for GridFormation {
+    fn get_priority(&self) -> f32 {
+        self.priority
+    }
+
+    fn create_formation(&self, n_agents: usize) -> Formation {
+        if n_agents == 0 {
+            return Formation::new(Vec::new());
+        }
+
+        let (size_x, size_y, size_z) = Self::get_dimensions(n_agents);
+        let separation = self.spacing + Vec3::splat(2.0 * self.agent_radius);
+        let offset = Vec3::new(
+            (size_x - 1) as f32,
+            (size_y - 1) as f32,
+            (size_z - 1) as f32,
+        ) / 2.0;
+
+        // Layers are filled from the bottom, rows within a layer from the front
+        let positions = (0..n_agents)
+            .map(|i| {
+                let x = i % size_x;
+                let z = (i / size_x) % size_z;
+                let y = i / (size_x * size_z);
+
+                (Vec3::new(x as f32, y as f32, z as f32) - offset) * separation
+            })
+            .collect();
+
+        Formation::new(positions)
+    }
+
+    fn get_aabb(&self, n_agents: usize) -> Aabb {
+        if n_agents == 0 {
+            return Aabb::new(Vec3::ZERO, Vec3::ZERO);
+        }
+
+        // The last layer doesn't have to be full, the bounds are taken from the actual positions
+        self.create_formation(n_agents)
+            .get_bounds(self.agent_radius)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dimensions() {
+        assert_eq!(GridFormation::get_dimensions(0), (0, 0, 0));
+        assert_eq!(GridFormation::get_dimensions(1), (1, 1, 1));
+        assert_eq!(GridFormation::get_dimensions(8), (2, 2, 2));
+        assert_eq!(GridFormation::get_dimensions(27), (3, 3, 3));
+
+        for n_agents in 1..100 {
+            let (x, y, z) = GridFormation::get_dimensions(n_agents);
+            assert!(x * y * z >= n_agents);
+        }
+    }
+
+    #[test]
+    fn test_neighbors_are_spaced_along_each_axis() {
+        let positions = GridFormation::new(0.5, Vec3::new(1.0, 2.0, 0.5), 1.0)
+            .create_formation(27)
+            .get_positions()
+            .to_vec();
+
+        // The spacing is added to the diameter of an agent along every axis
+        assert!((positions[1] - positions[0]).distance(Vec3::new(2.0, 0.0, 0.0)) < 1e-5);
+        assert!((positions[3] - positions[0]).distance(Vec3::new(0.0, 0.0, 1.5)) < 1e-5);
+        assert!((positions[9] - positions[0]).distance(Vec3::new(0.0, 3.0, 0.0)) < 1e-5);
+
+        // The full lattice is centered in the origin
+        assert!(positions.iter().sum::<Vec3>().length() < 1e-4);
+    }
+}
@@ -0,0 +1,110 @@
+use std::f32::consts::{PI, TAU};
+
+use bevy_math::Vec3;
+use geometry::Aabb;
+
+use crate::{Formation, FormationTemplate};
+
+// Agents spiral around the travel axis (z), one full turn is made every
+// agents_per_turn agents and the turns are one separation apart.
+pub struct HelixFormation {
+    priority: f32,
+    spacing: f32,
+    agent_radius: f32,
+    agents_per_turn: usize,
+}
+
+impl HelixFormation {
+    // Fewer than three agents per turn don't make a helix, agents_per_turn is clamped to three
+    pub fn new(agent_radius: f32, spacing: f32, agents_per_turn: usize, priority: f32) -> Self {
+        Self {
+            priority,
+            spacing: spacing.max(0.0),
+            agent_radius,
+            agents_per_turn: agents_per_turn.max(3),
+        }
+    }
+
+    fn get_separation(&self) -> f32 {
+        self.spacing + 2.0 * self.agent_radius
+    }
+
+    // The radius at which neighboring agents on the helix are one separation apart
+    fn get_radius(&self) -> f32 {
+        self.get_separation() / (2.0 * (PI / self.agents_per_turn as f32).sin())
+    }
+}
+
+impl FormationTemplate for HelixFormation {
+    fn get_priority(&self) -> f32 {
+        self.priority
+    }
+
+    fn create_formation(&self, n_agents: usize) -> Formation {
+        if n_agents == 0 {
+            return Formation::new(Vec::new());
+        }
+
+        let radius = self.get_radius();
+        let step_along_axis = self.get_separation() / self.agents_per_turn as f32;
+        let angle_increment = TAU / self.agents_per_turn as f32;
+        let center = (n_agents - 1) as f32 * step_along_axis / 2.0;
+
+        let positions = (0..n_agents)
+            .map(|i| {
+                let angle = angle_increment * i as f32;
+                let z = center - i as f32 * step_along_axis;
+
+                Vec3::new(angle.cos() * radius, angle.sin() * radius, z)
+            })
+            .collect();
+
+        Formation::new(positions)
+    }
+
+    fn get_aabb(&self, n_agents: usize) -> Aabb {
+        if n_agents == 0 {
+            return Aabb::new(Vec3::ZERO, Vec3::ZERO);
+        }
+
+        // The helix doesn't have to make a full turn, the bounds are taken from the actual positions
+        self.create_formation(n_agents)
+            .get_bounds(self.agent_radius)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_neighbors_are_one_separation_apart() {
+        let positions = HelixFormation::new(0.5, 1.0, 1, 1.0)
+            .create_formation(10)
+            .get_positions()
+            .to_vec();
+
+        // The number of agents per turn was clamped to three
+        assert!((positions[0].z - positions[3].z - 2.0).abs() < 1e-4);
+
+        for pair in positions.windows(2) {
+            assert!(pair[0].distance(pair[1]) >= 2.0 - 1e-4);
+        }
+    }
+
+    #[test]
+    fn test_pitch_is_one_separation() {
+        let formation = HelixFormation::new(0.5, 1.0, 6, 1.0);
+        let positions = formation.create_formation(20).get_positions().to_vec();
+        let radius = formation.get_radius();
+
+        for position in &positions {
+            assert!((position.truncate().length() - radius).abs() < 1e-4);
+        }
+
+        // After a full turn an agent is right behind the agent in front of it
+        for (front, back) in positions.iter().zip(&positions[6..]) {
+            assert!((*front - *back).distance(Vec3::Z * 2.0) < 1e-4);
+        }
+    }
+}
@@ -1,6 +1,19 @@
-pub fn hungarian(cost: &[&[f32]]) -> Vec<(usize, usize, f32)> {
-    let j = cost.len();
-    let w = cost[0].len();
+use crate::{AssignmentCost, CoordinationError};
+
+// Solves the linear assignment problem with the Hungarian method
+//
+// Rows are assigned to columns, the number of rows must be less than or equal to the number of
+// columns. Pairs with a cost of f32::INFINITY are forbidden and never assigned, which is more
+// reliable than modelling them with large finite costs that swallow the other costs in the duals.
+//
+// cost: The cost of assigning a row to a column, read many times so it should be cheap
+// Returns: A vector of (row, column, cost) tuples, or NoPerfectMatching when the forbidden pairs
+//          leave some row without a column
+pub fn hungarian<C: AssignmentCost + ?Sized>(
+    cost: &C,
+) -> Result<Vec<(usize, usize, f32)>, CoordinationError> {
+    let j = cost.rows();
+    let w = cost.columns();
 
     assert!(
         j <= w,
@@ -27,7 +40,7 @@ pub fn hungarian(cost: &[&[f32]]) -> Vec<(usize, usize, f32)> {
 
             for w_ in 0..w {
                 if !in_z[w_] {
-                    let diff = cost[j][w_] - ys[j] - yt[w_];
+                    let diff = cost.cost(j, w_) - ys[j] - yt[w_];
                     if diff < min_to[w_] {
                         min_to[w_] = diff;
                         prv[w_] = Some(w_curr);
@@ -39,6 +52,12 @@ pub fn hungarian(cost: &[&[f32]]) -> Vec<(usize, usize, f32)> {
                 }
             }
 
+            // Only forbidden pairs lead out of the visited rows, so there is no augmenting path
+            // and the row can't be assigned without leaving another one unassigned
+            if delta == f32::INFINITY {
+                return Err(CoordinationError::NoPerfectMatching);
+            }
+
             for w_ in 0..=w {
                 if in_z[w_] {
                     if let Some(j_) = job[w_] {
@@ -69,13 +88,16 @@ pub fn hungarian(cost: &[&[f32]]) -> Vec<(usize, usize, f32)> {
         }
     }
 
-    let result = job[..j]
+    // job is indexed by the worker (column) and holds the job (row) assigned to it
+    let mut result = job[..w]
         .iter()
         .enumerate()
-        .filter_map(|(j, w)| w.as_ref().map(|w| (j, *w, cost[j][*w])))
-        .collect();
+        .filter_map(|(column, row)| row.map(|row| (row, column, cost.cost(row, column))))
+        .collect::<Vec<_>>();
+
+    result.sort_by_key(|(row, _, _)| *row);
 
-    result
+    Ok(result)
 }
 
 #[cfg(test)]
@@ -90,8 +112,43 @@ mod tests {
             vec![7.0, 3.0, 8.0],
         ];
 
-        let result = hungarian(&cost.iter().map(|row| row.as_slice()).collect::<Vec<_>>());
+        let result = hungarian(&cost).unwrap();
 
         assert_eq!(result, vec![(0, 0, 8.0), (1, 2, 4.0), (2, 1, 3.0)]);
     }
+
+    #[test]
+    fn test_hungarian_more_columns_than_rows() {
+        let cost = vec![vec![5.0, 9.0, 1.0], vec![2.0, 6.0, 7.0]];
+
+        let result = hungarian(&cost).unwrap();
+
+        assert_eq!(result, vec![(0, 2, 1.0), (1, 0, 2.0)]);
+    }
+
+    #[test]
+    fn test_hungarian_forbidden_pairs() {
+        let cost = vec![
+            vec![1.0, f32::INFINITY, 9.0],
+            vec![f32::INFINITY, f32::INFINITY, 4.0],
+            vec![2.0, 3.0, f32::INFINITY],
+        ];
+
+        let result = hungarian(&cost).unwrap();
+
+        assert_eq!(result, vec![(0, 0, 1.0), (1, 2, 4.0), (2, 1, 3.0)]);
+    }
+
+    #[test]
+    fn test_hungarian_no_perfect_matching() {
+        let cost = vec![
+            vec![1.0, f32::INFINITY, f32::INFINITY],
+            vec![2.0, f32::INFINITY, f32::INFINITY],
+            vec![3.0, 4.0, 5.0],
+        ];
+
+        let result = hungarian(&cost);
+
+        assert_eq!(result, Err(CoordinationError::NoPerfectMatching));
+    }
 }
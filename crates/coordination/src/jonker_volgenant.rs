@@ -0,0 +1,229 @@
+use crate::{AssignmentCost, CoordinationError};
+
+// Solves the linear assignment problem with the Jonker-Volgenant shortest augmenting path method.
+//
+// Rows are assigned to columns, the number of rows must be less than or equal to the number of
+// columns. For square problems the duals are initialized by column reduction, which already assigns
+// most of the rows, the remaining ones are assigned by Dijkstra-like shortest augmenting paths.
+// All buffers are allocated once, which makes it considerably faster than `hungarian`
+// for large problems while producing an assignment with the same (optimal) total cost.
+// Pairs with a cost of f32::INFINITY are forbidden, the same as in `hungarian`.
+//
+// cost: The cost of assigning a row to a column, read many times so it should be cheap
+// Returns: A vector of (row, column, cost) tuples, or NoPerfectMatching when the forbidden pairs
+//          leave some row without a column
+pub fn jonker_volgenant<C: AssignmentCost + ?Sized>(
+    cost: &C,
+) -> Result<Vec<(usize, usize, f32)>, CoordinationError> {
+    let rows = cost.rows();
+
+    if rows == 0 {
+        return Ok(Vec::new());
+    }
+
+    let cols = cost.columns();
+
+    assert!(
+        rows <= cols,
+        "The number of rows must be less than or equal to the number of columns"
+    );
+
+    let mut u = vec![0.0; rows];
+    let mut v = vec![0.0; cols];
+    let mut col_of_row = vec![None; rows];
+    let mut row_of_col = vec![None; cols];
+
+    // Column reduction, every column's dual is its smallest cost and the row with
+    // that cost takes the column if it is still free
+    if rows == cols {
+        for col in (0..cols).rev() {
+            let (row, min) = (0..rows)
+                .map(|row| (row, cost.cost(row, col)))
+                .min_by(|(_, a), (_, b)| a.total_cmp(b))
+                .expect("The cost matrix can't be empty");
+
+            // Every column of a square problem has to be assigned
+            if min == f32::INFINITY {
+                return Err(CoordinationError::NoPerfectMatching);
+            }
+
+            v[col] = min;
+
+            if col_of_row[row].is_none() {
+                col_of_row[row] = Some(col);
+                row_of_col[col] = Some(row);
+            }
+        }
+    }
+
+    let mut path = vec![0; cols];
+    let mut shortest_path_costs = vec![f32::INFINITY; cols];
+    let mut remaining = Vec::with_capacity(cols);
+    let mut visited_rows = vec![false; rows];
+    let mut visited_cols = vec![false; cols];
+
+    for current_row in 0..rows {
+        if col_of_row[current_row].is_some() {
+            continue;
+        }
+
+        shortest_path_costs.fill(f32::INFINITY);
+        visited_rows.fill(false);
+        visited_cols.fill(false);
+        remaining.clear();
+        remaining.extend((0..cols).rev());
+
+        let mut min_value = 0.0;
+        let mut row = current_row;
+
+        // Grow the shortest path tree until a free column is reached
+        let sink = loop {
+            visited_rows[row] = true;
+
+            let mut lowest = f32::INFINITY;
+            let mut lowest_index = 0;
+
+            for (index, &col) in remaining.iter().enumerate() {
+                let reduced_cost = min_value + cost.cost(row, col) - u[row] - v[col];
+
+                if reduced_cost < shortest_path_costs[col] {
+                    path[col] = row;
+                    shortest_path_costs[col] = reduced_cost;
+                }
+
+                if shortest_path_costs[col] < lowest
+                    || (shortest_path_costs[col] == lowest && row_of_col[col].is_none())
+                {
+                    lowest = shortest_path_costs[col];
+                    lowest_index = index;
+                }
+            }
+
+            // Only forbidden pairs lead to the remaining columns
+            if lowest == f32::INFINITY {
+                return Err(CoordinationError::NoPerfectMatching);
+            }
+
+            min_value = lowest;
+
+            let col = remaining.swap_remove(lowest_index);
+            visited_cols[col] = true;
+
+            match row_of_col[col] {
+                Some(next_row) => row = next_row,
+                None => break col,
+            }
+        };
+
+        // Update the duals so the reduced costs stay non-negative
+        u[current_row] += min_value;
+
+        for i in 0..rows {
+            if visited_rows[i] && i != current_row {
+                let col = col_of_row[i].expect("Visited rows are always assigned");
+
+                u[i] += min_value - shortest_path_costs[col];
+            }
+        }
+
+        for col in 0..cols {
+            if visited_cols[col] {
+                v[col] -= min_value - shortest_path_costs[col];
+            }
+        }
+
+        // Augment the assignment along the found path
+        let mut col = sink;
+
+        loop {
+            let row = path[col];
+            row_of_col[col] = Some(row);
+
+            let previous = col_of_row[row].replace(col);
+
+            if row == current_row {
+                break;
+            }
+
+            col = previous.expect("Rows on the augmenting path are always assigned");
+        }
+    }
+
+    Ok(col_of_row
+        .iter()
+        .enumerate()
+        .filter_map(|(row, col)| col.map(|col| (row, col, cost.cost(row, col))))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use crate::hungarian::hungarian;
+
+    use super::*;
+
+    fn total_cost(result: &[(usize, usize, f32)]) -> f32 {
+        result.iter().map(|(_, _, cost)| cost).sum()
+    }
+
+    #[test]
+    fn test_jonker_volgenant() {
+        let cost = vec![
+            vec![8.0, 5.0, 9.0],
+            vec![4.0, 2.0, 4.0],
+            vec![7.0, 3.0, 8.0],
+        ];
+
+        let result = jonker_volgenant(&cost).unwrap();
+
+        assert_eq!(result, vec![(0, 0, 8.0), (1, 2, 4.0), (2, 1, 3.0)]);
+    }
+
+    #[test]
+    fn test_jonker_volgenant_forbidden_pairs() {
+        let cost = vec![
+            vec![1.0, f32::INFINITY, 9.0],
+            vec![f32::INFINITY, f32::INFINITY, 4.0],
+            vec![2.0, 3.0, f32::INFINITY],
+        ];
+
+        assert_eq!(
+            jonker_volgenant(&cost).unwrap(),
+            vec![(0, 0, 1.0), (1, 2, 4.0), (2, 1, 3.0)]
+        );
+
+        let cost = vec![
+            vec![1.0, f32::INFINITY, f32::INFINITY, 2.0],
+            vec![2.0, f32::INFINITY, f32::INFINITY, 3.0],
+            vec![3.0, f32::INFINITY, f32::INFINITY, 1.0],
+        ];
+
+        assert_eq!(
+            jonker_volgenant(&cost),
+            Err(CoordinationError::NoPerfectMatching)
+        );
+    }
+
+    #[test]
+    fn test_jonker_volgenant_matches_hungarian() {
+        let mut rng = rand::thread_rng();
+
+        for (rows, cols) in [(1, 1), (5, 5), (20, 20), (7, 12), (30, 30)] {
+            let cost = (0..rows)
+                .map(|_| {
+                    (0..cols)
+                        .map(|_| rng.gen_range(0.0..100.0))
+                        .collect::<Vec<f32>>()
+                })
+                .collect::<Vec<_>>();
+
+            let expected = hungarian(&cost).unwrap();
+            let result = jonker_volgenant(&cost).unwrap();
+
+            assert_eq!(result.len(), rows);
+            approx::assert_relative_eq!(total_cost(&result), total_cost(&expected), epsilon = 1e-2);
+        }
+    }
+}
@@ -1,8 +1,13 @@
 use bevy_math::Vec3;
 use geometry::Matrix;
 
+use crate::CoordinationError;
+
 #[allow(dead_code)]
-pub fn least_squares(measured_values: &[Vec3], independent_variables: &[&[Vec3]]) -> Matrix {
+pub fn least_squares(
+    measured_values: &[Vec3],
+    independent_variables: &[&[Vec3]],
+) -> Result<Matrix, CoordinationError> {
     let f_matrix = Matrix::new(
         measured_values
             .iter()
@@ -40,19 +45,22 @@ pub fn least_squares(measured_values: &[Vec3], independent_variables: &[&[Vec3]]
 
     let a = t_transposed
         .mul_left(&t_matrix)
-        .expect("Unable to multiple transposed matrix with original matrix");
+        .ok_or(CoordinationError::MatrixDecomposition)?;
 
-    let a_inv = a.pseudoinverse();
+    let a_inv = a
+        .pseudoinverse()
+        .ok_or(CoordinationError::MatrixDecomposition)?;
 
     let b = a_inv
         .mul_left(&t_transposed)
-        .expect("Unable to multiple inverse matrix with transposed matrix");
+        .ok_or(CoordinationError::MatrixDecomposition)?;
 
     b.mul_left(&f_matrix)
-        .expect("Unable to multiple b matrix with f matrix")
+        .ok_or(CoordinationError::MatrixDecomposition)
 }
 
 #[cfg(test)]
+#[allow(non_upper_case_globals)]
 mod tests {
     use super::*;
     use approx::relative_eq;
@@ -104,7 +112,8 @@ mod tests {
                 .map(|v| v.as_slice())
                 .collect::<Vec<_>>()
                 .as_slice(),
-        );
+        )
+        .unwrap();
 
         relative_eq!(result.get(0, 0).unwrap(), 1.7);
         relative_eq!(result.get(1, 0).unwrap(), 4.1);
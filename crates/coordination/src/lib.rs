@@ -1,20 +1,64 @@
+pub mod allocation;
+mod assignment;
+mod cached_formation;
 mod circle_formation;
+mod column_formation;
+mod custom_formation;
+mod deformation;
+mod error;
 mod expectation_maximization;
 mod formation;
+#[cfg(feature = "serde")]
+mod formation_loader;
+mod formation_path_follower;
+mod formation_split;
 mod formation_template;
+mod formation_template_registry;
+mod formation_transition;
+mod greedy_assignment;
+mod grid_formation;
+mod helix_formation;
 mod hungarian;
+mod jonker_volgenant;
 mod least_squares;
 mod line_formation;
+mod priority_provider;
 mod queue_formation;
+mod sphere_formation;
 mod v_formation;
+mod virtual_structure;
+mod wedge_formation;
 
-pub use expectation_maximization::best_matching_indexes;
+pub use assignment::*;
+pub use cached_formation::*;
+pub use deformation::*;
+pub use error::*;
+pub use expectation_maximization::{
+    best_matching, best_matching_indexes, best_matching_indexes_with_solver, constrained_matching,
+    expectation_maximization, expectation_maximization_with_trace, sticky_matching_indexes,
+    warm_started_expectation_maximization, weighted_expectation_maximization,
+    ExpectationMaximizationResult, ExpectationMaximizationStep, ExpectationMaximizationWarmStart,
+};
 pub use formation::*;
+#[cfg(feature = "serde")]
+pub use formation_loader::*;
+pub use formation_path_follower::*;
+pub use formation_split::*;
 pub use formation_template::*;
+pub use formation_template_registry::*;
+pub use formation_transition::*;
+pub use priority_provider::*;
+pub use virtual_structure::*;
 
 pub mod formations {
     pub use crate::circle_formation::CircleFormation;
+    pub use crate::column_formation::ColumnFormation;
+    pub use crate::custom_formation::{CustomFormation, OffsetResampling};
+    pub use crate::grid_formation::GridFormation;
+    pub use crate::helix_formation::HelixFormation;
     pub use crate::line_formation::LineFormation;
     pub use crate::queue_formation::QueueFormation;
+    pub use crate::sphere_formation::SphereFormation;
     pub use crate::v_formation::VFormation;
+    pub use crate::wedge_formation::WedgeFormation;
 }
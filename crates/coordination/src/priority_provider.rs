@@ -0,0 +1,115 @@
+use bevy_math::{Quat, Vec3};
+use geometry::Aabb;
+use orca::Agent3D;
+
+use crate::{Formation, FormationTemplate};
+
+// The situation a formation is evaluated in, priorities of templates can depend on it
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PriorityContext {
+    // The preferred velocity of the formation
+    pub velocity: Vec3,
+    // A game specific measure of danger, supplied by FormationEvaluationParams
+    pub threat_level: f32,
+    // The number of obstacles per unit of volume within the reach of the formation
+    pub obstacle_density: f32,
+}
+
+// Supplies the priority of a formation template based on the context it's evaluated in
+// Providers are owned by templates, so they have to be Send and Sync as well
+pub trait PriorityProvider: Send + Sync {
+    fn get_priority(&self, context: &PriorityContext) -> f32;
+}
+
+impl<F> PriorityProvider for F
+where
+    F: Fn(&PriorityContext) -> f32 + Send + Sync,
+{
+    fn get_priority(&self, context: &PriorityContext) -> f32 {
+        self(context)
+    }
+}
+
+// Wraps a template and replaces its constant priority with the one from the provider,
+// e.g. to prefer the queue formation in corridors and the sphere formation under attack
+pub struct PrioritizedFormation<T, P> {
+    template: T,
+    provider: P,
+}
+
+impl<T, P> PrioritizedFormation<T, P>
+where
+    T: FormationTemplate,
+    P: PriorityProvider,
+{
+    pub fn new(template: T, provider: P) -> Self {
+        Self { template, provider }
+    }
+}
+
+impl<T, P> FormationTemplate for PrioritizedFormation<T, P>
+where
+    T: FormationTemplate,
+    P: PriorityProvider,
+{
+    fn create_formation(&self, n_agents: usize) -> Formation {
+        self.template.create_formation(n_agents)
+    }
+
+    // Without a context the priority of the wrapped template is used
+    fn get_priority(&self) -> f32 {
+        self.template.get_priority()
+    }
+
+    fn get_priority_in_context(&self, context: &PriorityContext) -> f32 {
+        self.provider.get_priority(context)
+    }
+
+    fn get_aabb(&self, n_agents: usize) -> Aabb {
+        self.template.get_aabb(n_agents)
+    }
+
+    fn create_formation_with_radii(&self, radii: &[f32]) -> Formation {
+        self.template.create_formation_with_radii(radii)
+    }
+
+    fn create_formation_near_obstacles(
+        &self,
+        n_agents: usize,
+        position: Vec3,
+        rotation: Quat,
+        obstacles: &[Agent3D],
+        agent_radius: f32,
+    ) -> Formation {
+        self.template.create_formation_near_obstacles(
+            n_agents,
+            position,
+            rotation,
+            obstacles,
+            agent_radius,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::formations::QueueFormation;
+
+    use super::*;
+
+    #[test]
+    fn test_prioritized_formation() {
+        let template = PrioritizedFormation::new(
+            QueueFormation::new(1.0, 1.0, 1.0),
+            |context: &PriorityContext| 1.0 + context.obstacle_density * 10.0,
+        );
+
+        let context = PriorityContext {
+            obstacle_density: 0.5,
+            ..Default::default()
+        };
+
+        assert_eq!(template.get_priority(), 1.0);
+        assert_eq!(template.get_priority_in_context(&context), 6.0);
+    }
+}
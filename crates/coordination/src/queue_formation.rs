@@ -51,7 +51,7 @@ impl FormationTemplate for QueueFormation {
 
         let half_sizes = Vec3::new(self.agent_radius, self.agent_radius, half_size_from_center);
 
-        let center = if n_agents % 2 == 0 {
+        let center = if n_agents.is_multiple_of(2) {
             -(self.agent_radius + self.spacing / 2.0)
         } else {
             0.0
@@ -0,0 +1,131 @@
+use std::f32::consts::{PI, TAU};
+
+use bevy_math::Vec3;
+use geometry::Aabb;
+
+use crate::{Formation, FormationTemplate};
+
+// Area occupied by a single agent on a surface with hexagonal packing,
+// expressed as a multiple of the squared separation
+const HEXAGONAL_PACKING_AREA: f32 = 0.866_025_4;
+
+// The leader sits in the center and the rest of the agents are distributed on a sphere
+// around it along a golden spiral.
+pub struct SphereFormation {
+    priority: f32,
+    spacing: f32,
+    agent_radius: f32,
+    radius_growth: f32,
+}
+
+impl SphereFormation {
+    // radius_growth scales how fast the sphere grows with the number of agents.
+    // With 1.0 the agents on the sphere are packed as tightly as the spacing allows.
+    pub fn new(agent_radius: f32, spacing: f32, radius_growth: f32, priority: f32) -> Self {
+        Self {
+            priority,
+            spacing: spacing.max(0.0),
+            agent_radius,
+            radius_growth: radius_growth.max(1.0),
+        }
+    }
+
+    fn get_radius(&self, n_agents: usize) -> f32 {
+        if n_agents <= 1 {
+            return 0.0;
+        }
+
+        let separation = self.spacing + 2.0 * self.agent_radius;
+        let agents_on_sphere = (n_agents - 1) as f32;
+        let packed_radius =
+            (agents_on_sphere * HEXAGONAL_PACKING_AREA * separation.powi(2) / (4.0 * PI)).sqrt();
+
+        (packed_radius * self.radius_growth).max(separation)
+    }
+}
+
+impl FormationTemplate for SphereFormation {
+    fn get_priority(&self) -> f32 {
+        self.priority
+    }
+
+    fn create_formation(&self, n_agents: usize) -> Formation {
+        if n_agents == 0 {
+            return Formation::new(Vec::new());
+        }
+
+        let mut positions = Vec::with_capacity(n_agents);
+        positions.push(Vec3::ZERO);
+
+        let radius = self.get_radius(n_agents);
+        let agents_on_sphere = n_agents - 1;
+        let golden_ratio = (1.0 + 5.0_f32.sqrt()) / 2.0;
+        let angle_increment = TAU / golden_ratio;
+
+        for i in 0..agents_on_sphere {
+            let y = 1.0 - 2.0 * (i as f32 + 0.5) / agents_on_sphere as f32;
+            let ring_radius = (1.0 - y * y).sqrt();
+            let theta = angle_increment * i as f32;
+
+            positions
+                .push(Vec3::new(theta.cos() * ring_radius, y, theta.sin() * ring_radius) * radius);
+        }
+
+        Formation::new(positions)
+    }
+
+    fn get_aabb(&self, n_agents: usize) -> Aabb {
+        if n_agents == 0 {
+            return Aabb::new(Vec3::ZERO, Vec3::ZERO);
+        }
+
+        let radius = self.get_radius(n_agents) + self.agent_radius;
+
+        Aabb::new(Vec3::ZERO, Vec3::splat(radius))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_agents_lie_on_the_sphere() {
+        let formation = SphereFormation::new(0.5, 1.0, 1.2, 1.0);
+        let positions = formation.create_formation(30).get_positions().to_vec();
+        let radius = formation.get_radius(30);
+
+        assert_eq!(positions[0], Vec3::ZERO);
+
+        for position in &positions[1..] {
+            assert!((position.length() - radius).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn test_radius_grows_with_the_agents() {
+        let packed = SphereFormation::new(0.5, 1.0, 1.0, 1.0);
+        let loose = SphereFormation::new(0.5, 1.0, 2.0, 1.0);
+
+        assert_eq!(packed.get_radius(1), 0.0);
+        // A single agent on the sphere is still one separation away from the leader
+        assert_eq!(packed.get_radius(2), 2.0);
+
+        assert!(packed.get_radius(40) > packed.get_radius(20));
+        assert!((loose.get_radius(40) - 2.0 * packed.get_radius(40)).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_agents_do_not_overlap() {
+        let positions = SphereFormation::new(0.5, 0.0, 1.0, 1.0)
+            .create_formation(40)
+            .get_positions()
+            .to_vec();
+
+        for (i, a) in positions.iter().enumerate() {
+            for b in &positions[i + 1..] {
+                assert!(a.distance(*b) > 0.5);
+            }
+        }
+    }
+}
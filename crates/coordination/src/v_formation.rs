@@ -48,7 +48,7 @@ impl FormationTemplate for VFormation {
 
         let half_size_along_z = (max_agents_at_each_side * axis_separation) / 2.0;
 
-        let (center_x, half_size_along_x) = if n_agents % 2 == 0 {
+        let (center_x, half_size_along_x) = if n_agents.is_multiple_of(2) {
             let left_size = max_agents_at_each_side * axis_separation;
             let right_size = (max_agents_at_each_side - 1.0) * axis_separation;
             (
@@ -0,0 +1,225 @@
+use bevy_math::{Quat, Vec3};
+
+use crate::Formation;
+
+// Formation control where the formation is a rigid virtual body with its own kinematics.
+// The body moves and turns with limited speed and turn rate, and every agent tracks its
+// slot on the body with a PD controller. Compared to re-targeting every agent on each
+// evaluation this keeps the shape of the formation stiff, because all slots move together.
+//
+// The forward direction of the body is the local Z axis.
+#[derive(Clone, Debug)]
+pub struct VirtualStructure {
+    formation: Formation,
+    position: Vec3,
+    rotation: Quat,
+    velocity: Vec3,
+    angular_velocity: Vec3,
+    max_speed: f32,
+    max_turn_rate: f32,
+}
+
+impl VirtualStructure {
+    // formation: The slots relative to the center of the body
+    // max_speed: The maximum speed of the body
+    // max_turn_rate: The maximum turning speed of the body in radians per second
+    pub fn new(
+        formation: Formation,
+        position: Vec3,
+        rotation: Quat,
+        max_speed: f32,
+        max_turn_rate: f32,
+    ) -> Self {
+        assert!(max_speed >= 0.0);
+        assert!(max_turn_rate >= 0.0);
+
+        Self {
+            formation,
+            position,
+            rotation,
+            velocity: Vec3::ZERO,
+            angular_velocity: Vec3::ZERO,
+            max_speed,
+            max_turn_rate,
+        }
+    }
+
+    pub fn get_position(&self) -> Vec3 {
+        self.position
+    }
+
+    pub fn get_rotation(&self) -> Quat {
+        self.rotation
+    }
+
+    pub fn get_velocity(&self) -> Vec3 {
+        self.velocity
+    }
+
+    pub fn get_angular_velocity(&self) -> Vec3 {
+        self.angular_velocity
+    }
+
+    pub fn get_forward(&self) -> Vec3 {
+        self.rotation * Vec3::Z
+    }
+
+    // Replaces the slots of the body, e.g. after a new formation was chosen
+    pub fn set_formation(&mut self, formation: Formation) {
+        self.formation = formation;
+    }
+
+    // Returns the slots in world space
+    pub fn get_formation(&self) -> Formation {
+        Formation::new(
+            (0..self.formation.get_positions().len())
+                .map(|slot| self.get_slot_position(slot))
+                .collect(),
+        )
+    }
+
+    pub fn get_slot_position(&self, slot: usize) -> Vec3 {
+        self.position + self.rotation * self.formation.get_positions()[slot]
+    }
+
+    // The velocity of the slot includes the tangential velocity caused by the turning of the body
+    pub fn get_slot_velocity(&self, slot: usize) -> Vec3 {
+        let offset = self.rotation * self.formation.get_positions()[slot];
+
+        self.velocity + self.angular_velocity.cross(offset)
+    }
+
+    // Turns the body towards the preferred velocity and moves it along its forward direction
+    //
+    // preferred_velocity: The velocity the body should move with
+    // delta_time: The time step
+    pub fn update(&mut self, preferred_velocity: Vec3, delta_time: f32) {
+        if delta_time <= 0.0 {
+            return;
+        }
+
+        let speed = preferred_velocity.length().min(self.max_speed);
+
+        if speed <= f32::EPSILON {
+            self.velocity = Vec3::ZERO;
+            self.angular_velocity = Vec3::ZERO;
+            return;
+        }
+
+        let direction = preferred_velocity / preferred_velocity.length();
+        let (axis, angle) = Quat::from_rotation_arc(self.get_forward(), direction).to_axis_angle();
+        let angle = angle.min(self.max_turn_rate * delta_time);
+
+        if angle > f32::EPSILON && axis.is_finite() {
+            self.rotation = (Quat::from_axis_angle(axis, angle) * self.rotation).normalize();
+            self.angular_velocity = axis * angle / delta_time;
+        } else {
+            self.angular_velocity = Vec3::ZERO;
+        }
+
+        // The body doesn't move against its forward direction, it first has to turn around
+        let forward = self.get_forward();
+        self.velocity = forward * speed * forward.dot(direction).max(0.0);
+        self.position += self.velocity * delta_time;
+    }
+}
+
+// PD controller that makes an agent track a moving slot of a virtual structure
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "reflect", derive(bevy_reflect::Reflect))]
+pub struct SlotTracker {
+    pub proportional_gain: f32,
+    pub derivative_gain: f32,
+    pub max_acceleration: f32,
+}
+
+impl SlotTracker {
+    pub fn new(proportional_gain: f32, derivative_gain: f32, max_acceleration: f32) -> Self {
+        Self {
+            proportional_gain,
+            derivative_gain,
+            max_acceleration,
+        }
+    }
+
+    // Returns a critically damped tracker with the given natural frequency
+    pub fn critically_damped(frequency: f32, max_acceleration: f32) -> Self {
+        Self::new(frequency * frequency, 2.0 * frequency, max_acceleration)
+    }
+
+    // Returns the acceleration that moves the agent towards the slot
+    pub fn get_acceleration(
+        &self,
+        agent_position: Vec3,
+        agent_velocity: Vec3,
+        slot_position: Vec3,
+        slot_velocity: Vec3,
+    ) -> Vec3 {
+        (self.proportional_gain * (slot_position - agent_position)
+            + self.derivative_gain * (slot_velocity - agent_velocity))
+            .clamp_length_max(self.max_acceleration)
+    }
+
+    // Returns the velocity the agent should have after the time step, usable as the preferred
+    // velocity for collision avoidance
+    pub fn get_preferred_velocity(
+        &self,
+        agent_position: Vec3,
+        agent_velocity: Vec3,
+        slot_position: Vec3,
+        slot_velocity: Vec3,
+        delta_time: f32,
+    ) -> Vec3 {
+        agent_velocity
+            + self.get_acceleration(agent_position, agent_velocity, slot_position, slot_velocity)
+                * delta_time
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_turn_rate_is_limited() {
+        let mut structure = VirtualStructure::new(
+            Formation::new(vec![Vec3::ZERO, Vec3::new(0.0, 0.0, -1.0)]),
+            Vec3::ZERO,
+            Quat::IDENTITY,
+            10.0,
+            1.0,
+        );
+
+        structure.update(Vec3::new(20.0, 0.0, 0.0), 0.1);
+
+        approx::assert_relative_eq!(
+            structure.get_rotation().angle_between(Quat::IDENTITY),
+            0.1,
+            epsilon = 1e-4
+        );
+        approx::assert_relative_eq!(
+            structure.get_velocity().length(),
+            10.0 * 0.1_f32.sin(),
+            epsilon = 1e-3
+        );
+
+        // The slot behind the center swings to the opposite side of the turn
+        assert!(structure.get_slot_velocity(1).x < structure.get_velocity().x);
+    }
+
+    #[test]
+    fn test_agent_converges_to_slot() {
+        let tracker = SlotTracker::critically_damped(2.0, 100.0);
+        let slot = Vec3::new(1.0, 2.0, 3.0);
+        let mut position = Vec3::ZERO;
+        let mut velocity = Vec3::ZERO;
+
+        for _ in 0..200 {
+            velocity = tracker.get_preferred_velocity(position, velocity, slot, Vec3::ZERO, 0.05);
+            position += velocity * 0.05;
+        }
+
+        assert!(position.distance(slot) < 0.01);
+        assert!(velocity.length() < 0.01);
+    }
+}
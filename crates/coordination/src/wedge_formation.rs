@@ -0,0 +1,123 @@
+use bevy_math::Vec3;
+use geometry::Aabb;
+
+use crate::{Formation, FormationTemplate};
+
+const SQRT_3_OVER_2: f32 = 0.866_025_4;
+
+// A solid triangular wedge with the leader at its tip. Every following row
+// holds one more agent than the row in front of it.
+pub struct WedgeFormation {
+    priority: f32,
+    spacing: f32,
+    agent_radius: f32,
+}
+
+impl WedgeFormation {
+    pub fn new(agent_radius: f32, spacing: f32, priority: f32) -> Self {
+        Self {
+            priority,
+            spacing: spacing.max(0.0),
+            agent_radius,
+        }
+    }
+
+    fn get_separation(&self) -> f32 {
+        self.spacing + 2.0 * self.agent_radius
+    }
+
+    // Returns the number of rows and the number of agents in the widest row
+    fn get_rows(n_agents: usize) -> (usize, usize) {
+        let mut rows = 0;
+        let mut placed = 0;
+
+        while placed < n_agents {
+            rows += 1;
+            placed += rows;
+        }
+
+        let agents_in_last_row = rows - (placed - n_agents);
+        let widest_row = agents_in_last_row.max(rows - 1);
+
+        (rows, widest_row)
+    }
+}
+
+impl FormationTemplate for WedgeFormation {
+    fn get_priority(&self) -> f32 {
+        self.priority
+    }
+
+    fn create_formation(&self, n_agents: usize) -> Formation {
+        let mut positions = Vec::with_capacity(n_agents);
+        let separation = self.get_separation();
+        let row_separation = separation * SQRT_3_OVER_2;
+
+        let mut row = 0;
+        while positions.len() < n_agents {
+            let agents_in_row = (row + 1).min(n_agents - positions.len());
+            let z = -(row as f32) * row_separation;
+
+            for i in 0..agents_in_row {
+                let x = (i as f32 - (agents_in_row - 1) as f32 / 2.0) * separation;
+                positions.push(Vec3::new(x, 0.0, z));
+            }
+
+            row += 1;
+        }
+
+        Formation::new(positions)
+    }
+
+    fn get_aabb(&self, n_agents: usize) -> Aabb {
+        if n_agents == 0 {
+            return Aabb::new(Vec3::ZERO, Vec3::ZERO);
+        }
+
+        let separation = self.get_separation();
+        let row_separation = separation * SQRT_3_OVER_2;
+        let (rows, widest_row) = Self::get_rows(n_agents);
+
+        let half_size_along_x = (widest_row - 1) as f32 * separation / 2.0;
+        let half_size_along_z = (rows - 1) as f32 * row_separation / 2.0;
+
+        Aabb::new(
+            Vec3::new(0.0, 0.0, -half_size_along_z),
+            Vec3::new(half_size_along_x, 0.0, half_size_along_z) + Vec3::splat(self.agent_radius),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rows_grow_by_one() {
+        let positions = WedgeFormation::new(0.5, 1.0, 1.0)
+            .create_formation(6)
+            .get_positions()
+            .to_vec();
+
+        assert_eq!(positions[0], Vec3::ZERO);
+        assert_eq!(positions[1].z, positions[2].z);
+        assert_eq!(positions[3].z, positions[5].z);
+        assert!(positions[3].z < positions[1].z);
+    }
+
+    #[test]
+    fn test_legs_form_a_sixty_degree_wedge() {
+        // Four full rows, the last agent of each row is on the right leg of the wedge
+        let positions = WedgeFormation::new(0.5, 1.0, 1.0)
+            .create_formation(10)
+            .get_positions()
+            .to_vec();
+
+        for last_in_row in [2, 5, 9] {
+            let leg = positions[last_in_row] - positions[0];
+            let half_angle = leg.x.atan2(-leg.z);
+
+            assert!((half_angle - 30.0_f32.to_radians()).abs() < 1e-4);
+        }
+    }
+}
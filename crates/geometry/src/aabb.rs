@@ -1,8 +1,14 @@
-use bevy_math::Vec3;
+use bevy_math::{Mat3, Quat, Vec3};
 
-use crate::Vec3Operations;
+use crate::{Ray3D, Ray3DIntersection, Ray3DIntersectionResult, Vec3Operations, EPSILON};
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "reflect", derive(bevy_reflect::Reflect))]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    archive(check_bytes)
+)]
 pub struct Aabb {
     pub center: Vec3,
     pub half_sizes: Vec3,
@@ -14,6 +20,19 @@ impl Aabb {
         Self { center, half_sizes }
     }
 
+    // Returns the smallest axis aligned box containing this box rotated around the origin
+    #[must_use]
+    pub fn rotated(&self, rotation: Quat) -> Self {
+        let matrix = Mat3::from_quat(rotation);
+        let abs_matrix = Mat3::from_cols(
+            matrix.x_axis.abs(),
+            matrix.y_axis.abs(),
+            matrix.z_axis.abs(),
+        );
+
+        Self::new(rotation * self.center, abs_matrix * self.half_sizes)
+    }
+
     pub fn merge(&mut self, other: &Self) {
         let min = self.center - self.half_sizes;
         let max = self.center + self.half_sizes;
@@ -92,3 +111,43 @@ impl Vec3Operations for Aabb {
         }
     }
 }
+
+impl Ray3DIntersection for Aabb {
+    fn intersect_ray(&self, ray: &Ray3D) -> Ray3DIntersectionResult {
+        let min = self.center - self.half_sizes;
+        let max = self.center + self.half_sizes;
+
+        let mut t_enter = f32::NEG_INFINITY;
+        let mut t_exit = f32::INFINITY;
+
+        // Slab method, the ray is clipped by each pair of parallel faces
+        for axis in 0..3 {
+            let origin = ray.origin[axis];
+            let direction = ray.direction[axis];
+
+            if direction.abs() < EPSILON {
+                if origin < min[axis] || origin > max[axis] {
+                    return Ray3DIntersectionResult::None;
+                }
+
+                continue;
+            }
+
+            let t1 = (min[axis] - origin) / direction;
+            let t2 = (max[axis] - origin) / direction;
+
+            t_enter = t_enter.max(t1.min(t2));
+            t_exit = t_exit.min(t1.max(t2));
+        }
+
+        if t_enter > t_exit || t_exit < 0.0 {
+            return Ray3DIntersectionResult::None;
+        }
+
+        if (t_exit - t_enter).abs() < EPSILON {
+            return Ray3DIntersectionResult::Point(t_enter);
+        }
+
+        Ray3DIntersectionResult::Segment(t_enter, t_exit)
+    }
+}
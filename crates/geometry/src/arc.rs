@@ -2,7 +2,9 @@ use bevy_math::{Mat2, Vec2};
 
 use crate::{Vec2Operations, EPSILON};
 
-#[derive(Debug)]
+// A circular arc going from start_direction to end_direction along the shorter way around
+// max_angle is the angle between the two directions, so arcs span at most half a circle.
+#[derive(Clone, Debug)]
 pub struct Arc2D {
     pub center: Vec2,
     pub start_direction: Vec2,
@@ -12,6 +14,27 @@ pub struct Arc2D {
 }
 
 impl Arc2D {
+    // Creates an arc going from start_angle to end_angle, angles are counter-clockwise from +X
+    // A negative sweep makes the arc go clockwise.
+    #[must_use]
+    #[allow(clippy::missing_panics_doc)]
+    pub fn from_center_angles(center: Vec2, radius: f32, start_angle: f32, end_angle: f32) -> Self {
+        let sweep = end_angle - start_angle;
+
+        assert!(
+            sweep.abs() <= std::f32::consts::PI + EPSILON,
+            "An arc can't span more than half a circle"
+        );
+
+        Self {
+            center,
+            start_direction: Vec2::from_angle(start_angle),
+            end_direction: Vec2::from_angle(end_angle),
+            max_angle: sweep.abs(),
+            radius,
+        }
+    }
+
     #[must_use]
     pub fn from_points(radius: f32, start: Vec2, end: Vec2) -> (Self, Self) {
         let midpoint = (start + end) / 2.0;
@@ -31,7 +54,7 @@ impl Arc2D {
             center: center_a,
             start_direction: (start - center_a).normalize(),
             end_direction: (end - center_a).normalize(),
-            max_angle: (end - center_a).angle_between(start - center_a),
+            max_angle: (end - center_a).angle_between(start - center_a).abs(),
             radius,
         };
 
@@ -39,31 +62,53 @@ impl Arc2D {
             center: center_b,
             start_direction: (start - center_b).normalize(),
             end_direction: (end - center_b).normalize(),
-            max_angle: (end - center_b).angle_between(start - center_b),
+            max_angle: (end - center_b).angle_between(start - center_b).abs(),
             radius,
         };
 
         (arc_a, arc_b)
     }
 
+    // Returns the point at t along the arc, 0 being the start and 1 the end
+    #[must_use]
     pub fn point_at(&self, t: f32) -> Vec2 {
-        let direction = {
-            let angle = self.start_direction.angle_between(self.end_direction);
-            let mut new_angle = angle * t;
+        self.center + self.direction_at(t) * self.radius
+    }
 
-            if cross_product(self.start_direction, self.end_direction) < 0.0 {
-                new_angle = -new_angle;
-            }
+    // Returns the unit direction in which the arc continues at t
+    #[must_use]
+    pub fn tangent_at(&self, t: f32) -> Vec2 {
+        let direction = self.direction_at(t).perp();
 
-            let mat = Mat2::from_cols(
-                Vec2::new(new_angle.cos(), new_angle.sin()),
-                Vec2::new(-new_angle.sin(), new_angle.cos()),
-            );
+        if self.is_clockwise() {
+            -direction
+        } else {
+            direction
+        }
+    }
 
-            mat * self.start_direction
-        };
+    #[must_use]
+    pub fn length(&self) -> f32 {
+        self.radius * self.max_angle
+    }
 
-        self.center + direction * self.radius
+    fn is_clockwise(&self) -> bool {
+        cross_product(self.start_direction, self.end_direction) < 0.0
+    }
+
+    fn direction_at(&self, t: f32) -> Vec2 {
+        let mut new_angle = self.max_angle * t;
+
+        if self.is_clockwise() {
+            new_angle = -new_angle;
+        }
+
+        let mat = Mat2::from_cols(
+            Vec2::new(new_angle.cos(), new_angle.sin()),
+            Vec2::new(-new_angle.sin(), new_angle.cos()),
+        );
+
+        mat * self.start_direction
     }
 }
 
@@ -118,3 +163,38 @@ impl Vec2Operations for Arc2D {
         (constrainted - pt).length()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::{FRAC_PI_2, PI};
+
+    use super::*;
+
+    #[test]
+    fn test_from_center_angles() {
+        let arc = Arc2D::from_center_angles(Vec2::ONE, 2.0, 0.0, FRAC_PI_2);
+
+        assert!(arc.point_at(0.0).abs_diff_eq(Vec2::new(3.0, 1.0), 1e-5));
+        assert!(arc.point_at(1.0).abs_diff_eq(Vec2::new(1.0, 3.0), 1e-5));
+        assert!(arc.contains(arc.point_at(0.5)));
+        assert!((arc.length() - PI).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_tangent_follows_the_arc() {
+        let counter_clockwise = Arc2D::from_center_angles(Vec2::ZERO, 1.0, 0.0, FRAC_PI_2);
+        let clockwise = Arc2D::from_center_angles(Vec2::ZERO, 1.0, FRAC_PI_2, 0.0);
+
+        assert!(counter_clockwise.tangent_at(0.0).abs_diff_eq(Vec2::Y, 1e-5));
+        assert!(clockwise.tangent_at(1.0).abs_diff_eq(-Vec2::Y, 1e-5));
+        assert!(clockwise
+            .point_at(0.5)
+            .abs_diff_eq(counter_clockwise.point_at(0.5), 1e-5));
+    }
+
+    #[test]
+    #[should_panic(expected = "An arc can't span more than half a circle")]
+    fn test_from_center_angles_rejects_long_arcs() {
+        let _ = Arc2D::from_center_angles(Vec2::ZERO, 1.0, 0.0, 1.5 * PI);
+    }
+}
@@ -1,8 +1,17 @@
 use bevy_math::Vec3;
 
-use crate::{Aabb, Cone, Plane, Sphere, Vec3Operations};
+use crate::{
+    Aabb, Cone, Plane, Ray3D, Ray3DIntersection, Ray3DIntersectionResult, Sphere, Vec3Operations,
+    EPSILON,
+};
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "reflect", derive(bevy_reflect::Reflect))]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    archive(check_bytes)
+)]
 pub enum Collider {
     Sphere(Sphere),
     Aabb(Aabb),
@@ -66,8 +75,16 @@ impl Collider {
     pub fn extend_cone(&self, vertex: Vec3) -> impl Vec3Operations {
         match self {
             Collider::Sphere(sphere) => {
-                let radius = sphere.radius;
+                // The cone is tangent to the sphere, so at the distance of the sphere center
+                // it is wider than the sphere itself
                 let direction = -vertex;
+                let distance_squared = direction.length_squared();
+                // A vertex on or inside the sphere has no tangent cone, so we widen it as far
+                // as possible instead of dividing by zero
+                let tangent_length_squared =
+                    (distance_squared - sphere.radius.powi(2)).max(EPSILON);
+                let radius =
+                    sphere.radius * distance_squared.sqrt() / tangent_length_squared.sqrt();
                 Cone::infinite(vertex, direction, radius)
             }
             Collider::Aabb(_) => todo!(),
@@ -84,6 +101,19 @@ impl Collider {
         }
     }
 
+    // Returns the point of the shape farthest in the given direction
+    pub fn support_point(&self, direction: Vec3) -> Vec3 {
+        match self {
+            Collider::Sphere(sphere) => {
+                sphere.origin + direction.normalize_or_zero() * sphere.radius
+            }
+            Collider::Aabb(aabb) => {
+                let signs = Vec3::select(direction.cmplt(Vec3::ZERO), -Vec3::ONE, Vec3::ONE);
+                aabb.center + aabb.half_sizes * signs
+            }
+        }
+    }
+
     pub fn is_symmetric(&self) -> bool {
         match self {
             Collider::Sphere(_) => true,
@@ -123,3 +153,74 @@ impl Vec3Operations for Collider {
         }
     }
 }
+
+impl Ray3DIntersection for Collider {
+    fn intersect_ray(&self, ray: &Ray3D) -> Ray3DIntersectionResult {
+        match self {
+            Collider::Sphere(sphere) => sphere.intersect_ray(ray),
+            Collider::Aabb(aabb) => aabb.intersect_ray(ray),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extend_cone_is_tangent_to_the_sphere() {
+        let collider = Collider::new_sphere(1.0);
+        let vertex = -Vec3::X * 2.0;
+        let cone = collider.extend_cone(vertex);
+
+        // The tangent points are at 60 degrees from the vertex to the sphere center
+        let tangent_point = Vec3::new(-0.5, 3.0_f32.sqrt() / 2.0, 0.0);
+        let outward = Vec3::Z.cross((tangent_point - vertex).normalize());
+
+        assert!(cone.contains(Vec3::ZERO));
+        assert!(cone.contains(tangent_point - outward * 0.01));
+        assert!(!cone.contains(tangent_point + outward * 0.01));
+    }
+
+    #[test]
+    fn test_extend_cone_contains_the_sphere() {
+        let collider = Collider::new_sphere(2.0);
+        let vertex = Vec3::new(-3.0, 4.0, 1.0);
+        let cone = collider.extend_cone(vertex);
+
+        for i in 0..16 {
+            for j in 1..8 {
+                let yaw = i as f32 * std::f32::consts::TAU / 16.0;
+                let pitch = j as f32 * std::f32::consts::PI / 8.0;
+                let direction = Vec3::new(
+                    pitch.sin() * yaw.cos(),
+                    pitch.cos(),
+                    pitch.sin() * yaw.sin(),
+                );
+
+                assert!(cone.contains(direction * 1.99));
+            }
+        }
+    }
+
+    #[test]
+    fn test_extend_cone_from_inside_the_sphere_is_finite() {
+        let collider = Collider::new_sphere(1.0);
+
+        for vertex in [-Vec3::X, -Vec3::X * 0.5] {
+            let cone = collider.extend_cone(vertex);
+            let (point, normal) = cone.closest_point_and_normal(Vec3::Y * 5.0);
+
+            assert!(cone.contains(Vec3::ZERO));
+            assert!(point.is_finite());
+            assert!(normal.is_finite());
+        }
+    }
+
+    #[test]
+    fn test_collider_is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+
+        assert_send_sync::<Collider>();
+    }
+}
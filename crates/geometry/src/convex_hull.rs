@@ -0,0 +1,282 @@
+use std::collections::HashSet;
+
+use bevy_math::Vec3;
+
+use crate::{Aabb, Plane, TriMesh, Vec3Operations, EPSILON};
+
+// Convex polyhedron given by its vertices and triangular faces
+// The faces are wound counter-clockwise when looking from the outside, so the normals of their
+// planes point out of the hull.
+#[derive(Clone, Debug)]
+pub struct ConvexHull {
+    pub vertices: Vec<Vec3>,
+    pub faces: Vec<[usize; 3]>,
+    pub planes: Vec<Plane>,
+}
+
+impl ConvexHull {
+    // Returns the smallest convex polyhedron containing all points,
+    // None if the points are all coplanar
+    #[must_use]
+    pub fn from_points(points: &[Vec3]) -> Option<Self> {
+        let first = *points.first()?;
+        let second = *points.iter().max_by(|a, b| {
+            a.distance_squared(first)
+                .total_cmp(&b.distance_squared(first))
+        })?;
+
+        let line = (second - first).try_normalize()?;
+        let distance_to_line = |point: &Vec3| {
+            let relative = *point - first;
+            (relative - line * relative.dot(line)).length_squared()
+        };
+
+        let third = *points
+            .iter()
+            .max_by(|a, b| distance_to_line(a).total_cmp(&distance_to_line(b)))?;
+
+        if distance_to_line(&third) < EPSILON * EPSILON {
+            return None;
+        }
+
+        let normal = (second - first).cross(third - first).normalize();
+        let fourth = *points.iter().max_by(|a, b| {
+            (**a - first)
+                .dot(normal)
+                .abs()
+                .total_cmp(&(**b - first).dot(normal).abs())
+        })?;
+
+        if (fourth - first).dot(normal).abs() < EPSILON {
+            return None;
+        }
+
+        let mut vertices = vec![first, second, third, fourth];
+        let mut faces = if (fourth - first).dot(normal) > 0.0 {
+            vec![[0, 2, 1], [0, 1, 3], [1, 2, 3], [2, 0, 3]]
+        } else {
+            vec![[0, 1, 2], [0, 3, 1], [1, 3, 2], [2, 3, 0]]
+        };
+
+        for point in points {
+            let is_visible = |face: &[usize; 3], vertices: &[Vec3]| {
+                let [a, b, c] = face.map(|index| vertices[index]);
+                let normal = (b - a).cross(c - a).normalize_or_zero();
+
+                normal.dot(*point - a) > EPSILON
+            };
+
+            if !faces.iter().any(|face| is_visible(face, &vertices)) {
+                continue;
+            }
+
+            let (visible, hidden): (Vec<_>, Vec<_>) = faces
+                .into_iter()
+                .partition(|face| is_visible(face, &vertices));
+
+            // The edges of the visible faces that aren't shared with another visible face form
+            // the horizon, which is connected to the new point
+            let edges = visible
+                .iter()
+                .flat_map(|[a, b, c]| [(*a, *b), (*b, *c), (*c, *a)])
+                .collect::<HashSet<_>>();
+
+            let index = vertices.len();
+            vertices.push(*point);
+
+            faces = hidden;
+            faces.extend(
+                edges
+                    .iter()
+                    .filter(|(a, b)| !edges.contains(&(*b, *a)))
+                    .map(|(a, b)| [*a, *b, index]),
+            );
+        }
+
+        Some(Self::from_faces(vertices, faces))
+    }
+
+    // Drops the vertices that aren't used by any face
+    fn from_faces(vertices: Vec<Vec3>, faces: Vec<[usize; 3]>) -> Self {
+        let mut used = vec![None; vertices.len()];
+        let mut hull_vertices = Vec::new();
+
+        let faces = faces
+            .into_iter()
+            .map(|face| {
+                face.map(|index| {
+                    *used[index].get_or_insert_with(|| {
+                        hull_vertices.push(vertices[index]);
+                        hull_vertices.len() - 1
+                    })
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let planes = faces
+            .iter()
+            .map(|face| {
+                let [a, b, c] = face.map(|index| hull_vertices[index]);
+                Plane::new(a, (b - a).cross(c - a).normalize())
+            })
+            .collect();
+
+        Self {
+            vertices: hull_vertices,
+            faces,
+            planes,
+        }
+    }
+
+    #[must_use]
+    pub fn contains(&self, point: Vec3) -> bool {
+        self.planes
+            .iter()
+            .all(|plane| plane.signed_distance(point) <= EPSILON)
+    }
+
+    #[must_use]
+    pub fn get_aabb(&self) -> Aabb {
+        TriMesh::from(self).get_aabb()
+    }
+}
+
+impl From<&ConvexHull> for TriMesh {
+    #[allow(clippy::cast_possible_truncation)]
+    fn from(hull: &ConvexHull) -> Self {
+        TriMesh::new(
+            hull.vertices.clone(),
+            hull.faces
+                .iter()
+                .map(|face| face.map(|index| index as u32))
+                .collect(),
+        )
+    }
+}
+
+impl TriMesh {
+    // Splits the mesh into parts that don't share any vertex
+    #[must_use]
+    pub fn get_connected_components(&self) -> Vec<TriMesh> {
+        fn find_root(parents: &mut [usize], mut index: usize) -> usize {
+            while parents[index] != index {
+                parents[index] = parents[parents[index]];
+                index = parents[index];
+            }
+
+            index
+        }
+
+        let mut parents = (0..self.vertices.len()).collect::<Vec<_>>();
+
+        for [a, b, c] in &self.indices {
+            let root_a = find_root(&mut parents, *a as usize);
+
+            for other in [*b, *c] {
+                let root_other = find_root(&mut parents, other as usize);
+                parents[root_other] = root_a;
+            }
+        }
+
+        let mut components = Vec::<(usize, TriMesh)>::new();
+
+        for triangle in &self.indices {
+            let root = find_root(&mut parents, triangle[0] as usize);
+            let points = triangle.map(|index| self.vertices[index as usize]);
+
+            let component = match components.iter_mut().position(|(r, _)| *r == root) {
+                Some(position) => &mut components[position].1,
+                None => {
+                    components.push((root, TriMesh::default()));
+                    &mut components.last_mut().unwrap().1
+                }
+            };
+
+            #[allow(clippy::cast_possible_truncation)]
+            let first = component.vertices.len() as u32;
+            component.vertices.extend(points);
+            component.indices.push([first, first + 1, first + 2]);
+        }
+
+        components
+            .into_iter()
+            .map(|(_, component)| component)
+            .collect()
+    }
+
+    // Approximates the mesh by the convex hulls of its connected components
+    // Concave parts become solid, which is conservative for avoidance. Flat components
+    // (e.g. a single quad) have no volume and are left out.
+    #[must_use]
+    pub fn convex_decomposition(&self) -> Vec<ConvexHull> {
+        self.get_connected_components()
+            .iter()
+            .filter_map(|component| ConvexHull::from_points(&component.vertices))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_cube_points(center: Vec3) -> Vec<Vec3> {
+        (0..8)
+            .map(|i| {
+                center
+                    + Vec3::new(
+                        if i & 1 == 0 { -1.0 } else { 1.0 },
+                        if i & 2 == 0 { -1.0 } else { 1.0 },
+                        if i & 4 == 0 { -1.0 } else { 1.0 },
+                    )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_hull_of_cube_with_inner_points() {
+        let mut points = get_cube_points(Vec3::ZERO);
+        points.push(Vec3::ZERO);
+        points.push(Vec3::new(0.5, -0.2, 0.3));
+
+        let hull = ConvexHull::from_points(&points).unwrap();
+
+        assert_eq!(hull.vertices.len(), 8);
+        assert!(hull.contains(Vec3::new(0.9, 0.9, -0.9)));
+        assert!(!hull.contains(Vec3::new(1.1, 0.0, 0.0)));
+
+        let aabb = hull.get_aabb();
+        assert!(aabb.center.length() < 1e-5);
+        assert!((aabb.half_sizes - Vec3::ONE).length() < 1e-5);
+    }
+
+    #[test]
+    fn test_coplanar_points_have_no_hull() {
+        let points = [Vec3::ZERO, Vec3::X, Vec3::Z, Vec3::new(1.0, 0.0, 1.0)];
+
+        assert!(ConvexHull::from_points(&points).is_none());
+    }
+
+    #[test]
+    fn test_convex_decomposition_of_separate_parts() {
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+
+        for center in [Vec3::ZERO, Vec3::new(10.0, 0.0, 0.0)] {
+            let hull = ConvexHull::from_points(&get_cube_points(center)).unwrap();
+            let mesh = TriMesh::from(&hull);
+
+            #[allow(clippy::cast_possible_truncation)]
+            let offset = vertices.len() as u32;
+            vertices.extend(mesh.vertices);
+            indices.extend(mesh.indices.iter().map(|face| face.map(|i| i + offset)));
+        }
+
+        let hulls = TriMesh::new(vertices, indices).convex_decomposition();
+
+        assert_eq!(hulls.len(), 2);
+        assert!(hulls[0].contains(Vec3::ZERO));
+        assert!(!hulls[0].contains(Vec3::new(10.0, 0.0, 0.0)));
+        assert!(hulls[1].contains(Vec3::new(10.0, 0.0, 0.0)));
+    }
+}
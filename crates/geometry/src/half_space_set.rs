@@ -0,0 +1,241 @@
+use bevy_math::{Mat3, Mat4, Vec3, Vec4};
+
+use crate::{Plane, Vec3Operations, EPSILON};
+
+// Intersection of the allowed sides of a set of planes, e.g. the velocities admitted by ORCA
+// The region can be unbounded, so the queries clip it to the cube of points with all
+// coordinates within the given bound. They enumerate all combinations of 3 or 4 planes, which
+// is fine for the handful of planes of a single agent but too slow for large sets.
+#[derive(Clone, Debug, Default)]
+pub struct HalfSpaceSet3 {
+    planes: Vec<Plane>,
+}
+
+impl HalfSpaceSet3 {
+    #[must_use]
+    pub fn new(planes: Vec<Plane>) -> Self {
+        Self { planes }
+    }
+
+    #[must_use]
+    pub fn with_plane(mut self, plane: Plane) -> Self {
+        self.planes.push(plane);
+        self
+    }
+
+    #[must_use]
+    pub fn planes(&self) -> &[Plane] {
+        &self.planes
+    }
+
+    #[must_use]
+    pub fn contains(&self, point: Vec3) -> bool {
+        self.planes.iter().all(|plane| plane.contains(point))
+    }
+
+    // Returns the indexes of the planes that don't allow the point
+    pub fn violated_planes(&self, point: Vec3) -> impl Iterator<Item = usize> + '_ {
+        self.planes
+            .iter()
+            .enumerate()
+            .filter(move |(_, plane)| !plane.contains(point))
+            .map(|(index, _)| index)
+    }
+
+    #[must_use]
+    pub fn is_feasible(&self, bound: f32) -> bool {
+        let (_, radius) = self.chebyshev_center(bound);
+
+        radius >= -tolerance(bound)
+    }
+
+    // Returns the center and radius of the largest ball inside the region
+    // The cube of the bound limits the ball as well. A negative radius means the region is empty,
+    // the center is then the point that violates the planes the least, which makes
+    // violated_planes at the center a good start when looking for conflicting planes.
+    #[must_use]
+    pub fn chebyshev_center(&self, bound: f32) -> (Vec3, f32) {
+        // Maximizing r subject to normal · x - r >= normal · origin for every plane and cube face
+        let constraints = self
+            .planes
+            .iter()
+            .map(|plane| (plane.normal, plane.normal.dot(plane.origin)))
+            .chain(cube_faces(bound))
+            .map(|(normal, offset)| (normal.extend(-1.0), offset))
+            .collect::<Vec<_>>();
+
+        let tolerance = tolerance(bound);
+        let mut best = (Vec3::ZERO, f32::NEG_INFINITY);
+
+        for_each_combination::<4>(constraints.len(), |indexes| {
+            let rows = indexes.map(|index| constraints[index].0);
+            let matrix = Mat4::from_cols(rows[0], rows[1], rows[2], rows[3]).transpose();
+
+            if matrix.determinant().abs() < EPSILON {
+                return;
+            }
+
+            let offsets = Vec4::from_array(indexes.map(|index| constraints[index].1));
+            let solution = matrix.inverse() * offsets;
+
+            if solution.w > best.1
+                && constraints
+                    .iter()
+                    .all(|(row, offset)| row.dot(solution) >= offset - tolerance)
+            {
+                best = (solution.truncate(), solution.w);
+            }
+        });
+
+        best
+    }
+
+    // Returns the vertices of the region clipped to the cube of the bound
+    // An empty list means the region is empty.
+    #[must_use]
+    pub fn vertices(&self, bound: f32) -> Vec<Vec3> {
+        let planes = self
+            .planes
+            .iter()
+            .map(|plane| (plane.normal, plane.normal.dot(plane.origin)))
+            .chain(cube_faces(bound))
+            .collect::<Vec<_>>();
+
+        let tolerance = tolerance(bound);
+        let mut vertices: Vec<Vec3> = Vec::new();
+
+        for_each_combination::<3>(planes.len(), |indexes| {
+            let rows = indexes.map(|index| planes[index].0);
+            let matrix = Mat3::from_cols(rows[0], rows[1], rows[2]).transpose();
+
+            if matrix.determinant().abs() < EPSILON {
+                return;
+            }
+
+            let offsets = Vec3::from_array(indexes.map(|index| planes[index].1));
+            let vertex = matrix.inverse() * offsets;
+
+            let is_inside = planes
+                .iter()
+                .all(|(normal, offset)| normal.dot(vertex) >= offset - tolerance);
+            let is_new = vertices
+                .iter()
+                .all(|other| other.distance(vertex) > tolerance);
+
+            if is_inside && is_new {
+                vertices.push(vertex);
+            }
+        });
+
+        vertices
+    }
+}
+
+impl FromIterator<Plane> for HalfSpaceSet3 {
+    fn from_iter<T: IntoIterator<Item = Plane>>(iter: T) -> Self {
+        Self::new(iter.into_iter().collect())
+    }
+}
+
+fn tolerance(bound: f32) -> f32 {
+    EPSILON * bound.max(1.0)
+}
+
+// The faces of the cube as normal · x >= offset
+fn cube_faces(bound: f32) -> impl Iterator<Item = (Vec3, f32)> {
+    [Vec3::X, Vec3::Y, Vec3::Z, -Vec3::X, -Vec3::Y, -Vec3::Z]
+        .into_iter()
+        .map(move |normal| (normal, -bound))
+}
+
+fn for_each_combination<const N: usize>(len: usize, mut f: impl FnMut([usize; N])) {
+    if len < N {
+        return;
+    }
+
+    let mut indexes: [usize; N] = std::array::from_fn(|index| index);
+
+    loop {
+        f(indexes);
+
+        // Advance the last index that still has room to move and reset the ones after it
+        let Some(position) = (0..N)
+            .rev()
+            .find(|&position| indexes[position] < len - N + position)
+        else {
+            return;
+        };
+
+        indexes[position] += 1;
+        for next in position + 1..N {
+            indexes[next] = indexes[next - 1] + 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit_cube() -> HalfSpaceSet3 {
+        [Vec3::X, Vec3::Y, Vec3::Z, -Vec3::X, -Vec3::Y, -Vec3::Z]
+            .into_iter()
+            .map(|normal| Plane::new(-normal, normal))
+            .collect()
+    }
+
+    #[test]
+    fn test_for_each_combination() {
+        let mut combinations = Vec::new();
+        for_each_combination::<2>(4, |indexes| combinations.push(indexes));
+
+        assert_eq!(
+            combinations,
+            vec![[0, 1], [0, 2], [0, 3], [1, 2], [1, 3], [2, 3]]
+        );
+    }
+
+    #[test]
+    fn test_cube() {
+        let cube = unit_cube();
+
+        assert!(cube.contains(Vec3::splat(0.5)));
+        assert!(!cube.contains(Vec3::splat(1.5)));
+        assert!(cube.is_feasible(10.0));
+        assert_eq!(cube.vertices(10.0).len(), 8);
+
+        let (center, radius) = cube.chebyshev_center(10.0);
+        assert!(center.length() < 1e-4);
+        assert!((radius - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_unbounded_region_is_clipped() {
+        let half_space = HalfSpaceSet3::default().with_plane(Plane::new(Vec3::ZERO, Vec3::Z));
+        let vertices = half_space.vertices(2.0);
+
+        assert_eq!(vertices.len(), 8);
+        assert!(vertices.iter().all(|vertex| vertex.z >= -1e-4));
+
+        let (center, radius) = half_space.chebyshev_center(2.0);
+        assert!((radius - 1.0).abs() < 1e-4);
+        assert!((center.z - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_infeasible_planes() {
+        let planes = HalfSpaceSet3::new(vec![
+            Plane::new(Vec3::X, Vec3::X),
+            Plane::new(-Vec3::X, -Vec3::X),
+            Plane::new(Vec3::ZERO, Vec3::Y),
+        ]);
+
+        assert!(!planes.is_feasible(10.0));
+        assert!(planes.vertices(10.0).is_empty());
+
+        let (center, radius) = planes.chebyshev_center(10.0);
+        assert!((radius + 1.0).abs() < 1e-4);
+        let violated = planes.violated_planes(center).collect::<Vec<_>>();
+        assert!(violated.contains(&0) && violated.contains(&1));
+    }
+}
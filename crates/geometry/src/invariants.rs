@@ -0,0 +1,236 @@
+use std::fmt;
+
+use bevy_math::Vec3;
+
+use crate::{colliders::Collider, Plane, Vec3Operations};
+
+// The tolerance the invariant checks are usually run with, the shapes work with f32 so exact
+// comparisons would fail on rounding alone
+pub const INVARIANT_TOLERANCE: f32 = 1e-3;
+
+// A broken invariant found by one of the check functions
+// The checks are meant for property-based tests and for debug assertions in code using the
+// shapes and solvers. The violation contains the offending values, so a failing case can be
+// turned into a regression test.
+#[derive(Clone, Debug, PartialEq)]
+pub enum InvariantViolation {
+    // constrain() returned a point outside of the shape
+    ConstrainedPointOutside {
+        point: Vec3,
+        constrained: Vec3,
+        signed_distance: f32,
+    },
+    // constrain() moved a point that was already inside the shape
+    ContainedPointMoved {
+        point: Vec3,
+        constrained: Vec3,
+    },
+    // The support point of a Minkowski sum in some direction lies outside of the sum
+    SupportOutsideMinkowskiSum {
+        direction: Vec3,
+        support: Vec3,
+        signed_distance: f32,
+    },
+    // A velocity lies on the wrong side of a plane, index is the position of the plane
+    PlaneViolated {
+        index: usize,
+        velocity: Vec3,
+        signed_distance: f32,
+    },
+    // A velocity is faster than the maximum velocity
+    SpeedExceeded {
+        velocity: Vec3,
+        maximum_velocity: f32,
+    },
+    // The preferred velocity was allowed but a different velocity was returned
+    PreferredVelocityChanged {
+        preferred_velocity: Vec3,
+        velocity: Vec3,
+    },
+}
+
+impl fmt::Display for InvariantViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ConstrainedPointOutside {
+                point,
+                constrained,
+                signed_distance,
+            } => write!(
+                f,
+                "{point} was constrained to {constrained}, which is {signed_distance} outside"
+            ),
+            Self::ContainedPointMoved { point, constrained } => {
+                write!(f, "{point} is inside but was constrained to {constrained}")
+            }
+            Self::SupportOutsideMinkowskiSum {
+                direction,
+                support,
+                signed_distance,
+            } => write!(
+                f,
+                "the support {support} in direction {direction} is {signed_distance} outside the Minkowski sum"
+            ),
+            Self::PlaneViolated {
+                index,
+                velocity,
+                signed_distance,
+            } => write!(
+                f,
+                "{velocity} violates plane {index} by {}",
+                -signed_distance
+            ),
+            Self::SpeedExceeded {
+                velocity,
+                maximum_velocity,
+            } => write!(
+                f,
+                "{velocity} is faster than the maximum velocity {maximum_velocity}"
+            ),
+            Self::PreferredVelocityChanged {
+                preferred_velocity,
+                velocity,
+            } => write!(
+                f,
+                "the allowed preferred velocity {preferred_velocity} was changed to {velocity}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for InvariantViolation {}
+
+// The constrained point is inside the shape, and points inside the shape aren't moved
+// Meant for closed shapes with a positive signed distance outside, planes are checked with
+// check_planes_satisfied instead.
+pub fn check_constrain<T: Vec3Operations + ?Sized>(
+    shape: &T,
+    point: Vec3,
+    tolerance: f32,
+) -> Result<(), InvariantViolation> {
+    let constrained = shape.constrain(point);
+    let signed_distance = shape.signed_distance(constrained);
+
+    if signed_distance > tolerance {
+        return Err(InvariantViolation::ConstrainedPointOutside {
+            point,
+            constrained,
+            signed_distance,
+        });
+    }
+
+    if shape.signed_distance(point) < -tolerance && constrained.distance(point) > tolerance {
+        return Err(InvariantViolation::ContainedPointMoved { point, constrained });
+    }
+
+    Ok(())
+}
+
+// The Minkowski sum of a and b, as used for velocity obstacles, contains a and the negated b
+// swept over each other. So the support of a in a direction plus the support of the negated b
+// in the same direction has to be inside the sum.
+pub fn check_minkowski_sum_support(
+    a: &Collider,
+    b: &Collider,
+    direction: Vec3,
+    tolerance: f32,
+) -> Result<(), InvariantViolation> {
+    let support = a.support_point(direction) - b.support_point(-direction);
+    let signed_distance = a.minkowski_sum(b).signed_distance(support);
+
+    if signed_distance > tolerance {
+        return Err(InvariantViolation::SupportOutsideMinkowskiSum {
+            direction,
+            support,
+            signed_distance,
+        });
+    }
+
+    Ok(())
+}
+
+// The velocity is on the allowed side of every plane
+pub fn check_planes_satisfied(
+    velocity: Vec3,
+    planes: &[Plane],
+    tolerance: f32,
+) -> Result<(), InvariantViolation> {
+    for (index, plane) in planes.iter().enumerate() {
+        let signed_distance = plane.signed_distance(velocity);
+
+        if signed_distance < -tolerance {
+            return Err(InvariantViolation::PlaneViolated {
+                index,
+                velocity,
+                signed_distance,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::{Aabb, Sphere};
+
+    fn vec3(range: f32) -> impl Strategy<Value = Vec3> {
+        (-range..range, -range..range, -range..range).prop_map(|(x, y, z)| Vec3::new(x, y, z))
+    }
+
+    fn collider() -> impl Strategy<Value = Collider> {
+        prop_oneof![
+            (vec3(10.0), 0.1_f32..5.0)
+                .prop_map(|(origin, radius)| Collider::Sphere(Sphere::new(radius, origin))),
+            (vec3(10.0), (0.1_f32..5.0, 0.1_f32..5.0, 0.1_f32..5.0)).prop_map(
+                |(center, (x, y, z))| Collider::Aabb(Aabb::new(center, Vec3::new(x, y, z)))
+            ),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn constrain_stays_inside_colliders(shape in collider(), point in vec3(20.0)) {
+            prop_assert_eq!(check_constrain(&shape, point, INVARIANT_TOLERANCE), Ok(()));
+        }
+
+        #[test]
+        fn minkowski_sum_contains_supports(
+            a in collider(),
+            b in collider(),
+            direction in vec3(1.0),
+        ) {
+            prop_assert_eq!(
+                check_minkowski_sum_support(&a, &b, direction, INVARIANT_TOLERANCE),
+                Ok(())
+            );
+        }
+
+        #[test]
+        fn constrained_point_satisfies_plane(
+            origin in vec3(10.0),
+            normal in vec3(1.0).prop_filter("normal can't be zero", |n| n.length() > 0.1),
+            point in vec3(20.0),
+        ) {
+            let plane = Plane::new(origin, normal.normalize());
+
+            prop_assert_eq!(
+                check_planes_satisfied(plane.constrain(point), &[plane], INVARIANT_TOLERANCE),
+                Ok(())
+            );
+        }
+    }
+
+    #[test]
+    fn test_violations_are_reported() {
+        let plane = Plane::new(Vec3::ZERO, Vec3::X);
+
+        assert!(matches!(
+            check_planes_satisfied(Vec3::NEG_X, &[plane], INVARIANT_TOLERANCE),
+            Err(InvariantViolation::PlaneViolated { index: 0, .. })
+        ));
+    }
+}
@@ -0,0 +1,355 @@
+use std::{
+    io::{self, BufRead, Write},
+    path::Path,
+};
+
+use bevy_math::{Mat4, Vec3};
+
+use crate::{TriMesh, Triangle};
+
+// Writes triangle meshes in formats DCC tools like Blender can open, which helps to inspect
+// velocity obstacles and other generated geometry offline
+pub trait MeshExport {
+    // Wavefront OBJ with one object containing all triangles
+    fn export_obj<W: Write>(&self, writer: W) -> io::Result<()>;
+
+    // Self-contained glTF 2.0 JSON with the buffer embedded as a base64 data URI
+    fn export_gltf<W: Write>(&self, writer: W) -> io::Result<()>;
+}
+
+impl MeshExport for TriMesh {
+    fn export_obj<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "o mesh")?;
+
+        for vertex in &self.vertices {
+            writeln!(writer, "v {} {} {}", vertex.x, vertex.y, vertex.z)?;
+        }
+
+        // OBJ indices start at 1
+        for [a, b, c] in &self.indices {
+            writeln!(writer, "f {} {} {}", a + 1, b + 1, c + 1)?;
+        }
+
+        Ok(())
+    }
+
+    fn export_gltf<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let mut buffer = Vec::with_capacity(self.vertices.len() * 12 + self.indices.len() * 12);
+
+        for vertex in &self.vertices {
+            for coordinate in vertex.to_array() {
+                buffer.extend_from_slice(&coordinate.to_le_bytes());
+            }
+        }
+
+        let positions_length = buffer.len();
+
+        for index in self.indices.iter().flatten() {
+            buffer.extend_from_slice(&index.to_le_bytes());
+        }
+
+        let indices_length = buffer.len() - positions_length;
+        let aabb = self.get_aabb();
+        let min = aabb.center - aabb.half_sizes;
+        let max = aabb.center + aabb.half_sizes;
+
+        write!(
+            writer,
+            concat!(
+                "{{\"asset\":{{\"version\":\"2.0\"}},",
+                "\"scene\":0,\"scenes\":[{{\"nodes\":[0]}}],\"nodes\":[{{\"mesh\":0}}],",
+                "\"meshes\":[{{\"primitives\":[{{\"attributes\":{{\"POSITION\":0}},\"indices\":1}}]}}],",
+                "\"buffers\":[{{\"byteLength\":{},\"uri\":\"data:application/octet-stream;base64,{}\"}}],",
+                "\"bufferViews\":[",
+                "{{\"buffer\":0,\"byteOffset\":0,\"byteLength\":{},\"target\":34962}},",
+                "{{\"buffer\":0,\"byteOffset\":{},\"byteLength\":{},\"target\":34963}}],",
+                "\"accessors\":[",
+                "{{\"bufferView\":0,\"componentType\":5126,\"count\":{},\"type\":\"VEC3\",",
+                "\"min\":[{},{},{}],\"max\":[{},{},{}]}},",
+                "{{\"bufferView\":1,\"componentType\":5125,\"count\":{},\"type\":\"SCALAR\"}}]}}"
+            ),
+            buffer.len(),
+            encode_base64(&buffer),
+            positions_length,
+            positions_length,
+            indices_length,
+            self.vertices.len(),
+            min.x,
+            min.y,
+            min.z,
+            max.x,
+            max.y,
+            max.z,
+            self.indices.len() * 3,
+        )
+    }
+}
+
+impl MeshExport for [Triangle] {
+    fn export_obj<W: Write>(&self, writer: W) -> io::Result<()> {
+        TriMesh::from_triangles(self).export_obj(writer)
+    }
+
+    fn export_gltf<W: Write>(&self, writer: W) -> io::Result<()> {
+        TriMesh::from_triangles(self).export_gltf(writer)
+    }
+}
+
+// Reads the vertices and faces of all objects of a Wavefront OBJ file into a single mesh
+// Polygons are split into triangle fans, texture coordinates and normals are ignored.
+pub fn import_obj<R: BufRead>(reader: R) -> io::Result<TriMesh> {
+    let invalid_data = |line_number: usize, message: &str| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("line {}: {message}", line_number + 1),
+        )
+    };
+
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+
+    for (line_number, line) in reader.lines().enumerate() {
+        let line = line?;
+        let mut tokens = line.split_whitespace();
+
+        match tokens.next() {
+            Some("v") => {
+                let coordinates = tokens
+                    .take(3)
+                    .map(str::parse::<f32>)
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|_| invalid_data(line_number, "invalid vertex"))?;
+
+                if coordinates.len() != 3 {
+                    return Err(invalid_data(line_number, "vertex needs 3 coordinates"));
+                }
+
+                vertices.push(Vec3::new(coordinates[0], coordinates[1], coordinates[2]));
+            }
+            Some("f") => {
+                let face = tokens
+                    .map(|token| {
+                        // Only the vertex index of v/vt/vn is used, negative indices count
+                        // from the last vertex read so far
+                        let index = token
+                            .split('/')
+                            .next()
+                            .and_then(|index| index.parse::<i64>().ok())
+                            .ok_or_else(|| invalid_data(line_number, "invalid face index"))?;
+
+                        let index = if index < 0 {
+                            vertices.len() as i64 + index
+                        } else {
+                            index - 1
+                        };
+
+                        u32::try_from(index)
+                            .ok()
+                            .filter(|index| (*index as usize) < vertices.len())
+                            .ok_or_else(|| invalid_data(line_number, "face index out of range"))
+                    })
+                    .collect::<io::Result<Vec<_>>>()?;
+
+                if face.len() < 3 {
+                    return Err(invalid_data(line_number, "face needs at least 3 vertices"));
+                }
+
+                for i in 1..face.len() - 1 {
+                    indices.push([face[0], face[i], face[i + 1]]);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(TriMesh::new(vertices, indices))
+}
+
+// Reads the triangles of all meshes in the default scene of a glTF file (.gltf or .glb) into
+// a single mesh in the scene's space, the transforms of the nodes are applied
+pub fn import_gltf<P: AsRef<Path>>(path: P) -> Result<TriMesh, gltf::Error> {
+    let (document, buffers, _) = gltf::import(path)?;
+    let mut mesh = TriMesh::default();
+
+    let Some(scene) = document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+    else {
+        return Ok(mesh);
+    };
+
+    let mut nodes = scene
+        .nodes()
+        .map(|node| (node, Mat4::IDENTITY))
+        .collect::<Vec<_>>();
+
+    while let Some((node, parent_transform)) = nodes.pop() {
+        let transform = parent_transform * Mat4::from_cols_array_2d(&node.transform().matrix());
+
+        if let Some(node_mesh) = node.mesh() {
+            for primitive in node_mesh.primitives() {
+                if primitive.mode() != gltf::mesh::Mode::Triangles {
+                    continue;
+                }
+
+                let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+
+                let Some(positions) = reader.read_positions() else {
+                    continue;
+                };
+
+                #[allow(clippy::cast_possible_truncation)]
+                let offset = mesh.vertices.len() as u32;
+                mesh.vertices.extend(
+                    positions.map(|position| transform.transform_point3(Vec3::from(position))),
+                );
+
+                #[allow(clippy::cast_possible_truncation)]
+                let count = mesh.vertices.len() as u32 - offset;
+                let primitive_indices = match reader.read_indices() {
+                    Some(indices) => indices.into_u32().collect::<Vec<_>>(),
+                    None => (0..count).collect(),
+                };
+
+                mesh.indices.extend(
+                    primitive_indices.chunks_exact(3).map(|triangle| {
+                        [triangle[0], triangle[1], triangle[2]].map(|i| i + offset)
+                    }),
+                );
+            }
+        }
+
+        nodes.extend(node.children().map(|child| (child, transform)));
+    }
+
+    Ok(mesh)
+}
+
+fn encode_base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let value = (u32::from(bytes[0]) << 16) | (u32::from(bytes[1]) << 8) | u32::from(bytes[2]);
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[((value >> (18 - 6 * i)) & 0x3F) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_mesh() -> TriMesh {
+        TriMesh::new(
+            vec![Vec3::ZERO, Vec3::X, Vec3::Y, Vec3::Z],
+            vec![[0, 1, 2], [0, 2, 3]],
+        )
+    }
+
+    #[test]
+    fn test_export_obj() {
+        let mut output = Vec::new();
+        get_mesh().export_obj(&mut output).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        let lines = output.lines().collect::<Vec<_>>();
+
+        assert_eq!(lines.len(), 7);
+        assert_eq!(lines[2], "v 1 0 0");
+        assert_eq!(lines[5], "f 1 2 3");
+        assert_eq!(lines[6], "f 1 3 4");
+    }
+
+    #[test]
+    fn test_export_triangles_obj() {
+        let triangles = vec![Triangle::new([Vec3::ZERO, Vec3::X, Vec3::Y])];
+
+        let mut output = Vec::new();
+        triangles.export_obj(&mut output).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+
+        assert_eq!(
+            output.lines().filter(|line| line.starts_with("v ")).count(),
+            3
+        );
+        assert_eq!(
+            output.lines().filter(|line| line.starts_with("f ")).count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_export_gltf() {
+        let mut output = Vec::new();
+        get_mesh().export_gltf(&mut output).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+
+        // 4 vertices of 12 bytes and 6 indices of 4 bytes
+        assert!(output.contains("\"byteLength\":72,"));
+        assert!(output.contains("\"count\":4,"));
+        assert!(output.contains("\"count\":6,"));
+        assert!(output.contains("\"max\":[1,1,1]"));
+    }
+
+    #[test]
+    fn test_import_obj() {
+        let obj = "# quad and triangle\n\
+                   o test\n\
+                   v 0 0 0\n\
+                   v 1 0 0\n\
+                   v 1 1 0\n\
+                   v 0 1 0\n\
+                   vn 0 0 1\n\
+                   f 1//1 2//1 3//1 4//1\n\
+                   f -4 -3 -1\n";
+
+        let mesh = import_obj(obj.as_bytes()).unwrap();
+
+        assert_eq!(mesh.vertices.len(), 4);
+        assert_eq!(mesh.indices, vec![[0, 1, 2], [0, 2, 3], [0, 1, 3]]);
+    }
+
+    #[test]
+    fn test_import_obj_rejects_invalid_index() {
+        let obj = "v 0 0 0\nv 1 0 0\nf 1 2 3\n";
+
+        assert!(import_obj(obj.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_obj_round_trip() {
+        let mut output = Vec::new();
+        get_mesh().export_obj(&mut output).unwrap();
+
+        let mesh = import_obj(output.as_slice()).unwrap();
+
+        assert_eq!(mesh.vertices, get_mesh().vertices);
+        assert_eq!(mesh.indices, get_mesh().indices);
+    }
+
+    #[test]
+    fn test_encode_base64() {
+        assert_eq!(encode_base64(b""), "");
+        assert_eq!(encode_base64(b"f"), "Zg==");
+        assert_eq!(encode_base64(b"fo"), "Zm8=");
+        assert_eq!(encode_base64(b"foo"), "Zm9v");
+        assert_eq!(encode_base64(b"foobar"), "Zm9vYmFy");
+    }
+}
@@ -5,19 +5,26 @@ mod arc;
 mod circle;
 mod circle_3d;
 mod cone;
+mod convex_hull;
 mod half_plane;
+mod half_space_set;
 mod hyperplane;
+mod invariants;
+#[cfg(feature = "io")]
+mod io;
 mod line_segment_2d;
 mod line_segment_3d;
 mod matrix;
 mod plane;
 mod points;
+mod polyline;
 mod ray_2d;
 mod ray_3d;
 mod sphere;
 mod spherinder;
 mod spherinder_hyperplane_intersecion;
 mod spherinder_hyperplane_plane_intersecion;
+mod tri_mesh;
 mod triangle;
 
 pub mod colliders;
@@ -27,17 +34,24 @@ pub use arc::*;
 pub use circle::*;
 pub use circle_3d::*;
 pub use cone::*;
+pub use convex_hull::*;
 pub use half_plane::*;
+pub use half_space_set::*;
 pub use hyperplane::*;
+pub use invariants::*;
+#[cfg(feature = "io")]
+pub use io::*;
 pub use line_segment_2d::*;
 pub use line_segment_3d::*;
 pub use matrix::*;
 pub use plane::*;
 pub use points::*;
+pub use polyline::*;
 pub use ray_2d::*;
 pub use ray_3d::*;
 pub use sphere::*;
 pub use spherinder::*;
 pub use spherinder_hyperplane_intersecion::*;
 pub use spherinder_hyperplane_plane_intersecion::*;
+pub use tri_mesh::*;
 pub use triangle::*;
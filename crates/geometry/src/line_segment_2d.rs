@@ -2,7 +2,7 @@ use bevy_math::Vec2;
 
 use crate::{Ray2D, Ray2DIntersection, Ray2DIntersectionResult, Vec2Operations, EPSILON};
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct LineSegment2D {
     pub origin: Vec2,
     pub direction: Vec2,
@@ -37,9 +37,35 @@ impl LineSegment2D {
         Ray2D::new(self.origin, self.direction)
     }
 
+    pub fn start(&self) -> Vec2 {
+        self.origin + self.direction * self.t_min
+    }
+
     pub fn end(&self) -> Vec2 {
         self.origin + self.direction * self.t_max
     }
+
+    #[must_use]
+    pub fn tangent(&self) -> Vec2 {
+        self.direction.normalize_or_zero()
+    }
+
+    // Returns the normal on the left side of the segment when looking along its direction
+    #[must_use]
+    pub fn normal(&self) -> Vec2 {
+        self.tangent().perp()
+    }
+
+    // Returns the parallel segment at the given distance, positive distances go to the left side
+    #[must_use]
+    pub fn offset(&self, distance: f32) -> Self {
+        Self::new(
+            self.origin + self.normal() * distance,
+            self.direction,
+            self.t_min,
+            self.t_max,
+        )
+    }
 }
 
 impl Vec2Operations for LineSegment2D {
@@ -144,3 +170,21 @@ impl LineSegment2DIntersection for LineSegment2D {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_offset() {
+        let segment = LineSegment2D::from_two_points(Vec2::ZERO, Vec2::X * 2.0);
+
+        assert_eq!(segment.tangent(), Vec2::X);
+        assert_eq!(segment.normal(), Vec2::Y);
+
+        let offset = segment.offset(-1.5);
+
+        assert!(offset.start().abs_diff_eq(Vec2::new(0.0, -1.5), 1e-5));
+        assert!(offset.end().abs_diff_eq(Vec2::new(2.0, -1.5), 1e-5));
+    }
+}
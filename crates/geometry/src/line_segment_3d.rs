@@ -2,6 +2,7 @@ use bevy_math::Vec3;
 
 use crate::{Ray3D, Vec3Operations, EPSILON};
 
+#[derive(Clone, Debug)]
 pub struct LineSegment3D {
     pub origin: Vec3,
     pub direction: Vec3,
@@ -40,6 +41,46 @@ impl LineSegment3D {
         self.t_max - self.t_min
     }
 
+    pub fn start(&self) -> Vec3 {
+        self.origin + self.direction * self.t_min
+    }
+
+    pub fn end(&self) -> Vec3 {
+        self.origin + self.direction * self.t_max
+    }
+
+    #[must_use]
+    pub fn tangent(&self) -> Vec3 {
+        self.direction.normalize_or_zero()
+    }
+
+    // Returns the normal of the segment that is closest to the up vector
+    // A straight segment has no curvature to define its normal, so the up vector picks one. When
+    // up is parallel to the segment any perpendicular direction is returned.
+    #[must_use]
+    pub fn normal(&self, up: Vec3) -> Vec3 {
+        let tangent = self.tangent();
+
+        (up - tangent * up.dot(tangent))
+            .try_normalize()
+            .unwrap_or_else(|| tangent.any_orthonormal_vector())
+    }
+
+    // Completes the tangent and normal to a right-handed frame
+    #[must_use]
+    pub fn binormal(&self, up: Vec3) -> Vec3 {
+        self.tangent().cross(self.normal(up))
+    }
+
+    // Returns the parallel segment moved by the part of offset perpendicular to the segment
+    #[must_use]
+    pub fn offset(&self, offset: Vec3) -> Self {
+        let tangent = self.tangent();
+        let offset = offset - tangent * offset.dot(tangent);
+
+        Self::new(self.origin + offset, self.direction, self.t_min, self.t_max)
+    }
+
     pub fn parameter_at_point(&self, pt: Vec3) -> f32 {
         let relative_pt = pt - self.origin;
 
@@ -95,3 +136,32 @@ impl Vec3Operations for LineSegment3D {
         (self.origin + self.direction * t - pt).length()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame() {
+        let segment = LineSegment3D::from_two_points(Vec3::ZERO, Vec3::X * 3.0);
+
+        assert_eq!(segment.tangent(), Vec3::X);
+        assert!(segment
+            .normal(Vec3::new(1.0, 1.0, 0.0))
+            .abs_diff_eq(Vec3::Y, 1e-5));
+        assert!(segment.binormal(Vec3::Y).abs_diff_eq(Vec3::Z, 1e-5));
+
+        let normal = segment.normal(Vec3::X);
+        assert!(normal.dot(Vec3::X).abs() < 1e-5);
+        assert!((normal.length() - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_offset() {
+        let segment = LineSegment3D::from_two_points(Vec3::ZERO, Vec3::X * 3.0);
+        let offset = segment.offset(Vec3::new(5.0, 0.0, 2.0));
+
+        assert!(offset.start().abs_diff_eq(Vec3::Z * 2.0, 1e-5));
+        assert!(offset.end().abs_diff_eq(Vec3::new(3.0, 0.0, 2.0), 1e-5));
+    }
+}
@@ -358,11 +358,7 @@ impl Matrix {
             .collect();
 
         for k in 0..n {
-            let mut norm = 0.0;
-            for i in 0..m {
-                norm += a_columns[k][i] * a_columns[k][i];
-            }
-            norm = norm.sqrt();
+            let norm = a_columns[k].iter().map(|a| a * a).sum::<f32>().sqrt();
             r[k * n + k] = norm;
 
             for i in 0..m {
@@ -409,19 +405,18 @@ impl Matrix {
         Some(Matrix::new(inv, n, n))
     }
 
-    pub fn pseudoinverse(&self) -> Matrix {
+    // Returns None if the decomposition doesn't give matrices of matching dimensions
+    pub fn pseudoinverse(&self) -> Option<Matrix> {
         let (mut q, r) = self.qr_decompose();
 
         // Invert R
-        let r_inv = r
-            .invert_upper_triangular()
-            .expect("R matrix from QR decomposition isn't a square matrix");
+        let r_inv = r.invert_upper_triangular()?;
 
         // Compute Q^T
         q.transpose();
 
         // Multiply R^{-1} * Q^T
-        r_inv.mul_left(&q).expect("Matrix cannot be multiplied")
+        r_inv.mul_left(&q)
     }
 
     pub fn add(&mut self, rhs: &Matrix) -> Option<()> {
@@ -716,7 +711,7 @@ mod tests {
     #[test]
     fn test_pseudoinverse2x2() {
         let m1 = Matrix::from_slice([[1.0, 2.0], [3.0, 4.0]]);
-        let m2 = m1.pseudoinverse();
+        let m2 = m1.pseudoinverse().unwrap();
 
         let m3 = (m1.clone() * m2).expect("Matrix cannot be multiplied");
 
@@ -734,7 +729,7 @@ mod tests {
     #[test]
     fn test_pseudoinverse3x3() {
         let m1 = Matrix::from_slice([[1.0, 2.0, 3.0], [4.0, 5.0, 6.0], [7.0, 8.0, 9.0]]);
-        let m2 = m1.pseudoinverse();
+        let m2 = m1.pseudoinverse().unwrap();
 
         let m3 = (m1.clone() * m2).expect("Matrix cannot be multiplied");
 
@@ -764,7 +759,7 @@ mod tests {
             [1.0, -5.0, -10.0, -8.0, 5.0, 1.0, 1.0, 3.0, 6.0, -7.0],
         ]);
 
-        let inverse = m1.pseudoinverse();
+        let inverse = m1.pseudoinverse().unwrap();
 
         let mul = (inverse * m1).expect("Matrix cannot be multiplied");
 
@@ -3,6 +3,11 @@ use bevy_math::{Vec2, Vec3};
 use crate::{Hyperplane, Ray2DIntersection, Vec2Operations, Vec3Operations, EPSILON};
 
 #[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    archive(check_bytes)
+)]
 pub struct Plane {
     pub normal: Vec3,
     pub origin: Vec3,
@@ -32,8 +37,11 @@ impl Plane {
             return None;
         }
 
-        let normal = Vec3::new(normal_x, normal_y, normal_z).normalize();
-        let origin = d_result * normal;
+        // The projected normal isn't unit length unless the hyperplanes are orthogonal
+        let projected_normal = Vec3::new(normal_x, normal_y, normal_z);
+        let length = projected_normal.length();
+        let normal = projected_normal / length;
+        let origin = d_result / length * normal;
 
         Some(Self::new(origin, normal))
     }
@@ -67,6 +75,26 @@ impl Plane {
         }
     }
 
+    // Rebuilds the basis of the plane with u_direction as close to the hint as possible
+    // The default basis is picked from the world axes and flips between them as the normal turns,
+    // so 2D coordinates of the same point jump between frames. A hint that follows the geometry,
+    // e.g. a velocity lying in the plane, keeps the basis steady. A hint parallel to the normal
+    // keeps the current basis.
+    #[must_use]
+    pub fn with_basis_hint(mut self, hint: Vec3) -> Self {
+        let Some(u_direction) = (hint - self.normal * hint.dot(self.normal)).try_normalize() else {
+            return self;
+        };
+
+        self.u_direction = u_direction;
+        self.v_direction = self.normal.cross(u_direction).normalize();
+        self
+    }
+
+    // Returns the coordinates of a point in the (u_direction, v_direction) basis of the plane
+    // The part of the point along the normal is dropped, so project_3d(project_2d(p)) is the
+    // projection of p onto the plane. The basis is orthonormal and u × v is the normal, so
+    // distances, angles and the orientation of shapes are the same in 2D and in the plane.
     #[must_use]
     pub fn project_2d(&self, p: Vec3) -> Vec2 {
         let u = self.u_direction.dot(p - self.origin);
@@ -75,6 +103,7 @@ impl Plane {
         Vec2::new(u, v)
     }
 
+    // Returns the point of the plane at the given 2D coordinates, the inverse of project_2d
     #[must_use]
     pub fn project_3d(&self, p: Vec2) -> Vec3 {
         Vec3::new(
@@ -117,7 +146,10 @@ impl Vec3Operations for Plane {
 
 #[cfg(test)]
 mod tests {
+    use bevy_math::Vec4;
+
     use super::*;
+    use crate::Vec4Operations;
 
     #[test]
     fn test_plane_new() {
@@ -183,4 +215,46 @@ mod tests {
 
         assert!(plane.contains(point));
     }
+
+    #[test]
+    fn test_plane_from_oblique_hyperplane_intersection() {
+        let hyperplane = Hyperplane::new(Vec4::ZERO, Vec4::W);
+        let other = Hyperplane::new(Vec4::new(0.0, 0.0, 0.0, 2.0), Vec4::new(1.0, 0.0, 0.0, 1.0));
+        let plane = Plane::from_hyperplane_intersection(&hyperplane, &other).unwrap();
+
+        // Every point of the plane has to lie on the other hyperplane as well
+        for point in [plane.origin, plane.origin + plane.u_direction * 3.0] {
+            let distance = other.signed_distance(hyperplane.project_4d(point));
+            assert!(distance.abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_plane_with_basis_hint() {
+        let plane = Plane::new(Vec3::ONE, Vec3::Z).with_basis_hint(Vec3::new(1.0, 1.0, 5.0));
+        let u_direction = Vec3::new(1.0, 1.0, 0.0).normalize();
+
+        assert!(plane.u_direction.abs_diff_eq(u_direction, 1e-5));
+        assert!(plane
+            .u_direction
+            .cross(plane.v_direction)
+            .abs_diff_eq(plane.normal, 1e-5));
+        assert!(plane
+            .project_2d(Vec3::ONE + u_direction * 2.0)
+            .abs_diff_eq(Vec2::new(2.0, 0.0), 1e-5));
+
+        let point = Vec3::new(3.0, -2.0, 7.0);
+        assert!(plane
+            .project_3d(plane.project_2d(point))
+            .abs_diff_eq(plane.constrain(point), 1e-5));
+    }
+
+    #[test]
+    fn test_plane_basis_hint_parallel_to_normal() {
+        let plane = Plane::new(Vec3::ZERO, Vec3::Z);
+        let hinted = plane.clone().with_basis_hint(Vec3::Z * 3.0);
+
+        assert_eq!(hinted.u_direction, plane.u_direction);
+        assert_eq!(hinted.v_direction, plane.v_direction);
+    }
 }
@@ -0,0 +1,162 @@
+use bevy_math::{Vec2, Vec3};
+
+use crate::EPSILON;
+
+// Limits how far a vertex of an offset polyline can move at sharp corners, in multiples of the
+// offset distance. Without it almost reversing corners would shoot off towards infinity.
+pub const MITER_LIMIT: f32 = 4.0;
+
+// Returns the parallel curve of a polyline at the given distance
+// Positive distances go to the left side when looking along the polyline. Corners are mitered,
+// so the offset segments stay parallel to the original ones and at exactly the distance from
+// them, up to MITER_LIMIT. Repeated points are skipped.
+#[must_use]
+pub fn offset_polyline_2d(points: &[Vec2], distance: f32) -> Vec<Vec2> {
+    let points = points
+        .iter()
+        .map(|point| point.extend(0.0))
+        .collect::<Vec<_>>();
+
+    offset_polyline_3d(&points, Vec3::Z, distance)
+        .into_iter()
+        .map(Vec3::truncate)
+        .collect()
+}
+
+// Returns the parallel curve of a polyline at the given distance sideways from it
+// The side is perpendicular to both the polyline and the up vector, positive distances go to the
+// left side when looking along the polyline with up pointing up. Segments parallel to up use
+// the side of their neighbours.
+#[must_use]
+pub fn offset_polyline_3d(points: &[Vec3], up: Vec3, distance: f32) -> Vec<Vec3> {
+    let mut points = points.to_vec();
+    points.dedup_by(|a, b| a.distance_squared(*b) < EPSILON);
+
+    if points.len() < 2 {
+        return points;
+    }
+
+    let sides = polyline_sides(&points, up);
+
+    points
+        .iter()
+        .enumerate()
+        .map(|(index, point)| {
+            let previous = sides[index.saturating_sub(1)];
+            let next = sides[index.min(sides.len() - 1)];
+
+            *point + miter(previous, next) * distance
+        })
+        .collect()
+}
+
+// Returns the unit tangent of the polyline at each of its points
+// Inner points get the average direction of the segments around them.
+#[must_use]
+pub fn polyline_tangents(points: &[Vec3]) -> Vec<Vec3> {
+    if points.len() < 2 {
+        return vec![Vec3::ZERO; points.len()];
+    }
+
+    let directions = points
+        .windows(2)
+        .map(|segment| (segment[1] - segment[0]).normalize_or_zero())
+        .collect::<Vec<_>>();
+
+    (0..points.len())
+        .map(|index| {
+            let previous = directions[index.saturating_sub(1)];
+            let next = directions[index.min(directions.len() - 1)];
+
+            (previous + next).try_normalize().unwrap_or(next)
+        })
+        .collect()
+}
+
+// Left side of every segment of the polyline
+fn polyline_sides(points: &[Vec3], up: Vec3) -> Vec<Vec3> {
+    let mut sides = points
+        .windows(2)
+        .map(|segment| up.cross(segment[1] - segment[0]).try_normalize())
+        .collect::<Vec<_>>();
+
+    // Segments going along up borrow the side of the closest segment that has one
+    let fallback = sides
+        .iter()
+        .flatten()
+        .copied()
+        .next()
+        .unwrap_or_else(|| up.any_orthonormal_vector());
+
+    let mut last = fallback;
+    for side in &mut sides {
+        last = side.unwrap_or(last);
+        *side = Some(last);
+    }
+
+    sides.into_iter().flatten().collect()
+}
+
+fn miter(previous: Vec3, next: Vec3) -> Vec3 {
+    let Some(direction) = (previous + next).try_normalize() else {
+        return next;
+    };
+
+    let scale = direction.dot(next).max(MITER_LIMIT.recip()).recip();
+
+    direction * scale
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_offset_straight_polyline() {
+        let points = [Vec2::ZERO, Vec2::X, Vec2::X * 2.0];
+        let offset = offset_polyline_2d(&points, 1.0);
+
+        assert_eq!(offset.len(), 3);
+        for (point, offset) in points.iter().zip(&offset) {
+            assert!(offset.abs_diff_eq(*point + Vec2::Y, 1e-5));
+        }
+    }
+
+    #[test]
+    fn test_offset_corner_keeps_distance() {
+        let points = [Vec2::ZERO, Vec2::X * 2.0, Vec2::new(2.0, 2.0)];
+        let offset = offset_polyline_2d(&points, -0.5);
+
+        assert!(offset[0].abs_diff_eq(Vec2::new(0.0, -0.5), 1e-5));
+        assert!(offset[1].abs_diff_eq(Vec2::new(2.5, -0.5), 1e-5));
+        assert!(offset[2].abs_diff_eq(Vec2::new(2.5, 2.0), 1e-5));
+    }
+
+    #[test]
+    fn test_offset_reversing_polyline_is_limited() {
+        let points = [Vec2::ZERO, Vec2::X, Vec2::new(0.0, 0.001)];
+        let offset = offset_polyline_2d(&points, 1.0);
+
+        assert!(offset[1].distance(points[1]) <= MITER_LIMIT + 1e-4);
+    }
+
+    #[test]
+    fn test_offset_3d_uses_up_vector() {
+        let points = [Vec3::ZERO, Vec3::X, Vec3::X, Vec3::new(1.0, 2.0, 0.0)];
+        let offset = offset_polyline_3d(&points, Vec3::Y, 1.0);
+
+        assert_eq!(offset.len(), 3);
+        assert!(offset[0].abs_diff_eq(-Vec3::Z, 1e-5));
+        assert!(offset[1].abs_diff_eq(Vec3::new(1.0, 0.0, -1.0), 1e-5));
+        assert!(offset[2].abs_diff_eq(Vec3::new(1.0, 2.0, -1.0), 1e-5));
+    }
+
+    #[test]
+    fn test_polyline_tangents() {
+        let tangents = polyline_tangents(&[Vec3::ZERO, Vec3::X, Vec3::new(1.0, 1.0, 0.0)]);
+
+        assert_eq!(tangents[0], Vec3::X);
+        assert!(tangents[1].abs_diff_eq(Vec3::new(1.0, 1.0, 0.0).normalize(), 1e-5));
+        assert_eq!(tangents[2], Vec3::Y);
+    }
+}
@@ -37,6 +37,39 @@ impl Ray3D {
     }
 }
 
+// Represents the result of a 3D ray intersection.
+// None: No intersection.
+// Point: The ray touches the shape at the parameter t.
+// Segment: The ray passes through the shape, entering it at t1 and leaving it at t2.
+//
+// The parameters represent the distance from the ray origin to the intersection point.
+// Intersections that lie entirely behind the ray origin are not reported, but t1 can be negative
+// when the ray origin is inside the shape.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Ray3DIntersectionResult {
+    None,
+    Point(f32),
+    Segment(f32, f32),
+}
+
+impl Ray3DIntersectionResult {
+    // Returns the first parameter at which the ray hits the shape, ignoring the part of the ray
+    // behind its origin.
+    #[must_use]
+    pub fn first_hit(&self) -> Option<f32> {
+        match *self {
+            Ray3DIntersectionResult::None => None,
+            Ray3DIntersectionResult::Point(t) => Some(t),
+            Ray3DIntersectionResult::Segment(t1, _) => Some(t1.max(0.0)),
+        }
+    }
+}
+
+// Represents an object that can be intersected by a 3D ray.
+pub trait Ray3DIntersection {
+    fn intersect_ray(&self, ray: &Ray3D) -> Ray3DIntersectionResult;
+}
+
 impl Vec3Operations for Ray3D {
     fn contains(&self, pt: Vec3) -> bool {
         let projected = (pt - self.origin).dot(self.direction) * self.direction;
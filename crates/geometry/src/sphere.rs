@@ -1,14 +1,46 @@
-use bevy_math::Vec3;
+use bevy_math::{Vec2, Vec3};
 
-use crate::{Circle, Circle3d, Plane, PlaneIntersecion, PlaneIntersecionShape, Vec3Operations};
+use crate::{
+    Circle, Circle3d, Cone, LineSegment2D, LineSegment3D, Plane, PlaneIntersecion,
+    PlaneIntersecionShape, Ray3D, Ray3DIntersection, Ray3DIntersectionResult, Vec2Operations,
+    Vec3Operations, EPSILON,
+};
 
 // Defines a 3D sphere with a radius and origin.
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "reflect", derive(bevy_reflect::Reflect))]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    archive(check_bytes)
+)]
 pub struct Sphere {
     pub radius: f32,
     pub origin: Vec3,
 }
 
+// The cone of directions from an apex that hit a sphere, its surface is tangent to the sphere
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TangentCone {
+    pub apex: Vec3,
+    pub axis: Vec3,
+    pub half_angle: f32,
+}
+
+impl TangentCone {
+    // Returns true if a ray from the apex in this direction hits the sphere
+    #[must_use]
+    pub fn contains_direction(&self, direction: Vec3) -> bool {
+        direction.angle_between(self.axis) <= self.half_angle
+    }
+
+    // Returns the infinite cone starting at the apex
+    #[must_use]
+    pub fn to_cone(&self) -> Cone {
+        Cone::infinite(self.apex, self.axis, self.half_angle.tan())
+    }
+}
+
 pub enum SphereSphereIntersection {
     Inside,
     Outside,
@@ -95,6 +127,103 @@ impl Sphere {
     pub fn distance_to(&self, other: &Sphere) -> f32 {
         self.origin.distance(other.origin) - (self.radius + other.radius)
     }
+
+    // Returns the parameters at which the segment enters and leaves the sphere
+    // The parameters are clipped to the bounds of the segment, so a segment starting inside the
+    // sphere enters it at t_min.
+    #[must_use]
+    pub fn intersect_segment(&self, segment: &LineSegment3D) -> Option<(f32, f32)> {
+        let a = segment.direction.length_squared();
+
+        if a < EPSILON {
+            return None;
+        }
+
+        let relative_origin = segment.origin - self.origin;
+        let b = relative_origin.dot(segment.direction);
+        let c = relative_origin.length_squared() - self.radius * self.radius;
+        let discriminant = b * b - a * c;
+
+        if discriminant < 0.0 {
+            return None;
+        }
+
+        let sqrt_discriminant = discriminant.sqrt();
+        let t1 = ((-b - sqrt_discriminant) / a).max(segment.t_min);
+        let t2 = ((-b + sqrt_discriminant) / a).min(segment.t_max);
+
+        if t1 > t2 {
+            return None;
+        }
+
+        Some((t1, t2))
+    }
+
+    // Returns true if the sphere overlaps the solid cone, including its caps
+    #[must_use]
+    pub fn intersects_cone(&self, cone: &Cone) -> bool {
+        cone.contains(self.origin) || distance_to_cone(cone, self.origin) <= self.radius
+    }
+
+    // Returns the cone of directions from the point that hit the sphere
+    // There is no such cone when the point is inside the sphere.
+    #[must_use]
+    pub fn tangent_cone_from_point(&self, point: Vec3) -> Option<TangentCone> {
+        let relative_origin = self.origin - point;
+        let distance = relative_origin.length();
+
+        if distance <= self.radius {
+            return None;
+        }
+
+        Some(TangentCone {
+            apex: point,
+            axis: relative_origin / distance,
+            half_angle: (self.radius / distance).asin(),
+        })
+    }
+}
+
+// Distance from a point outside the cone to its surface
+// The cone is rotationally symmetric, so it's enough to look at its cross section in the plane
+// of the axis and the point, with x along the axis and y away from it.
+fn distance_to_cone(cone: &Cone, pt: Vec3) -> f32 {
+    let relative_pt = pt - cone.vertex;
+    let x = relative_pt.dot(cone.direction);
+    let y = (relative_pt - cone.direction * x).length();
+    let pt = Vec2::new(x, y);
+
+    let min_height = cone.min_height.unwrap_or(0.0);
+    let near_rim = Vec2::new(min_height, cone.radius * min_height);
+    let lateral_length = cone.max_height.map_or(f32::INFINITY, |max_height| {
+        near_rim.distance(Vec2::new(max_height, cone.radius * max_height))
+    });
+
+    let lateral = LineSegment2D::new(
+        near_rim,
+        Vec2::new(1.0, cone.radius).normalize(),
+        0.0,
+        lateral_length,
+    );
+    let near_cap = LineSegment2D::new(Vec2::new(min_height, 0.0), Vec2::Y, 0.0, near_rim.y);
+
+    let distance = lateral
+        .signed_distance(pt)
+        .min(near_cap.signed_distance(pt));
+
+    match cone.max_height {
+        Some(max_height) => {
+            let far_cap = LineSegment2D::new(
+                Vec2::new(max_height, 0.0),
+                Vec2::Y,
+                0.0,
+                cone.radius * max_height,
+            );
+
+            distance.min(far_cap.signed_distance(pt))
+        }
+        None => distance,
+    }
 }
 
 pub trait SphereMinkowskiSum {
@@ -147,6 +276,41 @@ impl Vec3Operations for Sphere {
     }
 }
 
+impl Ray3DIntersection for Sphere {
+    fn intersect_ray(&self, ray: &Ray3D) -> Ray3DIntersectionResult {
+        // Substituting the ray equation into the sphere equation gives a quadratic
+        // t^2 + 2bt + c = 0, the direction of the ray is normalized so the leading term is 1.
+        let relative_origin = ray.origin - self.origin;
+        let b = relative_origin.dot(ray.direction);
+        let c = relative_origin.length_squared() - self.radius * self.radius;
+        let discriminant = b * b - c;
+
+        if discriminant < 0.0 {
+            return Ray3DIntersectionResult::None;
+        }
+
+        if discriminant < EPSILON {
+            let t = -b;
+
+            return if t >= 0.0 {
+                Ray3DIntersectionResult::Point(t)
+            } else {
+                Ray3DIntersectionResult::None
+            };
+        }
+
+        let sqrt_discriminant = discriminant.sqrt();
+        let t1 = -b - sqrt_discriminant;
+        let t2 = -b + sqrt_discriminant;
+
+        if t2 < 0.0 {
+            return Ray3DIntersectionResult::None;
+        }
+
+        Ray3DIntersectionResult::Segment(t1, t2)
+    }
+}
+
 impl PlaneIntersecion for Sphere {
     fn intersect(&self, plane: &Plane) -> Option<impl PlaneIntersecionShape> {
         self.intersect_plane(plane)
@@ -170,3 +334,64 @@ impl SphereIntersection for Sphere {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_intersect_segment() {
+        let sphere = Sphere::new(1.0, Vec3::X * 3.0);
+
+        let through = LineSegment3D::from_two_points(Vec3::ZERO, Vec3::X * 10.0);
+        let (t1, t2) = sphere.intersect_segment(&through).unwrap();
+        assert!((t1 - 2.0).abs() < 1e-5);
+        assert!((t2 - 4.0).abs() < 1e-5);
+
+        let ending_inside = LineSegment3D::from_two_points(Vec3::ZERO, Vec3::X * 3.0);
+        let (t1, t2) = sphere.intersect_segment(&ending_inside).unwrap();
+        assert!((t1 - 2.0).abs() < 1e-5);
+        assert!((t2 - 3.0).abs() < 1e-5);
+
+        let too_short = LineSegment3D::from_two_points(Vec3::ZERO, Vec3::X);
+        assert_eq!(sphere.intersect_segment(&too_short), None);
+    }
+
+    #[test]
+    fn test_intersects_cone() {
+        let cone = Cone::infinite(Vec3::ZERO, Vec3::X, 0.5);
+
+        assert!(Sphere::new(0.1, Vec3::X * 5.0).intersects_cone(&cone));
+        assert!(Sphere::new(1.5, Vec3::new(5.0, 4.0, 0.0)).intersects_cone(&cone));
+        assert!(!Sphere::new(1.0, Vec3::new(5.0, 4.0, 0.0)).intersects_cone(&cone));
+        assert!(!Sphere::new(0.5, -Vec3::X).intersects_cone(&cone));
+
+        // Spheres in front of the flat cap of a truncated cone
+        let truncated = Cone::new(1.0, Vec3::X * 2.0, 2.0, Vec3::X * 4.0);
+        assert!(Sphere::new(0.6, Vec3::X * 1.5).intersects_cone(&truncated));
+        assert!(!Sphere::new(0.4, Vec3::X * 1.5).intersects_cone(&truncated));
+    }
+
+    #[test]
+    fn test_tangent_cone_from_point() {
+        let sphere = Sphere::new(1.0, Vec3::X * 2.0);
+        let tangent_cone = sphere.tangent_cone_from_point(Vec3::ZERO).unwrap();
+
+        assert_eq!(tangent_cone.axis, Vec3::X);
+        assert!((tangent_cone.half_angle - std::f32::consts::FRAC_PI_6).abs() < 1e-5);
+        assert!(tangent_cone.contains_direction(Vec3::new(1.0, 0.5, 0.0)));
+        assert!(!tangent_cone.contains_direction(Vec3::new(1.0, 0.6, 0.0)));
+
+        // The surface of the cone touches the sphere
+        let tangent = Vec3::new(
+            tangent_cone.half_angle.cos(),
+            tangent_cone.half_angle.sin(),
+            0.0,
+        );
+        let touching_point = tangent * tangent.dot(sphere.origin);
+        assert!(sphere.signed_distance(touching_point).abs() < 1e-4);
+        assert!(tangent_cone.to_cone().contains(sphere.origin));
+
+        assert_eq!(sphere.tangent_cone_from_point(Vec3::X * 1.5), None);
+    }
+}
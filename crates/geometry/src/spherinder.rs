@@ -1,43 +1,68 @@
-use bevy_math::{Vec4, Vec4Swizzles};
+use bevy_math::{Vec3, Vec4, Vec4Swizzles};
 
 use crate::{
     Hyperplane, HyperplaneIntersecionShape, HyperplaneIntersection,
-    SpherinderHyperplaneIntersecion, Vec4Operations,
+    SpherinderHyperplaneIntersecion, Vec4Operations, EPSILON,
 };
 
-// A four dimensional shape that is a sphere in three dimensions extended into the fourth dimension.
+// A four dimensional shape that is an ellipsoid in three dimensions extended into the fourth dimension.
 // This shape assumes that it extends into the w dimension infinitely.
+// With equal radii the cross-section is a sphere, which is what the 4D relaxation of the solver
+// uses by default. Different radii make the shape a duocylinder-like body whose cross-section
+// is an axis aligned ellipsoid.
 #[derive(Clone, Debug)]
 pub struct Spherinder {
     pub origin: Vec4,
-    pub radius: f32,
+    pub radii: Vec3,
 }
 
 impl Spherinder {
     #[must_use]
     pub fn new(origin: Vec4, radius: f32) -> Self {
-        Self { origin, radius }
+        Self::with_radii(origin, Vec3::splat(radius))
+    }
+
+    #[must_use]
+    pub fn with_radii(origin: Vec4, radii: Vec3) -> Self {
+        Self { origin, radii }
+    }
+
+    // Weights of the axes in the equation of the cross-section, sum(weight * x^2) = radius^2
+    // The radius is the largest of the radii, so a spherical cross-section has all weights 1.
+    #[must_use]
+    pub fn axis_weights(&self) -> (Vec3, f32) {
+        let radius = self.radii.max_element();
+
+        ((radius / self.radii).powf(2.0), radius)
+    }
+
+    // How far the point is from the origin relative to the boundary in the same direction,
+    // 1 being on the boundary
+    fn relative_length(&self, xyz: Vec3) -> f32 {
+        (xyz / self.radii).length()
     }
 }
 
 impl Vec4Operations for Spherinder {
     fn contains(&self, pt: Vec4) -> bool {
         let relative_pt = pt - self.origin;
-        let xyz = relative_pt.xyz();
 
-        xyz.length_squared() <= self.radius * self.radius
+        self.relative_length(relative_pt.xyz()) <= 1.0
     }
 
+    // Points outside are moved towards the axis of the shape until they reach the boundary,
+    // which is the closest point only for a spherical cross-section
     fn constrain(&self, pt: Vec4) -> Vec4 {
         let relative_pt = pt - self.origin;
         let xyz = relative_pt.xyz();
         let w = relative_pt.w;
+        let relative_length = self.relative_length(xyz);
 
-        if xyz.length_squared() < self.radius * self.radius {
+        if relative_length < 1.0 {
             return pt;
         }
 
-        let new_xyz = xyz.normalize() * self.radius;
+        let new_xyz = xyz / relative_length;
 
         self.origin + Vec4::new(new_xyz.x, new_xyz.y, new_xyz.z, w)
     }
@@ -46,7 +71,11 @@ impl Vec4Operations for Spherinder {
         let relative_pt = pt - self.origin;
         let xyz = relative_pt.xyz();
 
-        xyz.length() - self.radius
+        if xyz.length_squared() < EPSILON {
+            return -self.radii.min_element();
+        }
+
+        xyz.length() * (1.0 - self.relative_length(xyz).recip())
     }
 }
 
@@ -58,3 +87,37 @@ impl HyperplaneIntersection for Spherinder {
         ))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spherical_cross_section() {
+        let spherinder = Spherinder::new(Vec4::ZERO, 2.0);
+
+        assert!(spherinder.contains(Vec4::new(0.0, 2.0, 0.0, 100.0)));
+        assert!(!spherinder.contains(Vec4::new(1.5, 1.5, 0.0, 0.0)));
+        assert!((spherinder.signed_distance(Vec4::new(3.0, 0.0, 0.0, 5.0)) - 1.0).abs() < 1e-5);
+        assert_eq!(
+            spherinder.constrain(Vec4::new(0.0, 0.0, 4.0, 5.0)),
+            Vec4::new(0.0, 0.0, 2.0, 5.0)
+        );
+        assert_eq!(spherinder.axis_weights(), (Vec3::ONE, 2.0));
+    }
+
+    #[test]
+    fn test_ellipsoidal_cross_section() {
+        let spherinder = Spherinder::with_radii(Vec4::ZERO, Vec3::new(2.0, 1.0, 2.0));
+
+        assert!(spherinder.contains(Vec4::new(2.0, 0.0, 0.0, 0.0)));
+        assert!(!spherinder.contains(Vec4::new(0.0, 1.5, 0.0, 0.0)));
+        assert!(spherinder
+            .constrain(Vec4::new(0.0, 3.0, 0.0, -1.0))
+            .abs_diff_eq(Vec4::new(0.0, 1.0, 0.0, -1.0), 1e-5));
+        assert!((spherinder.signed_distance(Vec4::new(0.0, 3.0, 0.0, 0.0)) - 2.0).abs() < 1e-5);
+
+        let constrained = spherinder.constrain(Vec4::new(4.0, 4.0, 0.0, 0.0));
+        assert!((spherinder.relative_length(constrained.xyz()) - 1.0).abs() < 1e-5);
+    }
+}
@@ -74,50 +74,29 @@ impl PlaneIntersecion for SpherinderHyperplaneIntersecion {
         // equation of an ellipsoid:
         // a1*h^2 + a4*h*s + a2*s^2 + a5*h*t + a6*s*t + a3*t^2 + a7*h + a8*s + a9*t + a10 == 0
 
-        let a1 = self.hyperplane.w_direction.xyz().length_squared();
-        let a2 = self.hyperplane.v_direction.xyz().length_squared();
-        let a3 = self.hyperplane.u_direction.xyz().length_squared();
-
-        let a4 = 2.0
-            * self
-                .hyperplane
-                .w_direction
-                .xyz()
-                .dot(self.hyperplane.v_direction.xyz());
-        let a5 = 2.0
-            * self
-                .hyperplane
-                .w_direction
-                .xyz()
-                .dot(self.hyperplane.u_direction.xyz());
-        let a6 = 2.0
-            * self
-                .hyperplane
-                .v_direction
-                .xyz()
-                .dot(self.hyperplane.u_direction.xyz());
-
-        let a7 = 2.0
-            * self
-                .hyperplane
-                .w_direction
-                .xyz()
-                .dot(self.hyperplane.origin.xyz());
-        let a8 = 2.0
-            * self
-                .hyperplane
-                .v_direction
-                .xyz()
-                .dot(self.hyperplane.origin.xyz());
-        let a9 = 2.0
-            * self
-                .hyperplane
-                .u_direction
-                .xyz()
-                .dot(self.hyperplane.origin.xyz());
-
-        let a10 = self.hyperplane.origin.xyz().length_squared()
-            - self.spherinder.radius * self.spherinder.radius;
+        // An ellipsoidal cross-section weighs the axes in the dot products, for a spherical one
+        // the weights are 1 and these are the plain dot products
+        let (weights, radius) = self.spherinder.axis_weights();
+        let dot = |a: Vec3, b: Vec3| (a * weights).dot(b);
+
+        let w_direction = self.hyperplane.w_direction.xyz();
+        let v_direction = self.hyperplane.v_direction.xyz();
+        let u_direction = self.hyperplane.u_direction.xyz();
+        let origin = self.hyperplane.origin.xyz() - self.spherinder.origin.xyz();
+
+        let a1 = dot(w_direction, w_direction);
+        let a2 = dot(v_direction, v_direction);
+        let a3 = dot(u_direction, u_direction);
+
+        let a4 = 2.0 * dot(w_direction, v_direction);
+        let a5 = 2.0 * dot(w_direction, u_direction);
+        let a6 = 2.0 * dot(v_direction, u_direction);
+
+        let a7 = 2.0 * dot(w_direction, origin);
+        let a8 = 2.0 * dot(v_direction, origin);
+        let a9 = 2.0 * dot(u_direction, origin);
+
+        let a10 = dot(origin, origin) - radius * radius;
 
         // Spherinder x hyperplane x plane intersection coefficients given the general equation of an
         // ellipse: Ax^2 + Bxy + Cy^2 + Dx + Ey + F = 0
@@ -0,0 +1,68 @@
+use bevy_math::Vec3;
+
+use crate::{Aabb, Triangle};
+
+// Indexed triangle mesh
+// Every triple of indices refers to the vertices of one triangle in counter-clockwise order.
+#[derive(Clone, Debug, Default)]
+pub struct TriMesh {
+    pub vertices: Vec<Vec3>,
+    pub indices: Vec<[u32; 3]>,
+}
+
+impl TriMesh {
+    #[must_use]
+    pub fn new(vertices: Vec<Vec3>, indices: Vec<[u32; 3]>) -> Self {
+        assert!(indices
+            .iter()
+            .flatten()
+            .all(|index| (*index as usize) < vertices.len()));
+
+        Self { vertices, indices }
+    }
+
+    // Every triangle gets its own vertices, shared vertices aren't detected
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn from_triangles(triangles: &[Triangle]) -> Self {
+        let vertices = triangles
+            .iter()
+            .flat_map(|triangle| *triangle.points())
+            .collect::<Vec<_>>();
+
+        let indices = (0..triangles.len() as u32)
+            .map(|i| [i * 3, i * 3 + 1, i * 3 + 2])
+            .collect();
+
+        Self { vertices, indices }
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    pub fn triangles(&self) -> impl Iterator<Item = Triangle> + '_ {
+        self.indices.iter().map(|[a, b, c]| {
+            Triangle::new([
+                self.vertices[*a as usize],
+                self.vertices[*b as usize],
+                self.vertices[*c as usize],
+            ])
+        })
+    }
+
+    #[must_use]
+    pub fn get_aabb(&self) -> Aabb {
+        let (min, max) = self.vertices.iter().fold(
+            (Vec3::splat(f32::INFINITY), Vec3::splat(f32::NEG_INFINITY)),
+            |(min, max), vertex| (min.min(*vertex), max.max(*vertex)),
+        );
+
+        if self.vertices.is_empty() {
+            return Aabb::new(Vec3::ZERO, Vec3::ZERO);
+        }
+
+        Aabb::new((min + max) / 2.0, (max - min) / 2.0)
+    }
+}
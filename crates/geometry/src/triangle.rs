@@ -151,7 +151,7 @@ mod tests {
     fn test_triangle_new_with_duplicate_points() {
         let p = Vec3::new(0.0, 0.0, 0.0);
         let p3 = Vec3::new(1.0, 1.0, 1.0);
-        let triangle = Triangle::new([p, p, p3]);
+        let _triangle = Triangle::new([p, p, p3]);
 
         // Verify handling of degenerate triangle
         // Assuming that Plane::from_points can handle this case
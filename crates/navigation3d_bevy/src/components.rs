@@ -0,0 +1,127 @@
+use bevy::prelude::*;
+use geometry::colliders::Collider;
+
+// Agent that avoids other agents and obstacles
+// The plugin writes the velocity every tick and moves the transform by it.
+#[derive(Component, Reflect, Clone, Debug)]
+#[reflect(Component)]
+pub struct NavAgent {
+    pub shape: Collider,
+    pub max_speed: f32,
+    // The velocity the agent would like to have, set by the plugin from NavGoal or by the user
+    pub preferred_velocity: Vec3,
+    // The velocity chosen by the avoidance
+    pub velocity: Vec3,
+    // Whether the avoidance keeps the agent from moving in its preferred direction
+    pub is_blocked: bool,
+}
+
+impl NavAgent {
+    pub fn new(shape: Collider, max_speed: f32) -> Self {
+        Self {
+            shape,
+            max_speed,
+            preferred_velocity: Vec3::ZERO,
+            velocity: Vec3::ZERO,
+            is_blocked: false,
+        }
+    }
+}
+
+// A unit sphere that doesn't move, the shape and speed are expected to be set afterwards
+impl Default for NavAgent {
+    fn default() -> Self {
+        Self::new(Collider::new_sphere(1.0), 0.0)
+    }
+}
+
+// Marks an agent whose Transform is moved by something else than the plugin, like a physics engine
+// The plugin still computes its velocity, but doesn't apply it.
+#[derive(Component, Reflect, Clone, Copy, Debug, Default)]
+#[reflect(Component)]
+pub struct ExternalMovement;
+
+// Obstacle that agents avoid, but which doesn't avoid anything itself
+// Moving obstacles have to keep their velocity up to date, so agents can avoid them in time.
+#[derive(Component, Reflect, Clone, Debug)]
+#[reflect(Component)]
+pub struct NavObstacle {
+    pub shape: Collider,
+    pub velocity: Vec3,
+}
+
+impl NavObstacle {
+    pub fn new(shape: Collider) -> Self {
+        Self {
+            shape,
+            velocity: Vec3::ZERO,
+        }
+    }
+}
+
+impl Default for NavObstacle {
+    fn default() -> Self {
+        Self::new(Collider::new_sphere(1.0))
+    }
+}
+
+// Position the agent heads to, it sets the preferred velocity of the NavAgent on the same entity
+// The agent slows down within the slowing distance and stops within the arrival distance.
+#[derive(Component, Reflect, Clone, Debug)]
+#[reflect(Component)]
+pub struct NavGoal {
+    pub position: Vec3,
+    pub slowing_distance: f32,
+    pub arrival_distance: f32,
+    // Whether the agent was within the arrival distance in the last tick
+    pub arrived: bool,
+}
+
+impl NavGoal {
+    pub fn new(position: Vec3) -> Self {
+        Self {
+            position,
+            slowing_distance: 0.0,
+            arrival_distance: 0.1,
+            arrived: false,
+        }
+    }
+
+    pub fn with_slowing_distance(mut self, slowing_distance: f32) -> Self {
+        self.slowing_distance = slowing_distance;
+        self
+    }
+
+    pub fn with_arrival_distance(mut self, arrival_distance: f32) -> Self {
+        self.arrival_distance = arrival_distance;
+        self
+    }
+
+    pub fn is_reached(&self, position: Vec3) -> bool {
+        position.distance(self.position) <= self.arrival_distance
+    }
+
+    // Returns the preferred velocity of an agent at the position
+    pub fn get_preferred_velocity(&self, position: Vec3, max_speed: f32) -> Vec3 {
+        let offset = self.position - position;
+        let distance = offset.length();
+
+        if distance <= self.arrival_distance {
+            return Vec3::ZERO;
+        }
+
+        let speed = if distance < self.slowing_distance {
+            max_speed * distance / self.slowing_distance
+        } else {
+            max_speed
+        };
+
+        offset / distance * speed
+    }
+}
+
+impl Default for NavGoal {
+    fn default() -> Self {
+        Self::new(Vec3::ZERO)
+    }
+}
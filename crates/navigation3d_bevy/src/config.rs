@@ -0,0 +1,212 @@
+use std::fmt::{self, Display, Formatter};
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+    utils::BoxedFuture,
+};
+use coordination::{FormationEvaluationParams, FormationTemplateDescription};
+use serde::Deserialize;
+
+use crate::{NavFormation, NavigationSettings};
+
+// Avoidance and formation parameters that can be tuned without recompiling, e.g.
+//
+// (
+//     settings: (time_horizon: 4.0, max_neighbours: 10),
+//     formation_templates: [
+//         (type: "Line", agent_radius: 5.0, spacing: 2.0, priority: 1.0),
+//     ],
+// )
+//
+// Everything is optional, missing settings keep their defaults and formations keep their
+// templates and parameters if none are given.
+#[derive(Asset, TypePath, Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct NavigationConfig {
+    pub settings: Option<NavigationSettings>,
+    pub formation_templates: Vec<FormationTemplateDescription>,
+    pub formation_params: Option<FormationEvaluationParams>,
+}
+
+impl NavigationConfig {
+    pub fn from_ron(source: &str) -> Result<Self, NavigationConfigError> {
+        ron::from_str(source).map_err(NavigationConfigError::Ron)
+    }
+
+    pub fn from_json(source: &str) -> Result<Self, NavigationConfigError> {
+        serde_json::from_str(source).map_err(NavigationConfigError::Json)
+    }
+
+    // Replaces the templates and the parameters of the formation by the ones in the config
+    pub fn apply_to_formation(&self, formation: &mut NavFormation) {
+        if !self.formation_templates.is_empty() {
+            formation.templates = self
+                .formation_templates
+                .iter()
+                .cloned()
+                .map(FormationTemplateDescription::into_template)
+                .collect();
+
+            // Indexes of the old templates mean nothing for the new ones
+            formation.choice = None;
+            formation.params.em_warm_start = None;
+        }
+
+        if let Some(params) = &self.formation_params {
+            formation.params = params.clone();
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum NavigationConfigError {
+    Io(std::io::Error),
+    Ron(ron::error::SpannedError),
+    Json(serde_json::Error),
+}
+
+impl Display for NavigationConfigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "could not read the navigation config: {error}"),
+            Self::Ron(error) => write!(f, "invalid RON navigation config: {error}"),
+            Self::Json(error) => write!(f, "invalid JSON navigation config: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for NavigationConfigError {}
+
+// Loads *.nav.ron and *.nav.json files as NavigationConfig
+#[derive(Default)]
+pub struct NavigationConfigLoader;
+
+impl AssetLoader for NavigationConfigLoader {
+    type Asset = NavigationConfig;
+    type Settings = ();
+    type Error = NavigationConfigError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut source = String::new();
+            reader
+                .read_to_string(&mut source)
+                .await
+                .map_err(NavigationConfigError::Io)?;
+
+            let is_json = load_context
+                .path()
+                .extension()
+                .is_some_and(|extension| extension == "json");
+
+            if is_json {
+                NavigationConfig::from_json(&source)
+            } else {
+                NavigationConfig::from_ron(&source)
+            }
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["nav.ron", "nav.json"]
+    }
+}
+
+// The config the NavigationSettings resource is taken from, every change of it is applied
+#[derive(Resource, Clone, Debug, Default)]
+pub struct NavigationConfigHandle(pub Handle<NavigationConfig>);
+
+// The config the templates and parameters of the NavFormation on the same entity are taken from
+#[derive(Component, Clone, Debug, Default)]
+pub struct NavFormationConfig(pub Handle<NavigationConfig>);
+
+// Applies configs when they finish loading and whenever they are modified on disk
+pub fn apply_navigation_configs(
+    mut events: EventReader<AssetEvent<NavigationConfig>>,
+    configs: Res<Assets<NavigationConfig>>,
+    handle: Option<Res<NavigationConfigHandle>>,
+    mut settings: ResMut<NavigationSettings>,
+    mut formations: Query<(&NavFormationConfig, &mut NavFormation)>,
+) {
+    for event in events.read() {
+        let (AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id }) = *event
+        else {
+            continue;
+        };
+
+        let Some(config) = configs.get(id) else {
+            continue;
+        };
+
+        if let (Some(handle), Some(new_settings)) = (&handle, &config.settings) {
+            if handle.0.id() == id {
+                *settings = new_settings.clone();
+            }
+        }
+
+        for (formation_config, mut formation) in formations.iter_mut() {
+            if formation_config.0.id() == id {
+                config.apply_to_formation(&mut formation);
+            }
+        }
+    }
+}
+
+// Registers the NavigationConfig asset and keeps the settings and formations in sync with it
+// Hot reloading requires the file_watcher feature of Bevy.
+pub struct NavigationConfigPlugin;
+
+impl Plugin for NavigationConfigPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<NavigationConfig>()
+            .init_asset_loader::<NavigationConfigLoader>()
+            .add_systems(PreUpdate, apply_navigation_configs);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ron_and_json_configs() {
+        let from_ron = NavigationConfig::from_ron(
+            r#"(
+                settings: Some((time_horizon: 4.0, max_neighbours: 10)),
+                formation_templates: [
+                    (type: "Line", agent_radius: 5.0, spacing: 2.0, priority: 1.0),
+                ],
+            )"#,
+        )
+        .unwrap();
+
+        let from_json = NavigationConfig::from_json(
+            r#"{
+                "settings": { "time_horizon": 4.0, "max_neighbours": 10 },
+                "formation_templates": [
+                    { "type": "Line", "agent_radius": 5.0, "spacing": 2.0, "priority": 1.0 }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        for config in [from_ron, from_json] {
+            let settings = config.settings.unwrap();
+
+            assert_eq!(settings.time_horizon, 4.0);
+            assert_eq!(settings.max_neighbours, 10);
+            assert_eq!(
+                settings.neighbour_distance,
+                NavigationSettings::default().neighbour_distance
+            );
+            assert_eq!(config.formation_templates.len(), 1);
+            assert!(config.formation_params.is_none());
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use bevy::prelude::*;
+use coordination::Formation;
+use orca::{Agent3D, FormationVelocityObstacle3D};
+
+use crate::{
+    get_neighbours, get_orca_plane, FormationMember, NavAgent, NavFormation, NavigationSet,
+    NavigationSettings, SpatialIndex,
+};
+
+const AVO_SAMPLES: usize = 25;
+
+// Marks the agents and formations the debug overlay draws
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct NavDebugSelected;
+
+// Which parts of the avoidance the overlay draws, everything is drawn in world space
+// Velocities and velocity obstacles are drawn relative to the position of the agent.
+//
+// orca_planes: The half-spaces the velocity of the agent was constrained by
+// avo_boundaries: The boundary curve of the acceleration velocity obstacle of every neighbour
+// fvo_triangles: The formation velocity obstacle of every obstacle near a selected formation
+// neighbour_links: Lines to the neighbours the agent avoids
+// velocities: The preferred velocity in blue and the chosen velocity in green
+// acc_control_param: The acceleration control parameter of the drawn AVO boundaries
+// plane_size: The size of the drawn ORCA planes
+#[derive(Resource, Clone, Debug)]
+pub struct NavDebugSettings {
+    pub orca_planes: bool,
+    pub avo_boundaries: bool,
+    pub fvo_triangles: bool,
+    pub neighbour_links: bool,
+    pub velocities: bool,
+    pub acc_control_param: f32,
+    pub fvo_samples: u16,
+    pub plane_size: f32,
+}
+
+impl Default for NavDebugSettings {
+    fn default() -> Self {
+        Self {
+            orca_planes: true,
+            avo_boundaries: false,
+            fvo_triangles: false,
+            neighbour_links: true,
+            velocities: true,
+            acc_control_param: 1.0,
+            fvo_samples: 16,
+            plane_size: 10.0,
+        }
+    }
+}
+
+// Draws the avoidance of every entity with NavDebugSelected, requires the NavigationPlugin
+// The drawn categories can be toggled at runtime through the NavDebugSettings resource.
+#[derive(Default)]
+pub struct NavigationDebugPlugin {
+    pub settings: NavDebugSettings,
+}
+
+impl Plugin for NavigationDebugPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(self.settings.clone()).add_systems(
+            Update,
+            (draw_agent_debug, draw_formation_debug).after(NavigationSet::Avoidance),
+        );
+    }
+}
+
+pub fn draw_agent_debug(
+    mut gizmos: Gizmos,
+    time: Res<Time>,
+    debug_settings: Res<NavDebugSettings>,
+    settings: Res<NavigationSettings>,
+    index: Res<SpatialIndex>,
+    agents: Query<(Entity, &Transform, &NavAgent), With<NavDebugSelected>>,
+) {
+    let time_step = time.delta_seconds().max(settings.time_step);
+
+    for (entity, transform, agent) in agents.iter() {
+        let position = transform.translation;
+        let agent_self = Agent3D::new(position, agent.velocity, agent.shape.clone());
+        let neighbours = get_neighbours(&index, &settings, entity, position);
+
+        if debug_settings.velocities {
+            gizmos.line(position, position + agent.preferred_velocity, Color::BLUE);
+            gizmos.line(position, position + agent.velocity, Color::GREEN);
+        }
+
+        for neighbour in &neighbours {
+            if debug_settings.neighbour_links {
+                gizmos.line(position, neighbour.agent.position, Color::GRAY);
+            }
+
+            if debug_settings.orca_planes {
+                let plane = get_orca_plane(&settings, &agent_self, neighbour, time_step);
+                let origin = position + plane.origin;
+                let rotation = Transform::from_translation(origin)
+                    .looking_to(plane.normal, plane.u_direction)
+                    .rotation;
+
+                gizmos.rect(
+                    origin,
+                    rotation,
+                    Vec2::splat(debug_settings.plane_size),
+                    Color::YELLOW,
+                );
+                gizmos.line(origin, origin + plane.normal, Color::YELLOW);
+            }
+
+            if debug_settings.avo_boundaries {
+                draw_avo_boundary(
+                    &mut gizmos,
+                    position + neighbour.agent.velocity,
+                    &agent_self,
+                    &neighbour.agent,
+                    if neighbour.is_obstacle {
+                        settings.obstacle_time_horizon
+                    } else {
+                        settings.time_horizon
+                    },
+                    debug_settings.acc_control_param,
+                );
+            }
+        }
+    }
+}
+
+pub fn draw_formation_debug(
+    mut gizmos: Gizmos,
+    debug_settings: Res<NavDebugSettings>,
+    index: Res<SpatialIndex>,
+    formations: Query<(&NavFormation, &Transform), With<NavDebugSelected>>,
+    members: Query<(&Transform, &NavAgent), With<FormationMember>>,
+) {
+    if !debug_settings.fvo_triangles && !debug_settings.velocities {
+        return;
+    }
+
+    for (formation, transform) in formations.iter() {
+        let center = transform.translation;
+
+        if debug_settings.velocities {
+            gizmos.line(center, center + formation.preferred_velocity, Color::BLUE);
+            gizmos.line(center, center + formation.velocity, Color::GREEN);
+        }
+
+        if !debug_settings.fvo_triangles {
+            continue;
+        }
+
+        let mut positions = Vec::new();
+        let mut agent_radius = 0.0f32;
+
+        for &member in &formation.members {
+            if let Ok((transform, agent)) = members.get(member) {
+                positions.push(transform.translation);
+                agent_radius = agent_radius.max(agent.shape.bounding_sphere().radius);
+            }
+        }
+
+        if positions.is_empty() {
+            continue;
+        }
+
+        let formation_agent = Formation::new(positions).get_agent(agent_radius, formation.velocity);
+
+        for obstacle in index
+            .query(center, formation.obstacle_distance)
+            .filter(|entry| entry.is_obstacle)
+        {
+            let triangles = FormationVelocityObstacle3D::new(
+                &formation_agent,
+                &obstacle.agent,
+                formation.params.obstacle_avoidance_time_horizon,
+            )
+            .construct_vo_mesh(
+                debug_settings.fvo_samples,
+                debug_settings.fvo_samples,
+                0.0,
+            );
+
+            for triangle in triangles {
+                let [a, b, c] = *triangle.points();
+
+                gizmos.linestrip(
+                    [a + center, b + center, c + center, a + center],
+                    Color::ORANGE_RED,
+                );
+            }
+        }
+    }
+}
+
+// Draws the curve of centers of the acceleration velocity obstacle between the time step and
+// the time horizon, see AccelerationVelocityObstacle3D
+fn draw_avo_boundary(
+    gizmos: &mut Gizmos,
+    offset: Vec3,
+    agent_self: &Agent3D,
+    agent_other: &Agent3D,
+    time_horizon: f32,
+    acc_control_param: f32,
+) {
+    let relative_position = agent_other.position - agent_self.position;
+    let relative_velocity = agent_self.velocity - agent_other.velocity;
+
+    let points = (0..=AVO_SAMPLES).map(|i| {
+        let t = 0.001 + (time_horizon - 0.001) * i as f32 / AVO_SAMPLES as f32;
+        let param = acc_control_param * ((-t / acc_control_param).exp() - 1.0);
+
+        offset + (param * relative_velocity + relative_position) / (t + param)
+    });
+
+    gizmos.linestrip(points, Color::WHITE);
+}
@@ -0,0 +1,36 @@
+use bevy::prelude::*;
+use coordination::FormationChoice;
+
+// Sent once when an agent gets within the arrival distance of its NavGoal
+// It's sent again only after the agent leaves the arrival distance and comes back.
+#[derive(Event, Clone, Debug)]
+pub struct ArrivedAtGoal {
+    pub entity: Entity,
+    pub goal: Vec3,
+}
+
+// Sent when the avoidance stops an agent from moving in its preferred direction
+// It's sent once when the agent gets blocked, not in every tick it stays blocked.
+#[derive(Event, Clone, Debug)]
+pub struct PathBlocked {
+    pub entity: Entity,
+    pub position: Vec3,
+}
+
+// Sent when a formation switches to a different template or keeps its current shape
+// previous is None for the first decision of the formation.
+#[derive(Event, Clone, Debug)]
+pub struct FormationChanged {
+    pub formation: Entity,
+    pub previous: Option<FormationChoice>,
+    pub current: FormationChoice,
+}
+
+// Sent when an agent would collide with another agent or obstacle within the time horizon,
+// if both kept their velocities. The avoidance usually resolves it in the same tick.
+#[derive(Event, Clone, Debug)]
+pub struct CollisionPredicted {
+    pub entity: Entity,
+    pub other: Entity,
+    pub time_to_collision: f32,
+}
@@ -0,0 +1,272 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use coordination::{
+    sticky_matching_indexes, Formation, FormationChoice, FormationEvaluationParams,
+    FormationTemplate, FormationTemplateSet,
+};
+
+use crate::{FormationChanged, NavAgent, SpatialIndex};
+
+// Group of agents that keeps a formation while moving with the preferred velocity
+// Every tick the best template is selected, the agents are assigned to its slots and each
+// FormationMember gets the position of its slot as the target. The Transform of the formation
+// entity follows the center and the heading of the formation.
+//
+// templates: The formations the group can switch between
+// params: The parameters of the template selection, see FormationTemplateSet::get_best_formation
+// preferred_velocity: The velocity the whole formation would like to have, set by the user
+// slot_switching_penalty: The cost of moving an agent to a different slot, higher values keep
+//                         the slots stable while the formation turns
+// lookahead: How many seconds ahead of the formation the slots are placed
+// catch_up_time: How many seconds the members take to reach their slots
+// obstacle_distance: Obstacles further away from the center aren't considered
+// em_refinement_steps: When set, the expectation maximization continues from the result of the
+//                      last tick and only runs this many steps
+#[derive(Component)]
+pub struct NavFormation {
+    pub templates: Vec<Box<dyn FormationTemplate>>,
+    pub params: FormationEvaluationParams,
+    pub preferred_velocity: Vec3,
+    pub slot_switching_penalty: f32,
+    pub lookahead: f32,
+    pub catch_up_time: f32,
+    pub obstacle_distance: f32,
+    pub em_refinement_steps: Option<usize>,
+    // The collision-free velocity of the formation chosen in the last tick
+    pub velocity: Vec3,
+    // The formation chosen in the last tick
+    pub choice: Option<FormationChoice>,
+    // The members ordered by entity, the slot assignment is indexed by this order
+    pub members: Vec<Entity>,
+    pub slot_assignment: HashMap<usize, usize>,
+}
+
+impl NavFormation {
+    pub fn new(
+        templates: Vec<Box<dyn FormationTemplate>>,
+        params: FormationEvaluationParams,
+    ) -> Self {
+        assert!(!templates.is_empty());
+
+        Self {
+            templates,
+            params,
+            preferred_velocity: Vec3::ZERO,
+            slot_switching_penalty: 100.0,
+            lookahead: 1.0,
+            catch_up_time: 1.0,
+            obstacle_distance: 200.0,
+            em_refinement_steps: None,
+            velocity: Vec3::ZERO,
+            choice: None,
+            members: Vec::new(),
+            slot_assignment: HashMap::new(),
+        }
+    }
+
+    pub fn with_preferred_velocity(mut self, preferred_velocity: Vec3) -> Self {
+        self.preferred_velocity = preferred_velocity;
+        self
+    }
+
+    pub fn with_slot_switching_penalty(mut self, slot_switching_penalty: f32) -> Self {
+        self.slot_switching_penalty = slot_switching_penalty;
+        self
+    }
+
+    pub fn with_lookahead(mut self, lookahead: f32) -> Self {
+        self.lookahead = lookahead;
+        self
+    }
+
+    pub fn with_catch_up_time(mut self, catch_up_time: f32) -> Self {
+        assert!(catch_up_time > 0.0);
+
+        self.catch_up_time = catch_up_time;
+        self
+    }
+
+    pub fn with_obstacle_distance(mut self, obstacle_distance: f32) -> Self {
+        self.obstacle_distance = obstacle_distance;
+        self
+    }
+
+    pub fn with_em_refinement_steps(mut self, em_refinement_steps: usize) -> Self {
+        self.em_refinement_steps = Some(em_refinement_steps);
+        self
+    }
+}
+
+#[derive(Bundle)]
+pub struct FormationBundle {
+    pub formation: NavFormation,
+    pub transform: Transform,
+}
+
+impl FormationBundle {
+    pub fn new(formation: NavFormation) -> Self {
+        Self {
+            formation,
+            transform: Transform::default(),
+        }
+    }
+}
+
+// Marks a NavAgent as a member of the formation entity
+// The plugin sets the slot and the target, the preferred velocity of the agent leads to the target.
+#[derive(Component, Reflect, Clone, Debug)]
+#[reflect(Component)]
+pub struct FormationMember {
+    pub formation: Entity,
+    pub slot: Option<usize>,
+    pub target: Option<Vec3>,
+}
+
+impl FormationMember {
+    pub fn new(formation: Entity) -> Self {
+        Self {
+            formation,
+            slot: None,
+            target: None,
+        }
+    }
+}
+
+impl Default for FormationMember {
+    fn default() -> Self {
+        Self::new(Entity::PLACEHOLDER)
+    }
+}
+
+pub fn update_formations(
+    index: Res<SpatialIndex>,
+    mut formations: Query<(Entity, &mut Transform, &mut NavFormation)>,
+    mut members: Query<(Entity, &Transform, &mut FormationMember), Without<NavFormation>>,
+    mut changed_events: EventWriter<FormationChanged>,
+) {
+    let mut members_of = HashMap::<Entity, Vec<(Entity, Vec3)>>::new();
+
+    for (entity, transform, member) in members.iter() {
+        members_of
+            .entry(member.formation)
+            .or_default()
+            .push((entity, transform.translation));
+    }
+
+    for (entity, mut transform, mut formation) in formations.iter_mut() {
+        let mut formation_members = members_of.remove(&entity).unwrap_or_default();
+        formation_members.sort_by_key(|(member, _)| *member);
+
+        let member_entities = formation_members
+            .iter()
+            .map(|(member, _)| *member)
+            .collect::<Vec<_>>();
+
+        // Indexes of the previous assignment don't mean anything once the members change
+        if member_entities != formation.members {
+            formation.members = member_entities;
+            formation.slot_assignment.clear();
+            formation.params.em_warm_start = None;
+        }
+
+        if formation_members.is_empty() {
+            formation.velocity = Vec3::ZERO;
+            formation.choice = None;
+            continue;
+        }
+
+        let positions = formation_members
+            .iter()
+            .map(|(_, position)| *position)
+            .collect::<Vec<_>>();
+
+        let center = Formation::new(positions.clone()).get_bounds(0.0).center;
+
+        let obstacles = index
+            .query(center, formation.obstacle_distance)
+            .filter(|entry| entry.is_obstacle)
+            .map(|entry| entry.agent.clone())
+            .collect::<Vec<_>>();
+
+        let template_set = FormationTemplateSet::from_iter(
+            formation.templates.iter().map(|template| template.as_ref()),
+        );
+
+        // Invalid parameters, e.g. agent weights not matching the members, stop the formation
+        // instead of crashing the app
+        let Ok(decision) = template_set.get_best_formation(
+            &positions,
+            formation.preferred_velocity,
+            &obstacles,
+            &[],
+            &formation.params,
+        ) else {
+            formation.velocity = Vec3::ZERO;
+            formation.choice = None;
+            formation.params.em_warm_start = None;
+            continue;
+        };
+
+        if let Some(em_refinement_steps) = formation.em_refinement_steps {
+            formation.params.em_warm_start = Some(decision.em_warm_start());
+            formation.params.em_refinement_steps = em_refinement_steps;
+        }
+
+        let slots = decision
+            .formation
+            .get_positions()
+            .iter()
+            .map(|position| {
+                decision.rotation * *position + center + decision.velocity * formation.lookahead
+            })
+            .collect::<Vec<_>>();
+
+        let assignment = sticky_matching_indexes(
+            &positions,
+            &slots,
+            &formation.slot_assignment,
+            formation.slot_switching_penalty,
+        );
+
+        for (&member_index, &slot) in &assignment {
+            if let Ok((_, _, mut member)) = members.get_mut(formation.members[member_index]) {
+                member.slot = Some(slot);
+                member.target = Some(slots[slot]);
+            }
+        }
+
+        transform.translation = center;
+        transform.rotation = decision.rotation;
+
+        if formation.choice != Some(decision.choice) {
+            changed_events.send(FormationChanged {
+                formation: entity,
+                previous: formation.choice,
+                current: decision.choice,
+            });
+        }
+
+        formation.velocity = decision.velocity;
+        formation.choice = Some(decision.choice);
+        formation.slot_assignment = assignment;
+    }
+}
+
+// Leads members to their slots while moving with the velocity of the formation
+pub fn update_formation_member_velocities(
+    formations: Query<&NavFormation>,
+    mut members: Query<(&Transform, &FormationMember, &mut NavAgent)>,
+) {
+    for (transform, member, mut agent) in members.iter_mut() {
+        let (Ok(formation), Some(target)) = (formations.get(member.formation), member.target)
+        else {
+            continue;
+        };
+
+        let catch_up_velocity = (target - transform.translation) / formation.catch_up_time;
+
+        agent.preferred_velocity =
+            (formation.velocity + catch_up_velocity).clamp_length_max(agent.max_speed);
+    }
+}
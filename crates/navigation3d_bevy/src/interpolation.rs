@@ -0,0 +1,88 @@
+use bevy::prelude::*;
+
+use crate::NavAgent;
+
+// Smooths the motion of an agent simulated at a fixed rate
+// The Transform of the agent is moved between the positions of the last two simulation steps,
+// the simulation itself always starts from the simulated position.
+#[derive(Component, Reflect, Clone, Debug, Default)]
+#[reflect(Component)]
+pub struct NavInterpolation {
+    pub previous_position: Vec3,
+    pub position: Vec3,
+    pub previous_velocity: Vec3,
+    pub velocity: Vec3,
+}
+
+impl NavInterpolation {
+    pub fn new(position: Vec3) -> Self {
+        Self {
+            previous_position: position,
+            position,
+            previous_velocity: Vec3::ZERO,
+            velocity: Vec3::ZERO,
+        }
+    }
+
+    // Returns the position between the last two simulation steps
+    // alpha: 0 is the previous step, 1 the last one
+    pub fn position_at(&self, alpha: f32) -> Vec3 {
+        self.previous_position.lerp(self.position, alpha)
+    }
+
+    // Returns the velocity between the last two simulation steps
+    // alpha: 0 is the previous step, 1 the last one
+    pub fn velocity_at(&self, alpha: f32) -> Vec3 {
+        self.previous_velocity.lerp(self.velocity, alpha)
+    }
+
+    fn push(&mut self, position: Vec3, velocity: Vec3) {
+        self.previous_position = self.position;
+        self.position = position;
+        self.previous_velocity = self.velocity;
+        self.velocity = velocity;
+    }
+}
+
+// Moves the agents back to their simulated positions before a simulation step
+pub fn restore_simulated_positions(mut agents: Query<(&NavInterpolation, &mut Transform)>) {
+    for (interpolation, mut transform) in agents.iter_mut() {
+        transform.translation = interpolation.position;
+    }
+}
+
+pub fn store_simulated_positions(
+    mut agents: Query<(&Transform, &NavAgent, &mut NavInterpolation)>,
+) {
+    for (transform, agent, mut interpolation) in agents.iter_mut() {
+        interpolation.push(transform.translation, agent.velocity);
+    }
+}
+
+pub fn interpolate_transforms(
+    time: Res<Time<Fixed>>,
+    mut agents: Query<(&NavInterpolation, &mut Transform)>,
+) {
+    let alpha = time.overstep_percentage().clamp(0.0, 1.0);
+
+    for (interpolation, mut transform) in agents.iter_mut() {
+        transform.translation = interpolation.position_at(alpha);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interpolation_between_steps() {
+        let mut interpolation = NavInterpolation::new(Vec3::ZERO);
+        interpolation.push(Vec3::new(1.0, 0.0, 0.0), Vec3::new(10.0, 0.0, 0.0));
+        interpolation.push(Vec3::new(2.0, 0.0, 0.0), Vec3::new(20.0, 0.0, 0.0));
+
+        assert_eq!(interpolation.position_at(0.0), Vec3::new(1.0, 0.0, 0.0));
+        assert_eq!(interpolation.position_at(1.0), Vec3::new(2.0, 0.0, 0.0));
+        assert_eq!(interpolation.position_at(0.5), Vec3::new(1.5, 0.0, 0.0));
+        assert_eq!(interpolation.velocity_at(0.5), Vec3::new(15.0, 0.0, 0.0));
+    }
+}
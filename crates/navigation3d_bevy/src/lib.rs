@@ -0,0 +1,31 @@
+mod components;
+#[cfg(feature = "config")]
+mod config;
+#[cfg(feature = "debug")]
+mod debug;
+mod events;
+mod formation;
+mod interpolation;
+mod obstacle_source;
+#[cfg(feature = "rapier")]
+mod physics;
+mod plugin;
+mod spatial_index;
+mod stats;
+mod systems;
+
+pub use components::*;
+#[cfg(feature = "config")]
+pub use config::*;
+#[cfg(feature = "debug")]
+pub use debug::*;
+pub use events::*;
+pub use formation::*;
+pub use interpolation::*;
+pub use obstacle_source::*;
+#[cfg(feature = "rapier")]
+pub use physics::*;
+pub use plugin::*;
+pub use spatial_index::*;
+pub use stats::*;
+pub use systems::*;
@@ -0,0 +1,327 @@
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+#[cfg(feature = "mesh")]
+use bevy::render::mesh::{Mesh, VertexAttributeValues};
+use geometry::{colliders::Collider, ConvexHull, Plane, TriMesh, Vec3Operations};
+use orca::Agent3D;
+use pathfinding::ObstacleBvh;
+
+// Static level geometry that agents avoid
+// The geometry is approximated by colliders once when it's added to StaticObstacles.
+pub trait NavObstacleSource {
+    // Returns the colliders in world space
+    fn get_colliders(&self) -> Vec<Collider>;
+}
+
+impl NavObstacleSource for Collider {
+    fn get_colliders(&self) -> Vec<Collider> {
+        vec![self.clone()]
+    }
+}
+
+impl NavObstacleSource for [Collider] {
+    fn get_colliders(&self) -> Vec<Collider> {
+        self.to_vec()
+    }
+}
+
+// Every hull is approximated by its bounding box, see TriMesh::convex_decomposition
+impl NavObstacleSource for [ConvexHull] {
+    fn get_colliders(&self) -> Vec<Collider> {
+        self.iter()
+            .map(|hull| Collider::Aabb(hull.get_aabb()))
+            .collect()
+    }
+}
+
+// Terrain given by a height per cell of a regular grid in the XZ plane
+// Every cell is filled by cubes of the cell size from the origin up to its height.
+//
+// origin: The corner of the first cell at the base of the terrain
+// columns: The number of cells along X, the heights are stored row by row
+pub struct HeightfieldSource {
+    pub origin: Vec3,
+    pub cell_size: f32,
+    pub columns: usize,
+    pub heights: Vec<f32>,
+}
+
+impl HeightfieldSource {
+    pub fn new(origin: Vec3, cell_size: f32, columns: usize, heights: Vec<f32>) -> Self {
+        assert!(cell_size > 0.0);
+        assert!(columns > 0);
+        assert!(heights.len().is_multiple_of(columns));
+
+        Self {
+            origin,
+            cell_size,
+            columns,
+            heights,
+        }
+    }
+}
+
+impl NavObstacleSource for HeightfieldSource {
+    fn get_colliders(&self) -> Vec<Collider> {
+        let mut colliders = Vec::new();
+        let half_size = Vec3::splat(self.cell_size / 2.0);
+
+        for (index, &height) in self.heights.iter().enumerate() {
+            let x = (index % self.columns) as f32;
+            let z = (index / self.columns) as f32;
+            let number_of_cubes = (height / self.cell_size).ceil().max(0.0) as usize;
+
+            for y in 0..number_of_cubes {
+                let center = self.origin + (Vec3::new(x, y as f32, z) + 0.5) * self.cell_size;
+
+                colliders.push(Collider::new_aabb(center, half_size));
+            }
+        }
+
+        colliders
+    }
+}
+
+// Engine independent triangle mesh, e.g. loaded by geometry::import_obj, voxelized into cubes
+// of the voxel size like MeshSource
+pub struct TriMeshSource<'a> {
+    pub mesh: &'a TriMesh,
+    pub voxel_size: f32,
+}
+
+impl<'a> TriMeshSource<'a> {
+    pub fn new(mesh: &'a TriMesh, voxel_size: f32) -> Self {
+        assert!(voxel_size > 0.0);
+
+        Self { mesh, voxel_size }
+    }
+}
+
+impl NavObstacleSource for TriMeshSource<'_> {
+    fn get_colliders(&self) -> Vec<Collider> {
+        let triangles = self
+            .mesh
+            .triangles()
+            .map(|triangle| *triangle.points())
+            .collect::<Vec<_>>();
+
+        voxelize_triangles(&triangles, self.voxel_size)
+    }
+}
+
+// Triangle mesh voxelized into cubes of the voxel size
+// Every voxel touching the surface of the mesh becomes an obstacle, the inside stays empty.
+#[cfg(feature = "mesh")]
+pub struct MeshSource<'a> {
+    pub mesh: &'a Mesh,
+    pub transform: Transform,
+    pub voxel_size: f32,
+}
+
+#[cfg(feature = "mesh")]
+impl<'a> MeshSource<'a> {
+    pub fn new(mesh: &'a Mesh, transform: Transform, voxel_size: f32) -> Self {
+        assert!(voxel_size > 0.0);
+
+        Self {
+            mesh,
+            transform,
+            voxel_size,
+        }
+    }
+}
+
+#[cfg(feature = "mesh")]
+impl NavObstacleSource for MeshSource<'_> {
+    fn get_colliders(&self) -> Vec<Collider> {
+        let Some(VertexAttributeValues::Float32x3(positions)) =
+            self.mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+        else {
+            return Vec::new();
+        };
+
+        let positions = positions
+            .iter()
+            .map(|&position| self.transform.transform_point(Vec3::from_array(position)))
+            .collect::<Vec<_>>();
+
+        let indices = match self.mesh.indices() {
+            Some(indices) => indices.iter().collect::<Vec<_>>(),
+            None => (0..positions.len()).collect(),
+        };
+
+        let triangles = indices
+            .chunks_exact(3)
+            .map(|triangle| {
+                [
+                    positions[triangle[0]],
+                    positions[triangle[1]],
+                    positions[triangle[2]],
+                ]
+            })
+            .collect::<Vec<_>>();
+
+        voxelize_triangles(&triangles, self.voxel_size)
+    }
+}
+
+// Returns a cube for every voxel the triangles pass through
+// A voxel is taken when it overlaps the bounds of a triangle and the plane of the triangle
+// is closer to its center than half of its diagonal, which may take a few extra voxels
+// next to sharp corners but never misses one.
+pub fn voxelize_triangles(triangles: &[[Vec3; 3]], voxel_size: f32) -> Vec<Collider> {
+    let mut voxels = HashSet::new();
+    let half_diagonal = voxel_size * 3.0f32.sqrt() / 2.0;
+
+    for &[a, b, c] in triangles {
+        let normal = (b - a).cross(c - a);
+
+        if normal.length_squared() <= f32::EPSILON {
+            continue;
+        }
+
+        let plane = Plane::new(a, normal.normalize());
+        let min = (a.min(b).min(c) / voxel_size).floor().as_ivec3();
+        let max = (a.max(b).max(c) / voxel_size).floor().as_ivec3();
+
+        for z in min.z..=max.z {
+            for y in min.y..=max.y {
+                for x in min.x..=max.x {
+                    let voxel = IVec3::new(x, y, z);
+                    let center = (voxel.as_vec3() + 0.5) * voxel_size;
+
+                    if plane.signed_distance(center).abs() <= half_diagonal {
+                        voxels.insert(voxel);
+                    }
+                }
+            }
+        }
+    }
+
+    voxels
+        .into_iter()
+        .map(|voxel| {
+            Collider::new_aabb(
+                (voxel.as_vec3() + 0.5) * voxel_size,
+                Vec3::splat(voxel_size / 2.0),
+            )
+        })
+        .collect()
+}
+
+// Static obstacles of the level indexed by a BVH
+// Agents avoid the bounding spheres of the colliders within their neighbour distance.
+#[derive(Resource, Clone, Debug)]
+pub struct StaticObstacles {
+    bvh: ObstacleBvh,
+}
+
+impl Default for StaticObstacles {
+    fn default() -> Self {
+        Self {
+            bvh: ObstacleBvh::new(Vec::new()),
+        }
+    }
+}
+
+impl StaticObstacles {
+    pub fn bvh(&self) -> &ObstacleBvh {
+        &self.bvh
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bvh.colliders().is_empty()
+    }
+
+    // Adds the colliders of the source and rebuilds the BVH, meant to be done at load time
+    pub fn add_source(&mut self, source: &(impl NavObstacleSource + ?Sized)) {
+        let mut colliders = self.bvh.colliders().to_vec();
+        colliders.extend(source.get_colliders());
+
+        self.bvh = ObstacleBvh::new(colliders);
+    }
+
+    pub fn clear(&mut self) {
+        self.bvh = ObstacleBvh::new(Vec::new());
+    }
+
+    // Returns the closest obstacles around the position as agents that don't avoid anything
+    pub fn get_obstacle_agents(
+        &self,
+        position: Vec3,
+        radius: f32,
+        max_obstacles: usize,
+    ) -> Vec<Agent3D> {
+        let mut obstacles = self
+            .bvh
+            .query_sphere(position, radius)
+            .into_iter()
+            .map(|collider| {
+                let sphere = collider.bounding_sphere();
+
+                let mut agent = Agent3D::new(
+                    sphere.origin,
+                    Vec3::ZERO,
+                    Collider::new_sphere(sphere.radius),
+                );
+                agent.responsibility = 0.0;
+
+                agent
+            })
+            .collect::<Vec<_>>();
+
+        obstacles.sort_by(|a, b| {
+            a.position
+                .distance_squared(position)
+                .total_cmp(&b.position.distance_squared(position))
+        });
+
+        obstacles.truncate(max_obstacles);
+        obstacles
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_heightfield_columns() {
+        let heightfield = HeightfieldSource::new(Vec3::ZERO, 1.0, 2, vec![0.0, 1.0, 2.5, 0.5]);
+        let colliders = heightfield.get_colliders();
+
+        assert_eq!(colliders.len(), 5);
+
+        let mut obstacles = StaticObstacles::default();
+        obstacles.add_source(&heightfield);
+
+        let nearby = obstacles.get_obstacle_agents(Vec3::new(0.5, 5.0, 1.5), 2.5, 10);
+
+        assert_eq!(nearby.len(), 1);
+        assert_eq!(nearby[0].position, Vec3::new(0.5, 2.5, 1.5));
+    }
+
+    #[test]
+    fn test_voxelized_triangle_covers_its_surface() {
+        let triangle = [
+            Vec3::new(0.0, 0.5, 0.0),
+            Vec3::new(4.0, 0.5, 0.0),
+            Vec3::new(0.0, 0.5, 4.0),
+        ];
+
+        let colliders = voxelize_triangles(&[triangle], 1.0);
+
+        for point in [
+            Vec3::new(0.5, 0.5, 0.5),
+            Vec3::new(3.5, 0.5, 0.2),
+            Vec3::new(0.2, 0.5, 3.5),
+        ] {
+            assert!(colliders.iter().any(|collider| collider.contains(point)));
+        }
+
+        assert!(colliders
+            .iter()
+            .all(|collider| !collider.contains(Vec3::new(2.0, 3.0, 2.0))));
+    }
+}
@@ -0,0 +1,135 @@
+use bevy::{ecs::schedule::ScheduleLabel, prelude::*};
+use bevy_rapier3d::prelude::{
+    Collider as RapierCollider, ExternalForce, Velocity as RapierVelocity,
+};
+use geometry::colliders::Collider;
+
+use crate::{
+    apply_velocities, build_spatial_index, ExternalMovement, NavAgent, NavObstacle, NavigationSet,
+    NavigationSettings,
+};
+
+// How the velocity chosen by the avoidance is handed over to the rigid body
+#[derive(Clone, Copy, Debug)]
+pub enum PhysicsOutput {
+    // The linear velocity of the body is set directly
+    Velocity,
+    // A force accelerating the body towards the velocity within one time step
+    Force { mass: f32, max_force: f32 },
+}
+
+// Agent moved by a bevy_rapier rigid body instead of by the plugin
+// Its shape and velocity are read from the Collider and Velocity of the body every tick.
+#[derive(Component, Clone, Debug)]
+pub struct PhysicsNavAgent {
+    pub output: PhysicsOutput,
+}
+
+#[derive(Bundle)]
+pub struct PhysicsNavAgentBundle {
+    pub physics: PhysicsNavAgent,
+    pub external_movement: ExternalMovement,
+}
+
+impl PhysicsNavAgentBundle {
+    pub fn new(output: PhysicsOutput) -> Self {
+        Self {
+            physics: PhysicsNavAgent { output },
+            external_movement: ExternalMovement,
+        }
+    }
+}
+
+// Converts a rapier collider to the closest shape the avoidance supports
+// Balls and cuboids are converted exactly, other shapes are replaced by their bounding sphere.
+pub fn collider_from_rapier(collider: &RapierCollider) -> Collider {
+    if let Some(ball) = collider.as_ball() {
+        return Collider::new_sphere(ball.radius());
+    }
+
+    if let Some(cuboid) = collider.as_cuboid() {
+        return Collider::new_aabb(Vec3::ZERO, cuboid.half_extents());
+    }
+
+    Collider::new_sphere(collider.raw.compute_local_bounding_sphere().radius())
+}
+
+pub fn read_physics_state(
+    mut agents: Query<
+        (&mut NavAgent, &RapierCollider, Option<&RapierVelocity>),
+        With<PhysicsNavAgent>,
+    >,
+    mut obstacles: Query<(&mut NavObstacle, &RapierCollider, Option<&RapierVelocity>)>,
+) {
+    for (mut agent, collider, velocity) in agents.iter_mut() {
+        agent.shape = collider_from_rapier(collider);
+        agent.velocity = velocity.map_or(Vec3::ZERO, |velocity| velocity.linvel);
+    }
+
+    for (mut obstacle, collider, velocity) in obstacles.iter_mut() {
+        obstacle.shape = collider_from_rapier(collider);
+        obstacle.velocity = velocity.map_or(Vec3::ZERO, |velocity| velocity.linvel);
+    }
+}
+
+pub fn write_physics_output(
+    time: Res<Time>,
+    settings: Res<NavigationSettings>,
+    mut agents: Query<(
+        &NavAgent,
+        &PhysicsNavAgent,
+        Option<&mut RapierVelocity>,
+        Option<&mut ExternalForce>,
+    )>,
+) {
+    let time_step = time.delta_seconds().max(settings.time_step);
+
+    for (agent, physics, velocity, force) in agents.iter_mut() {
+        match physics.output {
+            PhysicsOutput::Velocity => {
+                if let Some(mut velocity) = velocity {
+                    velocity.linvel = agent.velocity;
+                }
+            }
+            PhysicsOutput::Force { mass, max_force } => {
+                let current_velocity = velocity.map_or(Vec3::ZERO, |velocity| velocity.linvel);
+
+                if let Some(mut force) = force {
+                    force.force = ((agent.velocity - current_velocity) * mass / time_step)
+                        .clamp_length_max(max_force);
+                }
+            }
+        }
+    }
+}
+
+// Keeps agents and obstacles in sync with their bevy_rapier rigid bodies
+// Requires the NavigationPlugin, fixed_update has to match it.
+#[derive(Default)]
+pub struct NavigationPhysicsPlugin {
+    pub fixed_update: bool,
+}
+
+impl Plugin for NavigationPhysicsPlugin {
+    fn build(&self, app: &mut App) {
+        if self.fixed_update {
+            add_physics_systems(app, FixedUpdate);
+        } else {
+            add_physics_systems(app, Update);
+        }
+    }
+}
+
+fn add_physics_systems(app: &mut App, schedule: impl ScheduleLabel) {
+    app.add_systems(
+        schedule,
+        (
+            read_physics_state
+                .before(build_spatial_index)
+                .in_set(NavigationSet::BuildSpatialIndex),
+            write_physics_output
+                .after(apply_velocities)
+                .in_set(NavigationSet::ApplyVelocity),
+        ),
+    );
+}
@@ -0,0 +1,333 @@
+use bevy::{ecs::schedule::ScheduleLabel, prelude::*};
+
+use crate::{
+    apply_velocities, build_spatial_index, compute_avoidance_velocities, detect_blocked_agents,
+    interpolate_transforms, predict_collisions, restore_simulated_positions,
+    store_simulated_positions, update_formation_member_velocities, update_formations,
+    update_preferred_velocities, ArrivedAtGoal, CollisionPredicted, ExternalMovement,
+    FormationChanged, FormationMember, NavAgent, NavGoal, NavInterpolation, NavObstacle, NavStats,
+    NavigationSet, NavigationSettings, PathBlocked, SpatialIndex, StaticObstacles,
+};
+
+// Adds collision avoidance to every entity with a NavAgent
+// Agents and obstacles are expected to be top level entities, their Transform is their world position.
+//
+// cell_size: The size of a cell of the spatial index, about the neighbour distance works well
+// fixed_update_rate: Runs the navigation in FixedUpdate at this many ticks per second instead of
+//                    every frame. Agents with NavInterpolation are then moved smoothly between
+//                    the ticks.
+pub struct NavigationPlugin {
+    pub settings: NavigationSettings,
+    pub cell_size: f32,
+    pub fixed_update_rate: Option<f64>,
+}
+
+impl Default for NavigationPlugin {
+    fn default() -> Self {
+        let settings = NavigationSettings::default();
+
+        Self {
+            cell_size: settings.neighbour_distance,
+            settings,
+            fixed_update_rate: None,
+        }
+    }
+}
+
+impl NavigationPlugin {
+    pub fn with_fixed_update_rate(mut self, ticks_per_second: f64) -> Self {
+        assert!(ticks_per_second > 0.0);
+
+        self.fixed_update_rate = Some(ticks_per_second);
+        self
+    }
+}
+
+impl Plugin for NavigationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ArrivedAtGoal>()
+            .add_event::<PathBlocked>()
+            .add_event::<CollisionPredicted>()
+            .register_type::<NavAgent>()
+            .register_type::<NavObstacle>()
+            .register_type::<NavGoal>()
+            .register_type::<ExternalMovement>()
+            .register_type::<NavInterpolation>()
+            .register_type::<NavigationSettings>()
+            .insert_resource(self.settings.clone())
+            .register_type::<NavStats>()
+            .insert_resource(SpatialIndex::new(self.cell_size))
+            .init_resource::<NavStats>()
+            .init_resource::<StaticObstacles>();
+
+        match self.fixed_update_rate {
+            Some(ticks_per_second) => {
+                add_navigation_systems(app, FixedUpdate);
+
+                app.insert_resource(Time::<Fixed>::from_hz(ticks_per_second))
+                    .add_systems(
+                        FixedUpdate,
+                        (
+                            restore_simulated_positions
+                                .before(build_spatial_index)
+                                .in_set(NavigationSet::BuildSpatialIndex),
+                            store_simulated_positions
+                                .after(apply_velocities)
+                                .in_set(NavigationSet::ApplyVelocity),
+                        ),
+                    )
+                    .add_systems(Update, interpolate_transforms);
+            }
+            None => add_navigation_systems(app, Update),
+        }
+    }
+}
+
+fn add_navigation_systems(app: &mut App, schedule: impl ScheduleLabel + Clone) {
+    app.configure_sets(
+        schedule.clone(),
+        (
+            NavigationSet::BuildSpatialIndex,
+            NavigationSet::PreferredVelocity,
+            NavigationSet::Avoidance,
+            NavigationSet::ApplyVelocity,
+        )
+            .chain(),
+    )
+    .add_systems(
+        schedule,
+        (
+            build_spatial_index.in_set(NavigationSet::BuildSpatialIndex),
+            update_preferred_velocities.in_set(NavigationSet::PreferredVelocity),
+            (
+                predict_collisions,
+                compute_avoidance_velocities,
+                detect_blocked_agents,
+            )
+                .chain()
+                .in_set(NavigationSet::Avoidance),
+            apply_velocities.in_set(NavigationSet::ApplyVelocity),
+        ),
+    );
+}
+
+// Keeps the members of every NavFormation in their slots, requires the NavigationPlugin
+// The formations are updated after the preferred velocities from NavGoals, so a member follows
+// its slot even when it also has a goal.
+//
+// fixed_update: Has to match the NavigationPlugin, true if it runs in FixedUpdate
+#[derive(Default)]
+pub struct FormationPlugin {
+    pub fixed_update: bool,
+}
+
+impl Plugin for FormationPlugin {
+    fn build(&self, app: &mut App) {
+        let systems = (update_formations, update_formation_member_velocities)
+            .chain()
+            .after(update_preferred_velocities)
+            .in_set(NavigationSet::PreferredVelocity);
+
+        app.add_event::<FormationChanged>()
+            .register_type::<FormationMember>();
+
+        if self.fixed_update {
+            app.add_systems(FixedUpdate, systems);
+        } else {
+            app.add_systems(Update, systems);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use coordination::{formations::LineFormation, FormationChoice, FormationEvaluationParams};
+    use geometry::colliders::Collider;
+
+    use super::*;
+    use crate::{FormationBundle, NavFormation};
+
+    #[test]
+    fn test_agent_heads_to_goal_and_avoids_obstacle() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, NavigationPlugin::default()));
+
+        let free_agent = app
+            .world
+            .spawn((
+                Transform::from_translation(Vec3::new(0.0, 100.0, 0.0)),
+                NavAgent::new(Collider::new_sphere(1.0), 10.0),
+                NavGoal::new(Vec3::new(100.0, 100.0, 0.0)),
+            ))
+            .id();
+
+        let blocked_agent = app
+            .world
+            .spawn((
+                Transform::from_translation(Vec3::ZERO),
+                NavAgent::new(Collider::new_sphere(1.0), 10.0),
+                NavGoal::new(Vec3::new(100.0, 0.0, 0.0)),
+            ))
+            .id();
+
+        app.world.spawn((
+            Transform::from_translation(Vec3::new(5.0, 0.0, 0.0)),
+            NavObstacle::new(Collider::new_sphere(2.0)),
+        ));
+
+        app.update();
+
+        let free_velocity = app.world.get::<NavAgent>(free_agent).unwrap().velocity;
+        let blocked_velocity = app.world.get::<NavAgent>(blocked_agent).unwrap().velocity;
+
+        assert!(free_velocity.distance(Vec3::new(10.0, 0.0, 0.0)) < 1e-3);
+        assert!(blocked_velocity.distance(Vec3::new(10.0, 0.0, 0.0)) > 1.0);
+
+        let stats = app.world.resource::<NavStats>();
+        assert_eq!(stats.number_of_agents, 2);
+        assert_eq!(stats.number_of_obstacles, 1);
+        assert_eq!(stats.colliding_agents, 0);
+        assert!(stats.average_speed_ratio > 0.0 && stats.average_speed_ratio <= 1.0 + 1e-3);
+    }
+
+    #[test]
+    fn test_formation_members_get_distinct_slots() {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            NavigationPlugin::default(),
+            FormationPlugin::default(),
+        ));
+
+        let formation = app
+            .world
+            .spawn(FormationBundle::new(
+                NavFormation::new(
+                    vec![Box::new(LineFormation::new(1.0, 1.0, 1.0))],
+                    FormationEvaluationParams::new(10.0),
+                )
+                .with_preferred_velocity(Vec3::new(0.0, 0.0, 10.0)),
+            ))
+            .id();
+
+        let members = (0..3)
+            .map(|i| {
+                app.world
+                    .spawn((
+                        Transform::from_translation(Vec3::new(0.0, 0.0, i as f32 * 5.0)),
+                        NavAgent::new(Collider::new_sphere(1.0), 10.0),
+                        FormationMember::new(formation),
+                    ))
+                    .id()
+            })
+            .collect::<Vec<_>>();
+
+        app.update();
+
+        let mut slots = members
+            .iter()
+            .map(|member| {
+                let member = app.world.get::<FormationMember>(*member).unwrap();
+
+                assert!(member.target.is_some());
+
+                member.slot.unwrap()
+            })
+            .collect::<Vec<_>>();
+
+        slots.sort();
+        assert_eq!(slots, vec![0, 1, 2]);
+
+        let nav_formation = app.world.get::<NavFormation>(formation).unwrap();
+        assert_eq!(nav_formation.members.len(), 3);
+        assert!(nav_formation.velocity.length() > 0.0);
+
+        for member in &members {
+            let agent = app.world.get::<NavAgent>(*member).unwrap();
+            assert!(agent.preferred_velocity.length() > 0.0);
+        }
+    }
+
+    #[test]
+    fn test_members_keep_their_positions_in_the_current_formation() {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            NavigationPlugin::default(),
+            FormationPlugin::default(),
+        ));
+
+        // The obstacle is next to the compact group, but inside of the wide line template
+        let params = FormationEvaluationParams::new(10.0)
+            .with_deformation_penalty_multiplier(0.0)
+            .with_obstacle_avoidance_time_horizon(5.0);
+        let formation = app
+            .world
+            .spawn(FormationBundle::new(
+                NavFormation::new(vec![Box::new(LineFormation::new(1.0, 10.0, 1.0))], params)
+                    .with_preferred_velocity(Vec3::new(0.0, 0.0, 5.0))
+                    .with_lookahead(0.0),
+            ))
+            .id();
+
+        app.world.spawn((
+            Transform::from_translation(Vec3::new(6.0, 0.5, 1.5)),
+            NavObstacle::new(Collider::new_sphere(1.0)),
+        ));
+
+        let positions = [
+            Vec3::new(-1.0, 0.0, 0.0),
+            Vec3::new(1.0, 0.0, 0.5),
+            Vec3::new(0.0, 1.0, -0.5),
+        ];
+        let members = positions
+            .iter()
+            .map(|position| {
+                app.world
+                    .spawn((
+                        Transform::from_translation(*position),
+                        NavAgent::new(Collider::new_sphere(0.1), 10.0),
+                        FormationMember::new(formation),
+                    ))
+                    .id()
+            })
+            .collect::<Vec<_>>();
+
+        app.update();
+
+        let nav_formation = app.world.get::<NavFormation>(formation).unwrap();
+        assert_eq!(nav_formation.choice, Some(FormationChoice::Current));
+
+        for (member, position) in members.iter().zip(positions) {
+            let member = app.world.get::<FormationMember>(*member).unwrap();
+            assert!(member.target.unwrap().distance(position) < 1e-4);
+        }
+    }
+
+    #[test]
+    fn test_arrival_is_reported_once() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, NavigationPlugin::default()));
+
+        let agent = app
+            .world
+            .spawn((
+                Transform::from_translation(Vec3::new(1.0, 0.0, 0.0)),
+                NavAgent::new(Collider::new_sphere(1.0), 10.0),
+                NavGoal::new(Vec3::new(1.0, 0.0, 0.0)),
+            ))
+            .id();
+
+        let events = app.world.resource::<Events<ArrivedAtGoal>>();
+        let mut reader = events.get_reader();
+
+        app.update();
+        app.update();
+
+        let events = app.world.resource::<Events<ArrivedAtGoal>>();
+        let arrivals = reader.read(events).collect::<Vec<_>>();
+
+        assert_eq!(arrivals.len(), 1);
+        assert_eq!(arrivals[0].entity, agent);
+    }
+}
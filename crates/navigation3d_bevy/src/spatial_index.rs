@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use orca::Agent3D;
+
+// Agent or obstacle stored in the spatial index
+#[derive(Clone, Debug)]
+pub struct SpatialEntry {
+    pub entity: Entity,
+    pub agent: Agent3D,
+    pub is_obstacle: bool,
+}
+
+// Uniform hash grid over all agents and obstacles, rebuilt every tick
+// Entries are stored in the cell of their position, so shapes larger than a cell
+// are found only by queries whose radius includes their extent.
+#[derive(Resource, Clone, Debug)]
+pub struct SpatialIndex {
+    cell_size: f32,
+    entries: Vec<SpatialEntry>,
+    cells: HashMap<IVec3, Vec<usize>>,
+}
+
+impl SpatialIndex {
+    pub fn new(cell_size: f32) -> Self {
+        assert!(cell_size > 0.0);
+
+        Self {
+            cell_size,
+            entries: Vec::new(),
+            cells: HashMap::new(),
+        }
+    }
+
+    pub fn cell_size(&self) -> f32 {
+        self.cell_size
+    }
+
+    pub fn entries(&self) -> &[SpatialEntry] {
+        &self.entries
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.cells.clear();
+    }
+
+    pub fn insert(&mut self, entry: SpatialEntry) {
+        let cell = self.cell_of(entry.agent.position);
+
+        self.cells.entry(cell).or_default().push(self.entries.len());
+        self.entries.push(entry);
+    }
+
+    // Returns all entries with their position within the radius around the position
+    pub fn query(&self, position: Vec3, radius: f32) -> impl Iterator<Item = &SpatialEntry> {
+        let min = self.cell_of(position - Vec3::splat(radius));
+        let max = self.cell_of(position + Vec3::splat(radius));
+
+        (min.z..=max.z)
+            .flat_map(move |z| {
+                (min.y..=max.y).flat_map(move |y| (min.x..=max.x).map(move |x| IVec3::new(x, y, z)))
+            })
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .map(|&index| &self.entries[index])
+            .filter(move |entry| entry.agent.position.distance(position) <= radius)
+    }
+
+    fn cell_of(&self, position: Vec3) -> IVec3 {
+        (position / self.cell_size).floor().as_ivec3()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use geometry::colliders::Collider;
+
+    use super::*;
+
+    #[test]
+    fn test_query_returns_entries_within_radius() {
+        let mut index = SpatialIndex::new(10.0);
+
+        for i in 0..10 {
+            index.insert(SpatialEntry {
+                entity: Entity::from_raw(i),
+                agent: Agent3D::new(
+                    Vec3::new(i as f32 * 7.0, 0.0, 0.0),
+                    Vec3::ZERO,
+                    Collider::new_sphere(1.0),
+                ),
+                is_obstacle: false,
+            });
+        }
+
+        let mut found = index
+            .query(Vec3::new(21.0, 0.0, 0.0), 14.0)
+            .map(|entry| entry.entity.index())
+            .collect::<Vec<_>>();
+        found.sort_unstable();
+
+        assert_eq!(found, vec![1, 2, 3, 4, 5]);
+    }
+}
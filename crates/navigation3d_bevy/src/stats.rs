@@ -0,0 +1,35 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+// Measurements of the last navigation tick, plus totals since the start
+// Each stage of NavigationSet overwrites its own measurements, so the values are consistent
+// once the whole tick ran.
+#[derive(Resource, Reflect, Clone, Debug, Default)]
+#[reflect(Resource)]
+pub struct NavStats {
+    pub number_of_agents: usize,
+    pub number_of_obstacles: usize,
+    // Agents overlapping at least one of their neighbours
+    pub colliding_agents: usize,
+    // Agents whose ORCA planes couldn't all be satisfied, the velocity violating them the least
+    // was used instead
+    pub infeasible_solutions: usize,
+    // The mean of the chosen speed divided by the preferred speed, of agents that want to move
+    pub average_speed_ratio: f32,
+    pub total_colliding_agents: usize,
+    pub total_infeasible_solutions: usize,
+    pub spatial_index_time: Duration,
+    pub preferred_velocity_time: Duration,
+    pub avoidance_time: Duration,
+    pub apply_velocity_time: Duration,
+}
+
+impl NavStats {
+    pub fn total_time(&self) -> Duration {
+        self.spatial_index_time
+            + self.preferred_velocity_time
+            + self.avoidance_time
+            + self.apply_velocity_time
+    }
+}
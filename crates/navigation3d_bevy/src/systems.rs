@@ -0,0 +1,444 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use bevy::{
+    ecs::query::BatchingStrategy,
+    prelude::*,
+    tasks::{ComputeTaskPool, TaskPool},
+    utils::Instant,
+};
+use geometry::{Plane, Vec3Operations};
+use orca::{optimize_velocity_3d, Agent3D, VelocityObstacle3D};
+
+use crate::{
+    ArrivedAtGoal, CollisionPredicted, ExternalMovement, NavAgent, NavGoal, NavObstacle, NavStats,
+    PathBlocked, SpatialEntry, SpatialIndex, StaticObstacles,
+};
+
+// An agent is blocked when it moves in its preferred direction slower than this fraction
+// of its preferred speed
+const BLOCKED_SPEED_FRACTION: f32 = 0.1;
+// The number of batches each thread gets, more batches balance uneven neighbourhoods better
+const BATCHES_PER_THREAD: usize = 4;
+
+// Parameters of the avoidance shared by all agents
+//
+// time_horizon: How far ahead in seconds agents avoid each other
+// obstacle_time_horizon: How far ahead in seconds agents avoid obstacles
+// neighbour_distance: Agents and obstacles further away are ignored
+// max_neighbours: The number of the closest neighbours each agent avoids
+// time_step: The minimal time step used for the ORCA planes of already colliding agents
+// min_batch_size: The smallest number of agents processed by one task, smaller crowds are
+//                 processed by fewer threads
+#[derive(Resource, Reflect, Clone, Debug)]
+#[reflect(Resource)]
+#[cfg_attr(feature = "config", derive(serde::Deserialize))]
+#[cfg_attr(feature = "config", serde(default))]
+pub struct NavigationSettings {
+    pub time_horizon: f32,
+    pub obstacle_time_horizon: f32,
+    pub neighbour_distance: f32,
+    pub max_neighbours: usize,
+    pub time_step: f32,
+    pub min_batch_size: usize,
+}
+
+impl Default for NavigationSettings {
+    fn default() -> Self {
+        Self {
+            time_horizon: 5.0,
+            obstacle_time_horizon: 2.0,
+            neighbour_distance: 50.0,
+            max_neighbours: 15,
+            time_step: 0.1,
+            min_batch_size: 32,
+        }
+    }
+}
+
+// The stages of a navigation tick, run in this order
+#[derive(SystemSet, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum NavigationSet {
+    BuildSpatialIndex,
+    PreferredVelocity,
+    Avoidance,
+    ApplyVelocity,
+}
+
+pub fn build_spatial_index(
+    mut index: ResMut<SpatialIndex>,
+    mut stats: ResMut<NavStats>,
+    agents: Query<(Entity, &Transform, &NavAgent)>,
+    obstacles: Query<(Entity, &Transform, &NavObstacle)>,
+) {
+    let start = Instant::now();
+
+    index.clear();
+
+    for (entity, transform, agent) in agents.iter() {
+        index.insert(SpatialEntry {
+            entity,
+            agent: Agent3D::new(transform.translation, agent.velocity, agent.shape.clone()),
+            is_obstacle: false,
+        });
+    }
+
+    for (entity, transform, obstacle) in obstacles.iter() {
+        let mut agent = Agent3D::new(
+            transform.translation,
+            obstacle.velocity,
+            obstacle.shape.clone(),
+        );
+
+        // Obstacles don't avoid, so agents take the whole responsibility
+        agent.responsibility = 0.0;
+
+        index.insert(SpatialEntry {
+            entity,
+            agent,
+            is_obstacle: true,
+        });
+    }
+
+    stats.number_of_agents = agents.iter().len();
+    stats.number_of_obstacles = obstacles.iter().len();
+    stats.spatial_index_time = start.elapsed();
+}
+
+pub fn update_preferred_velocities(
+    mut stats: ResMut<NavStats>,
+    mut agents: Query<(Entity, &Transform, &mut NavGoal, &mut NavAgent)>,
+    mut arrived_events: EventWriter<ArrivedAtGoal>,
+) {
+    let start = Instant::now();
+
+    for (entity, transform, mut goal, mut agent) in agents.iter_mut() {
+        agent.preferred_velocity =
+            goal.get_preferred_velocity(transform.translation, agent.max_speed);
+
+        let arrived = goal.is_reached(transform.translation);
+
+        if arrived && !goal.arrived {
+            arrived_events.send(ArrivedAtGoal {
+                entity,
+                goal: goal.position,
+            });
+        }
+
+        goal.arrived = arrived;
+    }
+
+    stats.preferred_velocity_time = start.elapsed();
+}
+
+// Sends CollisionPredicted for every neighbour the agent would hit within the time horizon
+// with the current velocities. Obstacles are reported only by the agents.
+pub fn predict_collisions(
+    settings: Res<NavigationSettings>,
+    index: Res<SpatialIndex>,
+    mut stats: ResMut<NavStats>,
+    mut collision_events: EventWriter<CollisionPredicted>,
+) {
+    let start = Instant::now();
+
+    let agents = index
+        .entries()
+        .iter()
+        .filter(|entry| !entry.is_obstacle)
+        .collect::<Vec<_>>();
+
+    let task_pool = ComputeTaskPool::get();
+    let batch_size = get_batch_size(agents.len(), task_pool, settings.min_batch_size);
+
+    let batches = task_pool.scope(|scope| {
+        for batch in agents.chunks(batch_size) {
+            let settings = &settings;
+            let index = &index;
+
+            scope.spawn(async move {
+                batch
+                    .iter()
+                    .flat_map(|entry| get_predicted_collisions(index, settings, entry))
+                    .collect::<Vec<_>>()
+            });
+        }
+    });
+
+    collision_events.send_batch(batches.into_iter().flatten());
+
+    stats.avoidance_time = start.elapsed();
+}
+
+fn get_predicted_collisions(
+    index: &SpatialIndex,
+    settings: &NavigationSettings,
+    entry: &SpatialEntry,
+) -> Vec<CollisionPredicted> {
+    let agent = &entry.agent;
+
+    index
+        .query(agent.position, settings.neighbour_distance)
+        .filter(|other| other.entity != entry.entity)
+        .filter_map(|other| {
+            let time_horizon = if other.is_obstacle {
+                settings.obstacle_time_horizon
+            } else {
+                settings.time_horizon
+            };
+
+            let time_to_collision = get_time_to_collision(
+                other.agent.position - agent.position,
+                agent.velocity - other.agent.velocity,
+                agent.shape.bounding_sphere().radius + other.agent.shape.bounding_sphere().radius,
+            )?;
+
+            (time_to_collision <= time_horizon).then_some(CollisionPredicted {
+                entity: entry.entity,
+                other: other.entity,
+                time_to_collision,
+            })
+        })
+        .collect()
+}
+
+pub fn compute_avoidance_velocities(
+    time: Res<Time>,
+    settings: Res<NavigationSettings>,
+    index: Res<SpatialIndex>,
+    static_obstacles: Res<StaticObstacles>,
+    mut stats: ResMut<NavStats>,
+    mut agents: Query<(Entity, &Transform, &mut NavAgent)>,
+) {
+    let start = Instant::now();
+    let colliding_agents = AtomicUsize::new(0);
+    let infeasible_solutions = AtomicUsize::new(0);
+
+    let time_step = time.delta_seconds().max(settings.time_step);
+    let batch_size = get_batch_size(
+        agents.iter().len(),
+        ComputeTaskPool::get(),
+        settings.min_batch_size,
+    );
+
+    agents
+        .par_iter_mut()
+        .batching_strategy(BatchingStrategy::fixed(batch_size))
+        .for_each(|(entity, transform, mut agent)| {
+            let agent_self =
+                Agent3D::new(transform.translation, agent.velocity, agent.shape.clone());
+
+            let neighbours = get_neighbours(&index, &settings, entity, transform.translation);
+            let radius = agent.shape.bounding_sphere().radius;
+
+            if neighbours.iter().any(|entry| {
+                entry.agent.position.distance(transform.translation)
+                    < radius + entry.agent.shape.bounding_sphere().radius
+            }) {
+                colliding_agents.fetch_add(1, Ordering::Relaxed);
+            }
+
+            let static_planes = static_obstacles
+                .get_obstacle_agents(
+                    transform.translation,
+                    settings.neighbour_distance,
+                    settings.max_neighbours,
+                )
+                .into_iter()
+                .map(|obstacle| {
+                    VelocityObstacle3D::new(&agent_self, &obstacle, settings.obstacle_time_horizon)
+                        .orca_plane(time_step)
+                });
+
+            let planes = neighbours
+                .iter()
+                .map(|entry| get_orca_plane(&settings, &agent_self, entry, time_step))
+                .chain(static_planes)
+                .collect::<Vec<Plane>>();
+
+            agent.velocity =
+                optimize_velocity_3d(agent.preferred_velocity, agent.max_speed, &planes);
+
+            if !planes.iter().all(|plane| plane.contains(agent.velocity)) {
+                infeasible_solutions.fetch_add(1, Ordering::Relaxed);
+            }
+        });
+
+    stats.colliding_agents = colliding_agents.into_inner();
+    stats.infeasible_solutions = infeasible_solutions.into_inner();
+    stats.total_colliding_agents += stats.colliding_agents;
+    stats.total_infeasible_solutions += stats.infeasible_solutions;
+    stats.avoidance_time += start.elapsed();
+}
+
+// Returns the closest neighbours the agent avoids, ordered by distance
+pub(crate) fn get_neighbours<'a>(
+    index: &'a SpatialIndex,
+    settings: &NavigationSettings,
+    entity: Entity,
+    position: Vec3,
+) -> Vec<&'a SpatialEntry> {
+    let mut neighbours = index
+        .query(position, settings.neighbour_distance)
+        .filter(|entry| entry.entity != entity)
+        .collect::<Vec<_>>();
+
+    neighbours.sort_by(|a, b| {
+        a.agent
+            .position
+            .distance_squared(position)
+            .total_cmp(&b.agent.position.distance_squared(position))
+    });
+
+    neighbours.truncate(settings.max_neighbours);
+    neighbours
+}
+
+pub(crate) fn get_orca_plane(
+    settings: &NavigationSettings,
+    agent_self: &Agent3D,
+    neighbour: &SpatialEntry,
+    time_step: f32,
+) -> Plane {
+    let time_horizon = if neighbour.is_obstacle {
+        settings.obstacle_time_horizon
+    } else {
+        settings.time_horizon
+    };
+
+    VelocityObstacle3D::new(agent_self, &neighbour.agent, time_horizon).orca_plane(time_step)
+}
+
+pub fn detect_blocked_agents(
+    mut stats: ResMut<NavStats>,
+    mut agents: Query<(Entity, &Transform, &mut NavAgent)>,
+    mut blocked_events: EventWriter<PathBlocked>,
+) {
+    let start = Instant::now();
+    let mut speed_ratio_sum = 0.0;
+    let mut moving_agents = 0;
+
+    for (entity, transform, mut agent) in agents.iter_mut() {
+        let preferred_speed = agent.preferred_velocity.length();
+
+        if preferred_speed > f32::EPSILON {
+            speed_ratio_sum += agent.velocity.length() / preferred_speed;
+            moving_agents += 1;
+        }
+
+        let is_blocked = preferred_speed > f32::EPSILON
+            && agent
+                .velocity
+                .dot(agent.preferred_velocity / preferred_speed)
+                < BLOCKED_SPEED_FRACTION * preferred_speed;
+
+        if is_blocked && !agent.is_blocked {
+            blocked_events.send(PathBlocked {
+                entity,
+                position: transform.translation,
+            });
+        }
+
+        agent.is_blocked = is_blocked;
+    }
+
+    stats.average_speed_ratio = if moving_agents > 0 {
+        speed_ratio_sum / moving_agents as f32
+    } else {
+        0.0
+    };
+    stats.avoidance_time += start.elapsed();
+}
+
+pub fn apply_velocities(
+    time: Res<Time>,
+    mut stats: ResMut<NavStats>,
+    mut agents: Query<(&NavAgent, &mut Transform), Without<ExternalMovement>>,
+) {
+    let start = Instant::now();
+
+    for (agent, mut transform) in agents.iter_mut() {
+        transform.translation += agent.velocity * time.delta_seconds();
+    }
+
+    stats.apply_velocity_time = start.elapsed();
+}
+
+// Returns how many agents one task processes, so that every thread gets a few batches
+// but no batch is smaller than min_batch_size
+fn get_batch_size(number_of_agents: usize, task_pool: &TaskPool, min_batch_size: usize) -> usize {
+    get_batch_size_for_threads(number_of_agents, task_pool.thread_num(), min_batch_size)
+}
+
+fn get_batch_size_for_threads(
+    number_of_agents: usize,
+    number_of_threads: usize,
+    min_batch_size: usize,
+) -> usize {
+    let number_of_batches = number_of_threads.max(1) * BATCHES_PER_THREAD;
+
+    number_of_agents
+        .div_ceil(number_of_batches)
+        .max(min_batch_size)
+        .max(1)
+}
+
+// Returns the time until two spheres touch, zero if they already overlap
+// and None if they never do.
+//
+// relative_position: The position of the other sphere relative to the first one
+// relative_velocity: The velocity of the first sphere relative to the other one
+// radius: The sum of the radii of the spheres
+fn get_time_to_collision(
+    relative_position: Vec3,
+    relative_velocity: Vec3,
+    radius: f32,
+) -> Option<f32> {
+    let c = relative_position.length_squared() - radius * radius;
+
+    if c <= 0.0 {
+        return Some(0.0);
+    }
+
+    let a = relative_velocity.length_squared();
+    let b = relative_position.dot(relative_velocity);
+
+    if a <= f32::EPSILON || b <= 0.0 {
+        return None;
+    }
+
+    let discriminant = b * b - a * c;
+
+    if discriminant < 0.0 {
+        return None;
+    }
+
+    Some((b - discriminant.sqrt()) / a)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_batch_size() {
+        assert_eq!(get_batch_size_for_threads(10, 8, 32), 32);
+        assert_eq!(get_batch_size_for_threads(3200, 8, 32), 100);
+        assert_eq!(get_batch_size_for_threads(0, 0, 0), 1);
+    }
+
+    #[test]
+    fn test_time_to_collision() {
+        let time = get_time_to_collision(Vec3::new(10.0, 0.0, 0.0), Vec3::new(2.0, 0.0, 0.0), 2.0);
+        assert!((time.unwrap() - 4.0).abs() < 1e-5);
+
+        let overlapping = get_time_to_collision(Vec3::new(1.0, 0.0, 0.0), Vec3::ZERO, 2.0);
+        assert_eq!(overlapping, Some(0.0));
+
+        let moving_away =
+            get_time_to_collision(Vec3::new(10.0, 0.0, 0.0), Vec3::new(-2.0, 0.0, 0.0), 2.0);
+        assert_eq!(moving_away, None);
+
+        let missing =
+            get_time_to_collision(Vec3::new(10.0, 0.0, 0.0), Vec3::new(0.0, 2.0, 0.0), 2.0);
+        assert_eq!(missing, None);
+    }
+}
@@ -0,0 +1,122 @@
+use bevy_math::Vec3;
+use coordination::{Formation, FormationDecision};
+use geometry::colliders::Collider;
+use orca::Agent3D;
+
+// Reads rows of 3 floats, None if the length isn't a multiple of 3
+pub fn vec3s_from_rows(values: &[f32]) -> Option<Vec<Vec3>> {
+    if !values.len().is_multiple_of(3) {
+        return None;
+    }
+
+    Some(values.chunks_exact(3).map(Vec3::from_slice).collect())
+}
+
+pub fn vec3s_to_rows(vectors: impl IntoIterator<Item = Vec3>) -> Vec<f32> {
+    vectors
+        .into_iter()
+        .flat_map(|vector| vector.to_array())
+        .collect()
+}
+
+// Reads rows of x, y, z and radius into static sphere obstacles,
+// None if the length isn't a multiple of 4 or a radius isn't positive
+pub fn sphere_obstacles_from_rows(values: &[f32]) -> Option<Vec<Agent3D>> {
+    if !values.len().is_multiple_of(4) {
+        return None;
+    }
+
+    values
+        .chunks_exact(4)
+        .map(|row| {
+            (row[3] > 0.0).then(|| {
+                let mut obstacle = Agent3D::new(
+                    Vec3::from_slice(row),
+                    Vec3::ZERO,
+                    Collider::new_sphere(row[3]),
+                );
+                obstacle.responsibility = 0.0;
+                obstacle
+            })
+        })
+        .collect()
+}
+
+// Places the slots of the chosen formation in the world around the center of the bounds of
+// the positions the formation was chosen for
+pub fn formation_slots_in_world(decision: &FormationDecision, positions: &[Vec3]) -> Vec<Vec3> {
+    let center = Formation::new(positions.to_vec()).get_bounds(0.0).center;
+
+    decision
+        .formation
+        .get_positions()
+        .iter()
+        .map(|position| decision.rotation * *position + center)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use coordination::{
+        formations::LineFormation, FormationChoice, FormationEvaluationParams, FormationTemplate,
+        FormationTemplateSet,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_vec3_rows_round_trip() {
+        let rows = [1.0, 2.0, 3.0, 4.0, 5.0, 6.0];
+
+        let vectors = vec3s_from_rows(&rows).unwrap();
+
+        assert_eq!(
+            vectors,
+            vec![Vec3::new(1.0, 2.0, 3.0), Vec3::new(4.0, 5.0, 6.0)]
+        );
+        assert_eq!(vec3s_to_rows(vectors), rows.to_vec());
+        assert!(vec3s_from_rows(&rows[..4]).is_none());
+    }
+
+    #[test]
+    fn test_current_formation_slots_are_the_positions() {
+        let template = LineFormation::new(1.0, 10.0, 1.0);
+        let template_set = FormationTemplateSet::from_slice(&[&template as &dyn FormationTemplate]);
+        let positions = [
+            Vec3::new(-1.0, 0.0, 0.0),
+            Vec3::new(1.0, 0.0, 0.5),
+            Vec3::new(0.0, 1.0, -0.5),
+        ];
+
+        // The obstacle is next to the compact group, but inside of the wide line template
+        let obstacles = sphere_obstacles_from_rows(&[6.0, 0.5, 1.5, 1.0]).unwrap();
+        let params = FormationEvaluationParams::new(10.0)
+            .with_deformation_penalty_multiplier(0.0)
+            .with_obstacle_avoidance_time_horizon(5.0);
+
+        let decision = template_set
+            .get_best_formation(&positions, Vec3::Z * 5.0, &obstacles, &[], &params)
+            .unwrap();
+
+        assert_eq!(decision.choice, FormationChoice::Current);
+
+        for (slot, position) in formation_slots_in_world(&decision, &positions)
+            .iter()
+            .zip(positions)
+        {
+            assert!(slot.distance(position) < 1e-4);
+        }
+    }
+
+    #[test]
+    fn test_sphere_obstacles_from_rows() {
+        let obstacles = sphere_obstacles_from_rows(&[1.0, 2.0, 3.0, 0.5]).unwrap();
+
+        assert_eq!(obstacles.len(), 1);
+        assert_eq!(obstacles[0].position, Vec3::new(1.0, 2.0, 3.0));
+        assert!((obstacles[0].shape.bounding_sphere().radius - 0.5).abs() < 1e-6);
+
+        assert!(sphere_obstacles_from_rows(&[1.0, 2.0, 3.0, 0.0]).is_none());
+        assert!(sphere_obstacles_from_rows(&[1.0, 2.0, 3.0]).is_none());
+    }
+}
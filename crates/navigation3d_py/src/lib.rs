@@ -0,0 +1,13 @@
+// Python bindings of the avoidance, formation and path planning crates
+// The bindings are only compiled with the python feature, the module is built with maturin:
+//
+//     maturin develop --release
+//
+// Lists of vectors are passed as numpy float32 arrays of shape (n, 3), sphere obstacles as
+// arrays of shape (n, 4) with the radius in the last column.
+
+mod conversions;
+#[cfg(feature = "python")]
+mod python;
+
+pub use conversions::*;
@@ -0,0 +1,278 @@
+use bevy_math::Vec3;
+use coordination::{
+    formation_templates_from_json, FormationChoice, FormationEvaluationParams, FormationTemplate,
+    FormationTemplateSet,
+};
+use geometry::{colliders::Collider, Sphere};
+use numpy::{ndarray::Array2, IntoPyArray, PyArray2, PyReadonlyArray2};
+use orca::{Agent3D, AgentId, Simulator, SimulatorAgent, SimulatorParams};
+use pathfinding::VoxelGrid;
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+use crate::{formation_slots_in_world, sphere_obstacles_from_rows, vec3s_from_rows, vec3s_to_rows};
+
+fn read_rows(array: &PyReadonlyArray2<f32>, columns: usize) -> PyResult<Vec<f32>> {
+    let array = array.as_array();
+
+    if array.ncols() != columns {
+        return Err(PyValueError::new_err(format!(
+            "Expected an array of shape (n, {columns})"
+        )));
+    }
+
+    Ok(array.iter().copied().collect())
+}
+
+fn read_vec3s(array: &PyReadonlyArray2<f32>) -> PyResult<Vec<Vec3>> {
+    let rows = read_rows(array, 3)?;
+
+    vec3s_from_rows(&rows).ok_or_else(|| PyValueError::new_err("Expected rows of 3 values"))
+}
+
+fn read_obstacles(array: Option<&PyReadonlyArray2<f32>>) -> PyResult<Vec<Agent3D>> {
+    let Some(array) = array else {
+        return Ok(Vec::new());
+    };
+
+    let rows = read_rows(array, 4)?;
+
+    sphere_obstacles_from_rows(&rows)
+        .ok_or_else(|| PyValueError::new_err("Obstacle radii have to be positive"))
+}
+
+fn to_array(py: Python<'_>, vectors: impl IntoIterator<Item = Vec3>) -> &PyArray2<f32> {
+    let rows = vec3s_to_rows(vectors);
+    let len = rows.len() / 3;
+
+    Array2::from_shape_vec((len, 3), rows)
+        .expect("Rows of 3 values always fit the shape")
+        .into_pyarray(py)
+}
+
+// ORCA simulation of sphere agents
+// Agents are identified by integers that stay valid until the agent is removed. Added agents
+// join the simulation in the next step.
+#[pyclass(name = "Simulator")]
+struct PySimulator {
+    simulator: Simulator,
+}
+
+#[pymethods]
+impl PySimulator {
+    #[new]
+    #[pyo3(signature = (time_horizon, neighbour_distance, max_neighbours = 10))]
+    fn new(time_horizon: f32, neighbour_distance: f32, max_neighbours: usize) -> Self {
+        Self {
+            simulator: Simulator::new(
+                SimulatorParams::new(time_horizon, neighbour_distance)
+                    .with_max_neighbours(max_neighbours),
+            ),
+        }
+    }
+
+    #[pyo3(signature = (position, radius, max_speed, velocity = [0.0; 3]))]
+    fn add_agent(
+        &mut self,
+        position: [f32; 3],
+        radius: f32,
+        max_speed: f32,
+        velocity: [f32; 3],
+    ) -> PyResult<u64> {
+        if radius <= 0.0 {
+            return Err(PyValueError::new_err("The radius has to be positive"));
+        }
+
+        let agent = Agent3D::new(
+            Vec3::from_array(position),
+            Vec3::from_array(velocity),
+            Collider::new_sphere(radius),
+        );
+
+        Ok(self
+            .simulator
+            .add_agent(SimulatorAgent::new(agent, max_speed))
+            .to_bits())
+    }
+
+    // Returns false when there is no such agent
+    fn remove_agent(&mut self, id: u64) -> bool {
+        self.simulator
+            .remove_agent(AgentId::from_bits(id))
+            .is_some()
+    }
+
+    fn set_preferred_velocity(&mut self, id: u64, velocity: [f32; 3]) -> PyResult<()> {
+        let agent = self
+            .simulator
+            .get_mut(AgentId::from_bits(id))
+            .ok_or_else(|| PyValueError::new_err("Unknown agent"))?;
+
+        agent.preferred_velocity = Vec3::from_array(velocity);
+        Ok(())
+    }
+
+    // Sets the preferred velocities of all agents at once, in the order of ids()
+    fn set_preferred_velocities(&mut self, velocities: PyReadonlyArray2<f32>) -> PyResult<()> {
+        let velocities = read_vec3s(&velocities)?;
+
+        if velocities.len() != self.simulator.agents().count() {
+            return Err(PyValueError::new_err(
+                "Expected one velocity per active agent",
+            ));
+        }
+
+        for ((_, agent), velocity) in self.simulator.agents_mut().zip(velocities) {
+            agent.preferred_velocity = velocity;
+        }
+
+        Ok(())
+    }
+
+    fn step(&mut self, time_step: f32) -> PyResult<()> {
+        if time_step <= 0.0 {
+            return Err(PyValueError::new_err("The time step has to be positive"));
+        }
+
+        self.simulator.step(time_step);
+        Ok(())
+    }
+
+    // The ids of the active agents, the rows of positions() and velocities() follow this order
+    fn ids(&self) -> Vec<u64> {
+        self.simulator
+            .agents()
+            .map(|(id, _)| id.to_bits())
+            .collect()
+    }
+
+    fn positions<'py>(&self, py: Python<'py>) -> &'py PyArray2<f32> {
+        to_array(
+            py,
+            self.simulator
+                .agents()
+                .map(|(_, agent)| agent.agent.position),
+        )
+    }
+
+    fn velocities<'py>(&self, py: Python<'py>) -> &'py PyArray2<f32> {
+        to_array(
+            py,
+            self.simulator
+                .agents()
+                .map(|(_, agent)| agent.agent.velocity),
+        )
+    }
+
+    fn __len__(&self) -> usize {
+        self.simulator.len()
+    }
+}
+
+// Picks the best formation for a group of agents, see FormationTemplateSet::get_best_formation
+// The templates are given as a JSON list of formation template descriptions. Returns the index
+// of the chosen template (None when the current formation is kept), the collision-free velocity,
+// the rotation of the formation as a quaternion (x, y, z, w) and the slot positions in the world.
+#[pyfunction]
+#[pyo3(signature = (templates_json, positions, preferred_velocity, maximum_velocity, obstacles = None))]
+#[allow(clippy::type_complexity)]
+fn evaluate_formation<'py>(
+    py: Python<'py>,
+    templates_json: &str,
+    positions: PyReadonlyArray2<f32>,
+    preferred_velocity: [f32; 3],
+    maximum_velocity: f32,
+    obstacles: Option<PyReadonlyArray2<f32>>,
+) -> PyResult<(Option<usize>, [f32; 3], [f32; 4], &'py PyArray2<f32>)> {
+    let templates = formation_templates_from_json(templates_json)
+        .map_err(|error| PyValueError::new_err(error.to_string()))?;
+
+    if templates.is_empty() {
+        return Err(PyValueError::new_err("At least one template is needed"));
+    }
+
+    let positions = read_vec3s(&positions)?;
+
+    if positions.is_empty() {
+        return Err(PyValueError::new_err("At least one agent is needed"));
+    }
+
+    let obstacles = read_obstacles(obstacles.as_ref())?;
+    let template_set = FormationTemplateSet::from_iter(
+        templates
+            .iter()
+            .map(|template| template.as_ref() as &dyn FormationTemplate),
+    );
+
+    let decision = template_set
+        .get_best_formation(
+            &positions,
+            Vec3::from_array(preferred_velocity),
+            &obstacles,
+            &[],
+            &FormationEvaluationParams::new(maximum_velocity),
+        )
+        .map_err(|error| PyValueError::new_err(error.to_string()))?;
+
+    let slots = formation_slots_in_world(&decision, &positions);
+
+    let template = match decision.choice {
+        FormationChoice::Template(index) => Some(index),
+        FormationChoice::Current => None,
+    };
+
+    Ok((
+        template,
+        decision.velocity.to_array(),
+        decision.rotation.to_array(),
+        to_array(py, slots),
+    ))
+}
+
+// Finds a path through a voxel grid built from sphere obstacles, None if there is no path
+#[pyfunction]
+#[allow(clippy::too_many_arguments)]
+fn find_path<'py>(
+    py: Python<'py>,
+    start: [f32; 3],
+    goal: [f32; 3],
+    obstacles: PyReadonlyArray2<f32>,
+    bounds_min: [f32; 3],
+    bounds_max: [f32; 3],
+    voxel_size: f32,
+    agent_radius: f32,
+) -> PyResult<Option<&'py PyArray2<f32>>> {
+    let bounds_min = Vec3::from_array(bounds_min);
+    let bounds_max = Vec3::from_array(bounds_max);
+
+    if voxel_size <= 0.0 || !bounds_max.cmpgt(bounds_min).all() {
+        return Err(PyValueError::new_err(
+            "The bounds need a positive volume and the voxel size has to be positive",
+        ));
+    }
+
+    let colliders = read_obstacles(Some(&obstacles))?
+        .into_iter()
+        .map(|obstacle| {
+            Collider::Sphere(Sphere::new(
+                obstacle.shape.bounding_sphere().radius,
+                obstacle.position,
+            ))
+        })
+        .collect::<Vec<_>>();
+
+    let grid =
+        VoxelGrid::from_colliders(bounds_min, bounds_max, voxel_size, &colliders, agent_radius);
+
+    Ok(grid
+        .find_path(Vec3::from_array(start), Vec3::from_array(goal))
+        .map(|path| to_array(py, path)))
+}
+
+#[pymodule]
+fn navigation3d(_py: Python<'_>, module: &PyModule) -> PyResult<()> {
+    module.add_class::<PySimulator>()?;
+    module.add_function(wrap_pyfunction!(evaluate_formation, module)?)?;
+    module.add_function(wrap_pyfunction!(find_path, module)?)?;
+
+    Ok(())
+}
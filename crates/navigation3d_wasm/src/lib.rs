@@ -0,0 +1,153 @@
+// Browser bindings of the ORCA simulation, built with wasm-pack:
+//
+//     wasm-pack build crates/navigation3d_wasm --target web
+//
+// Vectors are passed as flat Float32Arrays of x, y, z triples. Agent ids are BigInts, they
+// stay valid until the agent is removed.
+
+use bevy_math::Vec3;
+use geometry::colliders::Collider;
+use orca::{Agent3D, AgentId, SimulatorAgent, SimulatorParams};
+use wasm_bindgen::prelude::*;
+
+fn vec3s_to_rows(vectors: impl Iterator<Item = Vec3>) -> Vec<f32> {
+    vectors.flat_map(|vector| vector.to_array()).collect()
+}
+
+#[wasm_bindgen]
+pub struct Simulator {
+    simulator: orca::Simulator,
+}
+
+#[wasm_bindgen]
+impl Simulator {
+    #[wasm_bindgen(constructor)]
+    #[must_use]
+    pub fn new(time_horizon: f32, neighbour_distance: f32, max_neighbours: usize) -> Self {
+        Self {
+            simulator: orca::Simulator::new(
+                SimulatorParams::new(time_horizon, neighbour_distance)
+                    .with_max_neighbours(max_neighbours),
+            ),
+        }
+    }
+
+    // Adds a sphere agent at rest, it joins the simulation in the next step
+    #[wasm_bindgen(js_name = addAgent)]
+    pub fn add_agent(&mut self, x: f32, y: f32, z: f32, radius: f32, max_speed: f32) -> u64 {
+        let agent = Agent3D::new(Vec3::new(x, y, z), Vec3::ZERO, Collider::new_sphere(radius));
+
+        self.simulator
+            .add_agent(SimulatorAgent::new(agent, max_speed))
+            .to_bits()
+    }
+
+    // Returns false when there is no such agent
+    #[wasm_bindgen(js_name = removeAgent)]
+    pub fn remove_agent(&mut self, id: u64) -> bool {
+        self.simulator
+            .remove_agent(AgentId::from_bits(id))
+            .is_some()
+    }
+
+    // Returns false when there is no such agent
+    #[wasm_bindgen(js_name = setPreferredVelocity)]
+    pub fn set_preferred_velocity(&mut self, id: u64, x: f32, y: f32, z: f32) -> bool {
+        let Some(agent) = self.simulator.get_mut(AgentId::from_bits(id)) else {
+            return false;
+        };
+
+        agent.preferred_velocity = Vec3::new(x, y, z);
+        true
+    }
+
+    // Sets the preferred velocities of all active agents in the order of ids(),
+    // returns false when the number of values doesn't match
+    #[wasm_bindgen(js_name = setPreferredVelocities)]
+    pub fn set_preferred_velocities(&mut self, velocities: &[f32]) -> bool {
+        if velocities.len() != self.simulator.len() * 3 {
+            return false;
+        }
+
+        for ((_, agent), velocity) in self.simulator.agents_mut().zip(velocities.chunks_exact(3)) {
+            agent.preferred_velocity = Vec3::from_slice(velocity);
+        }
+
+        true
+    }
+
+    pub fn step(&mut self, time_step: f32) {
+        self.simulator.step(time_step);
+    }
+
+    // The ids of the active agents, positions() and velocities() follow the same order
+    #[must_use]
+    pub fn ids(&self) -> Vec<u64> {
+        self.simulator
+            .agents()
+            .map(|(id, _)| id.to_bits())
+            .collect()
+    }
+
+    #[must_use]
+    pub fn positions(&self) -> Vec<f32> {
+        vec3s_to_rows(
+            self.simulator
+                .agents()
+                .map(|(_, agent)| agent.agent.position),
+        )
+    }
+
+    #[must_use]
+    pub fn velocities(&self) -> Vec<f32> {
+        vec3s_to_rows(
+            self.simulator
+                .agents()
+                .map(|(_, agent)| agent.agent.velocity),
+        )
+    }
+
+    #[wasm_bindgen(getter)]
+    #[must_use]
+    pub fn length(&self) -> usize {
+        self.simulator.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_agents_swap_sides() {
+        let mut simulator = Simulator::new(2.0, 10.0, 10);
+        let a = simulator.add_agent(-5.0, 0.5, 0.0, 1.0, 2.0);
+        let b = simulator.add_agent(5.0, 0.0, 0.0, 1.0, 2.0);
+
+        assert!(simulator.set_preferred_velocity(a, 1.0, 0.0, 0.0));
+        assert!(simulator.set_preferred_velocity(b, -1.0, 0.0, 0.0));
+
+        for _ in 0..200 {
+            simulator.step(0.1);
+        }
+
+        let ids = simulator.ids();
+        let positions = simulator.positions();
+        let x_of = |id| positions[ids.iter().position(|i| *i == id).unwrap() * 3];
+
+        assert_eq!(simulator.length(), 2);
+        assert!(x_of(a) > 0.0);
+        assert!(x_of(b) < 0.0);
+    }
+
+    #[test]
+    fn test_preferred_velocities_need_one_row_per_agent() {
+        let mut simulator = Simulator::new(2.0, 10.0, 10);
+        simulator.add_agent(0.0, 0.0, 0.0, 1.0, 2.0);
+        simulator.step(0.1);
+
+        assert!(!simulator.set_preferred_velocities(&[1.0, 0.0]));
+        assert!(simulator.set_preferred_velocities(&[1.0, 0.0, 0.0]));
+        assert!(!simulator.remove_agent(AgentId::from_bits(u64::MAX).to_bits()));
+    }
+}
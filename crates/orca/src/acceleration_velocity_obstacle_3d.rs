@@ -20,13 +20,15 @@ pub struct AccelerationVelocityObstacle3D {
     pub discrete_steps: u16,
 }
 
-#[derive(Debug)]
-enum AVOBoundary {
+// A piece of the boundary of an acceleration velocity obstacle in its 2D plane
+#[derive(Clone, Debug)]
+pub enum AVOBoundary {
     LineSegment(LineSegment2D),
     Arc(Arc2D),
 }
 
 impl AVOBoundary {
+    #[must_use]
     pub fn new(
         v_ab: Vec2,
         p_ab: Vec2,
@@ -370,47 +372,8 @@ impl AccelerationVelocityObstacle3D {
 
             (u, normal)
         } else {
-            let p0 = Vec3::ZERO;
-            let p1 = self.relative_velocity;
-            let p2 = {
-                if self
-                    .relative_position
-                    .normalize_or_zero()
-                    .cross(p1.normalize_or_zero())
-                    .length_squared()
-                    < EPSILON
-                {
-                    let p1_dot_x = p1.normalize().dot(Vec3::X);
-                    let p1_dot_y = p1.normalize().dot(Vec3::Y);
-                    let p1_dot_z = p1.normalize().dot(Vec3::Z);
-
-                    let basis =
-                        if p1_dot_x.abs() < p1_dot_y.abs() && p1_dot_x.abs() < p1_dot_z.abs() {
-                            Vec3::X
-                        } else if p1_dot_y.abs() < p1_dot_z.abs() {
-                            Vec3::Y
-                        } else {
-                            Vec3::Z
-                        };
-
-                    p1.cross(basis).normalize_or_zero()
-                } else {
-                    self.relative_position
-                }
-            };
-
-            let plane = Plane::from_points(p0, p1, p2);
+            let (plane, boundary) = self.boundary()?;
             let v_ab = plane.project_2d(self.relative_velocity);
-            let p_ab = plane.project_2d(self.relative_position);
-
-            let boundary = AVOBoundary::new(
-                v_ab,
-                p_ab,
-                radius,
-                self.time_horizon,
-                self.acc_control_param,
-                self.discrete_steps,
-            );
 
             //for boundary in &boundary {
             //    match boundary {
@@ -462,6 +425,62 @@ impl AccelerationVelocityObstacle3D {
         Some(Plane::new(self.responsibility * u, normal))
     }
 
+    // Returns the boundary of the obstacle together with the plane it lies in
+    // The plane is spanned by the relative velocity and position, project_3d brings points of the
+    // boundary back to relative velocity space. There is no such plane without relative velocity.
+    #[must_use]
+    pub fn boundary(&self) -> Option<(Plane, Vec<AVOBoundary>)> {
+        if self.relative_velocity.length_squared() < EPSILON {
+            return None;
+        }
+
+        let radius = self.shape.bounding_sphere().radius;
+
+        let p0 = Vec3::ZERO;
+        let p1 = self.relative_velocity;
+        let p2 = {
+            if self
+                .relative_position
+                .normalize_or_zero()
+                .cross(p1.normalize_or_zero())
+                .length_squared()
+                < EPSILON
+            {
+                let p1_dot_x = p1.normalize().dot(Vec3::X);
+                let p1_dot_y = p1.normalize().dot(Vec3::Y);
+                let p1_dot_z = p1.normalize().dot(Vec3::Z);
+
+                let basis = if p1_dot_x.abs() < p1_dot_y.abs() && p1_dot_x.abs() < p1_dot_z.abs() {
+                    Vec3::X
+                } else if p1_dot_y.abs() < p1_dot_z.abs() {
+                    Vec3::Y
+                } else {
+                    Vec3::Z
+                };
+
+                p1.cross(basis).normalize_or_zero()
+            } else {
+                self.relative_position
+            }
+        };
+
+        // Keeping u along the relative velocity stops the boundary from jumping between frames
+        let plane = Plane::from_points(p0, p1, p2).with_basis_hint(p1);
+        let v_ab = plane.project_2d(self.relative_velocity);
+        let p_ab = plane.project_2d(self.relative_position);
+
+        let boundary = AVOBoundary::new(
+            v_ab,
+            p_ab,
+            radius,
+            self.time_horizon,
+            self.acc_control_param,
+            self.discrete_steps,
+        );
+
+        Some((plane, boundary))
+    }
+
     fn avo_center(
         acc_control_param: f32,
         relative_velocity: Vec3,
@@ -538,3 +557,34 @@ fn draw_truncated_cone(
         gizmos.line(start, end, Color::RED);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_boundary() {
+        let agent_self = Agent3D::new(Vec3::ZERO, Vec3::X, Collider::new_sphere(1.0));
+        let agent_other = Agent3D::new(Vec3::X * 10.0, Vec3::ZERO, Collider::new_sphere(1.0));
+
+        let avo = AccelerationVelocityObstacle3D::new(&agent_self, &agent_other, 5.0, 1.0, 10);
+        let (plane, boundary) = avo.boundary().unwrap();
+
+        assert!(!boundary.is_empty());
+        assert!(plane
+            .project_3d(plane.project_2d(Vec3::X))
+            .abs_diff_eq(Vec3::X, 1e-4));
+
+        let still = Agent3D::new(Vec3::ZERO, Vec3::ZERO, Collider::new_sphere(1.0));
+        let avo = AccelerationVelocityObstacle3D::new(&still, &agent_other, 5.0, 1.0, 10);
+
+        assert!(avo.boundary().is_none());
+    }
+
+    #[test]
+    fn test_obstacle_is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+
+        assert_send_sync::<AccelerationVelocityObstacle3D>();
+    }
+}
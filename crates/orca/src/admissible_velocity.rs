@@ -0,0 +1,258 @@
+use std::f32::consts::{FRAC_PI_2, PI};
+
+use bevy_math::Vec3;
+use geometry::{Plane, Sphere};
+
+use crate::{velocity_constraint::optimize_velocity_in_sphere, VelocityConstraint, EPSILON};
+
+// The velocities an agent can actually reach in the next step
+// Without any limits this is the sphere of the maximum speed. Limiting the turn speed intersects
+// it with a cone around the current heading, so the solver doesn't return velocities that
+// would have to be clamped afterwards, which could lead the agent back into a collision.
+// Limiting the acceleration intersects it with a ball around the current velocity.
+//
+// max_speed: The maximum speed of the agent
+// current_velocity: The velocity of the agent at the beginning of the step
+// max_turn_angle: How much the heading can change within the step, None means no limit
+// max_velocity_change: How much the velocity can change within the step, None means no limit
+// cone_samples: Number of planes approximating the heading cone
+#[derive(Debug, Clone)]
+pub struct AdmissibleVelocity {
+    pub max_speed: f32,
+    pub current_velocity: Vec3,
+    pub max_turn_angle: Option<f32>,
+    pub max_velocity_change: Option<f32>,
+    pub cone_samples: usize,
+}
+
+impl AdmissibleVelocity {
+    #[must_use]
+    pub fn new(max_speed: f32, current_velocity: Vec3) -> Self {
+        Self {
+            max_speed,
+            current_velocity,
+            max_turn_angle: None,
+            max_velocity_change: None,
+            cone_samples: 8,
+        }
+    }
+
+    // max_turn_speed is in radians per second, the heading can change by
+    // max_turn_speed * time_step within the step
+    #[must_use]
+    #[allow(clippy::missing_panics_doc)]
+    pub fn with_max_turn_speed(mut self, max_turn_speed: f32, time_step: f32) -> Self {
+        assert!(max_turn_speed >= 0.0);
+        assert!(time_step > 0.0);
+
+        self.max_turn_angle = Some(max_turn_speed * time_step);
+        self
+    }
+
+    // The velocity can change by max_acceleration * time_step within the step
+    #[must_use]
+    #[allow(clippy::missing_panics_doc)]
+    pub fn with_max_acceleration(mut self, max_acceleration: f32, time_step: f32) -> Self {
+        assert!(max_acceleration >= 0.0);
+        assert!(time_step > 0.0);
+
+        self.max_velocity_change = Some(max_acceleration * time_step);
+        self
+    }
+
+    #[must_use]
+    #[allow(clippy::missing_panics_doc)]
+    pub fn with_cone_samples(mut self, cone_samples: usize) -> Self {
+        assert!(cone_samples >= 3);
+
+        self.cone_samples = cone_samples;
+        self
+    }
+
+    // Hard constraints restricting the maximum speed sphere to the reachable velocities
+    #[must_use]
+    pub fn get_constraints(&self) -> Vec<VelocityConstraint> {
+        let mut constraints = Vec::new();
+
+        if let Some(max_turn_angle) = self.max_turn_angle {
+            constraints.extend(
+                get_heading_cone_planes(self.current_velocity, max_turn_angle, self.cone_samples)
+                    .into_iter()
+                    .map(VelocityConstraint::hard),
+            );
+        }
+
+        if let Some(plane) = self.get_speed_limit_plane() {
+            constraints.push(VelocityConstraint::hard(plane));
+        }
+
+        constraints
+    }
+
+    // The sphere the solver is bounded by, the constraints cut it further
+    #[must_use]
+    pub fn get_bounding_sphere(&self) -> Sphere {
+        let speed_sphere = Sphere::new(self.max_speed, Vec3::ZERO);
+
+        let Some(max_velocity_change) = self.max_velocity_change else {
+            return speed_sphere;
+        };
+
+        // The speed sphere is inside the acceleration ball, so the acceleration doesn't limit anything
+        if max_velocity_change >= self.current_velocity.length() + self.max_speed {
+            return speed_sphere;
+        }
+
+        Sphere::new(max_velocity_change, self.current_velocity)
+    }
+
+    // When the acceleration ball sticks out of the speed sphere, the part of the ball on the
+    // origin's side of the plane through their intersection circle is kept. That part lies
+    // inside the speed sphere, so the result is conservative.
+    fn get_speed_limit_plane(&self) -> Option<Plane> {
+        let max_velocity_change = self.max_velocity_change?;
+        let distance = self.current_velocity.length();
+
+        // Either the ball is inside the speed sphere or the other way around
+        if distance + max_velocity_change <= self.max_speed
+            || max_velocity_change >= distance + self.max_speed
+        {
+            return None;
+        }
+
+        // The agent is too fast to get below the maximum speed within one step,
+        // so it only gets as slow as it can
+        if distance - max_velocity_change >= self.max_speed {
+            return None;
+        }
+
+        let direction = self.current_velocity / distance;
+        let plane_distance = (distance * distance + self.max_speed * self.max_speed
+            - max_velocity_change * max_velocity_change)
+            / (2.0 * distance);
+
+        Some(Plane::new(direction * plane_distance, -direction))
+    }
+}
+
+// Planes through the origin whose intersection is a polyhedral cone inscribed in the cone
+// of velocities deviating at most max_angle from the heading
+// Stopping (zero velocity) is always admissible. Turning by more than 90 degrees doesn't give
+// a convex region, so it is conservatively limited to the half-space in front of the agent.
+#[allow(clippy::cast_precision_loss)]
+fn get_heading_cone_planes(heading: Vec3, max_angle: f32, samples: usize) -> Vec<Plane> {
+    let Some(heading) = heading.try_normalize() else {
+        return Vec::new();
+    };
+
+    if max_angle >= PI {
+        return Vec::new();
+    }
+
+    if max_angle >= FRAC_PI_2 - EPSILON {
+        return vec![Plane::new(Vec3::ZERO, heading)];
+    }
+
+    // The polygon circumscribed to a circle of this angle is inscribed in the max_angle circle
+    let angle = (max_angle.tan() * (PI / samples as f32).cos()).atan();
+    let (u, v) = heading.any_orthonormal_pair();
+
+    (0..samples)
+        .map(|i| {
+            let phi = 2.0 * PI * i as f32 / samples as f32;
+            let side = u * phi.cos() + v * phi.sin();
+
+            // Tangent to the cone along the generator heading * cos + side * sin
+            Plane::new(Vec3::ZERO, heading * angle.sin() - side * angle.cos())
+        })
+        .collect()
+}
+
+// Same as optimize_velocity_3d_with_constraints, but the result is also limited to the
+// velocities admissible in the next step
+#[must_use]
+pub fn optimize_velocity_3d_admissible(
+    preffered_velocity: Vec3,
+    admissible: &AdmissibleVelocity,
+    constraints: &[VelocityConstraint],
+) -> Vec3 {
+    let mut all_constraints = admissible.get_constraints();
+    all_constraints.extend_from_slice(constraints);
+
+    optimize_velocity_in_sphere(
+        preffered_velocity,
+        &admissible.get_bounding_sphere(),
+        &all_constraints,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_without_limits_it_is_the_speed_sphere() {
+        let admissible = AdmissibleVelocity::new(2.0, Vec3::X);
+
+        let velocity = optimize_velocity_3d_admissible(Vec3::Y * 5.0, &admissible, &[]);
+
+        assert!((velocity - Vec3::Y * 2.0).length() < 1e-3);
+    }
+
+    #[test]
+    fn test_turn_is_limited_to_the_cone() {
+        let admissible = AdmissibleVelocity::new(2.0, Vec3::X).with_max_turn_speed(1.0, 0.1);
+
+        let velocity = optimize_velocity_3d_admissible(Vec3::Y * 2.0, &admissible, &[]);
+
+        assert!(velocity.length() > 0.0);
+        assert!(velocity.angle_between(Vec3::X) <= 0.1 + 1e-3);
+        assert!(velocity.y > 0.0);
+    }
+
+    #[test]
+    fn test_velocity_inside_the_cone_is_unchanged() {
+        let admissible = AdmissibleVelocity::new(2.0, Vec3::X).with_max_turn_speed(1.0, 0.5);
+        let preffered_velocity = Vec3::new(1.0, 0.1, 0.0);
+
+        let velocity = optimize_velocity_3d_admissible(preffered_velocity, &admissible, &[]);
+
+        assert!((velocity - preffered_velocity).length() < 1e-3);
+    }
+
+    #[test]
+    fn test_velocity_change_is_limited_by_acceleration() {
+        let admissible = AdmissibleVelocity::new(2.0, Vec3::X).with_max_acceleration(5.0, 0.1);
+
+        let velocity = optimize_velocity_3d_admissible(-Vec3::X * 2.0, &admissible, &[]);
+
+        assert!((velocity - Vec3::X * 0.5).length() < 1e-3);
+    }
+
+    #[test]
+    fn test_acceleration_ball_is_cut_by_the_speed_sphere() {
+        let admissible =
+            AdmissibleVelocity::new(2.0, Vec3::X * 1.8).with_max_acceleration(5.0, 0.1);
+
+        let velocity = optimize_velocity_3d_admissible(Vec3::X * 5.0, &admissible, &[]);
+
+        assert!(velocity.length() <= 2.0 + 1e-3);
+        assert!((velocity - Vec3::X * 1.8).length() <= 0.5 + 1e-3);
+        assert!(velocity.x > 1.8);
+    }
+
+    #[test]
+    fn test_wide_turns_are_limited_to_the_front_half_space() {
+        let planes = get_heading_cone_planes(Vec3::X, 2.0, 8);
+
+        assert_eq!(planes.len(), 1);
+        assert!((planes[0].normal - Vec3::X).length() < 1e-5);
+    }
+
+    #[test]
+    fn test_no_heading_means_no_cone() {
+        let admissible = AdmissibleVelocity::new(2.0, Vec3::ZERO).with_max_turn_speed(1.0, 0.1);
+
+        assert!(admissible.get_constraints().is_empty());
+    }
+}
@@ -3,6 +3,12 @@ use bevy_math::Vec3;
 use geometry::colliders::Collider;
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "reflect", derive(bevy_reflect::Reflect))]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    archive(check_bytes)
+)]
 pub struct Agent3D {
     pub position: Vec3,
     pub velocity: Vec3,
@@ -21,3 +27,42 @@ impl Agent3D {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_agent_is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+
+        assert_send_sync::<Agent3D>();
+    }
+
+    #[cfg(feature = "rkyv")]
+    #[test]
+    fn test_agents_snapshot_round_trip() {
+        let agents = vec![
+            Agent3D::new(Vec3::new(1.0, 2.0, 3.0), Vec3::X, Collider::new_sphere(0.5)),
+            Agent3D::new(
+                Vec3::ZERO,
+                Vec3::NEG_Y,
+                Collider::new_aabb(Vec3::ONE, Vec3::splat(2.0)),
+            ),
+        ];
+
+        let bytes = rkyv::to_bytes::<_, 256>(&agents).unwrap();
+        let archived = rkyv::check_archived_root::<Vec<Agent3D>>(&bytes).unwrap();
+
+        // The archived agents are read in place without deserializing them
+        assert_eq!(archived.len(), 2);
+        assert_eq!(archived[0].responsibility.to_bits(), 0.5_f32.to_bits());
+
+        let deserialized: Vec<Agent3D> =
+            rkyv::Deserialize::deserialize(archived, &mut rkyv::Infallible).unwrap();
+
+        assert_eq!(deserialized[0].position, agents[0].position);
+        assert_eq!(deserialized[1].velocity, agents[1].velocity);
+        assert!(matches!(deserialized[1].shape, Collider::Aabb(_)));
+    }
+}
@@ -0,0 +1,65 @@
+use geometry::Plane;
+
+use crate::{
+    AccelerationVelocityObstacle3D, Agent3D, FormationVelocityObstacle3D, VelocityObstacle3D,
+};
+
+// The kind of velocity obstacle an agent uses to avoid another one
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum AvoidancePolicy {
+    // Plain velocity obstacle, for agents that can change their velocity instantly
+    #[default]
+    VelocityObstacle,
+    // Acceleration velocity obstacle, for agents with limited acceleration
+    // see AccelerationVelocityObstacle3D
+    AccelerationVelocityObstacle {
+        acc_control_param: f32,
+        discrete_steps: u16,
+    },
+    // Formation velocity obstacle, for agents standing for a whole formation
+    // see FormationVelocityObstacle3D
+    FormationVelocityObstacle {
+        number_of_yaw_samples: u16,
+        number_of_pitch_samples: u16,
+    },
+}
+
+impl AvoidancePolicy {
+    // Returns the ORCA plane of agent_self against agent_other using the velocity obstacle
+    // of this policy, or None if the velocity obstacle doesn't constrain the velocity
+    #[must_use]
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", skip_all, fields(policy = ?self))
+    )]
+    pub fn orca_plane(
+        &self,
+        agent_self: &Agent3D,
+        agent_other: &Agent3D,
+        time_horizon: f32,
+        time_step: f32,
+    ) -> Option<Plane> {
+        match *self {
+            AvoidancePolicy::VelocityObstacle => Some(
+                VelocityObstacle3D::new(agent_self, agent_other, time_horizon)
+                    .orca_plane(time_step),
+            ),
+            AvoidancePolicy::AccelerationVelocityObstacle {
+                acc_control_param,
+                discrete_steps,
+            } => AccelerationVelocityObstacle3D::new(
+                agent_self,
+                agent_other,
+                time_horizon,
+                acc_control_param,
+                discrete_steps,
+            )
+            .orca_plane(time_step),
+            AvoidancePolicy::FormationVelocityObstacle {
+                number_of_yaw_samples,
+                number_of_pitch_samples,
+            } => FormationVelocityObstacle3D::new_reciprocal(agent_self, agent_other, time_horizon)
+                .orca_plane(number_of_yaw_samples, number_of_pitch_samples, 0.0),
+        }
+    }
+}
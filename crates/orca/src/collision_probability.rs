@@ -0,0 +1,222 @@
+use bevy_math::{Mat3, Vec3};
+use geometry::{Ray3D, Ray3DIntersection, Vec3Operations};
+use rand::Rng;
+
+use crate::{Agent3D, EPSILON};
+
+// Estimates the probability that two agents collide within the time horizon when their
+// velocities are uncertain
+// The velocity of each agent is modeled as a normal distribution with the agent's velocity as
+// the mean and the given covariance. The agents are assumed to be independent, so the relative
+// velocity has the sum of both covariances. The probability is estimated by sampling the relative
+// velocity and checking whether it leads into the velocity obstacle, so the result is exact
+// (up to the sampling error) even for large uncertainties where the linear approximations fail.
+//
+// This can be used to increase the responsibility of an agent or to start evasive maneuvers
+// earlier when a collision is likely, but not yet certain.
+#[allow(clippy::cast_precision_loss)]
+#[allow(clippy::missing_panics_doc)]
+pub fn get_collision_probability(
+    agent_self: &Agent3D,
+    agent_other: &Agent3D,
+    covariance_self: Mat3,
+    covariance_other: Mat3,
+    time_horizon: f32,
+    samples: usize,
+    rng: &mut impl Rng,
+) -> f32 {
+    assert!(samples > 0);
+
+    // The shape of the other agent grown by the shape of this agent, seen from this agent
+    let shape = agent_other.shape.minkowski_sum(&agent_self.shape);
+    let relative_position = agent_self.position - agent_other.position;
+
+    if shape.contains(relative_position) {
+        return 1.0;
+    }
+
+    let mean = agent_self.velocity - agent_other.velocity;
+    let cholesky = get_cholesky_decomposition(covariance_self + covariance_other);
+
+    let collisions = (0..samples)
+        .filter(|_| {
+            let relative_velocity = mean + cholesky * get_standard_normal_sample(rng);
+            let speed = relative_velocity.length();
+
+            if speed < EPSILON {
+                return false;
+            }
+
+            shape
+                .intersect_ray(&Ray3D::new(relative_position, relative_velocity))
+                .first_hit()
+                .is_some_and(|distance| distance <= speed * time_horizon)
+        })
+        .count();
+
+    collisions as f32 / samples as f32
+}
+
+// Three independent samples of the standard normal distribution using the Box-Muller transform
+fn get_standard_normal_sample(rng: &mut impl Rng) -> Vec3 {
+    let mut sample = || {
+        // 1 - [0, 1) is never zero, so the logarithm is always finite
+        let u1 = 1.0 - rng.gen::<f32>();
+        let u2 = rng.gen::<f32>();
+
+        (-2.0 * u1.ln()).sqrt() * (std::f32::consts::TAU * u2).cos()
+    };
+
+    Vec3::new(sample(), sample(), sample())
+}
+
+// Lower triangular matrix L with L * L^T = covariance
+// Covariances are only positive semi-definite (e.g. no uncertainty along some axis), the
+// degenerate directions are kept at zero instead of producing NaNs.
+fn get_cholesky_decomposition(covariance: Mat3) -> Mat3 {
+    let a = |row: usize, column: usize| covariance.col(column)[row];
+    let divide = |numerator: f32, denominator: f32| {
+        if denominator > EPSILON {
+            numerator / denominator
+        } else {
+            0.0
+        }
+    };
+
+    let l00 = a(0, 0).max(0.0).sqrt();
+    let l10 = divide(a(1, 0), l00);
+    let l20 = divide(a(2, 0), l00);
+    let l11 = (a(1, 1) - l10 * l10).max(0.0).sqrt();
+    let l21 = divide(a(2, 1) - l20 * l10, l11);
+    let l22 = (a(2, 2) - l20 * l20 - l21 * l21).max(0.0).sqrt();
+
+    Mat3::from_cols(
+        Vec3::new(l00, l10, l20),
+        Vec3::new(0.0, l11, l21),
+        Vec3::new(0.0, 0.0, l22),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use geometry::colliders::Collider;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    fn get_agents(velocity_self: Vec3) -> (Agent3D, Agent3D) {
+        (
+            Agent3D::new(Vec3::ZERO, velocity_self, Collider::new_sphere(1.0)),
+            Agent3D::new(
+                Vec3::new(10.0, 0.0, 0.0),
+                Vec3::ZERO,
+                Collider::new_sphere(1.0),
+            ),
+        )
+    }
+
+    #[test]
+    fn test_certain_collision() {
+        let (agent_self, agent_other) = get_agents(Vec3::X * 5.0);
+        let mut rng = StdRng::seed_from_u64(0);
+
+        let probability = get_collision_probability(
+            &agent_self,
+            &agent_other,
+            Mat3::ZERO,
+            Mat3::ZERO,
+            5.0,
+            100,
+            &mut rng,
+        );
+
+        assert!((probability - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_certain_miss() {
+        let (agent_self, agent_other) = get_agents(Vec3::Y * 5.0);
+        let mut rng = StdRng::seed_from_u64(0);
+
+        let probability = get_collision_probability(
+            &agent_self,
+            &agent_other,
+            Mat3::ZERO,
+            Mat3::ZERO,
+            5.0,
+            100,
+            &mut rng,
+        );
+
+        assert!(probability.abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_too_far_within_horizon() {
+        let (agent_self, agent_other) = get_agents(Vec3::X);
+        let mut rng = StdRng::seed_from_u64(0);
+
+        let probability = get_collision_probability(
+            &agent_self,
+            &agent_other,
+            Mat3::ZERO,
+            Mat3::ZERO,
+            5.0,
+            100,
+            &mut rng,
+        );
+
+        assert!(probability.abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_uncertain_velocity_gives_partial_probability() {
+        let (agent_self, agent_other) = get_agents(Vec3::X * 5.0);
+        let mut rng = StdRng::seed_from_u64(0);
+
+        let probability = get_collision_probability(
+            &agent_self,
+            &agent_other,
+            Mat3::from_diagonal(Vec3::splat(4.0)),
+            Mat3::ZERO,
+            5.0,
+            1000,
+            &mut rng,
+        );
+
+        assert!(probability > 0.05);
+        assert!(probability < 0.95);
+    }
+
+    #[test]
+    fn test_overlapping_agents_collide() {
+        let agent_self = Agent3D::new(Vec3::ZERO, Vec3::ZERO, Collider::new_sphere(1.0));
+        let agent_other = Agent3D::new(Vec3::X, Vec3::ZERO, Collider::new_sphere(1.0));
+        let mut rng = StdRng::seed_from_u64(0);
+
+        let probability = get_collision_probability(
+            &agent_self,
+            &agent_other,
+            Mat3::ZERO,
+            Mat3::ZERO,
+            5.0,
+            10,
+            &mut rng,
+        );
+
+        assert!((probability - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_cholesky_decomposition() {
+        let l = Mat3::from_cols(
+            Vec3::new(2.0, 1.0, 0.5),
+            Vec3::new(0.0, 1.5, -0.3),
+            Vec3::new(0.0, 0.0, 0.7),
+        );
+
+        let decomposed = get_cholesky_decomposition(l * l.transpose());
+
+        assert!(decomposed.abs_diff_eq(l, 1e-4));
+    }
+}
@@ -12,6 +12,7 @@ pub struct FormationVelocityObstacle3D {
     obstacle_collider: Collider,
     formation_velocity: Vec3,
     time_horizon: f32,
+    responsibility: f32,
 }
 
 impl FormationVelocityObstacle3D {
@@ -32,11 +33,30 @@ impl FormationVelocityObstacle3D {
             obstacle_collider,
             formation_velocity,
             time_horizon,
+            responsibility: 1.0,
+        }
+    }
+
+    // Creates a velocity obstacle against another moving formation that also avoids this one.
+    // Each formation only takes its share of the avoidance, given by the responsibilities
+    // of both agents, so two formations crossing paths both give way.
+    #[must_use]
+    pub fn new_reciprocal(
+        formation: &Agent3D,
+        formation_other: &Agent3D,
+        time_horizon: f32,
+    ) -> Self {
+        let total_responsibility = formation.responsibility + formation_other.responsibility;
+
+        Self {
+            responsibility: formation.responsibility / total_responsibility,
+            ..Self::new(formation, formation_other, time_horizon)
         }
     }
 
     #[must_use]
     #[allow(clippy::too_many_lines)]
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip_all))]
     pub fn orca_plane(
         &self,
         number_of_yaw_samples: u16,
@@ -79,11 +99,16 @@ impl FormationVelocityObstacle3D {
             }
         }
 
-        if min_distance == f32::MAX {
+        if min_distance >= f32::MAX {
             return None;
         }
 
-        Some(Plane::new(point, normal))
+        let u = point - self.formation_velocity;
+
+        Some(Plane::new(
+            self.formation_velocity + self.responsibility * u,
+            normal,
+        ))
     }
 
     #[must_use]
@@ -0,0 +1,338 @@
+use std::f32::consts::PI;
+
+use bevy_math::{Vec3, Vec4};
+use geometry::{Plane, Sphere, Spherinder};
+
+use crate::{
+    solver_3d::{incremental_optimization_3d, OptimizationResult3D},
+    solver_4d::{incremental_optimization_4d, OptimizationResult4D},
+    velocity_constraint::relaxed_hyperplane,
+    EPSILON,
+};
+
+// Picks a velocity when no velocity within the maximum velocity satisfies all the planes
+// last_velocity is where the 3D solver got stuck, it satisfies the planes before the one that
+// made the problem infeasible. The returned velocity should stay within the maximum velocity.
+pub trait InfeasibilityStrategy {
+    fn resolve(
+        &self,
+        preffered_velocity: Vec3,
+        maximum_velocity: f32,
+        planes: &[Plane],
+        last_velocity: Vec3,
+    ) -> Vec3;
+}
+
+impl<F> InfeasibilityStrategy for F
+where
+    F: Fn(Vec3, f32, &[Plane], Vec3) -> Vec3,
+{
+    fn resolve(
+        &self,
+        preffered_velocity: Vec3,
+        maximum_velocity: f32,
+        planes: &[Plane],
+        last_velocity: Vec3,
+    ) -> Vec3 {
+        self(preffered_velocity, maximum_velocity, planes, last_velocity)
+    }
+}
+
+// Lifts the planes into 4D where the extra coordinate moves all of them back at once and
+// finds the velocity that needs the smallest move, the default of optimize_velocity_3d
+// The relaxation is how far a plane moves per unit of the extra coordinate. The axis scale
+// shapes the velocities the relaxed solution can take, e.g. scaling y down makes the fallback
+// resolve conflicts by moving horizontally rather than climbing or diving.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Relaxation4D {
+    pub relaxation: f32,
+    pub axis_scale: Vec3,
+}
+
+impl Relaxation4D {
+    #[must_use]
+    pub fn new(relaxation: f32) -> Self {
+        Self {
+            relaxation,
+            axis_scale: Vec3::ONE,
+        }
+    }
+
+    #[must_use]
+    pub fn with_axis_scale(mut self, axis_scale: Vec3) -> Self {
+        self.axis_scale = axis_scale;
+        self
+    }
+}
+
+impl Default for Relaxation4D {
+    fn default() -> Self {
+        Self::new(0.5)
+    }
+}
+
+impl InfeasibilityStrategy for Relaxation4D {
+    fn resolve(
+        &self,
+        preffered_velocity: Vec3,
+        maximum_velocity: f32,
+        planes: &[Plane],
+        _last_velocity: Vec3,
+    ) -> Vec3 {
+        let hyperplanes = planes
+            .iter()
+            .map(|plane| relaxed_hyperplane(plane, self.relaxation))
+            .collect::<Vec<_>>();
+
+        let result = incremental_optimization_4d(
+            Vec4::new(
+                preffered_velocity.x,
+                preffered_velocity.y,
+                preffered_velocity.z,
+                -1000.0,
+            ),
+            &Spherinder::with_radii(Vec4::ZERO, self.axis_scale * maximum_velocity),
+            hyperplanes.as_slice(),
+        );
+
+        match result {
+            OptimizationResult4D::Feasible { optimal_velocity } => optimal_velocity.truncate(),
+            OptimizationResult4D::Infeasible {
+                last_optimal_velocity,
+            } => last_optimal_velocity.truncate(),
+        }
+    }
+}
+
+// Moves all planes back by the same distance, found by bisection, until the problem is feasible
+// Unlike the 4D relaxation the velocity is then the closest one to the preferred velocity
+// among the velocities allowed by the moved planes, not only the least violating one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ScaledRelaxation {
+    pub iterations: usize,
+}
+
+impl ScaledRelaxation {
+    #[must_use]
+    pub fn new(iterations: usize) -> Self {
+        Self { iterations }
+    }
+}
+
+impl Default for ScaledRelaxation {
+    fn default() -> Self {
+        Self::new(16)
+    }
+}
+
+impl InfeasibilityStrategy for ScaledRelaxation {
+    fn resolve(
+        &self,
+        preffered_velocity: Vec3,
+        maximum_velocity: f32,
+        planes: &[Plane],
+        _last_velocity: Vec3,
+    ) -> Vec3 {
+        let bounding_sphere = Sphere::new(maximum_velocity, Vec3::ZERO);
+        let solve = |distance: f32| {
+            let moved_planes = planes
+                .iter()
+                .map(|plane| Plane::new(plane.origin - plane.normal * distance, plane.normal))
+                .collect::<Vec<_>>();
+
+            match incremental_optimization_3d(preffered_velocity, &bounding_sphere, &moved_planes) {
+                OptimizationResult3D::Feasible { optimal_velocity } => Some(optimal_velocity),
+                OptimizationResult3D::Infeasible { .. } => None,
+            }
+        };
+
+        // Once every plane allows zero velocity the problem is feasible
+        let mut low = 0.0;
+        let mut high = planes
+            .iter()
+            .map(|plane| plane.normal.dot(plane.origin))
+            .fold(0.0, f32::max)
+            + EPSILON;
+
+        let mut best = solve(high).unwrap_or(Vec3::ZERO);
+
+        for _ in 0..self.iterations {
+            let distance = f32::midpoint(low, high);
+
+            if let Some(velocity) = solve(distance) {
+                high = distance;
+                best = velocity;
+            } else {
+                low = distance;
+            }
+        }
+
+        best
+    }
+}
+
+// Tries a fixed set of velocities within the maximum velocity and picks the one with the
+// smallest largest violation, preferring the ones closer to the preferred velocity
+// Slower and less precise than the relaxations, but it makes no assumptions about the planes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SamplingRelaxation {
+    pub samples: usize,
+}
+
+impl SamplingRelaxation {
+    #[must_use]
+    pub fn new(samples: usize) -> Self {
+        Self { samples }
+    }
+}
+
+impl Default for SamplingRelaxation {
+    fn default() -> Self {
+        Self::new(256)
+    }
+}
+
+impl InfeasibilityStrategy for SamplingRelaxation {
+    #[allow(clippy::cast_precision_loss)]
+    fn resolve(
+        &self,
+        preffered_velocity: Vec3,
+        maximum_velocity: f32,
+        planes: &[Plane],
+        last_velocity: Vec3,
+    ) -> Vec3 {
+        let golden_angle = PI * (3.0 - 5.0_f32.sqrt());
+
+        // Points of a Fibonacci sphere, spread over the ball by a second low discrepancy sequence
+        let samples = (0..self.samples).map(|i| {
+            let i = i as f32;
+            let z = 1.0 - 2.0 * (i + 0.5) / self.samples as f32;
+            let ring_radius = (1.0 - z * z).sqrt();
+            let angle = golden_angle * i;
+            let radius = (i * golden_angle / (2.0 * PI)).fract().cbrt();
+
+            Vec3::new(ring_radius * angle.cos(), ring_radius * angle.sin(), z)
+                * radius
+                * maximum_velocity
+        });
+
+        let candidates = [
+            preffered_velocity.clamp_length_max(maximum_velocity),
+            last_velocity.clamp_length_max(maximum_velocity),
+            Vec3::ZERO,
+        ]
+        .into_iter()
+        .chain(samples);
+
+        let score = |velocity: Vec3| {
+            let violation = planes
+                .iter()
+                .map(|plane| -plane.normal.dot(velocity - plane.origin))
+                .fold(0.0, f32::max);
+
+            (violation, velocity.distance_squared(preffered_velocity))
+        };
+
+        candidates
+            .map(|velocity| (velocity, score(velocity)))
+            .reduce(|best, candidate| {
+                let (_, (best_violation, best_distance)) = best;
+                let (_, (violation, distance)) = candidate;
+
+                if violation < best_violation - EPSILON
+                    || (violation < best_violation + EPSILON && distance < best_distance)
+                {
+                    candidate
+                } else {
+                    best
+                }
+            })
+            .map_or(Vec3::ZERO, |(velocity, _)| velocity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::optimize_velocity_3d_with_strategy;
+
+    // x >= 1 and x <= -1 can't be satisfied together, the least violating velocities have x = 0
+    fn conflicting_planes() -> [Plane; 2] {
+        [Plane::new(Vec3::X, Vec3::X), Plane::new(-Vec3::X, -Vec3::X)]
+    }
+
+    #[test]
+    fn test_strategies_resolve_conflicting_planes() {
+        let planes = conflicting_planes();
+        let preffered_velocity = Vec3::new(0.5, 1.0, 0.0);
+
+        let relaxed = optimize_velocity_3d_with_strategy(
+            preffered_velocity,
+            2.0,
+            &planes,
+            &Relaxation4D::default(),
+        );
+        let scaled = optimize_velocity_3d_with_strategy(
+            preffered_velocity,
+            2.0,
+            &planes,
+            &ScaledRelaxation::default(),
+        );
+        let sampled = optimize_velocity_3d_with_strategy(
+            preffered_velocity,
+            2.0,
+            &planes,
+            &SamplingRelaxation::default(),
+        );
+
+        assert!(relaxed.x.abs() < 1e-3);
+        assert!(relaxed.length() <= 2.0 + 1e-3);
+
+        assert!((scaled - Vec3::Y).length() < 1e-3);
+
+        assert!(sampled.x.abs() < 0.2);
+        assert!(sampled.length() <= 2.0 + 1e-3);
+    }
+
+    #[test]
+    fn test_axis_scale_limits_relaxed_velocity() {
+        let planes = conflicting_planes();
+        let strategy = Relaxation4D::default().with_axis_scale(Vec3::new(1.0, 0.25, 1.0));
+
+        let velocity = optimize_velocity_3d_with_strategy(Vec3::Y * 2.0, 2.0, &planes, &strategy);
+
+        assert!(velocity.x.abs() < 1e-3);
+        assert!(velocity.y <= 0.5 + 1e-3);
+    }
+
+    #[test]
+    fn test_closure_strategy() {
+        let planes = conflicting_planes();
+        let stop = |_: Vec3, _: f32, _: &[Plane], _: Vec3| Vec3::ZERO;
+
+        assert_eq!(
+            optimize_velocity_3d_with_strategy(Vec3::X, 2.0, &planes, &stop),
+            Vec3::ZERO
+        );
+    }
+
+    #[test]
+    fn test_strategy_is_not_used_for_feasible_problems() {
+        let planes = [Plane::new(Vec3::X, Vec3::X)];
+        let unused = |_: Vec3, _: f32, _: &[Plane], _: Vec3| -> Vec3 { unreachable!() };
+
+        let velocity = optimize_velocity_3d_with_strategy(Vec3::ZERO, 2.0, &planes, &unused);
+
+        assert!((velocity - Vec3::X).length() < 1e-3);
+    }
+
+    // The strategies are shared by the solvers running in parallel ECS systems
+    #[test]
+    fn test_strategies_are_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+
+        assert_send_sync::<Relaxation4D>();
+        assert_send_sync::<ScaledRelaxation>();
+        assert_send_sync::<SamplingRelaxation>();
+    }
+}
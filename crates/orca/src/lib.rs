@@ -3,22 +3,42 @@
 pub(crate) const EPSILON: f32 = 0.0001;
 
 mod acceleration_velocity_obstacle_3d;
+mod admissible_velocity;
 mod agent_3d;
+mod avoidance_policy;
+mod collision_probability;
 mod formation_velocity_obstacle_3d;
+mod infeasibility;
+mod obstacle_clustering;
+mod scenarios;
+mod simulation;
 mod solver_2d;
 mod solver_3d;
 mod solver_4d;
+mod solver_input;
+mod solver_invariants;
+mod velocity_constraint;
 mod velocity_obstacle_3d;
 
 pub use acceleration_velocity_obstacle_3d::*;
+pub use admissible_velocity::*;
 pub use agent_3d::*;
+pub use avoidance_policy::*;
+pub use collision_probability::*;
 pub use formation_velocity_obstacle_3d::*;
+pub use infeasibility::*;
+pub use obstacle_clustering::*;
+pub use scenarios::*;
+pub use simulation::*;
+pub use solver_input::*;
+pub use solver_invariants::*;
+pub use velocity_constraint::*;
 pub use velocity_obstacle_3d::*;
 
-use bevy_math::{Vec3, Vec4};
-use geometry::{Hyperplane, Plane, Sphere, Spherinder};
+use bevy_math::Vec3;
+use geometry::{Plane, Sphere};
 use solver_3d::{incremental_optimization_3d, OptimizationResult3D};
-use solver_4d::{incremental_optimization_4d, OptimizationResult4D};
+use solver_input::check_solver_input;
 
 #[must_use]
 pub fn optimize_velocity_3d(
@@ -26,44 +46,53 @@ pub fn optimize_velocity_3d(
     maximum_velocity: f32,
     planes: &[Plane],
 ) -> Vec3 {
+    optimize_velocity_3d_with_strategy(
+        preffered_velocity,
+        maximum_velocity,
+        planes,
+        &Relaxation4D::default(),
+    )
+}
+
+// Same as optimize_velocity_3d, but the strategy picks the velocity when the planes can't be
+// satisfied together
+#[must_use]
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "trace", skip_all, fields(planes = planes.len()))
+)]
+pub fn optimize_velocity_3d_with_strategy(
+    preffered_velocity: Vec3,
+    maximum_velocity: f32,
+    planes: &[Plane],
+    strategy: &impl InfeasibilityStrategy,
+) -> Vec3 {
+    let (preffered_velocity, maximum_velocity, planes) =
+        check_solver_input(preffered_velocity, maximum_velocity, planes, |plane| plane);
+
     let result = incremental_optimization_3d(
         preffered_velocity,
         &Sphere::new(maximum_velocity, Vec3::ZERO),
-        planes,
+        &planes,
     );
 
     match result {
         OptimizationResult3D::Feasible { optimal_velocity } => optimal_velocity,
         OptimizationResult3D::Infeasible {
-            last_optimal_velocity: _,
+            last_optimal_velocity,
         } => {
-            let mut hyperplanes = Vec::with_capacity(planes.len());
-            for plane in planes {
-                let hyperplane = Hyperplane::new(
-                    Vec4::new(plane.origin.x, plane.origin.y, plane.origin.z, 0.0),
-                    Vec4::new(plane.normal.x, plane.normal.y, plane.normal.z, 0.5),
-                );
-
-                hyperplanes.push(hyperplane);
-            }
-
-            let result = incremental_optimization_4d(
-                Vec4::new(
-                    preffered_velocity.x,
-                    preffered_velocity.y,
-                    preffered_velocity.z,
-                    -1000.0,
-                ),
-                &Spherinder::new(Vec4::ZERO, maximum_velocity),
-                hyperplanes.as_slice(),
+            #[cfg(feature = "tracing")]
+            tracing::trace!(
+                monotonic_counter.orca_relaxed_solves = 1_u64,
+                "resolving infeasible planes"
             );
 
-            match result {
-                OptimizationResult4D::Feasible { optimal_velocity } => optimal_velocity.truncate(),
-                OptimizationResult4D::Infeasible {
-                    last_optimal_velocity,
-                } => last_optimal_velocity.truncate(),
-            }
+            strategy.resolve(
+                preffered_velocity,
+                maximum_velocity,
+                &planes,
+                last_optimal_velocity,
+            )
         }
     }
 }
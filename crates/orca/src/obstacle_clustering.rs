@@ -0,0 +1,222 @@
+use bevy_math::Vec3;
+use geometry::{colliders::Collider, Aabb};
+
+use crate::Agent3D;
+
+// Merges obstacles that are close to each other into a single conservative obstacle
+// Dozens of nearby obstacles produce dozens of formation velocity obstacle planes, which
+// over-constrain the optimization and freeze the formation even when there is a way around the
+// whole group. Two obstacles end up in the same cluster when their shapes grown by the formation
+// collider (the shapes the velocity obstacles are built from) come closer than cluster_distance
+// to each other at any time within the time horizon.
+//
+// The merged obstacle is an AABB containing all of its obstacles. It moves with their average
+// velocity and is grown by how far the obstacles drift away from that velocity within the time
+// horizon, so it covers the obstacles for the whole horizon.
+#[must_use]
+pub fn cluster_obstacles(
+    obstacles: &[Agent3D],
+    formation_collider: &Collider,
+    time_horizon: f32,
+    cluster_distance: f32,
+) -> Vec<Agent3D> {
+    let formation_half_sizes = get_local_aabb(formation_collider).half_sizes;
+
+    let swept_aabbs = obstacles
+        .iter()
+        .map(|obstacle| {
+            let mut aabb = get_world_aabb(obstacle);
+            aabb.half_sizes += formation_half_sizes;
+
+            let mut moved = aabb.clone();
+            moved.center += obstacle.velocity * time_horizon;
+            aabb.merge(&moved);
+
+            aabb
+        })
+        .collect::<Vec<_>>();
+
+    let mut parents = (0..obstacles.len()).collect::<Vec<_>>();
+
+    for i in 0..obstacles.len() {
+        for j in (i + 1)..obstacles.len() {
+            if get_aabb_distance(&swept_aabbs[i], &swept_aabbs[j]) <= cluster_distance {
+                let root_i = find_root(&mut parents, i);
+                let root_j = find_root(&mut parents, j);
+
+                parents[root_j] = root_i;
+            }
+        }
+    }
+
+    let mut clusters = Vec::<(usize, Vec<usize>)>::new();
+
+    for i in 0..obstacles.len() {
+        let root = find_root(&mut parents, i);
+
+        match clusters
+            .iter_mut()
+            .find(|(cluster_root, _)| *cluster_root == root)
+        {
+            Some((_, members)) => members.push(i),
+            None => clusters.push((root, vec![i])),
+        }
+    }
+
+    clusters
+        .into_iter()
+        .map(|(_, members)| {
+            if members.len() == 1 {
+                return obstacles[members[0]].clone();
+            }
+
+            merge_obstacles(members.iter().map(|&i| &obstacles[i]), time_horizon)
+        })
+        .collect()
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn merge_obstacles<'a>(
+    obstacles: impl Iterator<Item = &'a Agent3D> + Clone,
+    time_horizon: f32,
+) -> Agent3D {
+    let count = obstacles.clone().count() as f32;
+    let velocity = obstacles
+        .clone()
+        .map(|obstacle| obstacle.velocity)
+        .sum::<Vec3>()
+        / count;
+
+    let drift = obstacles
+        .clone()
+        .map(|obstacle| (obstacle.velocity - velocity).abs())
+        .fold(Vec3::ZERO, Vec3::max)
+        * time_horizon;
+
+    let responsibility = obstacles
+        .clone()
+        .map(|obstacle| obstacle.responsibility)
+        .fold(0.0, f32::max);
+
+    let mut aabb: Option<Aabb> = None;
+
+    for obstacle in obstacles {
+        let obstacle_aabb = get_world_aabb(obstacle);
+
+        match aabb.as_mut() {
+            Some(aabb) => aabb.merge(&obstacle_aabb),
+            None => aabb = Some(obstacle_aabb),
+        }
+    }
+
+    let aabb = aabb.unwrap_or_else(|| Aabb::new(Vec3::ZERO, Vec3::ZERO));
+
+    Agent3D {
+        position: aabb.center,
+        velocity,
+        shape: Collider::new_aabb(Vec3::ZERO, aabb.half_sizes + drift),
+        responsibility,
+    }
+}
+
+fn get_local_aabb(collider: &Collider) -> Aabb {
+    match collider {
+        Collider::Sphere(sphere) => Aabb::new(sphere.origin, Vec3::splat(sphere.radius)),
+        Collider::Aabb(aabb) => aabb.clone(),
+    }
+}
+
+fn get_world_aabb(agent: &Agent3D) -> Aabb {
+    let aabb = get_local_aabb(&agent.shape);
+
+    Aabb::new(agent.position + aabb.center, aabb.half_sizes)
+}
+
+// Distance between the closest points of two boxes, zero when they overlap
+fn get_aabb_distance(a: &Aabb, b: &Aabb) -> f32 {
+    ((a.center - b.center).abs() - a.half_sizes - b.half_sizes)
+        .max(Vec3::ZERO)
+        .length()
+}
+
+fn find_root(parents: &mut [usize], mut index: usize) -> usize {
+    while parents[index] != index {
+        parents[index] = parents[parents[index]];
+        index = parents[index];
+    }
+
+    index
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_obstacle(position: Vec3, velocity: Vec3) -> Agent3D {
+        Agent3D::new(position, velocity, Collider::new_sphere(1.0))
+    }
+
+    #[test]
+    fn test_distant_obstacles_stay_separate() {
+        let obstacles = [
+            get_obstacle(Vec3::ZERO, Vec3::ZERO),
+            get_obstacle(Vec3::new(20.0, 0.0, 0.0), Vec3::ZERO),
+        ];
+
+        let clusters = cluster_obstacles(&obstacles, &Collider::new_sphere(1.0), 5.0, 1.0);
+
+        assert_eq!(clusters.len(), 2);
+    }
+
+    #[test]
+    fn test_close_obstacles_are_merged() {
+        let obstacles = [
+            get_obstacle(Vec3::ZERO, Vec3::ZERO),
+            get_obstacle(Vec3::new(4.0, 0.0, 0.0), Vec3::ZERO),
+            get_obstacle(Vec3::new(20.0, 0.0, 0.0), Vec3::ZERO),
+        ];
+
+        let clusters = cluster_obstacles(&obstacles, &Collider::new_sphere(1.0), 5.0, 0.5);
+
+        assert_eq!(clusters.len(), 2);
+
+        let merged = &clusters[0];
+        assert!((merged.position - Vec3::new(2.0, 0.0, 0.0)).length() < 1e-5);
+
+        let Collider::Aabb(aabb) = &merged.shape else {
+            panic!("The merged obstacle should be an AABB");
+        };
+        assert!((aabb.half_sizes - Vec3::new(3.0, 1.0, 1.0)).length() < 1e-5);
+    }
+
+    #[test]
+    fn test_clustering_is_transitive() {
+        let obstacles = [
+            get_obstacle(Vec3::ZERO, Vec3::ZERO),
+            get_obstacle(Vec3::new(20.0, 0.0, 0.0), Vec3::ZERO),
+            get_obstacle(Vec3::new(10.0, 0.0, 0.0), Vec3::ZERO),
+        ];
+
+        let clusters = cluster_obstacles(&obstacles, &Collider::new_sphere(3.0), 5.0, 2.5);
+
+        assert_eq!(clusters.len(), 1);
+    }
+
+    #[test]
+    fn test_merged_obstacle_covers_diverging_obstacles() {
+        let obstacles = [
+            get_obstacle(Vec3::ZERO, -Vec3::Y),
+            get_obstacle(Vec3::new(3.0, 0.0, 0.0), Vec3::Y),
+        ];
+
+        let clusters = cluster_obstacles(&obstacles, &Collider::new_sphere(1.0), 5.0, 0.5);
+
+        assert_eq!(clusters.len(), 1);
+        assert!(clusters[0].velocity.length() < 1e-5);
+
+        let Collider::Aabb(aabb) = &clusters[0].shape else {
+            panic!("The merged obstacle should be an AABB");
+        };
+        assert!(aabb.half_sizes.y >= 1.0 + 5.0 - 1e-5);
+    }
+}
@@ -0,0 +1,430 @@
+use bevy_math::Vec3;
+use geometry::colliders::Collider;
+
+use crate::{Agent3D, AgentId, Simulator, SimulatorAgent, SimulatorParams};
+
+// Data-driven description of a simulation run, e.g.
+//
+// (
+//     time_horizon: 2.0,
+//     neighbour_distance: 10.0,
+//     time_step: 0.05,
+//     steps: 400,
+//     agents: [
+//         (position: (-10.0, 0.0, 0.0), goal: (10.0, 0.0, 0.0), radius: 1.0, max_speed: 5.0),
+//         (position: (10.0, 0.5, 0.0), goal: (-10.0, 0.5, 0.0), radius: 1.0, max_speed: 5.0),
+//     ],
+//     obstacles: [(position: (0.0, 5.0, 0.0), radius: 2.0)],
+// )
+//
+// Scenarios are meant for benchmark corpora and reproducible bug reports, running the same
+// scenario always gives the same report.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Scenario {
+    pub time_horizon: f32,
+    pub neighbour_distance: f32,
+    #[cfg_attr(feature = "serde", serde(default = "default_max_neighbours"))]
+    pub max_neighbours: usize,
+    pub time_step: f32,
+    pub steps: usize,
+    pub agents: Vec<ScenarioAgent>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub obstacles: Vec<ScenarioObstacle>,
+}
+
+#[cfg(feature = "serde")]
+fn default_max_neighbours() -> usize {
+    SimulatorParams::new(1.0, 1.0).max_neighbours
+}
+
+// A sphere agent flying to its goal at its maximum speed
+// The agent has reached its goal once its center is within goal_tolerance of the goal.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ScenarioAgent {
+    pub position: [f32; 3],
+    pub goal: [f32; 3],
+    pub radius: f32,
+    pub max_speed: f32,
+    #[cfg_attr(feature = "serde", serde(default = "default_goal_tolerance"))]
+    pub goal_tolerance: f32,
+}
+
+#[cfg(feature = "serde")]
+fn default_goal_tolerance() -> f32 {
+    0.1
+}
+
+impl ScenarioAgent {
+    #[must_use]
+    pub fn new(position: Vec3, goal: Vec3, radius: f32, max_speed: f32) -> Self {
+        Self {
+            position: position.to_array(),
+            goal: goal.to_array(),
+            radius,
+            max_speed,
+            goal_tolerance: 0.1,
+        }
+    }
+
+    #[must_use]
+    pub fn with_goal_tolerance(mut self, goal_tolerance: f32) -> Self {
+        self.goal_tolerance = goal_tolerance;
+        self
+    }
+}
+
+// A static sphere the agents have to avoid on their own
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ScenarioObstacle {
+    pub position: [f32; 3],
+    pub radius: f32,
+}
+
+// Metrics of a scenario run
+//
+// collisions: The number of times two bodies started to overlap, an agent and an obstacle
+//             count the same as two agents
+// makespan: The time until the last agent reached its goal, None if some agent never did
+// path_lengths: The distance every agent travelled, in the order of Scenario::agents
+// arrival_times: The time every agent reached its goal at, None if it never did
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScenarioReport {
+    pub steps: usize,
+    pub collisions: usize,
+    pub makespan: Option<f32>,
+    pub path_lengths: Vec<f32>,
+    pub arrival_times: Vec<Option<f32>>,
+}
+
+impl Scenario {
+    #[must_use]
+    pub fn new(time_horizon: f32, neighbour_distance: f32, time_step: f32, steps: usize) -> Self {
+        Self {
+            time_horizon,
+            neighbour_distance,
+            max_neighbours: SimulatorParams::new(time_horizon, neighbour_distance).max_neighbours,
+            time_step,
+            steps,
+            agents: Vec::new(),
+            obstacles: Vec::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn with_max_neighbours(mut self, max_neighbours: usize) -> Self {
+        self.max_neighbours = max_neighbours;
+        self
+    }
+
+    #[must_use]
+    pub fn with_agent(mut self, agent: ScenarioAgent) -> Self {
+        self.agents.push(agent);
+        self
+    }
+
+    #[must_use]
+    pub fn with_obstacle(mut self, position: Vec3, radius: f32) -> Self {
+        self.obstacles.push(ScenarioObstacle {
+            position: position.to_array(),
+            radius,
+        });
+        self
+    }
+
+    // Simulates the scenario without any rendering
+    // Every step the agents prefer to fly straight to their goals, slowing down so they don't
+    // overshoot it. Obstacles are agents that never move and leave the avoidance to the others.
+    // The run stops after the given number of steps or once every agent reached its goal.
+    #[must_use]
+    #[allow(clippy::missing_panics_doc, clippy::cast_precision_loss)]
+    pub fn run(&self) -> ScenarioReport {
+        assert!(self.time_step > 0.0);
+
+        let mut simulator = Simulator::new(
+            SimulatorParams::new(self.time_horizon, self.neighbour_distance)
+                .with_max_neighbours(self.max_neighbours),
+        );
+
+        let agent_ids = self
+            .agents
+            .iter()
+            .map(|agent| {
+                simulator.add_agent(SimulatorAgent::new(
+                    Agent3D::new(
+                        Vec3::from_array(agent.position),
+                        Vec3::ZERO,
+                        Collider::new_sphere(agent.radius),
+                    ),
+                    agent.max_speed,
+                ))
+            })
+            .collect::<Vec<_>>();
+
+        let obstacle_ids = self
+            .obstacles
+            .iter()
+            .map(|obstacle| {
+                let mut agent = Agent3D::new(
+                    Vec3::from_array(obstacle.position),
+                    Vec3::ZERO,
+                    Collider::new_sphere(obstacle.radius),
+                );
+                // Not zero, two obstacles next to each other would share no responsibility at all
+                agent.responsibility = 1e-6;
+
+                simulator.add_agent(SimulatorAgent::new(agent, 0.0))
+            })
+            .collect::<Vec<_>>();
+
+        let bodies = agent_ids
+            .iter()
+            .zip(self.agents.iter().map(|agent| agent.radius))
+            .chain(
+                obstacle_ids
+                    .iter()
+                    .zip(self.obstacles.iter().map(|obstacle| obstacle.radius)),
+            )
+            .map(|(id, radius)| (*id, radius))
+            .collect::<Vec<_>>();
+
+        let mut overlapping = get_overlapping_pairs(&simulator, &bodies);
+        let mut report = ScenarioReport {
+            steps: 0,
+            collisions: 0,
+            makespan: None,
+            path_lengths: vec![0.0; self.agents.len()],
+            arrival_times: self
+                .agents
+                .iter()
+                .map(|agent| {
+                    (Vec3::from_array(agent.position).distance(Vec3::from_array(agent.goal))
+                        <= agent.goal_tolerance)
+                        .then_some(0.0)
+                })
+                .collect(),
+        };
+
+        while report.steps < self.steps && report.arrival_times.iter().any(Option::is_none) {
+            for (id, agent) in agent_ids.iter().zip(&self.agents) {
+                let simulator_agent = simulator.get_mut(*id).unwrap();
+                let to_goal = Vec3::from_array(agent.goal) - simulator_agent.agent.position;
+
+                simulator_agent.preferred_velocity =
+                    to_goal.clamp_length_max(agent.max_speed * self.time_step) / self.time_step;
+            }
+
+            let previous_positions = agent_ids
+                .iter()
+                .map(|id| simulator.get(*id).unwrap().agent.position)
+                .collect::<Vec<_>>();
+
+            simulator.step(self.time_step);
+            report.steps += 1;
+
+            // Obstacles are put back where they were, whatever velocity the solver chose for them
+            for (id, obstacle) in obstacle_ids.iter().zip(&self.obstacles) {
+                let simulator_agent = simulator.get_mut(*id).unwrap();
+                simulator_agent.agent.position = Vec3::from_array(obstacle.position);
+                simulator_agent.agent.velocity = Vec3::ZERO;
+            }
+
+            let time = report.steps as f32 * self.time_step;
+
+            for (index, (id, agent)) in agent_ids.iter().zip(&self.agents).enumerate() {
+                let position = simulator.get(*id).unwrap().agent.position;
+                report.path_lengths[index] += position.distance(previous_positions[index]);
+
+                if report.arrival_times[index].is_none()
+                    && position.distance(Vec3::from_array(agent.goal)) <= agent.goal_tolerance
+                {
+                    report.arrival_times[index] = Some(time);
+                }
+            }
+
+            let now_overlapping = get_overlapping_pairs(&simulator, &bodies);
+            report.collisions += now_overlapping
+                .iter()
+                .filter(|pair| !overlapping.contains(pair))
+                .count();
+            overlapping = now_overlapping;
+        }
+
+        report.makespan = report
+            .arrival_times
+            .iter()
+            .try_fold(0.0_f32, |makespan, time| {
+                time.map(|time| makespan.max(time))
+            });
+
+        report
+    }
+}
+
+// Pairs of bodies that overlap by more than a small tolerance, so touching doesn't count
+fn get_overlapping_pairs(simulator: &Simulator, bodies: &[(AgentId, f32)]) -> Vec<(usize, usize)> {
+    const TOLERANCE: f32 = 1e-3;
+
+    let positions = bodies
+        .iter()
+        .map(|(id, _)| simulator.get(*id).map(|agent| agent.agent.position))
+        .collect::<Vec<_>>();
+
+    let mut pairs = Vec::new();
+
+    for a in 0..bodies.len() {
+        for b in a + 1..bodies.len() {
+            let (Some(position_a), Some(position_b)) = (positions[a], positions[b]) else {
+                continue;
+            };
+
+            if position_a.distance(position_b) < bodies[a].1 + bodies[b].1 - TOLERANCE {
+                pairs.push((a, b));
+            }
+        }
+    }
+
+    pairs
+}
+
+/// Parses a scenario from its RON representation.
+///
+/// # Errors
+/// Returns an error if the source isn't valid RON or doesn't describe a scenario
+#[cfg(feature = "ron")]
+pub fn scenario_from_ron(source: &str) -> Result<Scenario, ron::error::SpannedError> {
+    ron::from_str(source)
+}
+
+/// Writes a scenario as pretty-printed RON.
+///
+/// # Errors
+/// Returns an error if the scenario can't be serialized
+#[cfg(feature = "ron")]
+pub fn scenario_to_ron(scenario: &Scenario) -> Result<String, ron::Error> {
+    ron::ser::to_string_pretty(scenario, ron::ser::PrettyConfig::default())
+}
+
+/// Parses a scenario from its JSON representation.
+///
+/// # Errors
+/// Returns an error if the source isn't valid JSON or doesn't describe a scenario
+#[cfg(feature = "json")]
+pub fn scenario_from_json(source: &str) -> Result<Scenario, serde_json::Error> {
+    serde_json::from_str(source)
+}
+
+/// Writes a scenario as pretty-printed JSON.
+///
+/// # Errors
+/// Returns an error if the scenario can't be serialized
+#[cfg(feature = "json")]
+pub fn scenario_to_json(scenario: &Scenario) -> Result<String, serde_json::Error> {
+    serde_json::to_string_pretty(scenario)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_crossing_scenario() -> Scenario {
+        Scenario::new(2.0, 10.0, 0.05, 400)
+            .with_agent(ScenarioAgent::new(
+                Vec3::new(-10.0, 0.0, 0.0),
+                Vec3::new(10.0, 0.0, 0.0),
+                1.0,
+                5.0,
+            ))
+            .with_agent(ScenarioAgent::new(
+                Vec3::new(10.0, 0.5, 0.0),
+                Vec3::new(-10.0, 0.5, 0.0),
+                1.0,
+                5.0,
+            ))
+    }
+
+    #[test]
+    fn test_agents_reach_their_goals_without_collisions() {
+        let report = get_crossing_scenario().run();
+
+        assert_eq!(report.collisions, 0);
+        assert!(report.arrival_times.iter().all(Option::is_some));
+        assert!(report.steps < 400);
+
+        // Flying straight at full speed would take 4 seconds over 20 units
+        let makespan = report.makespan.unwrap();
+        assert!(makespan >= 4.0 - 1e-3);
+
+        for length in &report.path_lengths {
+            assert!(*length >= 20.0 - 0.2);
+        }
+    }
+
+    #[test]
+    fn test_agent_flies_around_an_obstacle_in_its_way() {
+        let scenario = Scenario::new(2.0, 10.0, 0.05, 400)
+            .with_obstacle(Vec3::new(0.0, 0.3, 0.0), 1.0)
+            .with_agent(ScenarioAgent::new(
+                Vec3::new(-10.0, 0.0, 0.0),
+                Vec3::new(10.0, 0.0, 0.0),
+                1.0,
+                5.0,
+            ));
+
+        let report = scenario.run();
+
+        assert_eq!(report.collisions, 0);
+        assert!(report.arrival_times[0].is_some());
+        assert!(report.path_lengths[0] > 20.0);
+    }
+
+    #[test]
+    fn test_runs_are_deterministic() {
+        let scenario = get_crossing_scenario().with_obstacle(Vec3::new(0.0, 3.0, 0.0), 1.0);
+
+        assert_eq!(scenario.run(), scenario.run());
+    }
+
+    #[test]
+    fn test_unreachable_goal_has_no_makespan() {
+        let scenario = Scenario::new(2.0, 10.0, 0.1, 10).with_agent(ScenarioAgent::new(
+            Vec3::ZERO,
+            Vec3::new(100.0, 0.0, 0.0),
+            1.0,
+            1.0,
+        ));
+
+        let report = scenario.run();
+
+        assert_eq!(report.steps, 10);
+        assert_eq!(report.makespan, None);
+        assert!((report.path_lengths[0] - 1.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_overlapping_start_counts_no_new_collision() {
+        let scenario = Scenario::new(2.0, 10.0, 0.1, 1).with_obstacle(Vec3::ZERO, 1.0);
+        let scenario = scenario.with_agent(ScenarioAgent::new(
+            Vec3::new(0.5, 0.0, 0.0),
+            Vec3::new(5.0, 0.0, 0.0),
+            1.0,
+            1.0,
+        ));
+
+        let report = scenario.run();
+
+        assert_eq!(report.steps, 1);
+        assert_eq!(report.collisions, 0);
+    }
+
+    #[cfg(feature = "ron")]
+    #[test]
+    fn test_ron_round_trip() {
+        let scenario = get_crossing_scenario().with_obstacle(Vec3::new(0.0, 3.0, 0.0), 1.0);
+        let source = scenario_to_ron(&scenario).unwrap();
+
+        assert_eq!(scenario_from_ron(&source).unwrap(), scenario);
+    }
+}
@@ -0,0 +1,614 @@
+use std::collections::HashMap;
+
+use bevy_math::{IVec3, Vec3};
+
+use crate::{optimize_velocity_3d, Agent3D, AvoidancePolicy, Plane};
+
+// Handle of an agent in a Simulator
+// Ids stay valid while the agent exists, the id of a removed agent never refers to another agent
+// even when its slot is reused.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct AgentId {
+    index: u32,
+    generation: u32,
+}
+
+impl AgentId {
+    // Packs the id into a single number, e.g. to hand it over to a scripting language
+    #[must_use]
+    pub fn to_bits(self) -> u64 {
+        (u64::from(self.generation) << 32) | u64::from(self.index)
+    }
+
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn from_bits(bits: u64) -> Self {
+        Self {
+            index: bits as u32,
+            generation: (bits >> 32) as u32,
+        }
+    }
+}
+
+// An agent of the simulation
+//
+// max_speed: The speed the avoidance velocity is limited to
+// preferred_velocity: The velocity the agent would like to have, set by the user every step
+// policy: The velocity obstacle the agent avoids others with, unless a pair policy of
+//         the simulator says otherwise
+// class: The kind of the agent the pair policies are selected by
+#[derive(Clone, Debug)]
+pub struct SimulatorAgent {
+    pub agent: Agent3D,
+    pub max_speed: f32,
+    pub preferred_velocity: Vec3,
+    pub policy: AvoidancePolicy,
+    pub class: u32,
+}
+
+impl SimulatorAgent {
+    #[must_use]
+    pub fn new(agent: Agent3D, max_speed: f32) -> Self {
+        Self {
+            agent,
+            max_speed,
+            preferred_velocity: Vec3::ZERO,
+            policy: AvoidancePolicy::default(),
+            class: 0,
+        }
+    }
+
+    // Returns the name of the first field that isn't finite
+    #[must_use]
+    pub fn find_non_finite_field(&self) -> Option<&'static str> {
+        [
+            ("position", self.agent.position.is_finite()),
+            ("velocity", self.agent.velocity.is_finite()),
+            ("preferred velocity", self.preferred_velocity.is_finite()),
+            ("max speed", self.max_speed.is_finite()),
+            ("responsibility", self.agent.responsibility.is_finite()),
+        ]
+        .into_iter()
+        .find(|(_, is_finite)| !is_finite)
+        .map(|(field, _)| field)
+    }
+
+    #[must_use]
+    pub fn with_policy(mut self, policy: AvoidancePolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    #[must_use]
+    pub fn with_class(mut self, class: u32) -> Self {
+        self.class = class;
+        self
+    }
+}
+
+// time_horizon: How far ahead in seconds agents avoid each other
+// neighbour_distance: Agents further away are ignored
+// max_neighbours: The number of the closest neighbours each agent avoids
+// pair_policies: The policy an agent of the first class uses to avoid an agent of the second one
+#[derive(Clone, Debug)]
+pub struct SimulatorParams {
+    pub time_horizon: f32,
+    pub neighbour_distance: f32,
+    pub max_neighbours: usize,
+    pub pair_policies: HashMap<(u32, u32), AvoidancePolicy>,
+}
+
+impl SimulatorParams {
+    #[must_use]
+    #[allow(clippy::missing_panics_doc)]
+    pub fn new(time_horizon: f32, neighbour_distance: f32) -> Self {
+        assert!(time_horizon > 0.0);
+        assert!(neighbour_distance > 0.0);
+
+        Self {
+            time_horizon,
+            neighbour_distance,
+            max_neighbours: 10,
+            pair_policies: HashMap::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn with_max_neighbours(mut self, max_neighbours: usize) -> Self {
+        self.max_neighbours = max_neighbours;
+        self
+    }
+
+    #[must_use]
+    pub fn with_pair_policy(
+        mut self,
+        class: u32,
+        other_class: u32,
+        policy: AvoidancePolicy,
+    ) -> Self {
+        self.pair_policies.insert((class, other_class), policy);
+        self
+    }
+
+    // Returns the policy the agent uses to avoid the other one
+    #[must_use]
+    pub fn get_policy(&self, agent: &SimulatorAgent, other: &SimulatorAgent) -> AvoidancePolicy {
+        self.pair_policies
+            .get(&(agent.class, other.class))
+            .copied()
+            .unwrap_or(agent.policy)
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Slot {
+    generation: u32,
+    state: SlotState,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum SlotState {
+    Free,
+    // Added, waiting for the next step; the index points to the pending agents
+    Pending(usize),
+    // The index points to the dense arrays of the active agents
+    Active(usize),
+}
+
+// Runs ORCA for a set of agents that may appear and disappear at any time
+// Agents added between two steps join the simulation at the start of the next step, so every
+// step works with the same set of agents and the neighbours found for one agent are never
+// invalidated by changes made for another. Removal swaps the last agent into the freed place,
+// so it takes constant time, and the slots of removed agents are reused for new ones.
+#[derive(Clone, Debug)]
+pub struct Simulator {
+    params: SimulatorParams,
+    slots: Vec<Slot>,
+    free_slots: Vec<u32>,
+    agents: Vec<SimulatorAgent>,
+    ids: Vec<AgentId>,
+    pending: Vec<(AgentId, Option<SimulatorAgent>)>,
+}
+
+impl Simulator {
+    #[must_use]
+    pub fn new(params: SimulatorParams) -> Self {
+        Self {
+            params,
+            slots: Vec::new(),
+            free_slots: Vec::new(),
+            agents: Vec::new(),
+            ids: Vec::new(),
+            pending: Vec::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn params(&self) -> &SimulatorParams {
+        &self.params
+    }
+
+    // Returns the number of active agents, agents waiting for the next step aren't counted
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.agents.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.agents.is_empty()
+    }
+
+    // Adds the agent to the simulation from the next step on
+    // The id can be used right away, the agent can be read and modified before it's active.
+    #[allow(clippy::missing_panics_doc)]
+    pub fn add_agent(&mut self, agent: SimulatorAgent) -> AgentId {
+        let index = self.free_slots.pop().unwrap_or_else(|| {
+            self.slots.push(Slot {
+                generation: 0,
+                state: SlotState::Free,
+            });
+
+            u32::try_from(self.slots.len() - 1).expect("Too many agents")
+        });
+
+        let slot = &mut self.slots[index as usize];
+        let id = AgentId {
+            index,
+            generation: slot.generation,
+        };
+
+        slot.state = SlotState::Pending(self.pending.len());
+        self.pending.push((id, Some(agent)));
+
+        id
+    }
+
+    // Removes the agent in constant time, returns it if it existed
+    pub fn remove_agent(&mut self, id: AgentId) -> Option<SimulatorAgent> {
+        let state = self.get_state(id)?;
+
+        let slot = &mut self.slots[id.index as usize];
+        slot.state = SlotState::Free;
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free_slots.push(id.index);
+
+        match state {
+            SlotState::Free => None,
+            // The pending list keeps its order, the entry is skipped when the agents are added
+            SlotState::Pending(index) => self.pending[index].1.take(),
+            SlotState::Active(index) => {
+                let agent = self.agents.swap_remove(index);
+                self.ids.swap_remove(index);
+
+                if let Some(moved) = self.ids.get(index) {
+                    self.slots[moved.index as usize].state = SlotState::Active(index);
+                }
+
+                Some(agent)
+            }
+        }
+    }
+
+    #[must_use]
+    pub fn contains(&self, id: AgentId) -> bool {
+        self.get_state(id).is_some()
+    }
+
+    // Returns true if the agent takes part in the simulation, false if it waits for the next step
+    #[must_use]
+    pub fn is_active(&self, id: AgentId) -> bool {
+        matches!(self.get_state(id), Some(SlotState::Active(_)))
+    }
+
+    #[must_use]
+    pub fn get(&self, id: AgentId) -> Option<&SimulatorAgent> {
+        match self.get_state(id)? {
+            SlotState::Free => None,
+            SlotState::Pending(index) => self.pending[index].1.as_ref(),
+            SlotState::Active(index) => Some(&self.agents[index]),
+        }
+    }
+
+    pub fn get_mut(&mut self, id: AgentId) -> Option<&mut SimulatorAgent> {
+        match self.get_state(id)? {
+            SlotState::Free => None,
+            SlotState::Pending(index) => self.pending[index].1.as_mut(),
+            SlotState::Active(index) => Some(&mut self.agents[index]),
+        }
+    }
+
+    // Iterates over the active agents in no particular order
+    pub fn agents(&self) -> impl Iterator<Item = (AgentId, &SimulatorAgent)> {
+        self.ids.iter().copied().zip(self.agents.iter())
+    }
+
+    pub fn agents_mut(&mut self) -> impl Iterator<Item = (AgentId, &mut SimulatorAgent)> {
+        self.ids.iter().copied().zip(self.agents.iter_mut())
+    }
+
+    // Activates the pending agents, computes the avoidance velocity of every agent and moves them
+    // The velocities are computed from the state at the start of the step before any agent moves.
+    #[allow(clippy::missing_panics_doc)]
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "debug", skip_all, fields(agents = self.agents.len()))
+    )]
+    pub fn step(&mut self, time_step: f32) {
+        assert!(time_step > 0.0);
+
+        self.activate_pending_agents();
+
+        #[cfg(any(debug_assertions, feature = "sanitize"))]
+        self.check_agents();
+
+        let grid = self.build_grid();
+
+        let velocities = (0..self.agents.len())
+            .map(|index| self.get_avoidance_velocity(&grid, index, time_step))
+            .collect::<Vec<_>>();
+
+        for (agent, velocity) in self.agents.iter_mut().zip(velocities) {
+            agent.agent.velocity = velocity;
+            agent.agent.position += velocity * time_step;
+        }
+    }
+
+    fn get_state(&self, id: AgentId) -> Option<SlotState> {
+        let slot = self.slots.get(id.index as usize)?;
+
+        (slot.generation == id.generation && slot.state != SlotState::Free).then_some(slot.state)
+    }
+
+    // Finds the agent a non-finite value comes from before it spreads to its neighbours through
+    // their velocity obstacles. Debug builds panic, with the sanitize feature the velocities of
+    // the agent are reset and it stops instead. Positions can't be repaired, the planes against
+    // such an agent are left out by the solver.
+    #[cfg(any(debug_assertions, feature = "sanitize"))]
+    #[cfg_attr(
+        all(feature = "sanitize", not(feature = "tracing")),
+        allow(unused_variables)
+    )]
+    fn check_agents(&mut self) {
+        for (id, agent) in self.ids.iter().zip(&mut self.agents) {
+            let field = agent.find_non_finite_field();
+
+            #[cfg(not(feature = "sanitize"))]
+            debug_assert!(
+                field.is_none(),
+                "Agent {id:?} has a non-finite {}",
+                field.unwrap()
+            );
+
+            #[cfg(feature = "sanitize")]
+            if let Some(field) = field {
+                #[cfg(feature = "tracing")]
+                tracing::warn!(agent = ?id, field, "sanitizing non-finite agent");
+
+                if !agent.agent.velocity.is_finite() {
+                    agent.agent.velocity = Vec3::ZERO;
+                }
+
+                if !agent.preferred_velocity.is_finite() || !agent.max_speed.is_finite() {
+                    agent.preferred_velocity = Vec3::ZERO;
+                    agent.max_speed = 0.0;
+                }
+
+                if !agent.agent.responsibility.is_finite() {
+                    agent.agent.responsibility = 0.5;
+                }
+            }
+        }
+    }
+
+    fn activate_pending_agents(&mut self) {
+        for (id, agent) in std::mem::take(&mut self.pending) {
+            // Agents removed before they became active left an empty entry
+            let Some(agent) = agent else {
+                continue;
+            };
+
+            self.slots[id.index as usize].state = SlotState::Active(self.agents.len());
+            self.agents.push(agent);
+            self.ids.push(id);
+        }
+    }
+
+    fn build_grid(&self) -> HashMap<IVec3, Vec<usize>> {
+        let mut grid = HashMap::<IVec3, Vec<usize>>::new();
+
+        for (index, agent) in self.agents.iter().enumerate() {
+            grid.entry(self.cell_of(agent.agent.position))
+                .or_default()
+                .push(index);
+        }
+
+        grid
+    }
+
+    fn cell_of(&self, position: Vec3) -> IVec3 {
+        (position / self.params.neighbour_distance)
+            .floor()
+            .as_ivec3()
+    }
+
+    fn get_avoidance_velocity(
+        &self,
+        grid: &HashMap<IVec3, Vec<usize>>,
+        index: usize,
+        time_step: f32,
+    ) -> Vec3 {
+        let agent = &self.agents[index];
+        let position = agent.agent.position;
+        let cell = self.cell_of(position);
+
+        let mut neighbours = Vec::new();
+
+        for z in -1..=1 {
+            for y in -1..=1 {
+                for x in -1..=1 {
+                    let Some(indexes) = grid.get(&(cell + IVec3::new(x, y, z))) else {
+                        continue;
+                    };
+
+                    neighbours.extend(indexes.iter().copied().filter(|&other| {
+                        other != index
+                            && self.agents[other].agent.position.distance(position)
+                                <= self.params.neighbour_distance
+                    }));
+                }
+            }
+        }
+
+        neighbours.sort_by(|&a, &b| {
+            self.agents[a]
+                .agent
+                .position
+                .distance_squared(position)
+                .total_cmp(&self.agents[b].agent.position.distance_squared(position))
+        });
+
+        let planes = neighbours
+            .iter()
+            .take(self.params.max_neighbours)
+            .filter_map(|&other| {
+                let other = &self.agents[other];
+
+                self.params.get_policy(agent, other).orca_plane(
+                    &agent.agent,
+                    &other.agent,
+                    self.params.time_horizon,
+                    time_step,
+                )
+            })
+            .collect::<Vec<Plane>>();
+
+        optimize_velocity_3d(agent.preferred_velocity, agent.max_speed, &planes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use geometry::colliders::Collider;
+
+    use super::*;
+
+    fn create_agent(position: Vec3) -> SimulatorAgent {
+        SimulatorAgent::new(
+            Agent3D::new(position, Vec3::ZERO, Collider::new_sphere(1.0)),
+            10.0,
+        )
+    }
+
+    #[test]
+    fn test_agent_id_bits_round_trip() {
+        let mut simulator = Simulator::new(SimulatorParams::new(2.0, 20.0));
+        let first = simulator.add_agent(create_agent(Vec3::ZERO));
+        simulator.step(0.1);
+        simulator.remove_agent(first);
+        let second = simulator.add_agent(create_agent(Vec3::ZERO));
+
+        assert_eq!(AgentId::from_bits(second.to_bits()), second);
+        assert_ne!(second.to_bits(), first.to_bits());
+    }
+
+    #[test]
+    fn test_agents_join_on_the_next_step() {
+        let mut simulator = Simulator::new(SimulatorParams::new(2.0, 20.0));
+        let id = simulator.add_agent(create_agent(Vec3::ZERO));
+
+        assert!(simulator.contains(id));
+        assert!(!simulator.is_active(id));
+        assert_eq!(simulator.len(), 0);
+
+        simulator.get_mut(id).unwrap().preferred_velocity = Vec3::new(1.0, 0.0, 0.0);
+        simulator.step(0.5);
+
+        assert!(simulator.is_active(id));
+        assert_eq!(simulator.len(), 1);
+        assert!(
+            simulator
+                .get(id)
+                .unwrap()
+                .agent
+                .position
+                .distance(Vec3::new(0.5, 0.0, 0.0))
+                < 1e-4
+        );
+    }
+
+    #[test]
+    fn test_removed_ids_stay_invalid() {
+        let mut simulator = Simulator::new(SimulatorParams::new(2.0, 20.0));
+        let first = simulator.add_agent(create_agent(Vec3::ZERO));
+        let second = simulator.add_agent(create_agent(Vec3::new(5.0, 0.0, 0.0)));
+        let third = simulator.add_agent(create_agent(Vec3::new(10.0, 0.0, 0.0)));
+        simulator.step(0.1);
+
+        assert!(simulator.remove_agent(first).is_some());
+        assert!(simulator.remove_agent(first).is_none());
+
+        // The slot of the first agent is reused, but the old id doesn't see the new agent
+        let fourth = simulator.add_agent(create_agent(Vec3::new(20.0, 0.0, 0.0)));
+        assert_eq!(fourth.index, first.index);
+        assert!(simulator.get(first).is_none());
+
+        // The third agent was moved into the freed place and can still be found
+        assert_eq!(
+            simulator.get(third).unwrap().agent.position,
+            Vec3::new(10.0, 0.0, 0.0)
+        );
+        assert_eq!(
+            simulator.get(second).unwrap().agent.position,
+            Vec3::new(5.0, 0.0, 0.0)
+        );
+
+        // An agent removed before it became active never joins
+        assert!(simulator.remove_agent(fourth).is_some());
+        simulator.step(0.1);
+
+        assert_eq!(simulator.len(), 2);
+        assert_eq!(simulator.agents().count(), 2);
+    }
+
+    #[test]
+    fn test_pair_policies_override_agent_policies() {
+        let avo = AvoidancePolicy::AccelerationVelocityObstacle {
+            acc_control_param: 1.0,
+            discrete_steps: 10,
+        };
+
+        let params = SimulatorParams::new(2.0, 20.0).with_pair_policy(1, 2, avo);
+        let fighter = create_agent(Vec3::ZERO).with_class(1);
+        let station = create_agent(Vec3::ZERO).with_class(2);
+        let drone = create_agent(Vec3::ZERO).with_class(3);
+
+        assert_eq!(params.get_policy(&fighter, &station), avo);
+        assert_eq!(
+            params.get_policy(&station, &fighter),
+            AvoidancePolicy::VelocityObstacle
+        );
+        assert_eq!(
+            params.get_policy(&fighter, &drone),
+            AvoidancePolicy::VelocityObstacle
+        );
+    }
+
+    #[test]
+    fn test_agents_avoid_each_other() {
+        let mut simulator = Simulator::new(SimulatorParams::new(2.0, 20.0));
+        let left = simulator.add_agent(create_agent(Vec3::new(-5.0, 0.5, 0.0)));
+        let right = simulator.add_agent(create_agent(Vec3::new(5.0, 0.0, 0.0)));
+
+        simulator.get_mut(left).unwrap().preferred_velocity = Vec3::new(10.0, 0.0, 0.0);
+        simulator.get_mut(right).unwrap().preferred_velocity = Vec3::new(-10.0, 0.0, 0.0);
+
+        for _ in 0..20 {
+            simulator.step(0.05);
+
+            let distance = simulator
+                .get(left)
+                .unwrap()
+                .agent
+                .position
+                .distance(simulator.get(right).unwrap().agent.position);
+
+            assert!(distance >= 2.0 - 1e-2);
+        }
+    }
+
+    #[cfg(all(debug_assertions, not(feature = "sanitize")))]
+    #[test]
+    #[should_panic(expected = "has a non-finite preferred velocity")]
+    fn test_non_finite_agent_is_reported() {
+        let mut simulator = Simulator::new(SimulatorParams::new(2.0, 20.0));
+        let id = simulator.add_agent(create_agent(Vec3::ZERO));
+        simulator.get_mut(id).unwrap().preferred_velocity = Vec3::new(f32::NAN, 0.0, 0.0);
+
+        simulator.step(0.1);
+    }
+
+    #[cfg(feature = "sanitize")]
+    #[test]
+    fn test_non_finite_agent_is_sanitized() {
+        let mut simulator = Simulator::new(SimulatorParams::new(2.0, 20.0));
+        let broken = simulator.add_agent(create_agent(Vec3::ZERO));
+        let other = simulator.add_agent(create_agent(Vec3::new(3.0, 0.0, 0.0)));
+        simulator.get_mut(broken).unwrap().preferred_velocity = Vec3::new(f32::NAN, 0.0, 0.0);
+        simulator.get_mut(other).unwrap().preferred_velocity = Vec3::new(-1.0, 0.0, 0.0);
+
+        simulator.step(0.1);
+
+        assert!(simulator.get(broken).unwrap().agent.velocity.length() < 1e-3);
+        assert!(simulator.get(other).unwrap().agent.velocity.is_finite());
+    }
+
+    // The simulator is moved into parallel ECS systems
+    #[test]
+    fn test_types_are_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+
+        assert_send_sync::<Simulator>();
+        assert_send_sync::<AgentId>();
+    }
+}
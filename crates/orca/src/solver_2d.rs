@@ -113,7 +113,7 @@ pub(crate) fn incremental_optimization_2d(
             }
 
             if (min_bound - max_bound).abs() < EPSILON {
-                let avg = (min_bound + max_bound) / 2.0;
+                let avg = f32::midpoint(min_bound, max_bound);
                 min_bound = avg;
                 max_bound = avg;
             }
@@ -126,15 +126,11 @@ pub(crate) fn incremental_optimization_2d(
                 };
             }
 
-            // Now we have the bounds of t, we will find find the closest point on the half plane
-            // within these bounds.
+            // Now we have the bounds of t, we will find find the closest point to the preffered
+            // velocity on the half plane within these bounds.
             let line_segment = LineSegment2D::new(point, direction, min_bound, max_bound);
-            optimal_velocity = line_segment.constrain(optimal_velocity);
-        } else if half_plane.contains(optimal_velocity) {
-            // If the intersection is None, but the half plane contains the optimal velocity
-            // we will skip this half plane.
-            continue;
-        } else {
+            optimal_velocity = line_segment.constrain(preffered_velocity);
+        } else if !half_plane.contains(optimal_velocity) {
             // If the intersection is None and the half plane doesn't contain the optimal velocity
             // we will return None as the optimization is invalid.
             return OptimizationResult2D::Infeasible {
@@ -145,3 +141,31 @@ pub(crate) fn incremental_optimization_2d(
 
     OptimizationResult2D::Feasible { optimal_velocity }
 }
+
+#[cfg(test)]
+mod tests {
+    use geometry::Circle;
+
+    use super::*;
+
+    #[test]
+    fn test_optimum_is_closest_to_the_preffered_velocity() {
+        // The first half-plane moves the optimum to (0, 1), the closest point of the second line
+        // to it would be (-0.5, 0.5), but (-1, 1) is closer to the preffered velocity
+        let half_planes = [
+            HalfPlane::new(Vec2::Y, -Vec2::Y),
+            HalfPlane::new(Vec2::ZERO, Vec2::new(-1.0, -1.0)),
+        ];
+
+        let result = incremental_optimization_2d(
+            Vec2::Y * 5.0,
+            &Circle::new(10.0, Vec2::ZERO),
+            &half_planes,
+        );
+
+        let OptimizationResult2D::Feasible { optimal_velocity } = result else {
+            panic!("Expected a feasible result, got {result:?}");
+        };
+        assert!((optimal_velocity - Vec2::new(-1.0, 1.0)).length() < 1e-3);
+    }
+}
@@ -33,6 +33,10 @@ where
     }
 }
 
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "trace", skip_all, fields(planes = planes.len()))
+)]
 pub fn incremental_optimization_3d(
     preffered_velocity: Vec3,
     bounding_shape: &impl MaximumVelocityShape3D,
@@ -64,12 +68,18 @@ pub fn incremental_optimization_3d(
 
         let bounding_shape_2d = bounding_shape_2d.unwrap();
 
-        let (optimal_velocity_on_plane, _) = plane.closest_point_and_normal(optimal_velocity);
+        // The closest point on the plane to the preffered velocity, not to the previous optimum
+        let (optimal_velocity_on_plane, _) = plane.closest_point_and_normal(preffered_velocity);
         let optimal_velocity_on_plane = plane.project_2d(optimal_velocity_on_plane);
 
         for plane_j in planes.iter().take(i) {
             if let Some(half_plane) = HalfPlane::from_plane_intersection(plane, plane_j) {
                 half_planes.push(half_plane);
+            } else if !plane_j.contains(plane.origin) {
+                // The planes are parallel and the previous one excludes the whole current plane
+                return OptimizationResult3D::Infeasible {
+                    last_optimal_velocity: optimal_velocity,
+                };
             }
         }
 
@@ -93,3 +103,57 @@ pub fn incremental_optimization_3d(
 
     OptimizationResult3D::Feasible { optimal_velocity }
 }
+
+#[cfg(test)]
+mod tests {
+    use geometry::Sphere;
+
+    use super::*;
+
+    #[test]
+    fn test_optimum_is_closest_to_the_preffered_velocity() {
+        // The first plane moves the optimum to (0, 1, 0), but the closest point of the second
+        // plane to the preffered velocity is further along the first one
+        let planes = [
+            Plane::new(Vec3::Y, -Vec3::Y),
+            Plane::new(Vec3::ZERO, Vec3::new(-1.0, -1.0, 0.0).normalize()),
+        ];
+
+        let result =
+            incremental_optimization_3d(Vec3::Y * 5.0, &Sphere::new(10.0, Vec3::ZERO), &planes);
+
+        let OptimizationResult3D::Feasible { optimal_velocity } = result else {
+            panic!("Expected a feasible result, got {result:?}");
+        };
+        assert!((optimal_velocity - Vec3::new(-1.0, 1.0, 0.0)).length() < 1e-3);
+    }
+
+    #[test]
+    fn test_opposing_parallel_planes_are_infeasible() {
+        let planes = [
+            Plane::new(Vec3::X, Vec3::X),
+            Plane::new(Vec3::ZERO, -Vec3::X),
+        ];
+
+        let result =
+            incremental_optimization_3d(Vec3::ZERO, &Sphere::new(10.0, Vec3::ZERO), &planes);
+
+        assert!(matches!(result, OptimizationResult3D::Infeasible { .. }));
+    }
+
+    #[test]
+    fn test_overlapping_parallel_planes_are_feasible() {
+        let planes = [
+            Plane::new(Vec3::X, Vec3::X),
+            Plane::new(Vec3::X * 2.0, -Vec3::X),
+        ];
+
+        let result =
+            incremental_optimization_3d(Vec3::ZERO, &Sphere::new(10.0, Vec3::ZERO), &planes);
+
+        let OptimizationResult3D::Feasible { optimal_velocity } = result else {
+            panic!("Expected a feasible result, got {result:?}");
+        };
+        assert!((optimal_velocity - Vec3::X).length() < 1e-3);
+    }
+}
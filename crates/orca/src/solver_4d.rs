@@ -29,6 +29,10 @@ where
 }
 
 #[allow(clippy::missing_panics_doc)]
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "trace", skip_all, fields(hyperplanes = hyperplanes.len()))
+)]
 pub fn incremental_optimization_4d(
     preffered_velocity: Vec4,
     bounding_shape: &impl MaximumVelocityShape4D,
@@ -61,7 +65,8 @@ pub fn incremental_optimization_4d(
 
         let bounding_shape_3d = bounding_shape_3d.unwrap();
 
-        let optimal_velocity_on_hyperplane = hyperplane.constrain(optimal_velocity);
+        // The closest point on the hyperplane to the preffered velocity, not to the previous optimum
+        let optimal_velocity_on_hyperplane = hyperplane.constrain(preffered_velocity);
         let optimal_velocity_on_hyperplane = hyperplane.project_3d(optimal_velocity_on_hyperplane);
 
         for hyperplaneplane_j in hyperplanes.iter().take(i) {
@@ -91,3 +96,31 @@ pub fn incremental_optimization_4d(
 
     OptimizationResult4D::Feasible { optimal_velocity }
 }
+
+#[cfg(test)]
+mod tests {
+    use geometry::Spherinder;
+
+    use super::*;
+
+    #[test]
+    fn test_optimum_is_closest_to_the_preffered_velocity() {
+        // The hyperplanes allow w >= y - 1 and w >= x + y, the first one alone moves the optimum
+        // to (0, 3, 0, 2) but with both of them it's at (-1, 3, 0, 2)
+        let hyperplanes = [
+            Hyperplane::new(Vec4::Y, Vec4::new(0.0, -1.0, 0.0, 1.0).normalize()),
+            Hyperplane::new(Vec4::ZERO, Vec4::new(-1.0, -1.0, 0.0, 1.0).normalize()),
+        ];
+
+        let result = incremental_optimization_4d(
+            Vec4::Y * 5.0,
+            &Spherinder::new(Vec4::ZERO, 10.0),
+            &hyperplanes,
+        );
+
+        let OptimizationResult4D::Feasible { optimal_velocity } = result else {
+            panic!("Expected a feasible result, got {result:?}");
+        };
+        assert!((optimal_velocity - Vec4::new(-1.0, 3.0, 0.0, 2.0)).length() < 1e-3);
+    }
+}
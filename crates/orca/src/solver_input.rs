@@ -0,0 +1,186 @@
+use std::{borrow::Cow, fmt};
+
+use bevy_math::Vec3;
+use geometry::Plane;
+
+// A solver input that isn't finite
+// Planes are identified by their index in the input of the solver.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NonFiniteInput {
+    PreferredVelocity,
+    MaximumVelocity,
+    Plane(usize),
+}
+
+impl fmt::Display for NonFiniteInput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::PreferredVelocity => write!(f, "preferred velocity"),
+            Self::MaximumVelocity => write!(f, "maximum velocity"),
+            Self::Plane(index) => write!(f, "plane {index}"),
+        }
+    }
+}
+
+// Returns the first input of optimize_velocity_3d that isn't finite
+// A single NaN makes every velocity the solver returns NaN, so it's worth checking the input
+// where it's produced when agents suddenly stop moving.
+#[must_use]
+pub fn find_non_finite_input(
+    preffered_velocity: Vec3,
+    maximum_velocity: f32,
+    planes: &[Plane],
+) -> Option<NonFiniteInput> {
+    find_non_finite(preffered_velocity, maximum_velocity, planes.iter())
+}
+
+pub(crate) fn is_plane_finite(plane: &Plane) -> bool {
+    plane.origin.is_finite() && plane.normal.is_finite()
+}
+
+fn find_non_finite<'a>(
+    preffered_velocity: Vec3,
+    maximum_velocity: f32,
+    planes: impl Iterator<Item = &'a Plane>,
+) -> Option<NonFiniteInput> {
+    if !preffered_velocity.is_finite() {
+        return Some(NonFiniteInput::PreferredVelocity);
+    }
+
+    if !maximum_velocity.is_finite() {
+        return Some(NonFiniteInput::MaximumVelocity);
+    }
+
+    planes
+        .enumerate()
+        .find(|(_, plane)| !is_plane_finite(plane))
+        .map(|(index, _)| NonFiniteInput::Plane(index))
+}
+
+// Checks the input of a solver before it's used
+// Debug builds panic on the first non-finite value. With the sanitize feature the input is
+// repaired instead: a non-finite preferred velocity becomes zero, a non-finite maximum velocity
+// makes the agent stop and non-finite planes are left out.
+#[cfg_attr(
+    all(feature = "sanitize", not(feature = "tracing")),
+    allow(unused_variables)
+)]
+pub(crate) fn check_solver_input<T: Clone>(
+    preffered_velocity: Vec3,
+    maximum_velocity: f32,
+    constraints: &[T],
+    get_plane: impl Fn(&T) -> &Plane,
+) -> (Vec3, f32, Cow<'_, [T]>) {
+    let non_finite = find_non_finite(
+        preffered_velocity,
+        maximum_velocity,
+        constraints.iter().map(&get_plane),
+    );
+
+    #[cfg(not(feature = "sanitize"))]
+    {
+        debug_assert!(
+            non_finite.is_none(),
+            "Non-finite solver input: {}",
+            non_finite.unwrap()
+        );
+
+        (
+            preffered_velocity,
+            maximum_velocity,
+            Cow::Borrowed(constraints),
+        )
+    }
+
+    #[cfg(feature = "sanitize")]
+    {
+        let Some(non_finite) = non_finite else {
+            return (
+                preffered_velocity,
+                maximum_velocity,
+                Cow::Borrowed(constraints),
+            );
+        };
+
+        #[cfg(feature = "tracing")]
+        tracing::warn!(input = %non_finite, "sanitizing non-finite solver input");
+
+        let preffered_velocity = if preffered_velocity.is_finite() {
+            preffered_velocity
+        } else {
+            Vec3::ZERO
+        };
+
+        let maximum_velocity = if maximum_velocity.is_finite() {
+            maximum_velocity
+        } else {
+            0.0
+        };
+
+        let constraints = constraints
+            .iter()
+            .filter(|constraint| is_plane_finite(get_plane(constraint)))
+            .cloned()
+            .collect();
+
+        (
+            preffered_velocity,
+            maximum_velocity,
+            Cow::Owned(constraints),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_non_finite_input() {
+        let planes = [
+            Plane::new(Vec3::X, Vec3::X),
+            Plane::new(Vec3::new(f32::NAN, 0.0, 0.0), Vec3::X),
+        ];
+
+        assert_eq!(find_non_finite_input(Vec3::X, 1.0, &planes[..1]), None);
+        assert_eq!(
+            find_non_finite_input(Vec3::X, 1.0, &planes),
+            Some(NonFiniteInput::Plane(1))
+        );
+        assert_eq!(
+            find_non_finite_input(Vec3::X, f32::INFINITY, &planes[..1]),
+            Some(NonFiniteInput::MaximumVelocity)
+        );
+        assert_eq!(
+            find_non_finite_input(Vec3::splat(f32::NAN), 1.0, &planes),
+            Some(NonFiniteInput::PreferredVelocity)
+        );
+    }
+
+    #[cfg(feature = "sanitize")]
+    #[test]
+    fn test_non_finite_input_is_sanitized() {
+        let planes = [
+            Plane::new(Vec3::X * 0.5, -Vec3::X),
+            Plane::new(Vec3::new(f32::NAN, 0.0, 0.0), Vec3::X),
+        ];
+
+        let velocity = crate::optimize_velocity_3d(Vec3::X, 1.0, &planes);
+        assert!((velocity - Vec3::X * 0.5).length() < 1e-3);
+
+        let velocity = crate::optimize_velocity_3d(Vec3::splat(f32::NAN), 1.0, &planes);
+        assert!(velocity.is_finite());
+    }
+
+    #[cfg(all(debug_assertions, not(feature = "sanitize")))]
+    #[test]
+    #[should_panic(expected = "Non-finite solver input: plane 1")]
+    fn test_non_finite_input_panics_in_debug_builds() {
+        let planes = [
+            Plane::new(Vec3::X * 0.5, -Vec3::X),
+            Plane::new(Vec3::new(f32::NAN, 0.0, 0.0), Vec3::X),
+        ];
+
+        let _ = crate::optimize_velocity_3d(Vec3::X, 1.0, &planes);
+    }
+}
@@ -0,0 +1,119 @@
+use bevy_math::Vec3;
+use geometry::{check_planes_satisfied, InvariantViolation, Plane};
+
+// Checks the result of optimize_velocity_3d for a feasible problem
+// The velocity has to be within the maximum velocity, on the allowed side of every plane and
+// equal to the preferred velocity when that one was allowed already. Infeasible problems are
+// relaxed by the solver, so their planes can't all be satisfied and only the speed holds.
+#[allow(clippy::missing_errors_doc)]
+pub fn check_optimized_velocity(
+    preffered_velocity: Vec3,
+    maximum_velocity: f32,
+    planes: &[Plane],
+    velocity: Vec3,
+    tolerance: f32,
+) -> Result<(), InvariantViolation> {
+    if velocity.length() > maximum_velocity + tolerance {
+        return Err(InvariantViolation::SpeedExceeded {
+            velocity,
+            maximum_velocity,
+        });
+    }
+
+    check_planes_satisfied(velocity, planes, tolerance)?;
+
+    let is_preferred_allowed = preffered_velocity.length() <= maximum_velocity
+        && check_planes_satisfied(preffered_velocity, planes, 0.0).is_ok();
+
+    if is_preferred_allowed && velocity.distance(preffered_velocity) > tolerance {
+        return Err(InvariantViolation::PreferredVelocityChanged {
+            preferred_velocity: preffered_velocity,
+            velocity,
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use geometry::INVARIANT_TOLERANCE;
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::optimize_velocity_3d;
+
+    fn vec3(range: f32) -> impl Strategy<Value = Vec3> {
+        (-range..range, -range..range, -range..range).prop_map(|(x, y, z)| Vec3::new(x, y, z))
+    }
+
+    fn direction() -> impl Strategy<Value = Vec3> {
+        vec3(1.0)
+            .prop_filter("direction can't be zero", |d| d.length() > 0.1)
+            .prop_map(Vec3::normalize)
+    }
+
+    // Planes that all keep a ball around zero velocity allowed, so the problem is feasible
+    fn feasible_planes() -> impl Strategy<Value = Vec<Plane>> {
+        prop::collection::vec((direction(), 0.1_f32..5.0), 0..6).prop_map(|planes| {
+            planes
+                .into_iter()
+                .map(|(normal, distance)| Plane::new(-normal * distance, normal))
+                .collect()
+        })
+    }
+
+    fn any_planes() -> impl Strategy<Value = Vec<Plane>> {
+        prop::collection::vec((vec3(10.0), direction()), 0..6).prop_map(|planes| {
+            planes
+                .into_iter()
+                .map(|(origin, normal)| Plane::new(origin, normal))
+                .collect()
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn feasible_problems_satisfy_all_planes(
+            preffered_velocity in vec3(20.0),
+            maximum_velocity in 1.0_f32..10.0,
+            planes in feasible_planes(),
+        ) {
+            let velocity = optimize_velocity_3d(preffered_velocity, maximum_velocity, &planes);
+
+            prop_assert_eq!(
+                check_optimized_velocity(
+                    preffered_velocity,
+                    maximum_velocity,
+                    &planes,
+                    velocity,
+                    INVARIANT_TOLERANCE,
+                ),
+                Ok(())
+            );
+        }
+
+        #[test]
+        fn velocity_never_exceeds_maximum(
+            preffered_velocity in vec3(20.0),
+            maximum_velocity in 1.0_f32..10.0,
+            planes in any_planes(),
+        ) {
+            let velocity = optimize_velocity_3d(preffered_velocity, maximum_velocity, &planes);
+
+            prop_assert!(velocity.is_finite());
+            prop_assert!(velocity.length() <= maximum_velocity + INVARIANT_TOLERANCE);
+        }
+    }
+
+    #[test]
+    fn test_changed_preferred_velocity_is_reported() {
+        assert_eq!(
+            check_optimized_velocity(Vec3::X, 2.0, &[], Vec3::Y, INVARIANT_TOLERANCE),
+            Err(InvariantViolation::PreferredVelocityChanged {
+                preferred_velocity: Vec3::X,
+                velocity: Vec3::Y,
+            })
+        );
+    }
+}
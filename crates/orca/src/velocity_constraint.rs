@@ -0,0 +1,246 @@
+use bevy_math::{Vec3, Vec4};
+use geometry::{Hyperplane, Plane, Sphere, Spherinder};
+
+use crate::{
+    solver_3d::{incremental_optimization_3d, OptimizationResult3D},
+    solver_4d::{incremental_optimization_4d, OptimizationResult4D},
+    solver_input::check_solver_input,
+};
+
+// Hard constraints (collisions) are always satisfied when they are feasible together,
+// soft constraints (comfort spacing, formation keeping) can be violated under pressure.
+// The weight of a soft constraint says how much it resists the violation, a constraint
+// with twice the weight is violated half as much.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConstraintHardness {
+    Hard,
+    Soft { weight: f32 },
+}
+
+// Half-space of admissible velocities together with its hardness
+#[derive(Debug, Clone)]
+pub struct VelocityConstraint {
+    pub plane: Plane,
+    pub hardness: ConstraintHardness,
+}
+
+impl VelocityConstraint {
+    #[must_use]
+    pub fn hard(plane: Plane) -> Self {
+        Self {
+            plane,
+            hardness: ConstraintHardness::Hard,
+        }
+    }
+
+    #[must_use]
+    #[allow(clippy::missing_panics_doc)]
+    pub fn soft(plane: Plane, weight: f32) -> Self {
+        assert!(weight > 0.0, "Soft constraints need a positive weight");
+
+        Self {
+            plane,
+            hardness: ConstraintHardness::Soft { weight },
+        }
+    }
+
+    #[must_use]
+    pub fn is_hard(&self) -> bool {
+        self.hardness == ConstraintHardness::Hard
+    }
+
+    // How much the plane moves per unit of the relaxation variable in the 4d problem
+    fn relaxation(&self) -> f32 {
+        match self.hardness {
+            ConstraintHardness::Hard => 0.0,
+            ConstraintHardness::Soft { weight } => 0.5 / weight,
+        }
+    }
+}
+
+// Lifts the plane to 4d where the w coordinate relaxes it, the larger the relaxation the
+// further the plane moves for the same w
+pub(crate) fn relaxed_hyperplane(plane: &Plane, relaxation: f32) -> Hyperplane {
+    Hyperplane::new(
+        Vec4::new(plane.origin.x, plane.origin.y, plane.origin.z, 0.0),
+        Vec4::new(plane.normal.x, plane.normal.y, plane.normal.z, relaxation),
+    )
+}
+
+// Same as optimize_velocity_3d, but only the soft constraints are relaxed when the problem is
+// infeasible. The soft constraints are then violated in the least-violation sense: the largest
+// violation scaled by the weight is minimized. When even the hard constraints can't be satisfied
+// together, all of them are relaxed equally like in optimize_velocity_3d.
+#[must_use]
+pub fn optimize_velocity_3d_with_constraints(
+    preffered_velocity: Vec3,
+    maximum_velocity: f32,
+    constraints: &[VelocityConstraint],
+) -> Vec3 {
+    optimize_velocity_in_sphere(
+        preffered_velocity,
+        &Sphere::new(maximum_velocity, Vec3::ZERO),
+        constraints,
+    )
+}
+
+// Solves the weighted problem with an arbitrary sphere bounding the admissible velocities
+// The problem is shifted so that the sphere is centered at the origin, which is what the
+// spherinder of the 4d fallback expects.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "trace", skip_all, fields(constraints = constraints.len()))
+)]
+pub(crate) fn optimize_velocity_in_sphere(
+    preffered_velocity: Vec3,
+    bounding_sphere: &Sphere,
+    constraints: &[VelocityConstraint],
+) -> Vec3 {
+    let (preffered_velocity, radius, constraints) = check_solver_input(
+        preffered_velocity,
+        bounding_sphere.radius,
+        constraints,
+        |constraint| &constraint.plane,
+    );
+
+    #[cfg(not(feature = "sanitize"))]
+    debug_assert!(
+        bounding_sphere.origin.is_finite(),
+        "Non-finite solver input: bounding sphere"
+    );
+
+    #[cfg(not(feature = "sanitize"))]
+    let offset = bounding_sphere.origin;
+
+    #[cfg(feature = "sanitize")]
+    let offset = if bounding_sphere.origin.is_finite() {
+        bounding_sphere.origin
+    } else {
+        Vec3::ZERO
+    };
+
+    // Hard constraints go first so that the incremental solvers satisfy them before the soft ones
+    let ordered = constraints
+        .iter()
+        .filter(|constraint| constraint.is_hard())
+        .chain(
+            constraints
+                .iter()
+                .filter(|constraint| !constraint.is_hard()),
+        )
+        .map(|constraint| VelocityConstraint {
+            plane: Plane::new(constraint.plane.origin - offset, constraint.plane.normal),
+            hardness: constraint.hardness,
+        })
+        .collect::<Vec<_>>();
+
+    let preffered_velocity = preffered_velocity - offset;
+
+    let planes = ordered
+        .iter()
+        .map(|constraint| constraint.plane.clone())
+        .collect::<Vec<_>>();
+
+    let result = incremental_optimization_3d(
+        preffered_velocity,
+        &Sphere::new(radius, Vec3::ZERO),
+        &planes,
+    );
+
+    if let OptimizationResult3D::Feasible { optimal_velocity } = result {
+        return optimal_velocity + offset;
+    }
+
+    #[cfg(feature = "tracing")]
+    tracing::trace!(
+        monotonic_counter.orca_relaxed_solves = 1_u64,
+        "relaxing the soft constraints in 4D"
+    );
+
+    let bounding_spherinder = Spherinder::new(Vec4::ZERO, radius);
+    let preffered_velocity_4d = preffered_velocity.extend(-1000.0);
+
+    let hyperplanes = ordered
+        .iter()
+        .map(|constraint| relaxed_hyperplane(&constraint.plane, constraint.relaxation()))
+        .collect::<Vec<_>>();
+
+    let result = incremental_optimization_4d(
+        preffered_velocity_4d,
+        &bounding_spherinder,
+        hyperplanes.as_slice(),
+    );
+
+    if let OptimizationResult4D::Feasible { optimal_velocity } = result {
+        return optimal_velocity.truncate() + offset;
+    }
+
+    // The hard constraints contradict each other, relax them all equally and drop the soft ones
+    let hyperplanes = ordered
+        .iter()
+        .filter(|constraint| constraint.is_hard())
+        .map(|constraint| relaxed_hyperplane(&constraint.plane, 0.5))
+        .collect::<Vec<_>>();
+
+    let optimal_velocity = match incremental_optimization_4d(
+        preffered_velocity_4d,
+        &bounding_spherinder,
+        hyperplanes.as_slice(),
+    ) {
+        OptimizationResult4D::Feasible { optimal_velocity } => optimal_velocity,
+        OptimizationResult4D::Infeasible {
+            last_optimal_velocity,
+        } => last_optimal_velocity,
+    };
+
+    optimal_velocity.truncate() + offset
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_feasible_constraints_are_all_satisfied() {
+        let constraints = [
+            VelocityConstraint::hard(Plane::new(Vec3::new(1.0, 0.0, 0.0), Vec3::X)),
+            VelocityConstraint::soft(Plane::new(Vec3::new(0.0, 1.0, 0.0), Vec3::Y), 1.0),
+        ];
+
+        let velocity = optimize_velocity_3d_with_constraints(Vec3::ZERO, 10.0, &constraints);
+
+        assert!((velocity - Vec3::new(1.0, 1.0, 0.0)).length() < 1e-3);
+    }
+
+    #[test]
+    fn test_hard_constraint_wins_over_soft() {
+        let constraints = [
+            VelocityConstraint::soft(Plane::new(Vec3::ZERO, -Vec3::X), 1.0),
+            VelocityConstraint::hard(Plane::new(Vec3::new(1.0, 0.0, 0.0), Vec3::X)),
+        ];
+
+        let velocity = optimize_velocity_3d_with_constraints(Vec3::ZERO, 10.0, &constraints);
+
+        assert!((velocity - Vec3::new(1.0, 0.0, 0.0)).length() < 1e-3);
+    }
+
+    #[test]
+    fn test_heavier_soft_constraint_is_violated_less() {
+        // x >= 1 with weight 1 and x <= 0 with weight 3, the weighted violations are equal
+        // at x = 0.25
+        let constraints = [
+            VelocityConstraint::soft(Plane::new(Vec3::new(1.0, 0.0, 0.0), Vec3::X), 1.0),
+            VelocityConstraint::soft(Plane::new(Vec3::ZERO, -Vec3::X), 3.0),
+        ];
+
+        let velocity = optimize_velocity_3d_with_constraints(Vec3::ZERO, 10.0, &constraints);
+
+        assert!((velocity.x - 0.25).abs() < 0.05);
+    }
+
+    #[test]
+    #[should_panic(expected = "Soft constraints need a positive weight")]
+    fn test_soft_constraint_needs_positive_weight() {
+        let _ = VelocityConstraint::soft(Plane::new(Vec3::ZERO, Vec3::X), 0.0);
+    }
+}
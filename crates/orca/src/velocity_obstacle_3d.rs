@@ -58,19 +58,20 @@ impl VelocityObstacle3D {
             (u, normal)
         } else {
             // We'll create a plane centered at the cutoff sphere with a normal pointing towards zero.
+            // The secant plane goes through the circle where the legs of the cone touch the
+            // cutoff shape, its normal points away from the apex at zero relative velocity.
             let is_in_front_of_secant_plane = {
-                let from_cutoff_center_to_relative_position =
-                    self.relative_position - self.relative_position / self.time_horizon;
+                let from_cutoff_center_to_apex = -self.relative_position / self.time_horizon;
 
                 let secant_plane = self
                     .cutoff_shape
-                    .get_secant_plane(from_cutoff_center_to_relative_position);
+                    .get_secant_plane(from_cutoff_center_to_apex);
 
                 let (p, _) = self
                     .cutoff_shape
                     .closest_point_and_normal(from_cutoff_center_to_relative_velocity);
 
-                secant_plane.contains(p)
+                !secant_plane.contains(p)
             };
 
             if is_in_front_of_secant_plane {
@@ -92,6 +93,15 @@ impl VelocityObstacle3D {
 
                 let u = pt + self.relative_position - self.relative_velocity;
 
+                // The cone orients the normal towards the relative velocity, which points inside
+                // when the velocity is in the cone and is ambiguous when it lies on the surface.
+                // The outward normal of the legs always points back towards the apex.
+                let normal = if normal.dot(self.relative_position) > 0.0 {
+                    -normal
+                } else {
+                    normal
+                };
+
                 (u, normal)
             }
         };
@@ -99,3 +109,56 @@ impl VelocityObstacle3D {
         Plane::new(self.agent_velocity + self.responsibility * u, normal)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn velocity_obstacle(relative_velocity: Vec3) -> VelocityObstacle3D {
+        let agent_self = Agent3D::new(Vec3::ZERO, relative_velocity, Collider::new_sphere(1.0));
+        let agent_other = Agent3D::new(Vec3::X * 10.0, Vec3::ZERO, Collider::new_sphere(1.0));
+
+        VelocityObstacle3D::new(&agent_self, &agent_other, 2.0)
+    }
+
+    #[test]
+    fn test_velocity_hitting_the_cutoff_is_pushed_back() {
+        let relative_velocity = Vec3::new(4.5, 0.1, 0.0);
+        let plane = velocity_obstacle(relative_velocity).orca_plane(0.1);
+
+        assert!(plane.normal.x < -0.9);
+        assert!(!plane.contains(Vec3::X * 5.0));
+    }
+
+    #[test]
+    fn test_velocity_inside_the_legs_is_pushed_outwards() {
+        let relative_velocity = Vec3::new(8.0, 1.0, 0.0);
+        let plane = velocity_obstacle(relative_velocity).orca_plane(0.1);
+
+        assert!(plane.normal.y > 0.9);
+        assert!(plane.normal.x < 0.0);
+        assert!(!plane.contains(relative_velocity));
+    }
+
+    #[test]
+    fn test_velocity_on_the_legs_gets_an_outward_normal() {
+        // The legs are tangent to the combined sphere of radius 2 at distance 10
+        let angle = 0.2_f32.asin();
+        let relative_velocity = Vec3::new(angle.cos(), angle.sin(), 0.0) * 8.0;
+        let plane = velocity_obstacle(relative_velocity).orca_plane(0.1);
+
+        assert!(plane.normal.dot(Vec3::new(-angle.sin(), angle.cos(), 0.0)) > 0.99);
+        assert!(plane.contains(relative_velocity + plane.normal * 0.01));
+        assert!(!plane.contains(Vec3::X * 8.0));
+    }
+
+    #[test]
+    fn test_velocity_outside_the_legs_is_allowed() {
+        let relative_velocity = Vec3::new(8.0, 3.0, 0.0);
+        let plane = velocity_obstacle(relative_velocity).orca_plane(0.1);
+
+        assert!(plane.normal.y > 0.9);
+        assert!(plane.normal.x < 0.0);
+        assert!(plane.contains(relative_velocity));
+    }
+}
@@ -0,0 +1,193 @@
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap},
+    hash::Hash,
+};
+
+// A graph that can be searched by A*
+pub trait SearchGraph {
+    type Node: Copy + Eq + Hash;
+
+    // Pushes all nodes reachable from the node together with the cost of getting to them
+    fn successors(&self, node: Self::Node, successors: &mut Vec<(Self::Node, f32)>);
+
+    // Estimated cost of getting from the node to the goal, must never overestimate the real cost
+    fn heuristic(&self, node: Self::Node, goal: Self::Node) -> f32;
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct OpenNode<N> {
+    pub(crate) node: N,
+    pub(crate) cost: f32,
+}
+
+impl<N> PartialEq for OpenNode<N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cost == other.cost
+    }
+}
+
+impl<N> Eq for OpenNode<N> {}
+
+impl<N> PartialOrd for OpenNode<N> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// Reversed, so the BinaryHeap pops the cheapest node first
+impl<N> Ord for OpenNode<N> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.total_cmp(&self.cost)
+    }
+}
+
+// Finds the cheapest path between two nodes of the graph
+// Returns: The nodes of the path including the start and the goal, and the cost of the path
+pub fn a_star<G: SearchGraph>(
+    graph: &G,
+    start: G::Node,
+    goal: G::Node,
+) -> Option<(Vec<G::Node>, f32)> {
+    a_star_with_budget(graph, start, goal, usize::MAX)
+}
+
+// Same as a_star, but gives up once more than max_nodes nodes were reached,
+// which bounds the memory used by the search on large graphs
+pub fn a_star_with_budget<G: SearchGraph>(
+    graph: &G,
+    start: G::Node,
+    goal: G::Node,
+    max_nodes: usize,
+) -> Option<(Vec<G::Node>, f32)> {
+    search(
+        start,
+        |node, successors| graph.successors(node, successors),
+        |node| graph.heuristic(node, goal),
+        |node| node == goal,
+        max_nodes,
+    )
+}
+
+// Best-first search shared by all the A* variants of the crate
+// The search ends at the first popped node that satisfies is_goal.
+pub(crate) fn search<N: Copy + Eq + Hash>(
+    start: N,
+    successors_of: impl Fn(N, &mut Vec<(N, f32)>),
+    heuristic: impl Fn(N) -> f32,
+    is_goal: impl Fn(N) -> bool,
+    max_nodes: usize,
+) -> Option<(Vec<N>, f32)> {
+    let mut open = BinaryHeap::new();
+    let mut costs = HashMap::new();
+    let mut came_from = HashMap::new();
+    let mut successors = Vec::new();
+
+    costs.insert(start, 0.0);
+    open.push(OpenNode {
+        node: start,
+        cost: heuristic(start),
+    });
+
+    while let Some(OpenNode { node, cost }) = open.pop() {
+        let node_cost = costs[&node];
+
+        // Skip stale entries of nodes that were reached more cheaply in the meantime
+        if cost > node_cost + heuristic(node) {
+            continue;
+        }
+
+        if is_goal(node) {
+            return Some((reconstruct_path(&came_from, node), node_cost));
+        }
+
+        successors.clear();
+        successors_of(node, &mut successors);
+
+        for &(successor, step_cost) in &successors {
+            let successor_cost = node_cost + step_cost;
+
+            if costs
+                .get(&successor)
+                .is_none_or(|&existing| successor_cost < existing)
+            {
+                costs.insert(successor, successor_cost);
+                came_from.insert(successor, node);
+
+                if costs.len() > max_nodes {
+                    return None;
+                }
+
+                open.push(OpenNode {
+                    node: successor,
+                    cost: successor_cost + heuristic(successor),
+                });
+            }
+        }
+    }
+
+    None
+}
+
+pub(crate) fn reconstruct_path<N: Copy + Eq + Hash>(came_from: &HashMap<N, N>, goal: N) -> Vec<N> {
+    let mut path = vec![goal];
+    let mut current = goal;
+
+    while let Some(&previous) = came_from.get(&current) {
+        path.push(previous);
+        current = previous;
+    }
+
+    path.reverse();
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Nodes on a line where every node is connected to its neighbours
+    // and there's an expensive shortcut from 0 to 3
+    struct LineGraph;
+
+    impl SearchGraph for LineGraph {
+        type Node = i32;
+
+        fn successors(&self, node: i32, successors: &mut Vec<(i32, f32)>) {
+            if node > 0 {
+                successors.push((node - 1, 1.0));
+            }
+
+            if node < 5 {
+                successors.push((node + 1, 1.0));
+            }
+
+            if node == 0 {
+                successors.push((3, 5.0));
+            }
+        }
+
+        fn heuristic(&self, node: i32, goal: i32) -> f32 {
+            (goal - node).abs() as f32
+        }
+    }
+
+    #[test]
+    fn test_a_star_finds_cheapest_path() {
+        let (path, cost) = a_star(&LineGraph, 0, 4).unwrap();
+
+        assert_eq!(path, vec![0, 1, 2, 3, 4]);
+        assert_eq!(cost, 4.0);
+    }
+
+    #[test]
+    fn test_a_star_gives_up_over_budget() {
+        assert!(a_star_with_budget(&LineGraph, 0, 4, 3).is_none());
+        assert!(a_star_with_budget(&LineGraph, 0, 4, 6).is_some());
+    }
+
+    #[test]
+    fn test_a_star_unreachable() {
+        assert!(a_star(&LineGraph, 0, 10).is_none());
+    }
+}
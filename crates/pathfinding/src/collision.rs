@@ -0,0 +1,78 @@
+use bevy_math::Vec3;
+use geometry::{colliders::Collider, Ray3D, Ray3DIntersection, Vec3Operations};
+
+// Returns true when an agent with the given radius placed at the point doesn't collide with any collider
+pub fn is_point_free(point: Vec3, colliders: &[Collider], agent_radius: f32) -> bool {
+    colliders
+        .iter()
+        .all(|collider| collider.signed_distance(point) >= agent_radius)
+}
+
+// Returns true when an agent with the given radius can move along the segment without hitting
+// any collider. The colliders are inflated by the agent radius and intersected with the segment,
+// the inflated boxes keep their sharp corners so the check is slightly conservative around them.
+pub fn is_segment_free(from: Vec3, to: Vec3, colliders: &[Collider], agent_radius: f32) -> bool {
+    colliders
+        .iter()
+        .all(|collider| segment_hit(from, to, collider, agent_radius).is_none())
+}
+
+// Returns the distance along the segment at which an agent with the given radius
+// moving from its start hits the collider, zero if it collides already at the start
+pub fn segment_hit(from: Vec3, to: Vec3, collider: &Collider, agent_radius: f32) -> Option<f32> {
+    let inflated = collider.minkowski_sum(&Collider::new_sphere(agent_radius));
+
+    if inflated.contains(from) {
+        return Some(0.0);
+    }
+
+    let length = from.distance(to);
+
+    if length <= f32::EPSILON {
+        return None;
+    }
+
+    inflated
+        .intersect_ray(&Ray3D::new(from, to - from))
+        .first_hit()
+        .filter(|&t| t <= length)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_segment_against_inflated_colliders() {
+        let colliders = [
+            Collider::Sphere(geometry::Sphere::new(1.0, Vec3::new(5.0, 0.0, 0.0))),
+            Collider::new_aabb(Vec3::new(0.0, 5.0, 0.0), Vec3::ONE),
+        ];
+
+        assert!(!is_segment_free(
+            Vec3::ZERO,
+            Vec3::new(10.0, 0.0, 0.0),
+            &colliders,
+            0.5
+        ));
+        assert!(is_segment_free(
+            Vec3::ZERO,
+            Vec3::new(3.0, 0.0, 0.0),
+            &colliders,
+            0.5
+        ));
+        assert!(!is_segment_free(
+            Vec3::new(-5.0, 3.7, 0.0),
+            Vec3::new(5.0, 3.7, 0.0),
+            &colliders,
+            0.5
+        ));
+        assert!(is_segment_free(
+            Vec3::new(-5.0, 3.4, 0.0),
+            Vec3::new(5.0, 3.4, 0.0),
+            &colliders,
+            0.5
+        ));
+        assert!(!is_point_free(Vec3::new(5.0, 1.4, 0.0), &colliders, 0.5));
+    }
+}
@@ -0,0 +1,191 @@
+use std::collections::HashMap;
+
+use bevy_math::IVec3;
+
+use crate::{a_star::search, SearchGraph, VoxelGrid};
+
+// Cooperative pathfinding with windowed hierarchical cooperative A* (WHCA*) by Silver
+// Agents plan one after another in space-time and reserve the cells they occupy at every
+// time step of the window, the following agents plan around those reservations. So instead of all
+// agents taking the same shortest route through a chokepoint, the later ones wait or take another way.
+//
+// Reservations are relative to the moment of planning, the agents are expected to replan
+// (after clear) every few steps, at the latest when half of the window has passed.
+#[derive(Clone, Debug)]
+pub struct CooperativePlanner {
+    window: u32,
+    // Agent occupying a cell at a time step
+    cells: HashMap<(IVec3, u32), usize>,
+    // Agent moving between two cells, starting at a time step
+    moves: HashMap<(IVec3, IVec3, u32), usize>,
+}
+
+impl CooperativePlanner {
+    // window: The number of time steps for which the paths are coordinated
+    pub fn new(window: u32) -> Self {
+        assert!(window > 0);
+
+        Self {
+            window,
+            cells: HashMap::new(),
+            moves: HashMap::new(),
+        }
+    }
+
+    pub fn window(&self) -> u32 {
+        self.window
+    }
+
+    // Removes all reservations
+    pub fn clear(&mut self) {
+        self.cells.clear();
+        self.moves.clear();
+    }
+
+    // Removes the reservations of a single agent
+    pub fn clear_agent(&mut self, agent: usize) {
+        self.cells.retain(|_, reserved_by| *reserved_by != agent);
+        self.moves.retain(|_, reserved_by| *reserved_by != agent);
+    }
+
+    // Returns the agent that occupies the cell at the time step
+    pub fn reserved_by(&self, cell: IVec3, time: u32) -> Option<usize> {
+        self.cells.get(&(cell, time)).copied()
+    }
+
+    // Plans a path for the agent around the reservations of the other agents and reserves it
+    // Returns: The cell of the agent at every time step, consecutive equal cells mean the agent waits.
+    // Only the first window steps are coordinated, the rest leads to the goal ignoring other agents.
+    pub fn plan(
+        &mut self,
+        grid: &VoxelGrid,
+        agent: usize,
+        start: IVec3,
+        goal: IVec3,
+    ) -> Option<Vec<IVec3>> {
+        self.clear_agent(agent);
+
+        if grid.is_occupied(start) || grid.is_occupied(goal) {
+            return None;
+        }
+
+        let window = self.window;
+        let wait_cost = grid.voxel_size();
+
+        let (nodes, _) = search(
+            (start, 0),
+            |(cell, time): (IVec3, u32), successors| {
+                let next_time = (time + 1).min(window);
+                let mut moves = Vec::with_capacity(27);
+
+                moves.push((cell, wait_cost));
+                grid.successors(cell, &mut moves);
+
+                for (next, cost) in moves {
+                    if time < window && !self.is_move_allowed(agent, cell, next, time) {
+                        continue;
+                    }
+
+                    successors.push(((next, next_time), cost));
+                }
+            },
+            |(cell, _)| grid.heuristic(cell, goal),
+            |(cell, time)| cell == goal && self.is_free_from(agent, goal, time),
+            usize::MAX,
+        )?;
+
+        let path = nodes.into_iter().map(|(cell, _)| cell).collect::<Vec<_>>();
+
+        self.reserve(agent, &path);
+
+        Some(path)
+    }
+
+    // Plans paths for all agents in the order of their priority
+    pub fn plan_all(
+        &mut self,
+        grid: &VoxelGrid,
+        requests: &[(IVec3, IVec3)],
+    ) -> Vec<Option<Vec<IVec3>>> {
+        requests
+            .iter()
+            .enumerate()
+            .map(|(agent, &(start, goal))| self.plan(grid, agent, start, goal))
+            .collect()
+    }
+
+    fn is_move_allowed(&self, agent: usize, from: IVec3, to: IVec3, time: u32) -> bool {
+        let is_other = |reserved_by: Option<&usize>| reserved_by.is_some_and(|&a| a != agent);
+
+        // The target cell is occupied, or another agent moves the opposite way
+        !is_other(self.cells.get(&(to, time + 1))) && !is_other(self.moves.get(&(to, from, time)))
+    }
+
+    // The agent can only stop at the goal if no other agent passes through it later in the window
+    fn is_free_from(&self, agent: usize, cell: IVec3, time: u32) -> bool {
+        (time..=self.window).all(|t| {
+            self.cells
+                .get(&(cell, t))
+                .is_none_or(|&reserved_by| reserved_by == agent)
+        })
+    }
+
+    fn reserve(&mut self, agent: usize, path: &[IVec3]) {
+        for time in 0..=self.window {
+            // After reaching the goal the agent stays there
+            let index = (time as usize).min(path.len() - 1);
+            self.cells.insert((path[index], time), agent);
+
+            if index + 1 < path.len() {
+                self.moves
+                    .insert((path[index], path[index + 1], time), agent);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_math::Vec3;
+
+    use super::*;
+
+    #[test]
+    fn test_crossing_agents_do_not_collide() {
+        let grid = VoxelGrid::new(Vec3::ZERO, Vec3::new(5.0, 5.0, 1.0), 1.0);
+        let mut planner = CooperativePlanner::new(8);
+
+        let requests = [
+            (IVec3::new(0, 2, 0), IVec3::new(4, 2, 0)),
+            (IVec3::new(4, 2, 0), IVec3::new(0, 2, 0)),
+            (IVec3::new(2, 0, 0), IVec3::new(2, 4, 0)),
+        ];
+
+        let paths = planner
+            .plan_all(&grid, &requests)
+            .into_iter()
+            .map(Option::unwrap)
+            .collect::<Vec<_>>();
+
+        let at = |path: &Vec<IVec3>, time: usize| path[time.min(path.len() - 1)];
+
+        for (path, (start, goal)) in paths.iter().zip(requests.iter()) {
+            assert_eq!(path.first(), Some(start));
+            assert_eq!(path.last(), Some(goal));
+        }
+
+        for time in 0..8 {
+            for a in 0..paths.len() {
+                for b in a + 1..paths.len() {
+                    assert_ne!(at(&paths[a], time), at(&paths[b], time));
+
+                    // Swapping cells means the agents pass through each other
+                    assert!(
+                        at(&paths[a], time) != at(&paths[b], time + 1)
+                            || at(&paths[a], time + 1) != at(&paths[b], time)
+                    );
+                }
+            }
+        }
+    }
+}
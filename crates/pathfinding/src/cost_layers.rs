@@ -0,0 +1,239 @@
+use std::collections::BTreeMap;
+
+use bevy_math::{IVec3, Vec3};
+use geometry::{Aabb, Sphere, Vec3Operations};
+
+use crate::{a_star, SearchGraph, VoxelGrid};
+
+// Soft cost over the planning volume
+// The cost is added to the unit cost of travelling through the point, so zero means no extra cost
+// and an infinite cost makes the point impassable. Negative costs are not allowed, they would make
+// the heuristic of the search overestimate.
+// Layers are owned by CostLayers, which is shared by parallel ECS systems, so they have to be
+// Send and Sync.
+pub trait CostLayer: Send + Sync {
+    fn cost_at(&self, position: Vec3) -> f32;
+}
+
+impl<F> CostLayer for F
+where
+    F: Fn(Vec3) -> f32 + Send + Sync,
+{
+    fn cost_at(&self, position: Vec3) -> f32 {
+        self(position)
+    }
+}
+
+// Zone that is dangerous to fly through, the cost is highest in its center and fades out to its edge
+pub struct DangerZone {
+    pub sphere: Sphere,
+    pub cost: f32,
+}
+
+impl CostLayer for DangerZone {
+    fn cost_at(&self, position: Vec3) -> f32 {
+        let depth = -self.sphere.signed_distance(position) / self.sphere.radius;
+
+        self.cost * depth.clamp(0.0, 1.0)
+    }
+}
+
+// Band of preferred altitudes, leaving it costs proportionally to the distance from the band
+pub struct AltitudeBand {
+    pub min: f32,
+    pub max: f32,
+    pub cost_per_unit: f32,
+}
+
+impl CostLayer for AltitudeBand {
+    fn cost_at(&self, position: Vec3) -> f32 {
+        let distance = (self.min - position.y).max(position.y - self.max).max(0.0);
+
+        distance * self.cost_per_unit
+    }
+}
+
+// Region that can't be entered at all
+pub struct NoFlyZone {
+    pub aabb: Aabb,
+}
+
+impl CostLayer for NoFlyZone {
+    fn cost_at(&self, position: Vec3) -> f32 {
+        if self.aabb.contains(position) {
+            f32::INFINITY
+        } else {
+            0.0
+        }
+    }
+}
+
+// Handle of a registered cost layer
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct CostLayerId(usize);
+
+// Set of cost layers that can be changed at runtime
+#[derive(Default)]
+pub struct CostLayers {
+    layers: BTreeMap<CostLayerId, Box<dyn CostLayer>>,
+    next_id: usize,
+}
+
+impl CostLayers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, layer: impl CostLayer + 'static) -> CostLayerId {
+        let id = CostLayerId(self.next_id);
+        self.next_id += 1;
+        self.layers.insert(id, Box::new(layer));
+
+        id
+    }
+
+    // Replaces the layer, e.g. when a danger zone moved
+    // Returns false if there's no layer with the id
+    pub fn replace(&mut self, id: CostLayerId, layer: impl CostLayer + 'static) -> bool {
+        match self.layers.get_mut(&id) {
+            Some(existing) => {
+                *existing = Box::new(layer);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn remove(&mut self, id: CostLayerId) -> bool {
+        self.layers.remove(&id).is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        self.layers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    // The total extra cost of all layers at the position
+    pub fn cost_at(&self, position: Vec3) -> f32 {
+        self.layers
+            .values()
+            .map(|layer| layer.cost_at(position))
+            .sum()
+    }
+
+    // Modulates the cost of moving between two points by the layers at its midpoint
+    pub fn edge_cost(&self, from: Vec3, to: Vec3) -> f32 {
+        from.distance(to) * (1.0 + self.cost_at(from.lerp(to, 0.5)))
+    }
+}
+
+// Voxel grid whose edge costs are modulated by cost layers
+pub struct CostLayerGrid<'a> {
+    pub grid: &'a VoxelGrid,
+    pub layers: &'a CostLayers,
+}
+
+impl<'a> SearchGraph for CostLayerGrid<'a> {
+    type Node = IVec3;
+
+    fn successors(&self, node: IVec3, successors: &mut Vec<(IVec3, f32)>) {
+        let from = successors.len();
+        self.grid.successors(node, successors);
+
+        let center = self.grid.cell_center(node);
+        let mut i = from;
+
+        while i < successors.len() {
+            let (successor, _) = successors[i];
+            let cost = self
+                .layers
+                .edge_cost(center, self.grid.cell_center(successor));
+
+            if cost.is_finite() {
+                successors[i].1 = cost;
+                i += 1;
+            } else {
+                successors.swap_remove(i);
+            }
+        }
+    }
+
+    fn heuristic(&self, node: IVec3, goal: IVec3) -> f32 {
+        self.grid.heuristic(node, goal)
+    }
+}
+
+impl VoxelGrid {
+    // Finds a path like find_path, with the edge costs modulated by the cost layers
+    pub fn find_path_with_costs(
+        &self,
+        start: Vec3,
+        goal: Vec3,
+        layers: &CostLayers,
+    ) -> Option<Vec<Vec3>> {
+        let start_cell = self.cell_at(start)?;
+        let goal_cell = self.cell_at(goal)?;
+
+        if self.is_occupied(start_cell) || self.is_occupied(goal_cell) {
+            return None;
+        }
+
+        let graph = CostLayerGrid { grid: self, layers };
+        let (cells, _) = a_star(&graph, start_cell, goal_cell)?;
+
+        Some(self.cells_to_waypoints(&cells, start, goal))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layers_modulate_path() {
+        let grid = VoxelGrid::new(Vec3::ZERO, Vec3::new(20.0, 20.0, 1.0), 1.0);
+        let start = Vec3::new(0.5, 10.5, 0.5);
+        let goal = Vec3::new(19.5, 10.5, 0.5);
+        let mut layers = CostLayers::new();
+
+        assert_eq!(
+            grid.find_path_with_costs(start, goal, &layers),
+            Some(vec![start, goal])
+        );
+
+        let danger = layers.add(DangerZone {
+            sphere: Sphere::new(4.0, Vec3::new(10.0, 10.5, 0.5)),
+            cost: 10.0,
+        });
+
+        approx::assert_relative_eq!(layers.cost_at(Vec3::new(10.0, 10.5, 0.5)), 10.0);
+        approx::assert_relative_eq!(layers.cost_at(Vec3::new(10.0, 12.5, 0.5)), 5.0);
+        assert_eq!(layers.cost_at(Vec3::new(10.0, 15.0, 0.5)), 0.0);
+
+        let path = grid.find_path_with_costs(start, goal, &layers).unwrap();
+        assert!(path.len() > 2);
+
+        // The whole straight line is closed, the path has to go around
+        assert!(layers.replace(
+            danger,
+            NoFlyZone {
+                aabb: Aabb::new(Vec3::new(10.0, 10.0, 0.5), Vec3::new(1.0, 10.0, 1.0)),
+            }
+        ));
+
+        assert!(grid.find_path_with_costs(start, goal, &layers).is_none());
+        assert!(layers.remove(danger));
+        assert!(layers.is_empty());
+    }
+
+    #[test]
+    fn test_cost_layers_are_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+
+        assert_send_sync::<CostLayers>();
+        assert_send_sync::<VoxelGrid>();
+    }
+}
@@ -0,0 +1,271 @@
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap},
+};
+
+use crate::SearchGraph;
+
+#[derive(Clone, Copy, Debug)]
+struct Key(f32, f32);
+
+impl Key {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0
+            .total_cmp(&other.0)
+            .then_with(|| self.1.total_cmp(&other.1))
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct QueuedNode<N> {
+    node: N,
+    key: Key,
+}
+
+impl<N> PartialEq for QueuedNode<N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.key.cmp(&other.key) == Ordering::Equal
+    }
+}
+
+impl<N> Eq for QueuedNode<N> {}
+
+impl<N> PartialOrd for QueuedNode<N> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// Reversed, so the BinaryHeap pops the smallest key first
+impl<N> Ord for QueuedNode<N> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.key.cmp(&self.key)
+    }
+}
+
+// Incremental replanner based on D* Lite by Koenig and Likhachev
+// The search runs backwards from the goal, so when the occupancy changes only the costs
+// of the nodes affected by the change get repaired instead of searching from scratch.
+//
+// The graph is expected to be undirected, the successors of a node are also its predecessors,
+// and the heuristic is expected to be symmetric.
+#[derive(Clone, Debug)]
+pub struct DStarLite<N> {
+    start: N,
+    goal: N,
+    key_modifier: f32,
+    costs: HashMap<N, f32>,
+    lookaheads: HashMap<N, f32>,
+    open: BinaryHeap<QueuedNode<N>>,
+}
+
+impl<N> DStarLite<N>
+where
+    N: Copy + Eq + std::hash::Hash,
+{
+    pub fn new<G: SearchGraph<Node = N>>(graph: &G, start: N, goal: N) -> Self {
+        let mut planner = Self {
+            start,
+            goal,
+            key_modifier: 0.0,
+            costs: HashMap::new(),
+            lookaheads: HashMap::new(),
+            open: BinaryHeap::new(),
+        };
+
+        planner.lookaheads.insert(goal, 0.0);
+        planner.open.push(QueuedNode {
+            node: goal,
+            key: planner.calculate_key(graph, goal),
+        });
+        planner.compute_shortest_path(graph);
+
+        planner
+    }
+
+    pub fn start(&self) -> N {
+        self.start
+    }
+
+    pub fn goal(&self) -> N {
+        self.goal
+    }
+
+    // The cost of the current path from the start to the goal, infinite if there's none
+    pub fn path_cost(&self) -> f32 {
+        self.cost(self.start)
+    }
+
+    // Moves the start of the search, usually to the node the agent got to
+    pub fn move_start<G: SearchGraph<Node = N>>(&mut self, graph: &G, start: N) {
+        self.key_modifier += graph.heuristic(self.start, start);
+        self.start = start;
+        self.compute_shortest_path(graph);
+    }
+
+    // Repairs the path after the edges of the given nodes changed
+    // All nodes whose outgoing edges were added, removed or changed their cost have to be passed in
+    pub fn update_nodes<G: SearchGraph<Node = N>>(&mut self, graph: &G, nodes: &[N]) {
+        for &node in nodes {
+            self.update_node(graph, node);
+        }
+
+        self.compute_shortest_path(graph);
+    }
+
+    // Returns the nodes of the current path from the start to the goal
+    pub fn get_path<G: SearchGraph<Node = N>>(&self, graph: &G) -> Option<Vec<N>> {
+        if !self.path_cost().is_finite() {
+            return None;
+        }
+
+        let mut path = vec![self.start];
+        let mut current = self.start;
+        let mut successors = Vec::new();
+
+        while current != self.goal {
+            // A path can't visit more nodes than the search knows about
+            if path.len() > self.costs.len() + 1 {
+                return None;
+            }
+
+            successors.clear();
+            graph.successors(current, &mut successors);
+
+            let (next, cost) = successors
+                .iter()
+                .map(|&(successor, step_cost)| (successor, step_cost + self.cost(successor)))
+                .min_by(|a, b| a.1.total_cmp(&b.1))?;
+
+            if !cost.is_finite() {
+                return None;
+            }
+
+            path.push(next);
+            current = next;
+        }
+
+        Some(path)
+    }
+
+    fn cost(&self, node: N) -> f32 {
+        self.costs.get(&node).copied().unwrap_or(f32::INFINITY)
+    }
+
+    fn lookahead(&self, node: N) -> f32 {
+        self.lookaheads.get(&node).copied().unwrap_or(f32::INFINITY)
+    }
+
+    fn calculate_key<G: SearchGraph<Node = N>>(&self, graph: &G, node: N) -> Key {
+        let cost = self.cost(node).min(self.lookahead(node));
+
+        Key(
+            cost + graph.heuristic(self.start, node) + self.key_modifier,
+            cost,
+        )
+    }
+
+    fn update_node<G: SearchGraph<Node = N>>(&mut self, graph: &G, node: N) {
+        if node != self.goal {
+            let mut successors = Vec::new();
+            graph.successors(node, &mut successors);
+
+            let lookahead = successors
+                .iter()
+                .map(|&(successor, step_cost)| step_cost + self.cost(successor))
+                .fold(f32::INFINITY, f32::min);
+
+            self.lookaheads.insert(node, lookahead);
+        }
+
+        // Outdated entries stay in the queue and are skipped when popped
+        if self.cost(node) != self.lookahead(node) {
+            self.open.push(QueuedNode {
+                node,
+                key: self.calculate_key(graph, node),
+            });
+        }
+    }
+
+    fn compute_shortest_path<G: SearchGraph<Node = N>>(&mut self, graph: &G) {
+        let mut predecessors = Vec::new();
+
+        while let Some(&QueuedNode { node, key }) = self.open.peek() {
+            let start_key = self.calculate_key(graph, self.start);
+
+            if key.cmp(&start_key) != Ordering::Less
+                && self.lookahead(self.start) == self.cost(self.start)
+            {
+                break;
+            }
+
+            self.open.pop();
+
+            let cost = self.cost(node);
+            let lookahead = self.lookahead(node);
+
+            if cost == lookahead {
+                continue;
+            }
+
+            let new_key = self.calculate_key(graph, node);
+
+            if key.cmp(&new_key) == Ordering::Less {
+                self.open.push(QueuedNode { node, key: new_key });
+                continue;
+            }
+
+            if cost > lookahead {
+                self.costs.insert(node, lookahead);
+            } else {
+                self.costs.insert(node, f32::INFINITY);
+                self.update_node(graph, node);
+            }
+
+            predecessors.clear();
+            graph.successors(node, &mut predecessors);
+
+            for &(predecessor, _) in &predecessors {
+                self.update_node(graph, predecessor);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_math::{IVec3, Vec3};
+
+    use super::*;
+    use crate::{a_star, VoxelGrid};
+
+    #[test]
+    fn test_repairs_path_after_occupancy_change() {
+        let mut grid = VoxelGrid::new(Vec3::ZERO, Vec3::new(10.0, 10.0, 1.0), 1.0);
+        let start = IVec3::new(0, 5, 0);
+        let goal = IVec3::new(9, 5, 0);
+
+        let mut planner = DStarLite::new(&grid, start, goal);
+
+        approx::assert_relative_eq!(planner.path_cost(), 9.0, epsilon = 1e-4);
+
+        let mut changed = Vec::new();
+
+        for y in 2..9 {
+            let cell = IVec3::new(5, y, 0);
+            grid.set_occupied(cell, true);
+            changed.extend(grid.cells_around(cell));
+        }
+
+        planner.update_nodes(&grid, &changed);
+
+        let (_, expected_cost) = a_star(&grid, start, goal).unwrap();
+        approx::assert_relative_eq!(planner.path_cost(), expected_cost, epsilon = 1e-4);
+
+        let path = planner.get_path(&grid).unwrap();
+
+        assert_eq!(path.first(), Some(&start));
+        assert_eq!(path.last(), Some(&goal));
+        assert!(path.iter().all(|cell| !grid.is_occupied(*cell)));
+    }
+}
@@ -0,0 +1,177 @@
+use std::collections::BinaryHeap;
+
+use bevy_math::{IVec3, UVec3, Vec3};
+
+use crate::{a_star::OpenNode, SearchGraph, VoxelGrid};
+
+// Flow field leading every cell of a voxel grid towards a shared goal
+// Instead of running a search for every agent, the whole grid is integrated once from the goal
+// and every agent just samples the direction at its position, which makes it a good fit for
+// hundreds of agents sharing one goal.
+#[derive(Clone, Debug)]
+pub struct FlowField {
+    min: Vec3,
+    voxel_size: f32,
+    dimensions: UVec3,
+    goal: Vec3,
+    // Cost of getting from every cell to the goal, infinite for unreachable cells
+    integration: Vec<f32>,
+    // Normalized direction towards the cheapest neighbour of every cell
+    directions: Vec<Vec3>,
+}
+
+impl FlowField {
+    // Integrates the grid from the cell containing the goal
+    // Returns None when the goal is outside of the grid or in an occupied cell
+    pub fn new(grid: &VoxelGrid, goal: Vec3) -> Option<Self> {
+        let goal_cell = grid.cell_at(goal)?;
+
+        if grid.is_occupied(goal_cell) {
+            return None;
+        }
+
+        let dimensions = grid.dimensions();
+        let len = (dimensions.x * dimensions.y * dimensions.z) as usize;
+        let mut integration = vec![f32::INFINITY; len];
+        let mut open = BinaryHeap::new();
+        let mut successors = Vec::new();
+
+        integration[grid.index(goal_cell)] = 0.0;
+        open.push(OpenNode {
+            node: goal_cell,
+            cost: 0.0,
+        });
+
+        // Dijkstra from the goal, the moves on the grid are symmetric so the costs
+        // from the goal are the same as the costs to the goal
+        while let Some(OpenNode { node, cost }) = open.pop() {
+            if cost > integration[grid.index(node)] {
+                continue;
+            }
+
+            successors.clear();
+            grid.successors(node, &mut successors);
+
+            for &(successor, step_cost) in &successors {
+                let index = grid.index(successor);
+                let successor_cost = cost + step_cost;
+
+                if successor_cost < integration[index] {
+                    integration[index] = successor_cost;
+                    open.push(OpenNode {
+                        node: successor,
+                        cost: successor_cost,
+                    });
+                }
+            }
+        }
+
+        let mut directions = vec![Vec3::ZERO; len];
+
+        for z in 0..dimensions.z as i32 {
+            for y in 0..dimensions.y as i32 {
+                for x in 0..dimensions.x as i32 {
+                    let cell = IVec3::new(x, y, z);
+                    let index = grid.index(cell);
+
+                    if cell == goal_cell || !integration[index].is_finite() {
+                        continue;
+                    }
+
+                    successors.clear();
+                    grid.successors(cell, &mut successors);
+
+                    if let Some((next, _)) = successors
+                        .iter()
+                        .map(|&(successor, step_cost)| {
+                            (successor, step_cost + integration[grid.index(successor)])
+                        })
+                        .min_by(|a, b| a.1.total_cmp(&b.1))
+                    {
+                        directions[index] = (next - cell).as_vec3().normalize();
+                    }
+                }
+            }
+        }
+
+        Some(Self {
+            min: grid.min(),
+            voxel_size: grid.voxel_size(),
+            dimensions,
+            goal,
+            integration,
+            directions,
+        })
+    }
+
+    pub fn goal(&self) -> Vec3 {
+        self.goal
+    }
+
+    // Returns the cost of getting from the position to the goal,
+    // infinite when the position is outside of the grid or the goal is unreachable from it
+    pub fn cost_at(&self, position: Vec3) -> f32 {
+        self.index_at(position)
+            .map_or(f32::INFINITY, |index| self.integration[index])
+    }
+
+    // Returns the normalized direction towards the goal at the position
+    // Scaled by the maximum speed of an agent it can be used directly as its preferred velocity.
+    // Inside of the goal cell it points straight at the goal, outside of the grid and in cells
+    // the goal can't be reached from it's zero.
+    pub fn flow_at(&self, position: Vec3) -> Vec3 {
+        let Some(index) = self.index_at(position) else {
+            return Vec3::ZERO;
+        };
+
+        if self.integration[index] == 0.0 {
+            return (self.goal - position).normalize_or_zero();
+        }
+
+        self.directions[index]
+    }
+
+    fn index_at(&self, position: Vec3) -> Option<usize> {
+        let cell = ((position - self.min) / self.voxel_size).floor().as_ivec3();
+
+        if cell.cmplt(IVec3::ZERO).any() || cell.cmpge(self.dimensions.as_ivec3()).any() {
+            return None;
+        }
+
+        let dimensions = self.dimensions.as_ivec3();
+
+        Some((cell.x + cell.y * dimensions.x + cell.z * dimensions.x * dimensions.y) as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flow_leads_around_obstacle() {
+        let mut grid = VoxelGrid::new(Vec3::ZERO, Vec3::new(10.0, 10.0, 1.0), 1.0);
+
+        for y in 0..9 {
+            grid.set_occupied(IVec3::new(5, y, 0), true);
+        }
+
+        let field = FlowField::new(&grid, Vec3::new(8.5, 0.5, 0.5)).unwrap();
+
+        // Blocked cells can't be reached
+        assert!(!field.cost_at(Vec3::new(5.5, 0.5, 0.5)).is_finite());
+        assert_eq!(field.flow_at(Vec3::new(5.5, 0.5, 0.5)), Vec3::ZERO);
+
+        // Following the flow leads to the goal through the gap at the top
+        let mut position = Vec3::new(1.5, 0.5, 0.5);
+        let mut reached_gap = false;
+
+        for _ in 0..100 {
+            position += field.flow_at(position) * 0.5;
+            reached_gap |= position.y > 9.0;
+        }
+
+        assert!(reached_gap);
+        assert!(position.distance(field.goal()) < 0.5);
+    }
+}
@@ -0,0 +1,148 @@
+use bevy_math::Vec3;
+use geometry::Vec3Operations;
+
+use crate::{a_star::search, SearchGraph, VoxelGrid};
+
+// Finds the cheapest path to whichever of the goals is the cheapest to reach, in a single search
+// Returns: The index of the reached goal, the nodes of the path and its cost
+pub fn a_star_to_any<G: SearchGraph>(
+    graph: &G,
+    start: G::Node,
+    goals: &[G::Node],
+) -> Option<(usize, Vec<G::Node>, f32)> {
+    if goals.is_empty() {
+        return None;
+    }
+
+    // The closest goal by the heuristic keeps the heuristic admissible
+    let (path, cost) = search(
+        start,
+        |node, successors| graph.successors(node, successors),
+        |node| {
+            goals
+                .iter()
+                .map(|&goal| graph.heuristic(node, goal))
+                .fold(f32::INFINITY, f32::min)
+        },
+        |node| goals.contains(&node),
+        usize::MAX,
+    )?;
+
+    let reached = *path.last().expect("A path always contains the start");
+    let goal_index = goals
+        .iter()
+        .position(|&goal| goal == reached)
+        .expect("The path ends in one of the goals");
+
+    Some((goal_index, path, cost))
+}
+
+// Finds the cheapest path to any node satisfying is_goal
+// heuristic: Estimated cost from a node to the closest goal node, must never overestimate it
+pub fn a_star_to_region<N: Copy + Eq + std::hash::Hash>(
+    start: N,
+    successors: impl Fn(N, &mut Vec<(N, f32)>),
+    heuristic: impl Fn(N) -> f32,
+    is_goal: impl Fn(N) -> bool,
+) -> Option<(Vec<N>, f32)> {
+    search(start, successors, heuristic, is_goal, usize::MAX)
+}
+
+impl VoxelGrid {
+    // Finds a path to the closest of the goals
+    // Returns: The index of the reached goal and the waypoints of the path
+    pub fn find_path_to_any(&self, start: Vec3, goals: &[Vec3]) -> Option<(usize, Vec<Vec3>)> {
+        let start_cell = self.cell_at(start)?;
+
+        if self.is_occupied(start_cell) {
+            return None;
+        }
+
+        // Goals outside of the grid or in occupied cells can't be reached, but keep their indexes
+        let goal_cells = goals
+            .iter()
+            .filter_map(|&goal| self.cell_at(goal).filter(|cell| !self.is_occupied(*cell)))
+            .collect::<Vec<_>>();
+
+        let (_, cells, _) = a_star_to_any(self, start_cell, &goal_cells)?;
+        let reached = *cells.last().expect("A path always contains the start");
+        let goal_index = goals
+            .iter()
+            .position(|&goal| self.cell_at(goal) == Some(reached))
+            .expect("The reached cell belongs to one of the goals");
+
+        Some((
+            goal_index,
+            self.cells_to_waypoints(&cells, start, goals[goal_index]),
+        ))
+    }
+
+    // Finds a path to the closest cell whose center lies inside of the region
+    // Returns: The waypoints of the path ending in the center of the reached cell
+    pub fn find_path_to_region(
+        &self,
+        start: Vec3,
+        region: &impl Vec3Operations,
+    ) -> Option<Vec<Vec3>> {
+        let start_cell = self.cell_at(start)?;
+
+        if self.is_occupied(start_cell) {
+            return None;
+        }
+
+        let (cells, _) = a_star_to_region(
+            start_cell,
+            |node, successors| self.successors(node, successors),
+            |node| region.signed_distance(self.cell_center(node)).max(0.0),
+            |node| region.contains(self.cell_center(node)),
+        )?;
+
+        let end = self.cell_center(*cells.last().expect("A path always contains the start"));
+
+        // Starting inside of the region doesn't need any movement
+        if cells.len() == 1 && region.contains(start) {
+            return Some(vec![start]);
+        }
+
+        Some(self.cells_to_waypoints(&cells, start, end))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_math::IVec3;
+    use geometry::Aabb;
+
+    use super::*;
+
+    #[test]
+    fn test_nearest_of_multiple_goals() {
+        let mut grid = VoxelGrid::new(Vec3::ZERO, Vec3::new(20.0, 20.0, 1.0), 1.0);
+
+        // Wall between the start and the closer goal
+        for y in 0..19 {
+            grid.set_occupied(IVec3::new(5, y, 0), true);
+        }
+
+        let start = Vec3::new(2.5, 2.5, 0.5);
+        let goals = [Vec3::new(8.5, 2.5, 0.5), Vec3::new(2.5, 12.5, 0.5)];
+
+        let (goal_index, path) = grid.find_path_to_any(start, &goals).unwrap();
+
+        assert_eq!(goal_index, 1);
+        assert_eq!(path, vec![start, goals[1]]);
+    }
+
+    #[test]
+    fn test_path_into_region() {
+        let grid = VoxelGrid::new(Vec3::ZERO, Vec3::new(20.0, 20.0, 1.0), 1.0);
+        let region = Aabb::new(Vec3::new(15.0, 10.0, 0.5), Vec3::new(3.0, 10.0, 1.0));
+
+        let path = grid
+            .find_path_to_region(Vec3::new(2.5, 5.5, 0.5), &region)
+            .unwrap();
+
+        assert_eq!(path.len(), 2);
+        assert_eq!(*path.last().unwrap(), Vec3::new(12.5, 5.5, 0.5));
+    }
+}
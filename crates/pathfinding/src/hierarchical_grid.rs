@@ -0,0 +1,356 @@
+use std::collections::{HashMap, HashSet};
+
+use bevy_math::{IVec3, Vec3};
+
+use crate::{a_star, SearchGraph, VoxelGrid};
+
+// Hierarchical pathfinding (HPA*) over a voxel grid split into cubic chunks
+// Neighbouring chunks are connected through entrances on their shared faces, and the entrances
+// of each chunk are connected by the costs of the paths between them inside of the chunk.
+// Long queries search only this small abstract graph, the full resolution search is limited
+// to a single chunk when a segment of the abstract path gets refined.
+#[derive(Clone, Debug)]
+pub struct HierarchicalGrid {
+    chunk_size: i32,
+    edges: HashMap<IVec3, Vec<(IVec3, f32)>>,
+}
+
+// Search graph over a voxel grid limited to a box of cells
+struct BoundedGrid<'a> {
+    grid: &'a VoxelGrid,
+    min: IVec3,
+    max: IVec3,
+}
+
+impl<'a> SearchGraph for BoundedGrid<'a> {
+    type Node = IVec3;
+
+    fn successors(&self, node: IVec3, successors: &mut Vec<(IVec3, f32)>) {
+        let from = successors.len();
+        self.grid.successors(node, successors);
+
+        let mut i = from;
+        while i < successors.len() {
+            let cell = successors[i].0;
+
+            if cell.cmplt(self.min).any() || cell.cmpgt(self.max).any() {
+                successors.swap_remove(i);
+            } else {
+                i += 1;
+            }
+        }
+    }
+
+    fn heuristic(&self, node: IVec3, goal: IVec3) -> f32 {
+        self.grid.heuristic(node, goal)
+    }
+}
+
+// The abstract graph with the start and the goal of a query temporarily connected to it
+struct AbstractGraph<'a> {
+    grid: &'a VoxelGrid,
+    edges: &'a HashMap<IVec3, Vec<(IVec3, f32)>>,
+    query_edges: HashMap<IVec3, Vec<(IVec3, f32)>>,
+}
+
+impl<'a> SearchGraph for AbstractGraph<'a> {
+    type Node = IVec3;
+
+    fn successors(&self, node: IVec3, successors: &mut Vec<(IVec3, f32)>) {
+        successors.extend(self.edges.get(&node).into_iter().flatten());
+        successors.extend(self.query_edges.get(&node).into_iter().flatten());
+    }
+
+    fn heuristic(&self, node: IVec3, goal: IVec3) -> f32 {
+        self.grid.heuristic(node, goal)
+    }
+}
+
+impl HierarchicalGrid {
+    // Builds the abstract graph of the grid
+    // chunk_size: The size of the edge of a chunk in cells
+    pub fn new(grid: &VoxelGrid, chunk_size: i32) -> Self {
+        assert!(chunk_size > 0);
+
+        let mut hierarchical_grid = Self {
+            chunk_size,
+            edges: HashMap::new(),
+        };
+
+        let chunks = (grid.dimensions().as_ivec3() + IVec3::splat(chunk_size - 1)) / chunk_size;
+        let mut entrances: HashMap<IVec3, HashSet<IVec3>> = HashMap::new();
+
+        for z in 0..chunks.z {
+            for y in 0..chunks.y {
+                for x in 0..chunks.x {
+                    let chunk = IVec3::new(x, y, z);
+
+                    for axis in [IVec3::X, IVec3::Y, IVec3::Z] {
+                        if (chunk + axis).cmplt(chunks).all() {
+                            for (from, to) in hierarchical_grid.find_entrances(grid, chunk, axis) {
+                                entrances.entry(chunk).or_default().insert(from);
+                                entrances.entry(chunk + axis).or_default().insert(to);
+
+                                hierarchical_grid.add_edge(from, to, grid.voxel_size());
+                                hierarchical_grid.add_edge(to, from, grid.voxel_size());
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        for (chunk, chunk_entrances) in &entrances {
+            let chunk_entrances = chunk_entrances.iter().copied().collect::<Vec<_>>();
+
+            for (i, &from) in chunk_entrances.iter().enumerate() {
+                for &to in &chunk_entrances[i + 1..] {
+                    if let Some((_, cost)) =
+                        hierarchical_grid.search_in_chunk(grid, *chunk, from, to)
+                    {
+                        hierarchical_grid.add_edge(from, to, cost);
+                        hierarchical_grid.add_edge(to, from, cost);
+                    }
+                }
+            }
+        }
+
+        hierarchical_grid
+    }
+
+    pub fn chunk_size(&self) -> i32 {
+        self.chunk_size
+    }
+
+    pub fn chunk_of(&self, cell: IVec3) -> IVec3 {
+        cell.div_euclid(IVec3::splat(self.chunk_size))
+    }
+
+    // The number of entrance cells in the abstract graph
+    pub fn number_of_entrances(&self) -> usize {
+        self.edges.len()
+    }
+
+    // Finds a path over the abstract graph
+    // Returns: The start cell, the entrance cells the path goes through, and the goal cell.
+    // Two consecutive cells are either in the same chunk or neighbours across a chunk face,
+    // so every segment can be refined with refine_segment.
+    pub fn find_abstract_path(
+        &self,
+        grid: &VoxelGrid,
+        start: Vec3,
+        goal: Vec3,
+    ) -> Option<Vec<IVec3>> {
+        let start_cell = grid.cell_at(start)?;
+        let goal_cell = grid.cell_at(goal)?;
+
+        if grid.is_occupied(start_cell) || grid.is_occupied(goal_cell) {
+            return None;
+        }
+
+        let start_chunk = self.chunk_of(start_cell);
+        let goal_chunk = self.chunk_of(goal_cell);
+        let mut query_edges: HashMap<IVec3, Vec<(IVec3, f32)>> = HashMap::new();
+
+        if start_chunk == goal_chunk {
+            if let Some((_, cost)) = self.search_in_chunk(grid, start_chunk, start_cell, goal_cell)
+            {
+                query_edges
+                    .entry(start_cell)
+                    .or_default()
+                    .push((goal_cell, cost));
+            }
+        }
+
+        for &entrance in self.edges.keys() {
+            if self.chunk_of(entrance) == start_chunk {
+                if let Some((_, cost)) =
+                    self.search_in_chunk(grid, start_chunk, start_cell, entrance)
+                {
+                    query_edges
+                        .entry(start_cell)
+                        .or_default()
+                        .push((entrance, cost));
+                }
+            }
+
+            if self.chunk_of(entrance) == goal_chunk {
+                if let Some((_, cost)) = self.search_in_chunk(grid, goal_chunk, entrance, goal_cell)
+                {
+                    query_edges
+                        .entry(entrance)
+                        .or_default()
+                        .push((goal_cell, cost));
+                }
+            }
+        }
+
+        let graph = AbstractGraph {
+            grid,
+            edges: &self.edges,
+            query_edges,
+        };
+
+        a_star(&graph, start_cell, goal_cell).map(|(path, _)| path)
+    }
+
+    // Refines a segment of the abstract path into cells at full resolution
+    pub fn refine_segment(&self, grid: &VoxelGrid, from: IVec3, to: IVec3) -> Option<Vec<IVec3>> {
+        if self.chunk_of(from) == self.chunk_of(to) {
+            return self
+                .search_in_chunk(grid, self.chunk_of(from), from, to)
+                .map(|(path, _)| path);
+        }
+
+        // Entrances in neighbouring chunks are direct neighbours
+        Some(vec![from, to])
+    }
+
+    // Finds a path and refines all of its segments
+    // Returns: Waypoints starting at start and ending at goal
+    pub fn find_path(&self, grid: &VoxelGrid, start: Vec3, goal: Vec3) -> Option<Vec<Vec3>> {
+        let abstract_path = self.find_abstract_path(grid, start, goal)?;
+        let mut cells = vec![abstract_path[0]];
+
+        for segment in abstract_path.windows(2) {
+            let refined = self.refine_segment(grid, segment[0], segment[1])?;
+            cells.extend_from_slice(&refined[1..]);
+        }
+
+        Some(grid.cells_to_waypoints(&cells, start, goal))
+    }
+
+    fn add_edge(&mut self, from: IVec3, to: IVec3, cost: f32) {
+        let edges = self.edges.entry(from).or_default();
+
+        if let Some(edge) = edges.iter_mut().find(|(cell, _)| *cell == to) {
+            edge.1 = edge.1.min(cost);
+        } else {
+            edges.push((to, cost));
+        }
+    }
+
+    fn search_in_chunk(
+        &self,
+        grid: &VoxelGrid,
+        chunk: IVec3,
+        from: IVec3,
+        to: IVec3,
+    ) -> Option<(Vec<IVec3>, f32)> {
+        let min = chunk * self.chunk_size;
+        let graph = BoundedGrid {
+            grid,
+            min,
+            max: min + IVec3::splat(self.chunk_size - 1),
+        };
+
+        a_star(&graph, from, to)
+    }
+
+    // Finds the entrances on the face between the chunk and its neighbour along the axis
+    // Every connected run of free cell pairs on the face becomes one entrance in its middle.
+    fn find_entrances(&self, grid: &VoxelGrid, chunk: IVec3, axis: IVec3) -> Vec<(IVec3, IVec3)> {
+        let (u, v) = if axis == IVec3::X {
+            (IVec3::Y, IVec3::Z)
+        } else if axis == IVec3::Y {
+            (IVec3::X, IVec3::Z)
+        } else {
+            (IVec3::X, IVec3::Y)
+        };
+
+        let corner = chunk * self.chunk_size + axis * (self.chunk_size - 1);
+        let is_free = |i: i32, j: i32| {
+            let cell = corner + u * i + v * j;
+
+            !grid.is_occupied(cell) && !grid.is_occupied(cell + axis)
+        };
+
+        let mut visited = HashSet::new();
+        let mut entrances = Vec::new();
+
+        for i in 0..self.chunk_size {
+            for j in 0..self.chunk_size {
+                if visited.contains(&(i, j)) || !is_free(i, j) {
+                    continue;
+                }
+
+                // Flood fill the connected free region of the face
+                let mut region = Vec::new();
+                let mut open = vec![(i, j)];
+                visited.insert((i, j));
+
+                while let Some((a, b)) = open.pop() {
+                    region.push((a, b));
+
+                    for (da, db) in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
+                        let next = (a + da, b + db);
+
+                        if (0..self.chunk_size).contains(&next.0)
+                            && (0..self.chunk_size).contains(&next.1)
+                            && !visited.contains(&next)
+                            && is_free(next.0, next.1)
+                        {
+                            visited.insert(next);
+                            open.push(next);
+                        }
+                    }
+                }
+
+                region.sort_unstable();
+
+                let (a, b) = region[region.len() / 2];
+                let cell = corner + u * a + v * b;
+
+                entrances.push((cell, cell + axis));
+            }
+        }
+
+        entrances
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hierarchical_path_matches_grid() {
+        let mut grid = VoxelGrid::new(Vec3::ZERO, Vec3::new(16.0, 16.0, 1.0), 1.0);
+
+        for y in 0..14 {
+            grid.set_occupied(IVec3::new(7, y, 0), true);
+        }
+
+        let hierarchical_grid = HierarchicalGrid::new(&grid, 4);
+        let start = Vec3::new(1.5, 1.5, 0.5);
+        let goal = Vec3::new(14.5, 1.5, 0.5);
+
+        let abstract_path = hierarchical_grid
+            .find_abstract_path(&grid, start, goal)
+            .unwrap();
+
+        // Going around the wall crosses nine chunk faces, two entrance cells each
+        assert!(abstract_path.len() <= 20);
+
+        let path = hierarchical_grid.find_path(&grid, start, goal).unwrap();
+
+        assert_eq!(path.first(), Some(&start));
+        assert_eq!(path.last(), Some(&goal));
+
+        // The path has to go around the wall through the gap at the top
+        assert!(path.iter().any(|point| point.y > 14.0));
+    }
+
+    #[test]
+    fn test_path_within_single_chunk() {
+        let grid = VoxelGrid::new(Vec3::ZERO, Vec3::new(16.0, 16.0, 1.0), 1.0);
+        let hierarchical_grid = HierarchicalGrid::new(&grid, 4);
+        let start = Vec3::new(0.5, 0.5, 0.5);
+        let goal = Vec3::new(3.5, 0.5, 0.5);
+
+        assert_eq!(
+            hierarchical_grid.find_path(&grid, start, goal),
+            Some(vec![start, goal])
+        );
+    }
+}
@@ -0,0 +1,35 @@
+mod a_star;
+mod collision;
+mod cooperative_pathfinding;
+mod cost_layers;
+mod d_star_lite;
+mod flow_field;
+mod goal_regions;
+mod hierarchical_grid;
+mod obstacle_bvh;
+mod path_smoothing;
+mod roadmap;
+#[cfg(feature = "serde")]
+mod roadmap_loader;
+mod rrt;
+mod sphere_corridor;
+mod svo_pathfinding;
+mod voxel_grid;
+
+pub use a_star::*;
+pub use collision::*;
+pub use cooperative_pathfinding::*;
+pub use cost_layers::*;
+pub use d_star_lite::*;
+pub use flow_field::*;
+pub use goal_regions::*;
+pub use hierarchical_grid::*;
+pub use obstacle_bvh::*;
+pub use path_smoothing::*;
+pub use roadmap::*;
+#[cfg(feature = "serde")]
+pub use roadmap_loader::*;
+pub use rrt::*;
+pub use sphere_corridor::*;
+pub use svo_pathfinding::*;
+pub use voxel_grid::*;
@@ -0,0 +1,337 @@
+use bevy_math::Vec3;
+use geometry::{colliders::Collider, Aabb, Ray3D, Ray3DIntersection, Vec3Operations};
+
+use crate::segment_hit;
+
+const MAX_LEAF_SIZE: usize = 4;
+
+#[derive(Clone, Debug)]
+enum BvhNode {
+    Leaf {
+        bounds: Aabb,
+        start: usize,
+        end: usize,
+    },
+    Inner {
+        bounds: Aabb,
+        left: usize,
+        right: usize,
+    },
+}
+
+impl BvhNode {
+    fn bounds(&self) -> &Aabb {
+        match self {
+            BvhNode::Leaf { bounds, .. } | BvhNode::Inner { bounds, .. } => bounds,
+        }
+    }
+}
+
+// Bounding volume hierarchy over a set of obstacles
+// Dynamic obstacles are expected to rebuild it every frame, which is cheap
+// compared to testing every segment against every obstacle.
+#[derive(Clone, Debug)]
+pub struct ObstacleBvh {
+    colliders: Vec<Collider>,
+    nodes: Vec<BvhNode>,
+}
+
+impl ObstacleBvh {
+    pub fn new(colliders: Vec<Collider>) -> Self {
+        let mut bvh = Self {
+            colliders,
+            nodes: Vec::new(),
+        };
+
+        if !bvh.colliders.is_empty() {
+            let len = bvh.colliders.len();
+            bvh.build(0, len);
+        }
+
+        bvh
+    }
+
+    pub fn colliders(&self) -> &[Collider] {
+        &self.colliders
+    }
+
+    // Returns the distance along the segment at which an agent with the given radius hits
+    // the first obstacle, or None if the segment is free
+    pub fn segment_hit(&self, from: Vec3, to: Vec3, agent_radius: f32) -> Option<f32> {
+        if self.nodes.is_empty() {
+            return None;
+        }
+
+        let length = from.distance(to);
+        let ray = (length > f32::EPSILON).then(|| Ray3D::new(from, to - from));
+        let mut closest: Option<f32> = None;
+        let mut open = vec![0];
+
+        while let Some(index) = open.pop() {
+            let node = &self.nodes[index];
+            let bounds = Aabb::new(
+                node.bounds().center,
+                node.bounds().half_sizes + Vec3::splat(agent_radius),
+            );
+
+            let reaches_node = bounds.contains(from)
+                || ray.as_ref().is_some_and(|ray| {
+                    bounds
+                        .intersect_ray(ray)
+                        .first_hit()
+                        .is_some_and(|t| t <= closest.unwrap_or(length))
+                });
+
+            if !reaches_node {
+                continue;
+            }
+
+            match *node {
+                BvhNode::Leaf { start, end, .. } => {
+                    for collider in &self.colliders[start..end] {
+                        if let Some(t) = segment_hit(from, to, collider, agent_radius) {
+                            closest = Some(closest.map_or(t, |closest| closest.min(t)));
+                        }
+                    }
+                }
+                BvhNode::Inner { left, right, .. } => {
+                    open.push(left);
+                    open.push(right);
+                }
+            }
+        }
+
+        closest
+    }
+
+    pub fn is_segment_free(&self, from: Vec3, to: Vec3, agent_radius: f32) -> bool {
+        self.segment_hit(from, to, agent_radius).is_none()
+    }
+
+    // Returns the colliders whose surface is within the radius of the center
+    pub fn query_sphere(&self, center: Vec3, radius: f32) -> Vec<&Collider> {
+        let mut colliders = Vec::new();
+
+        if self.nodes.is_empty() {
+            return colliders;
+        }
+
+        let mut open = vec![0];
+
+        while let Some(index) = open.pop() {
+            let node = &self.nodes[index];
+            let bounds = Aabb::new(
+                node.bounds().center,
+                node.bounds().half_sizes + Vec3::splat(radius),
+            );
+
+            if !bounds.contains(center) {
+                continue;
+            }
+
+            match *node {
+                BvhNode::Leaf { start, end, .. } => colliders.extend(
+                    self.colliders[start..end]
+                        .iter()
+                        .filter(|collider| collider.signed_distance(center) <= radius),
+                ),
+                BvhNode::Inner { left, right, .. } => {
+                    open.push(left);
+                    open.push(right);
+                }
+            }
+        }
+
+        colliders
+    }
+
+    // Builds the node over the colliders in the range and returns its index
+    // The colliders are sorted along the longest axis of their centers and split in half.
+    fn build(&mut self, start: usize, end: usize) -> usize {
+        let bounds = self.colliders[start..end]
+            .iter()
+            .map(collider_bounds)
+            .reduce(|mut bounds, other| {
+                bounds.merge(&other);
+                bounds
+            })
+            .expect("A node always contains a collider");
+
+        let index = self.nodes.len();
+
+        if end - start <= MAX_LEAF_SIZE {
+            self.nodes.push(BvhNode::Leaf { bounds, start, end });
+            return index;
+        }
+
+        let axis = bounds
+            .half_sizes
+            .to_array()
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .map(|(axis, _)| axis)
+            .expect("A vector has three axes");
+
+        self.colliders[start..end].sort_by(|a, b| {
+            collider_bounds(a).center[axis].total_cmp(&collider_bounds(b).center[axis])
+        });
+
+        // Reserve the slot of this node before its children are pushed
+        self.nodes.push(BvhNode::Leaf {
+            bounds: bounds.clone(),
+            start,
+            end,
+        });
+
+        let middle = (start + end) / 2;
+        let left = self.build(start, middle);
+        let right = self.build(middle, end);
+
+        self.nodes[index] = BvhNode::Inner {
+            bounds,
+            left,
+            right,
+        };
+
+        index
+    }
+}
+
+fn collider_bounds(collider: &Collider) -> Aabb {
+    match collider {
+        Collider::Sphere(sphere) => Aabb::new(sphere.origin, Vec3::splat(sphere.radius)),
+        Collider::Aabb(aabb) => aabb.clone(),
+    }
+}
+
+// The result of validating the remaining part of a path
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PathValidation {
+    Clear,
+    Blocked {
+        // The index of the first blocked segment, with the same meaning as the path index of follow_path
+        segment_index: usize,
+        // The distance the agent can still travel along the path before hitting the obstacle
+        distance: f32,
+    },
+}
+
+// Re-validates the part of the path the agent hasn't travelled yet by sweeping
+// a sphere of the agent radius from the agent position along the remaining segments
+//
+// path_index: The index of the segment the agent is following
+pub fn validate_remaining_path(
+    path: &[Vec3],
+    path_index: usize,
+    agent_position: Vec3,
+    obstacles: &ObstacleBvh,
+    agent_radius: f32,
+) -> PathValidation {
+    let mut travelled = 0.0;
+    let mut from = agent_position;
+
+    for segment_index in path_index..path.len().saturating_sub(1) {
+        let to = path[segment_index + 1];
+
+        if let Some(t) = obstacles.segment_hit(from, to, agent_radius) {
+            return PathValidation::Blocked {
+                segment_index,
+                distance: travelled + t,
+            };
+        }
+
+        travelled += from.distance(to);
+        from = to;
+    }
+
+    PathValidation::Clear
+}
+
+#[cfg(test)]
+mod tests {
+    use geometry::Sphere;
+
+    use super::*;
+    use crate::is_segment_free;
+
+    #[test]
+    fn test_bvh_matches_brute_force() {
+        let colliders = (0..50)
+            .map(|i| {
+                let position = Vec3::new((i % 10) as f32 * 4.0, (i / 10) as f32 * 4.0, 0.0);
+
+                if i % 2 == 0 {
+                    Collider::Sphere(Sphere::new(1.0, position))
+                } else {
+                    Collider::new_aabb(position, Vec3::splat(0.8))
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let bvh = ObstacleBvh::new(colliders.clone());
+
+        for i in 0..20 {
+            let from = Vec3::new(-2.0, i as f32, 0.0);
+            let to = Vec3::new(40.0, 20.0 - i as f32, 0.0);
+
+            assert_eq!(
+                bvh.is_segment_free(from, to, 0.3),
+                is_segment_free(from, to, &colliders, 0.3)
+            );
+        }
+    }
+
+    #[test]
+    fn test_query_sphere_matches_brute_force() {
+        let colliders = (0..50)
+            .map(|i| {
+                let position = Vec3::new((i % 10) as f32 * 4.0, (i / 10) as f32 * 4.0, 0.0);
+
+                if i % 2 == 0 {
+                    Collider::Sphere(Sphere::new(1.0, position))
+                } else {
+                    Collider::new_aabb(position, Vec3::splat(0.8))
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let bvh = ObstacleBvh::new(colliders.clone());
+        let center = Vec3::new(13.0, 7.0, 1.0);
+
+        let expected = colliders
+            .iter()
+            .filter(|collider| collider.signed_distance(center) <= 5.0)
+            .count();
+
+        assert!(expected > 0);
+        assert_eq!(bvh.query_sphere(center, 5.0).len(), expected);
+    }
+
+    #[test]
+    fn test_reports_first_blocked_segment() {
+        let bvh = ObstacleBvh::new(vec![Collider::Sphere(Sphere::new(
+            1.0,
+            Vec3::new(10.0, 5.0, 0.0),
+        ))]);
+
+        let path = [
+            Vec3::ZERO,
+            Vec3::new(10.0, 0.0, 0.0),
+            Vec3::new(10.0, 10.0, 0.0),
+        ];
+
+        assert_eq!(
+            validate_remaining_path(&path, 0, Vec3::new(5.0, 0.0, 0.0), &bvh, 0.5),
+            PathValidation::Blocked {
+                segment_index: 1,
+                distance: 8.5,
+            }
+        );
+
+        assert_eq!(
+            validate_remaining_path(&path[..2], 0, Vec3::ZERO, &bvh, 0.5),
+            PathValidation::Clear
+        );
+    }
+}
@@ -0,0 +1,153 @@
+use bevy_math::Vec3;
+use geometry::colliders::Collider;
+use steering::fillet_path;
+
+use crate::is_segment_free;
+
+// Removes waypoints that can be skipped
+// From every kept waypoint the path continues directly to the farthest later waypoint
+// that can be reached without hitting a collider.
+pub fn shortcut_path(path: &[Vec3], colliders: &[Collider], agent_radius: f32) -> Vec<Vec3> {
+    if path.len() < 3 {
+        return path.to_vec();
+    }
+
+    let mut shortcut = vec![path[0]];
+    let mut current = 0;
+
+    while current < path.len() - 1 {
+        let next = (current + 1..path.len())
+            .rev()
+            .find(|&candidate| {
+                candidate == current + 1
+                    || is_segment_free(path[current], path[candidate], colliders, agent_radius)
+            })
+            .expect("The next waypoint is always a candidate");
+
+        shortcut.push(path[next]);
+        current = next;
+    }
+
+    shortcut
+}
+
+// Shortcuts the path and replaces its turns with arcs the agent can follow at the given speed
+//
+// speed: The speed at which the agent intends to follow the path
+// max_turn_speed: The maximum turning speed of the agent
+// arc_segments: The number of segments each arc is made of
+// Returns: The smoothed path, turns where the arc would hit a collider are kept sharp
+pub fn smooth_path(
+    path: &[Vec3],
+    colliders: &[Collider],
+    agent_radius: f32,
+    speed: f32,
+    max_turn_speed: f32,
+    arc_segments: usize,
+) -> Vec<Vec3> {
+    let path = shortcut_path(path, colliders, agent_radius);
+
+    if path.len() < 3 {
+        return path;
+    }
+
+    let mut smoothed = vec![path[0]];
+
+    for i in 1..path.len() - 1 {
+        // The same halves of the neighbouring segments the fillet of the whole path would use
+        let start = if i == 1 {
+            path[0]
+        } else {
+            path[i - 1].lerp(path[i], 0.5)
+        };
+
+        let end = if i == path.len() - 2 {
+            path[i + 1]
+        } else {
+            path[i].lerp(path[i + 1], 0.5)
+        };
+
+        let turn = fillet_path(&[start, path[i], end], speed, max_turn_speed, arc_segments);
+        let arc = &turn[1..turn.len() - 1];
+
+        let previous = *smoothed.last().expect("The path always has a start");
+        let is_arc_free = std::iter::once(previous)
+            .chain(arc.iter().copied())
+            .chain(std::iter::once(end))
+            .collect::<Vec<_>>()
+            .windows(2)
+            .all(|segment| is_segment_free(segment[0], segment[1], colliders, agent_radius));
+
+        if is_arc_free {
+            smoothed.extend_from_slice(arc);
+        } else {
+            smoothed.push(path[i]);
+        }
+    }
+
+    smoothed.push(path[path.len() - 1]);
+
+    smoothed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shortcut_removes_unnecessary_waypoints() {
+        let colliders = [Collider::new_aabb(
+            Vec3::new(5.0, -5.0, 0.0),
+            Vec3::new(1.0, 5.0, 1.0),
+        )];
+
+        let path = [
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(1.0, 1.0, 0.0),
+            Vec3::new(2.0, 2.0, 0.0),
+            Vec3::new(5.0, 2.0, 0.0),
+            Vec3::new(8.0, 2.0, 0.0),
+            Vec3::new(10.0, 0.0, 0.0),
+        ];
+
+        let shortcut = shortcut_path(&path, &colliders, 0.5);
+
+        assert!(shortcut.len() < path.len());
+        assert_eq!(shortcut.first(), path.first());
+        assert_eq!(shortcut.last(), path.last());
+
+        for segment in shortcut.windows(2) {
+            assert!(is_segment_free(segment[0], segment[1], &colliders, 0.5));
+        }
+    }
+
+    #[test]
+    fn test_smooth_path_inserts_arcs() {
+        let path = [
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(10.0, 0.0, 0.0),
+            Vec3::new(10.0, 10.0, 0.0),
+        ];
+
+        // Without obstacles the corner is cut directly
+        assert_eq!(
+            smooth_path(&path, &[], 0.5, 1.0, 1.0, 4),
+            vec![path[0], path[2]]
+        );
+
+        // A box inside of the corner prevents the shortcut, but leaves room for the arc
+        let colliders = [Collider::new_aabb(
+            Vec3::new(5.0, 5.0, 0.0),
+            Vec3::new(1.0, 1.0, 1.0),
+        )];
+
+        let smoothed = smooth_path(&path, &colliders, 0.5, 1.0, 1.0, 4);
+
+        assert_eq!(smoothed.len(), 7);
+        assert!(!smoothed.contains(&path[1]));
+
+        for segment in smoothed.windows(2) {
+            assert!(is_segment_free(segment[0], segment[1], &colliders, 0.5));
+        }
+    }
+}
@@ -0,0 +1,272 @@
+use bevy_math::Vec3;
+use geometry::Aabb;
+use rand::Rng;
+
+use crate::{a_star, is_point_free, ObstacleBvh, SearchGraph};
+
+// Parameters of the probabilistic roadmap
+//
+// number_of_samples: The number of free nodes sampled in the bounds
+// connection_radius: Nodes further apart are never connected
+// max_neighbours: The number of the closest nodes each node tries to connect to
+#[derive(Clone, Debug)]
+pub struct RoadmapParams {
+    pub number_of_samples: usize,
+    pub connection_radius: f32,
+    pub max_neighbours: usize,
+}
+
+impl RoadmapParams {
+    pub fn new(number_of_samples: usize, connection_radius: f32) -> Self {
+        assert!(connection_radius > 0.0);
+
+        Self {
+            number_of_samples,
+            connection_radius,
+            max_neighbours: 10,
+        }
+    }
+
+    pub fn with_max_neighbours(mut self, max_neighbours: usize) -> Self {
+        self.max_neighbours = max_neighbours;
+        self
+    }
+}
+
+// Probabilistic roadmap, a graph of sampled free points connected by collision-free edges
+// It's built once at startup or offline for the static obstacles, queries then only connect
+// the start and the goal to the closest nodes and search the graph, which is much cheaper
+// than a full grid in mostly static worlds.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Roadmap {
+    nodes: Vec<Vec3>,
+    edges: Vec<Vec<(usize, f32)>>,
+}
+
+impl Roadmap {
+    pub fn new(nodes: Vec<Vec3>, edges: Vec<(usize, usize)>) -> Self {
+        let mut roadmap = Self {
+            edges: vec![Vec::new(); nodes.len()],
+            nodes,
+        };
+
+        for (a, b) in edges {
+            roadmap.connect(a, b);
+        }
+
+        roadmap
+    }
+
+    pub fn build(
+        bounds: &Aabb,
+        obstacles: &ObstacleBvh,
+        agent_radius: f32,
+        params: &RoadmapParams,
+        rng: &mut impl Rng,
+    ) -> Self {
+        let mut nodes = Vec::with_capacity(params.number_of_samples);
+        let min = bounds.center - bounds.half_sizes;
+
+        // Bounded number of attempts, so a volume that's mostly blocked doesn't loop forever
+        for _ in 0..params.number_of_samples * 10 {
+            if nodes.len() == params.number_of_samples {
+                break;
+            }
+
+            let point = min + Vec3::new(rng.gen(), rng.gen(), rng.gen()) * bounds.half_sizes * 2.0;
+
+            if is_point_free(point, obstacles.colliders(), agent_radius) {
+                nodes.push(point);
+            }
+        }
+
+        let mut roadmap = Self::new(nodes, Vec::new());
+
+        for node in 0..roadmap.nodes.len() {
+            let position = roadmap.nodes[node];
+
+            for neighbour in roadmap.nearest_nodes(position, params.max_neighbours + 1) {
+                let neighbour_position = roadmap.nodes[neighbour];
+
+                if neighbour != node
+                    && position.distance(neighbour_position) <= params.connection_radius
+                    && obstacles.is_segment_free(position, neighbour_position, agent_radius)
+                {
+                    roadmap.connect(node, neighbour);
+                }
+            }
+        }
+
+        roadmap
+    }
+
+    pub fn nodes(&self) -> &[Vec3] {
+        &self.nodes
+    }
+
+    // Each edge is returned once, with the smaller index first
+    pub fn edges(&self) -> Vec<(usize, usize)> {
+        self.edges
+            .iter()
+            .enumerate()
+            .flat_map(|(a, edges)| {
+                edges
+                    .iter()
+                    .filter(move |(b, _)| a < *b)
+                    .map(move |&(b, _)| (a, b))
+            })
+            .collect()
+    }
+
+    // Returns the indexes of the closest nodes sorted by their distance
+    pub fn nearest_nodes(&self, position: Vec3, count: usize) -> Vec<usize> {
+        let mut nodes = (0..self.nodes.len()).collect::<Vec<_>>();
+
+        nodes.sort_by(|&a, &b| {
+            self.nodes[a]
+                .distance_squared(position)
+                .total_cmp(&self.nodes[b].distance_squared(position))
+        });
+        nodes.truncate(count);
+
+        nodes
+    }
+
+    // Connects the start and the goal to the closest visible nodes and searches the roadmap
+    //
+    // max_connections: The number of the closest nodes the start and the goal try to connect to
+    // Returns: Waypoints starting at start and ending at goal
+    pub fn find_path(
+        &self,
+        start: Vec3,
+        goal: Vec3,
+        obstacles: &ObstacleBvh,
+        agent_radius: f32,
+        max_connections: usize,
+    ) -> Option<Vec<Vec3>> {
+        if obstacles.is_segment_free(start, goal, agent_radius) {
+            return Some(vec![start, goal]);
+        }
+
+        let visible = |position: Vec3| {
+            self.nearest_nodes(position, max_connections)
+                .into_iter()
+                .filter(|&node| obstacles.is_segment_free(position, self.nodes[node], agent_radius))
+                .map(|node| (node, position.distance(self.nodes[node])))
+                .collect::<Vec<_>>()
+        };
+
+        let query = RoadmapQuery {
+            roadmap: self,
+            start,
+            goal,
+            start_edges: visible(start),
+            goal_edges: visible(goal),
+        };
+
+        let (nodes, _) = a_star(&query, query.start_node(), query.goal_node())?;
+
+        Some(nodes.into_iter().map(|node| query.position(node)).collect())
+    }
+
+    fn connect(&mut self, a: usize, b: usize) {
+        if a == b || self.edges[a].iter().any(|(node, _)| *node == b) {
+            return;
+        }
+
+        let cost = self.nodes[a].distance(self.nodes[b]);
+
+        self.edges[a].push((b, cost));
+        self.edges[b].push((a, cost));
+    }
+}
+
+// The roadmap extended by the start and the goal of a query
+struct RoadmapQuery<'a> {
+    roadmap: &'a Roadmap,
+    start: Vec3,
+    goal: Vec3,
+    start_edges: Vec<(usize, f32)>,
+    goal_edges: Vec<(usize, f32)>,
+}
+
+impl<'a> RoadmapQuery<'a> {
+    fn start_node(&self) -> usize {
+        self.roadmap.nodes.len()
+    }
+
+    fn goal_node(&self) -> usize {
+        self.roadmap.nodes.len() + 1
+    }
+
+    fn position(&self, node: usize) -> Vec3 {
+        if node == self.start_node() {
+            self.start
+        } else if node == self.goal_node() {
+            self.goal
+        } else {
+            self.roadmap.nodes[node]
+        }
+    }
+}
+
+impl<'a> SearchGraph for RoadmapQuery<'a> {
+    type Node = usize;
+
+    fn successors(&self, node: usize, successors: &mut Vec<(usize, f32)>) {
+        if node == self.start_node() {
+            successors.extend_from_slice(&self.start_edges);
+            return;
+        }
+
+        if node == self.goal_node() {
+            return;
+        }
+
+        successors.extend_from_slice(&self.roadmap.edges[node]);
+
+        if let Some(&(_, cost)) = self.goal_edges.iter().find(|(n, _)| *n == node) {
+            successors.push((self.goal_node(), cost));
+        }
+    }
+
+    fn heuristic(&self, node: usize, goal: usize) -> f32 {
+        self.position(node).distance(self.position(goal))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use geometry::{colliders::Collider, Sphere};
+    use rand::SeedableRng;
+
+    use super::*;
+
+    #[test]
+    fn test_roadmap_path_around_obstacle() {
+        let obstacles = ObstacleBvh::new(vec![Collider::Sphere(Sphere::new(3.0, Vec3::ZERO))]);
+        let bounds = Aabb::new(Vec3::ZERO, Vec3::splat(10.0));
+        let mut rng = rand::rngs::StdRng::seed_from_u64(7);
+
+        let roadmap = Roadmap::build(
+            &bounds,
+            &obstacles,
+            0.5,
+            &RoadmapParams::new(300, 6.0),
+            &mut rng,
+        );
+
+        assert_eq!(roadmap.nodes().len(), 300);
+
+        let start = Vec3::new(-8.0, 0.0, 0.0);
+        let goal = Vec3::new(8.0, 0.0, 0.0);
+        let path = roadmap.find_path(start, goal, &obstacles, 0.5, 10).unwrap();
+
+        assert_eq!(path.first(), Some(&start));
+        assert_eq!(path.last(), Some(&goal));
+
+        for segment in path.windows(2) {
+            assert!(obstacles.is_segment_free(segment[0], segment[1], 0.5));
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use serde::{Deserialize, Serialize};
+
+use crate::Roadmap;
+
+// Serializable form of a roadmap, so it can be built offline and loaded at startup
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RoadmapDescription {
+    pub nodes: Vec<[f32; 3]>,
+    pub edges: Vec<(usize, usize)>,
+}
+
+impl From<&Roadmap> for RoadmapDescription {
+    fn from(roadmap: &Roadmap) -> Self {
+        Self {
+            nodes: roadmap.nodes().iter().map(|node| node.to_array()).collect(),
+            edges: roadmap.edges(),
+        }
+    }
+}
+
+impl From<RoadmapDescription> for Roadmap {
+    fn from(description: RoadmapDescription) -> Self {
+        Roadmap::new(
+            description
+                .nodes
+                .into_iter()
+                .map(bevy_math::Vec3::from_array)
+                .collect(),
+            description.edges,
+        )
+    }
+}
+
+#[cfg(feature = "ron")]
+pub fn roadmap_to_ron(roadmap: &Roadmap) -> Result<String, ron::Error> {
+    ron::to_string(&RoadmapDescription::from(roadmap))
+}
+
+#[cfg(feature = "ron")]
+pub fn roadmap_from_ron(source: &str) -> Result<Roadmap, ron::error::SpannedError> {
+    let description: RoadmapDescription = ron::from_str(source)?;
+
+    Ok(description.into())
+}
+
+#[cfg(feature = "json")]
+pub fn roadmap_to_json(roadmap: &Roadmap) -> Result<String, serde_json::Error> {
+    serde_json::to_string(&RoadmapDescription::from(roadmap))
+}
+
+#[cfg(feature = "json")]
+pub fn roadmap_from_json(source: &str) -> Result<Roadmap, serde_json::Error> {
+    let description: RoadmapDescription = serde_json::from_str(source)?;
+
+    Ok(description.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_math::Vec3;
+
+    use super::*;
+
+    #[test]
+    fn test_description_round_trip() {
+        let roadmap = Roadmap::new(
+            vec![Vec3::ZERO, Vec3::X, Vec3::new(1.0, 1.0, 0.0)],
+            vec![(0, 1), (1, 2)],
+        );
+
+        let description = RoadmapDescription::from(&roadmap);
+
+        assert_eq!(description.edges, vec![(0, 1), (1, 2)]);
+        assert_eq!(Roadmap::from(description), roadmap);
+    }
+}
@@ -0,0 +1,315 @@
+use bevy_math::{Quat, Vec3};
+use geometry::{colliders::Collider, Aabb};
+use rand::Rng;
+
+use crate::is_segment_free;
+
+// Parameters of the RRT* planner
+//
+// max_iterations: The number of samples drawn before the planner gives up or returns the best path
+// step_size: The maximum length of a new edge of the tree
+// goal_bias: The probability of sampling the goal instead of a random point
+// rewiring_radius: The radius in which new nodes look for better parents and rewire their neighbours
+// goal_tolerance: The distance from the goal at which a node can connect to it
+// informed: When a path is found, samples are only drawn from the ellipsoid of points
+//           that could still improve it (Informed RRT*)
+// stop_at_first_path: Returns as soon as any path is found, which makes the planner plain RRT
+#[derive(Clone, Debug)]
+pub struct RrtParams {
+    pub max_iterations: usize,
+    pub step_size: f32,
+    pub goal_bias: f32,
+    pub rewiring_radius: f32,
+    pub goal_tolerance: f32,
+    pub informed: bool,
+    pub stop_at_first_path: bool,
+}
+
+impl RrtParams {
+    pub fn new(step_size: f32) -> Self {
+        assert!(step_size > 0.0);
+
+        Self {
+            max_iterations: 1000,
+            step_size,
+            goal_bias: 0.05,
+            rewiring_radius: step_size * 2.0,
+            goal_tolerance: step_size,
+            informed: true,
+            stop_at_first_path: false,
+        }
+    }
+
+    pub fn with_max_iterations(mut self, max_iterations: usize) -> Self {
+        self.max_iterations = max_iterations;
+        self
+    }
+
+    pub fn with_goal_bias(mut self, goal_bias: f32) -> Self {
+        assert!((0.0..=1.0).contains(&goal_bias));
+
+        self.goal_bias = goal_bias;
+        self
+    }
+
+    pub fn with_rewiring_radius(mut self, rewiring_radius: f32) -> Self {
+        self.rewiring_radius = rewiring_radius;
+        self
+    }
+
+    pub fn with_goal_tolerance(mut self, goal_tolerance: f32) -> Self {
+        self.goal_tolerance = goal_tolerance;
+        self
+    }
+
+    pub fn with_informed_sampling(mut self, informed: bool) -> Self {
+        self.informed = informed;
+        self
+    }
+
+    pub fn with_stop_at_first_path(mut self, stop_at_first_path: bool) -> Self {
+        self.stop_at_first_path = stop_at_first_path;
+        self
+    }
+}
+
+#[derive(Clone, Debug)]
+struct RrtNode {
+    position: Vec3,
+    parent: Option<usize>,
+    cost: f32,
+}
+
+// Plans a path with RRT* directly against the colliders, without discretizing the space
+//
+// bounds: The volume random samples are drawn from
+// agent_radius: The radius of the agent, edges are checked by casting a sphere of this radius
+// Returns: The polyline from start to goal, or None if no path was found within the iterations
+pub fn rrt_star(
+    start: Vec3,
+    goal: Vec3,
+    bounds: &Aabb,
+    colliders: &[Collider],
+    agent_radius: f32,
+    params: &RrtParams,
+    rng: &mut impl Rng,
+) -> Option<Vec<Vec3>> {
+    if is_segment_free(start, goal, colliders, agent_radius) {
+        return Some(vec![start, goal]);
+    }
+
+    let mut nodes = vec![RrtNode {
+        position: start,
+        parent: None,
+        cost: 0.0,
+    }];
+    let mut best_cost = f32::INFINITY;
+
+    for _ in 0..params.max_iterations {
+        let sample = if rng.gen::<f32>() < params.goal_bias {
+            goal
+        } else if params.informed && best_cost.is_finite() {
+            sample_informed(start, goal, best_cost, bounds, rng)
+        } else {
+            sample_uniform(bounds, rng)
+        };
+
+        let nearest = nearest_node(&nodes, sample);
+        let nearest_position = nodes[nearest].position;
+        let offset = sample - nearest_position;
+
+        if offset.length() <= f32::EPSILON {
+            continue;
+        }
+
+        let position = nearest_position + offset.clamp_length_max(params.step_size);
+
+        if !is_segment_free(nearest_position, position, colliders, agent_radius) {
+            continue;
+        }
+
+        let neighbours = (0..nodes.len())
+            .filter(|&i| nodes[i].position.distance(position) <= params.rewiring_radius)
+            .collect::<Vec<_>>();
+
+        // Connect to the neighbour through which the new node is reached most cheaply
+        let mut parent = nearest;
+        let mut cost = nodes[nearest].cost + nearest_position.distance(position);
+
+        for &neighbour in &neighbours {
+            let neighbour_cost =
+                nodes[neighbour].cost + nodes[neighbour].position.distance(position);
+
+            if neighbour_cost < cost
+                && is_segment_free(nodes[neighbour].position, position, colliders, agent_radius)
+            {
+                parent = neighbour;
+                cost = neighbour_cost;
+            }
+        }
+
+        let index = nodes.len();
+        nodes.push(RrtNode {
+            position,
+            parent: Some(parent),
+            cost,
+        });
+
+        // Rewire the neighbours that are cheaper to reach through the new node
+        for &neighbour in &neighbours {
+            let rewired_cost = cost + position.distance(nodes[neighbour].position);
+
+            if rewired_cost < nodes[neighbour].cost
+                && is_segment_free(position, nodes[neighbour].position, colliders, agent_radius)
+            {
+                nodes[neighbour].parent = Some(index);
+                propagate_cost(&mut nodes, neighbour, rewired_cost);
+            }
+        }
+
+        if position.distance(goal) <= params.goal_tolerance
+            && is_segment_free(position, goal, colliders, agent_radius)
+        {
+            best_cost = best_cost.min(cost + position.distance(goal));
+
+            if params.stop_at_first_path {
+                break;
+            }
+        }
+    }
+
+    // Costs change while rewiring, so the best connection to the goal is searched at the end
+    let (last, _) = nodes
+        .iter()
+        .enumerate()
+        .filter(|(_, node)| {
+            node.position.distance(goal) <= params.goal_tolerance
+                && is_segment_free(node.position, goal, colliders, agent_radius)
+        })
+        .map(|(i, node)| (i, node.cost + node.position.distance(goal)))
+        .min_by(|a, b| a.1.total_cmp(&b.1))?;
+
+    let mut path = vec![goal];
+    let mut current = Some(last);
+
+    while let Some(index) = current {
+        if nodes[index].position != goal {
+            path.push(nodes[index].position);
+        }
+
+        current = nodes[index].parent;
+    }
+
+    path.reverse();
+
+    Some(path)
+}
+
+fn nearest_node(nodes: &[RrtNode], point: Vec3) -> usize {
+    nodes
+        .iter()
+        .enumerate()
+        .min_by(|a, b| {
+            a.1.position
+                .distance_squared(point)
+                .total_cmp(&b.1.position.distance_squared(point))
+        })
+        .map(|(i, _)| i)
+        .expect("The tree always contains the start")
+}
+
+// Sets the cost of the node and updates the costs of all its descendants
+fn propagate_cost(nodes: &mut [RrtNode], index: usize, cost: f32) {
+    nodes[index].cost = cost;
+
+    let mut open = vec![index];
+
+    while let Some(parent) = open.pop() {
+        for child in 0..nodes.len() {
+            if nodes[child].parent == Some(parent) {
+                nodes[child].cost =
+                    nodes[parent].cost + nodes[parent].position.distance(nodes[child].position);
+                open.push(child);
+            }
+        }
+    }
+}
+
+fn sample_uniform(bounds: &Aabb, rng: &mut impl Rng) -> Vec3 {
+    let unit = Vec3::new(rng.gen(), rng.gen(), rng.gen());
+
+    bounds.center - bounds.half_sizes + unit * bounds.half_sizes * 2.0
+}
+
+// Samples the prolate spheroid with the start and the goal in its foci, which contains
+// all points through which a path shorter than best_cost could go
+fn sample_informed(
+    start: Vec3,
+    goal: Vec3,
+    best_cost: f32,
+    bounds: &Aabb,
+    rng: &mut impl Rng,
+) -> Vec3 {
+    let minimal_cost = start.distance(goal);
+    let transverse = best_cost / 2.0;
+    let conjugate = (best_cost * best_cost - minimal_cost * minimal_cost)
+        .max(0.0)
+        .sqrt()
+        / 2.0;
+
+    let point_in_ball = loop {
+        let point = Vec3::new(
+            rng.gen_range(-1.0..=1.0),
+            rng.gen_range(-1.0..=1.0),
+            rng.gen_range(-1.0..=1.0),
+        );
+
+        if point.length_squared() <= 1.0 {
+            break point;
+        }
+    };
+
+    let rotation = Quat::from_rotation_arc(Vec3::X, (goal - start).normalize_or_zero());
+    let point = (start + goal) / 2.0
+        + rotation * (point_in_ball * Vec3::new(transverse, conjugate, conjugate));
+
+    point.clamp(
+        bounds.center - bounds.half_sizes,
+        bounds.center + bounds.half_sizes,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use geometry::Sphere;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    #[test]
+    fn test_path_around_sphere() {
+        let colliders = [Collider::Sphere(Sphere::new(2.0, Vec3::ZERO))];
+        let bounds = Aabb::new(Vec3::ZERO, Vec3::splat(6.0));
+        let start = Vec3::new(-5.0, 0.0, 0.0);
+        let goal = Vec3::new(5.0, 0.0, 0.0);
+        let mut rng = rand::rngs::StdRng::seed_from_u64(42);
+
+        let path = rrt_star(
+            start,
+            goal,
+            &bounds,
+            &colliders,
+            0.5,
+            &RrtParams::new(1.0).with_max_iterations(2000),
+            &mut rng,
+        )
+        .unwrap();
+
+        assert_eq!(path.first(), Some(&start));
+        assert_eq!(path.last(), Some(&goal));
+
+        for window in path.windows(2) {
+            assert!(is_segment_free(window[0], window[1], &colliders, 0.5));
+        }
+    }
+}
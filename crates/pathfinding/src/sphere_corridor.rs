@@ -0,0 +1,188 @@
+use bevy_math::Vec3;
+use geometry::{colliders::Collider, Plane, Sphere, Vec3Operations};
+
+// Part of the radius of a sphere the next sphere's center is placed at, so neighbouring spheres overlap
+const OVERLAP: f32 = 0.75;
+// Step along the path when searching for the next center, relative to the radius of the current sphere
+const STEP: f32 = 0.05;
+
+// Safe corridor made of overlapping spheres that are free of colliders
+// The spheres bound the position of the center of the agent, so the agent itself
+// never touches a collider while its center stays inside of them.
+#[derive(Clone, Debug)]
+pub struct SphereCorridor {
+    spheres: Vec<Sphere>,
+}
+
+impl SphereCorridor {
+    // Covers the path with free spheres
+    //
+    // agent_radius: The radius of the agent the corridor is built for
+    // min_radius: Spheres can't be smaller, if the path gets closer to a collider there's no corridor
+    // max_radius: Spheres can't be larger, even in open space
+    // Returns: The corridor from the start to the goal, or None if the path is too close to a collider
+    pub fn from_path(
+        path: &[Vec3],
+        colliders: &[Collider],
+        agent_radius: f32,
+        min_radius: f32,
+        max_radius: f32,
+    ) -> Option<Self> {
+        assert!(min_radius > 0.0 && min_radius <= max_radius);
+
+        let goal = *path.last()?;
+        let length = path.windows(2).map(|w| w[0].distance(w[1])).sum::<f32>();
+        let mut spheres = Vec::new();
+        let mut distance = 0.0;
+
+        loop {
+            let center = point_at(path, distance);
+            let clearance = colliders
+                .iter()
+                .map(|collider| collider.signed_distance(center))
+                .fold(f32::INFINITY, f32::min)
+                - agent_radius;
+
+            if clearance < min_radius {
+                return None;
+            }
+
+            let radius = clearance.min(max_radius);
+            spheres.push(Sphere::new(radius, center));
+
+            if center.distance(goal) <= radius {
+                break;
+            }
+
+            // The next center is the farthest point of the path that is still deep inside of this sphere
+            let step = radius * STEP;
+
+            while distance < length
+                && point_at(path, distance + step).distance(center) < radius * OVERLAP
+            {
+                distance += step;
+            }
+        }
+
+        Some(Self { spheres })
+    }
+
+    pub fn spheres(&self) -> &[Sphere] {
+        &self.spheres
+    }
+
+    // Advances the index of the current sphere as long as the agent is inside of the next one
+    pub fn current_sphere(&self, agent_position: Vec3, current_index: usize) -> usize {
+        let mut index = current_index.min(self.spheres.len().saturating_sub(1));
+
+        while index + 1 < self.spheres.len() && self.spheres[index + 1].contains(agent_position) {
+            index += 1;
+        }
+
+        index
+    }
+
+    // Returns the velocity closest to the preferred one, that keeps the agent
+    // inside of the sphere for the whole time horizon
+    pub fn keep_in_sphere(
+        &self,
+        index: usize,
+        agent_position: Vec3,
+        preferred_velocity: Vec3,
+        time_horizon: f32,
+    ) -> Vec3 {
+        let sphere = &self.spheres[index];
+        let predicted_position = agent_position + preferred_velocity * time_horizon;
+
+        if sphere.contains(predicted_position) {
+            return preferred_velocity;
+        }
+
+        (sphere.constrain(predicted_position) - agent_position) / time_horizon
+    }
+
+    // Expresses the sphere as a velocity constraint for ORCA
+    // The sphere is linearized at the point closest to the agent, the velocities on the normal side
+    // of the plane keep the agent inside of the sphere for the whole time horizon.
+    pub fn orca_plane(&self, index: usize, agent_position: Vec3, time_horizon: f32) -> Plane {
+        let sphere = &self.spheres[index];
+        let offset = agent_position - sphere.origin;
+        let distance = offset.length();
+        let direction = if distance > f32::EPSILON {
+            offset / distance
+        } else {
+            Vec3::X
+        };
+
+        Plane::new(
+            direction * (sphere.radius - distance) / time_horizon,
+            -direction,
+        )
+    }
+}
+
+// Returns the point at the given arc length of the polyline
+fn point_at(path: &[Vec3], distance: f32) -> Vec3 {
+    let mut remaining = distance;
+
+    for segment in path.windows(2) {
+        let length = segment[0].distance(segment[1]);
+
+        if remaining <= length {
+            return if length > f32::EPSILON {
+                segment[0].lerp(segment[1], remaining / length)
+            } else {
+                segment[0]
+            };
+        }
+
+        remaining -= length;
+    }
+
+    *path.last().expect("The path can't be empty")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_corridor_is_free_and_connected() {
+        let colliders = [Collider::Sphere(Sphere::new(
+            2.0,
+            Vec3::new(10.0, 3.0, 0.0),
+        ))];
+        let path = [Vec3::ZERO, Vec3::new(20.0, 0.0, 0.0)];
+
+        let corridor = SphereCorridor::from_path(&path, &colliders, 0.5, 0.1, 4.0).unwrap();
+        let spheres = corridor.spheres();
+
+        assert!(spheres.len() > 1);
+        assert!(spheres.last().unwrap().contains(path[1]));
+
+        for sphere in spheres {
+            assert!(colliders[0].signed_distance(sphere.origin) - sphere.radius >= 0.5 - 1e-4);
+        }
+
+        for pair in spheres.windows(2) {
+            assert!(pair[0].contains(pair[1].origin));
+        }
+    }
+
+    #[test]
+    fn test_velocity_stays_inside_sphere() {
+        let corridor = SphereCorridor {
+            spheres: vec![Sphere::new(1.0, Vec3::ZERO)],
+        };
+
+        let position = Vec3::new(0.5, 0.0, 0.0);
+        let velocity = corridor.keep_in_sphere(0, position, Vec3::new(2.0, 0.0, 0.0), 1.0);
+
+        approx::assert_relative_eq!(velocity.x, 0.5, epsilon = 1e-4);
+
+        let plane = corridor.orca_plane(0, position, 1.0);
+
+        assert!(plane.contains(Vec3::new(0.4, 0.0, 0.0)));
+        assert!(!plane.contains(Vec3::new(0.6, 0.0, 0.0)));
+    }
+}
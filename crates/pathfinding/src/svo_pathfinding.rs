@@ -0,0 +1,113 @@
+use bevy_math::Vec3;
+use svo::{SparseVoxelOctree, SparseVoxelOctreeLink};
+
+use crate::{a_star_with_budget, SearchGraph};
+
+// Search graph over the free nodes of a sparse voxel octree
+// Open space is covered by large nodes and the space around obstacles by single voxels,
+// so the number of nodes the search has to visit stays low even in large volumes.
+// Neighbours across faces of nodes with different sizes come from SparseVoxelOctree::successors.
+pub struct SvoGraph<'a> {
+    tree: &'a SparseVoxelOctree,
+}
+
+impl<'a> SvoGraph<'a> {
+    pub fn new(tree: &'a SparseVoxelOctree) -> Self {
+        Self { tree }
+    }
+
+    // Finds a path between two positions
+    //
+    // max_nodes: The memory budget of the search, the number of nodes it can keep track of
+    // Returns: Waypoints starting at start and ending at goal, passing through the centers
+    // of the faces between the visited nodes
+    pub fn find_path(&self, start: Vec3, goal: Vec3, max_nodes: usize) -> Option<Vec<Vec3>> {
+        let start_node = self.tree.find_node(start)?;
+        let goal_node = self.tree.find_node(goal)?;
+
+        let (nodes, _) = a_star_with_budget(self, start_node, goal_node, max_nodes)?;
+
+        let mut waypoints = vec![start];
+
+        for window in nodes.windows(2) {
+            waypoints.push(
+                self.tree
+                    .face_position_between(window[0], window[1])
+                    .unwrap_or_else(|| self.tree.node_position(window[1])),
+            );
+        }
+
+        waypoints.push(goal);
+
+        Some(waypoints)
+    }
+}
+
+impl<'a> SearchGraph for SvoGraph<'a> {
+    type Node = SparseVoxelOctreeLink;
+
+    fn successors(
+        &self,
+        node: SparseVoxelOctreeLink,
+        successors: &mut Vec<(SparseVoxelOctreeLink, f32)>,
+    ) {
+        let position = self.tree.node_position(node);
+
+        for successor in self.tree.successors(node) {
+            successors.push((
+                successor,
+                position.distance(self.tree.node_position(successor)),
+            ));
+        }
+    }
+
+    fn heuristic(&self, node: SparseVoxelOctreeLink, goal: SparseVoxelOctreeLink) -> f32 {
+        self.tree
+            .node_position(node)
+            .distance(self.tree.node_position(goal))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_math::{IVec3, UVec3};
+    use svo::{SparseVoxelOctreeBuilder, VoxelizedMesh};
+
+    use super::*;
+
+    fn build_tree() -> SparseVoxelOctree {
+        let mut builder = SparseVoxelOctreeBuilder::new(1.0);
+
+        builder.add_mesh(VoxelizedMesh::new(
+            vec![UVec3::new(0, 3, 0)],
+            1.0,
+            IVec3::ZERO,
+        ));
+        builder.set_bounds(Vec3::splat(-8.0), Vec3::splat(8.0));
+
+        builder.build()
+    }
+
+    #[test]
+    fn test_path_across_mixed_resolution_nodes() {
+        let tree = build_tree();
+        let graph = SvoGraph::new(&tree);
+        let start = Vec3::new(-6.5, -6.5, -6.5);
+        let goal = Vec3::new(0.5, 2.5, 0.5);
+
+        let path = graph.find_path(start, goal, usize::MAX).unwrap();
+
+        assert_eq!(path.first(), Some(&start));
+        assert_eq!(path.last(), Some(&goal));
+    }
+
+    #[test]
+    fn test_memory_budget() {
+        let tree = build_tree();
+        let graph = SvoGraph::new(&tree);
+
+        assert!(graph
+            .find_path(Vec3::new(-6.5, -6.5, -6.5), Vec3::new(0.5, 2.5, 0.5), 1)
+            .is_none());
+    }
+}
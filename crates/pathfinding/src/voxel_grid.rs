@@ -0,0 +1,294 @@
+use bevy_math::{IVec3, UVec3, Vec3};
+use geometry::{colliders::Collider, Vec3Operations};
+
+use crate::{a_star, SearchGraph};
+
+// Uniform occupancy grid over an axis aligned box of the world
+#[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize),
+    archive(check_bytes)
+)]
+pub struct VoxelGrid {
+    min: Vec3,
+    voxel_size: f32,
+    dimensions: UVec3,
+    occupied: Vec<bool>,
+}
+
+impl VoxelGrid {
+    // Creates an empty grid covering the box between min and max
+    pub fn new(min: Vec3, max: Vec3, voxel_size: f32) -> Self {
+        assert!(voxel_size > 0.0);
+        assert!(max.cmpgt(min).all(), "The grid needs a positive volume");
+
+        let dimensions = ((max - min) / voxel_size).ceil().as_uvec3();
+        let len = (dimensions.x * dimensions.y * dimensions.z) as usize;
+
+        Self {
+            min,
+            voxel_size,
+            dimensions,
+            occupied: vec![false; len],
+        }
+    }
+
+    // Creates a grid where all voxels colliding with an agent of the given radius are occupied
+    pub fn from_colliders(
+        min: Vec3,
+        max: Vec3,
+        voxel_size: f32,
+        colliders: &[Collider],
+        agent_radius: f32,
+    ) -> Self {
+        let mut grid = Self::new(min, max, voxel_size);
+
+        for collider in colliders {
+            grid.add_collider(collider, agent_radius);
+        }
+
+        grid
+    }
+
+    // Marks all voxels the collider inflated by the agent radius reaches into as occupied
+    // The inflation is conservative, a voxel is occupied when any point inside it
+    // could be closer to the collider than the agent radius.
+    pub fn add_collider(&mut self, collider: &Collider, agent_radius: f32) {
+        let half_diagonal = self.voxel_size * 3.0_f32.sqrt() / 2.0;
+        let sphere = collider.bounding_sphere();
+        let reach = Vec3::splat(sphere.radius + agent_radius + self.voxel_size);
+        let from = self.clamp_cell(self.cell_coordinates(sphere.origin - reach));
+        let to = self.clamp_cell(self.cell_coordinates(sphere.origin + reach));
+
+        for z in from.z..=to.z {
+            for y in from.y..=to.y {
+                for x in from.x..=to.x {
+                    let cell = IVec3::new(x, y, z);
+
+                    if collider.signed_distance(self.cell_center(cell))
+                        < agent_radius + half_diagonal
+                    {
+                        self.set_occupied(cell, true);
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn min(&self) -> Vec3 {
+        self.min
+    }
+
+    pub fn voxel_size(&self) -> f32 {
+        self.voxel_size
+    }
+
+    pub fn dimensions(&self) -> UVec3 {
+        self.dimensions
+    }
+
+    pub fn contains_cell(&self, cell: IVec3) -> bool {
+        cell.cmpge(IVec3::ZERO).all() && cell.cmplt(self.dimensions.as_ivec3()).all()
+    }
+
+    // Returns the cell containing the position, or None when it's outside of the grid
+    pub fn cell_at(&self, position: Vec3) -> Option<IVec3> {
+        let cell = self.cell_coordinates(position);
+
+        self.contains_cell(cell).then_some(cell)
+    }
+
+    pub fn cell_center(&self, cell: IVec3) -> Vec3 {
+        self.min + (cell.as_vec3() + Vec3::splat(0.5)) * self.voxel_size
+    }
+
+    // Cells outside of the grid are considered occupied
+    pub fn is_occupied(&self, cell: IVec3) -> bool {
+        !self.contains_cell(cell) || self.occupied[self.index(cell)]
+    }
+
+    pub fn set_occupied(&mut self, cell: IVec3, occupied: bool) {
+        if self.contains_cell(cell) {
+            let index = self.index(cell);
+            self.occupied[index] = occupied;
+        }
+    }
+
+    // Finds a path between two positions with 26-connected A*
+    // Returns: Waypoints starting at start and ending at goal, without collinear points in between
+    pub fn find_path(&self, start: Vec3, goal: Vec3) -> Option<Vec<Vec3>> {
+        let start_cell = self.cell_at(start)?;
+        let goal_cell = self.cell_at(goal)?;
+
+        if self.is_occupied(start_cell) || self.is_occupied(goal_cell) {
+            return None;
+        }
+
+        let (cells, _) = a_star(self, start_cell, goal_cell)?;
+
+        Some(self.cells_to_waypoints(&cells, start, goal))
+    }
+
+    // Converts a path of cells into waypoints, the first and the last cell are replaced
+    // by the exact start and goal positions
+    pub fn cells_to_waypoints(&self, cells: &[IVec3], start: Vec3, goal: Vec3) -> Vec<Vec3> {
+        let mut waypoints = vec![start];
+
+        for i in 1..cells.len().saturating_sub(1) {
+            // Only the cells where the path changes direction are kept
+            if cells[i] - cells[i - 1] != cells[i + 1] - cells[i] {
+                waypoints.push(self.cell_center(cells[i]));
+            }
+        }
+
+        waypoints.push(goal);
+        waypoints
+    }
+
+    // Returns the cell and all of its 26 neighbours inside of the grid
+    // These are the cells whose edges change when the occupancy of the cell changes
+    pub fn cells_around(&self, cell: IVec3) -> Vec<IVec3> {
+        let mut cells = Vec::with_capacity(27);
+
+        for z in -1..=1 {
+            for y in -1..=1 {
+                for x in -1..=1 {
+                    let neighbour = cell + IVec3::new(x, y, z);
+
+                    if self.contains_cell(neighbour) {
+                        cells.push(neighbour);
+                    }
+                }
+            }
+        }
+
+        cells
+    }
+
+    // Returns true when a move between two neighbouring cells doesn't cut through occupied corners
+    pub fn is_move_free(&self, from: IVec3, to: IVec3) -> bool {
+        let min = from.min(to);
+        let max = from.max(to);
+
+        for z in min.z..=max.z {
+            for y in min.y..=max.y {
+                for x in min.x..=max.x {
+                    if self.is_occupied(IVec3::new(x, y, z)) {
+                        return false;
+                    }
+                }
+            }
+        }
+
+        true
+    }
+
+    fn cell_coordinates(&self, position: Vec3) -> IVec3 {
+        ((position - self.min) / self.voxel_size).floor().as_ivec3()
+    }
+
+    fn clamp_cell(&self, cell: IVec3) -> IVec3 {
+        cell.clamp(IVec3::ZERO, self.dimensions.as_ivec3() - IVec3::ONE)
+    }
+
+    pub(crate) fn index(&self, cell: IVec3) -> usize {
+        let dimensions = self.dimensions.as_ivec3();
+
+        (cell.x + cell.y * dimensions.x + cell.z * dimensions.x * dimensions.y) as usize
+    }
+}
+
+impl SearchGraph for VoxelGrid {
+    type Node = IVec3;
+
+    fn successors(&self, node: IVec3, successors: &mut Vec<(IVec3, f32)>) {
+        for z in -1..=1 {
+            for y in -1..=1 {
+                for x in -1..=1 {
+                    let offset = IVec3::new(x, y, z);
+
+                    if offset == IVec3::ZERO {
+                        continue;
+                    }
+
+                    let neighbour = node + offset;
+
+                    if self.is_move_free(node, neighbour) {
+                        successors.push((neighbour, offset.as_vec3().length() * self.voxel_size));
+                    }
+                }
+            }
+        }
+    }
+
+    // Octile distance generalized to three dimensions
+    fn heuristic(&self, node: IVec3, goal: IVec3) -> f32 {
+        let mut delta = (goal - node).abs().to_array();
+        delta.sort_unstable();
+
+        let [smallest, middle, largest] = delta.map(|d| d as f32);
+
+        ((3.0_f32.sqrt() - 2.0_f32.sqrt()) * smallest + (2.0_f32.sqrt() - 1.0) * middle + largest)
+            * self.voxel_size
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "rkyv")]
+    #[test]
+    fn test_rkyv_round_trip() {
+        let collider = Collider::new_sphere(2.0);
+        let grid =
+            VoxelGrid::from_colliders(Vec3::splat(-5.0), Vec3::splat(5.0), 1.0, &[collider], 0.5);
+
+        let bytes = rkyv::to_bytes::<_, 1024>(&grid).unwrap();
+        let archived = rkyv::check_archived_root::<VoxelGrid>(&bytes).unwrap();
+        let deserialized: VoxelGrid =
+            rkyv::Deserialize::deserialize(archived, &mut rkyv::Infallible).unwrap();
+
+        assert_eq!(deserialized.dimensions(), grid.dimensions());
+        assert_eq!(deserialized.occupied, grid.occupied);
+    }
+
+    #[test]
+    fn test_straight_path_has_no_intermediate_waypoints() {
+        let grid = VoxelGrid::new(Vec3::ZERO, Vec3::splat(10.0), 1.0);
+        let start = Vec3::new(0.5, 0.5, 0.5);
+        let goal = Vec3::new(9.5, 0.5, 0.5);
+
+        assert_eq!(grid.find_path(start, goal), Some(vec![start, goal]));
+    }
+
+    #[test]
+    fn test_path_avoids_inflated_collider() {
+        let collider = Collider::new_aabb(Vec3::new(5.0, 5.0, 5.0), Vec3::new(1.0, 5.0, 5.0));
+        let grid = VoxelGrid::from_colliders(Vec3::ZERO, Vec3::splat(10.0), 1.0, &[collider], 0.5);
+
+        // The wall blocks everything but the space above y = 10, so there's no way around it
+        assert!(grid
+            .find_path(Vec3::new(1.5, 5.0, 5.0), Vec3::new(8.5, 5.0, 5.0))
+            .is_none());
+
+        let collider = Collider::new_aabb(Vec3::new(5.0, 3.0, 5.0), Vec3::new(1.0, 3.0, 5.0));
+        let grid = VoxelGrid::from_colliders(Vec3::ZERO, Vec3::splat(10.0), 1.0, &[collider], 0.5);
+        let path = grid
+            .find_path(Vec3::new(1.5, 2.5, 5.0), Vec3::new(8.5, 2.5, 5.0))
+            .unwrap();
+
+        assert!(path.len() > 2);
+
+        for window in path.windows(2) {
+            for i in 0..=10 {
+                let point = window[0].lerp(window[1], i as f32 / 10.0);
+                assert!(
+                    point.y > 6.0 || point.x < 4.0 || point.x > 6.0,
+                    "{point} is inside the wall"
+                );
+            }
+        }
+    }
+}
@@ -1,5 +1,30 @@
 use bevy_math::{Quat, Vec3};
 
+/// Describes how an agent rolls into turns.
+#[derive(Clone, Debug)]
+pub struct BankingModel {
+    /// The maximum bank angle in radians.
+    pub max_bank_angle: f32,
+    /// The maximum speed in radians per second at which the agent rolls.
+    pub roll_rate: f32,
+    /// How many radians the agent banks per unit of lateral acceleration.
+    pub bank_per_acceleration: f32,
+    /// The direction considered up when the agent flies level.
+    pub up: Vec3,
+}
+
+impl BankingModel {
+    #[must_use]
+    pub fn new(max_bank_angle: f32, roll_rate: f32, bank_per_acceleration: f32) -> Self {
+        Self {
+            max_bank_angle,
+            roll_rate,
+            bank_per_acceleration,
+            up: Vec3::Y,
+        }
+    }
+}
+
 #[allow(clippy::too_many_arguments)]
 pub fn update_agent_on_path(
     velocity: Vec3,
@@ -74,3 +99,150 @@ pub fn update_agent_on_path(
 
     (new_velocity, new_rotation)
 }
+
+/// Same as [`update_agent_on_path`], but the returned rotation is also rolled into the turn.
+///
+/// The target bank angle is proportional to the lateral acceleration of the agent, limited by
+/// the maximum bank angle of the banking model. The agent rolls towards it with the roll rate of the
+/// banking model and levels out again once the turn is over. When the agent is heading straight up
+/// or down the level orientation isn't defined and the roll is left untouched.
+#[allow(clippy::too_many_arguments)]
+pub fn update_agent_on_path_with_banking(
+    velocity: Vec3,
+    rotation: Quat,
+    max_turn_speed: f32,
+    max_speed: f32,
+    max_force: f32,
+    mass: f32,
+    desired_velocity: Vec3,
+    delta_time: f32,
+    banking: &BankingModel,
+) -> (Vec3, Quat) {
+    let (new_velocity, new_rotation) = update_agent_on_path(
+        velocity,
+        rotation,
+        max_turn_speed,
+        max_speed,
+        max_force,
+        mass,
+        desired_velocity,
+        delta_time,
+    );
+
+    if delta_time <= 0.0 {
+        return (new_velocity, new_rotation);
+    }
+
+    let heading = new_rotation.mul_vec3(Vec3::X).normalize();
+    let level_up = banking.up.reject_from(heading);
+
+    if level_up.length_squared() < f32::EPSILON {
+        return (new_velocity, new_rotation);
+    }
+
+    let level_up = level_up.normalize();
+    let level_side = heading.cross(level_up);
+    let current_up = new_rotation.mul_vec3(Vec3::Y);
+
+    // Signed angle between the level up direction and the current up direction around the heading
+    let current_bank = current_up.dot(level_side).atan2(current_up.dot(level_up));
+
+    let lateral_acceleration = ((new_velocity - velocity) / delta_time).reject_from(heading);
+    let target_bank = (lateral_acceleration.dot(level_side) * banking.bank_per_acceleration)
+        .clamp(-banking.max_bank_angle, banking.max_bank_angle);
+
+    let max_roll = banking.roll_rate * delta_time;
+    let roll = (target_bank - current_bank).clamp(-max_roll, max_roll);
+
+    (
+        new_velocity,
+        (Quat::from_axis_angle(heading, roll) * new_rotation).normalize(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bank_angle(rotation: Quat) -> f32 {
+        let heading = rotation.mul_vec3(Vec3::X);
+        let level_up = Vec3::Y.reject_from(heading).normalize();
+        let level_side = heading.cross(level_up);
+        let up = rotation.mul_vec3(Vec3::Y);
+
+        up.dot(level_side).atan2(up.dot(level_up))
+    }
+
+    #[test]
+    fn test_banking_rolls_into_the_turn() {
+        let banking = BankingModel::new(0.5, 1.0, 0.1);
+
+        let (_, rotation) = update_agent_on_path_with_banking(
+            Vec3::X * 10.0,
+            Quat::IDENTITY,
+            1.0,
+            10.0,
+            10.0,
+            1.0,
+            Vec3::new(10.0, 0.0, 10.0),
+            0.1,
+            &banking,
+        );
+
+        let bank = bank_angle(rotation);
+
+        // Turning towards +Z, the up vector leans into the turn limited by the roll rate
+        assert!(bank > 0.0);
+        assert!(bank <= 0.1 + 1e-5);
+    }
+
+    #[test]
+    fn test_banking_is_limited_by_max_bank_angle() {
+        let banking = BankingModel::new(0.3, 10.0, 1.0);
+        let mut velocity = Vec3::X * 10.0;
+        let mut rotation = Quat::IDENTITY;
+
+        for _ in 0..20 {
+            let desired_velocity = velocity + rotation.mul_vec3(Vec3::Z) * 10.0;
+
+            (velocity, rotation) = update_agent_on_path_with_banking(
+                velocity,
+                rotation,
+                1.0,
+                10.0,
+                10.0,
+                1.0,
+                desired_velocity,
+                0.1,
+                &banking,
+            );
+
+            assert!(bank_angle(rotation).abs() <= 0.3 + 1e-4);
+        }
+
+        assert!((bank_angle(rotation) - 0.3).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_banking_levels_out_after_turn() {
+        let banking = BankingModel::new(0.5, 1.0, 0.1);
+        let mut rotation = Quat::from_rotation_x(0.2);
+
+        for _ in 0..3 {
+            (_, rotation) = update_agent_on_path_with_banking(
+                Vec3::X * 10.0,
+                rotation,
+                1.0,
+                10.0,
+                10.0,
+                1.0,
+                Vec3::X * 10.0,
+                0.1,
+                &banking,
+            );
+        }
+
+        assert!(bank_angle(rotation).abs() < 1e-4);
+        assert!((rotation.mul_vec3(Vec3::X) - Vec3::X).length() < 1e-4);
+    }
+}
@@ -0,0 +1,222 @@
+use bevy_math::Vec3;
+
+/// Describes how reluctant an agent is to change its altitude while heading to a goal.
+///
+/// The vertical part of the direction towards the goal is divided by `1 + penalty`, so the agent
+/// covers the horizontal distance first and changes altitude gradually as it gets closer. With both
+/// penalties set to zero the preferred velocity points straight at the goal.
+#[derive(Clone, Debug)]
+pub struct AltitudePreference {
+    /// The direction that is considered up, normalized.
+    pub up: Vec3,
+    /// How much climbing is discouraged, zero means no penalty.
+    pub climb_penalty: f32,
+    /// How much descending is discouraged, zero means no penalty.
+    pub descent_penalty: f32,
+    /// The highest vertical speed the preferred velocity can have.
+    pub max_vertical_speed: f32,
+}
+
+impl AltitudePreference {
+    #[must_use]
+    pub fn new(up: Vec3) -> Self {
+        Self {
+            up: up.try_normalize().unwrap_or(Vec3::Y),
+            climb_penalty: 0.0,
+            descent_penalty: 0.0,
+            max_vertical_speed: f32::INFINITY,
+        }
+    }
+
+    #[must_use]
+    pub fn with_climb_penalty(mut self, climb_penalty: f32) -> Self {
+        assert!(climb_penalty >= 0.0);
+
+        self.climb_penalty = climb_penalty;
+        self
+    }
+
+    #[must_use]
+    pub fn with_descent_penalty(mut self, descent_penalty: f32) -> Self {
+        assert!(descent_penalty >= 0.0);
+
+        self.descent_penalty = descent_penalty;
+        self
+    }
+
+    #[must_use]
+    pub fn with_max_vertical_speed(mut self, max_vertical_speed: f32) -> Self {
+        assert!(max_vertical_speed >= 0.0);
+
+        self.max_vertical_speed = max_vertical_speed;
+        self
+    }
+}
+
+impl Default for AltitudePreference {
+    fn default() -> Self {
+        Self::new(Vec3::Y)
+    }
+}
+
+/// Calculates the preferred velocity of an agent heading to a goal while avoiding unnecessary
+/// changes of altitude.
+///
+/// # Arguments
+///
+/// * `goal` - A Vec3 that represents the position the agent wants to reach.
+/// * `agent_position` - A Vec3 that represents the agent's current position.
+/// * `max_speed` - A float that represents the maximum speed of the agent.
+/// * `slowing_distance` - A float that represents the distance from the goal at which the agent
+///   starts slowing down linearly.
+/// * `preference` - The altitude preference of the agent.
+///
+/// # Returns
+///
+/// * A Vec3 that represents the preferred velocity of the agent. The speed is limited by `max_speed`
+///   and the vertical speed by `preference.max_vertical_speed`.
+#[must_use]
+pub fn preferred_velocity_with_altitude(
+    goal: Vec3,
+    agent_position: Vec3,
+    max_speed: f32,
+    slowing_distance: f32,
+    preference: &AltitudePreference,
+) -> Vec3 {
+    let displacement = goal - agent_position;
+    let distance = displacement.length();
+
+    if distance <= f32::EPSILON {
+        return Vec3::ZERO;
+    }
+
+    let vertical_distance = displacement.dot(preference.up);
+    let horizontal = displacement - preference.up * vertical_distance;
+
+    let penalty = if vertical_distance > 0.0 {
+        preference.climb_penalty
+    } else {
+        preference.descent_penalty
+    };
+
+    let direction = (horizontal + preference.up * vertical_distance / (1.0 + penalty)).normalize();
+
+    let speed = if slowing_distance > 0.0 {
+        max_speed * (distance / slowing_distance).min(1.0)
+    } else {
+        max_speed
+    };
+
+    let velocity = direction * speed;
+    let vertical_speed = velocity.dot(preference.up);
+
+    // The agent shouldn't overshoot the goal altitude within one second either
+    let max_vertical_speed = preference.max_vertical_speed.min(vertical_distance.abs());
+
+    if vertical_speed.abs() > max_vertical_speed {
+        velocity - preference.up * (vertical_speed - vertical_speed.signum() * max_vertical_speed)
+    } else {
+        velocity
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_without_penalty_heads_straight_to_goal() {
+        let velocity = preferred_velocity_with_altitude(
+            Vec3::new(10.0, 10.0, 0.0),
+            Vec3::ZERO,
+            2.0,
+            1.0,
+            &AltitudePreference::default(),
+        );
+
+        assert!((velocity - Vec3::new(1.0, 1.0, 0.0).normalize() * 2.0).length() < 1e-5);
+    }
+
+    #[test]
+    fn test_climb_penalty_prefers_horizontal_motion() {
+        let preference = AltitudePreference::default().with_climb_penalty(3.0);
+
+        let velocity = preferred_velocity_with_altitude(
+            Vec3::new(10.0, 10.0, 0.0),
+            Vec3::ZERO,
+            2.0,
+            1.0,
+            &preference,
+        );
+
+        assert!((velocity.length() - 2.0).abs() < 1e-5);
+        assert!(velocity.y > 0.0);
+        assert!(velocity.y < velocity.x);
+    }
+
+    #[test]
+    fn test_descent_penalty_only_applies_to_descent() {
+        let preference = AltitudePreference::default().with_descent_penalty(3.0);
+
+        let climb = preferred_velocity_with_altitude(
+            Vec3::new(10.0, 10.0, 0.0),
+            Vec3::ZERO,
+            1.0,
+            1.0,
+            &preference,
+        );
+        let descent = preferred_velocity_with_altitude(
+            Vec3::new(10.0, -10.0, 0.0),
+            Vec3::ZERO,
+            1.0,
+            1.0,
+            &preference,
+        );
+
+        assert!((climb.x - climb.y).abs() < 1e-5);
+        assert!(-descent.y < descent.x);
+    }
+
+    #[test]
+    fn test_goal_directly_above_is_still_reached() {
+        let preference = AltitudePreference::default().with_climb_penalty(10.0);
+
+        let velocity = preferred_velocity_with_altitude(
+            Vec3::new(0.0, 10.0, 0.0),
+            Vec3::ZERO,
+            2.0,
+            1.0,
+            &preference,
+        );
+
+        assert!((velocity - Vec3::new(0.0, 2.0, 0.0)).length() < 1e-5);
+    }
+
+    #[test]
+    fn test_max_vertical_speed_is_respected() {
+        let preference = AltitudePreference::default().with_max_vertical_speed(0.5);
+
+        let velocity = preferred_velocity_with_altitude(
+            Vec3::new(0.0, 10.0, 10.0),
+            Vec3::ZERO,
+            2.0,
+            1.0,
+            &preference,
+        );
+
+        assert!((velocity.y - 0.5).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_slows_down_near_goal() {
+        let velocity = preferred_velocity_with_altitude(
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::ZERO,
+            4.0,
+            2.0,
+            &AltitudePreference::default(),
+        );
+
+        assert!((velocity - Vec3::new(2.0, 0.0, 0.0)).length() < 1e-5);
+    }
+}
@@ -1,7 +1,21 @@
 mod agent;
+mod altitude_preference;
+mod orientation;
+mod path_validation;
+mod pipeline;
+mod scheduler;
 mod steering_functions;
 mod turn_plane;
+mod velocity_filter;
+mod wander;
 
 pub use agent::*;
+pub use altitude_preference::*;
+pub use orientation::*;
+pub use path_validation::*;
+pub use pipeline::*;
+pub use scheduler::*;
 pub use steering_functions::*;
 pub use turn_plane::*;
+pub use velocity_filter::*;
+pub use wander::*;
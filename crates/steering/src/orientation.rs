@@ -0,0 +1,177 @@
+use bevy_math::{Mat3, Quat, Vec3};
+
+/// Angular limits of an agent, expressed per axis in the agent's local space.
+#[derive(Clone, Debug)]
+pub struct OrientationLimits {
+    /// The maximum angular velocity around each local axis in radians per second.
+    pub max_angular_velocity: Vec3,
+    /// The maximum angular acceleration around each local axis in radians per second squared.
+    pub max_angular_acceleration: Vec3,
+}
+
+impl OrientationLimits {
+    #[must_use]
+    pub fn new(max_angular_velocity: Vec3, max_angular_acceleration: Vec3) -> Self {
+        Self {
+            max_angular_velocity,
+            max_angular_acceleration,
+        }
+    }
+
+    #[must_use]
+    pub fn uniform(max_angular_velocity: f32, max_angular_acceleration: f32) -> Self {
+        Self::new(
+            Vec3::splat(max_angular_velocity),
+            Vec3::splat(max_angular_acceleration),
+        )
+    }
+}
+
+/// Returns the rotation of an agent facing along `forward` with its local Y axis as close to `up`
+/// as possible. The agent is expected to be facing along its local X axis.
+///
+/// If `forward` is parallel to `up`, any perpendicular up direction is used.
+#[must_use]
+pub fn face_direction(forward: Vec3, up: Vec3) -> Quat {
+    let Some(forward) = forward.try_normalize() else {
+        return Quat::IDENTITY;
+    };
+
+    let up = up
+        .reject_from(forward)
+        .try_normalize()
+        .unwrap_or_else(|| forward.any_orthonormal_vector());
+
+    Quat::from_mat3(&Mat3::from_cols(forward, up, forward.cross(up))).normalize()
+}
+
+/// Advances the orientation of an agent towards the desired orientation.
+///
+/// # Arguments
+///
+/// * `rotation` - A Quat that represents the agent's current rotation.
+/// * `angular_velocity` - A Vec3 that represents the agent's current angular velocity in its local space.
+/// * `desired_rotation` - A Quat that represents the rotation the agent should end up with.
+/// * `limits` - The angular limits of the agent.
+/// * `delta_time` - A float that represents the time step.
+///
+/// # Returns
+///
+/// * A tuple with the next rotation of the agent and its new local angular velocity.
+///
+/// # Description
+///
+/// Each local axis is controlled independently. The angular velocity around an axis is accelerated towards
+/// the highest velocity from which the agent can still stop at the desired orientation without exceeding
+/// its maximum angular acceleration. The angular acceleration applied during the step can be obtained as
+/// `(new_angular_velocity - angular_velocity) / delta_time`, multiplying it by the agent's inertia gives the torque.
+#[must_use]
+pub fn update_orientation(
+    rotation: Quat,
+    angular_velocity: Vec3,
+    desired_rotation: Quat,
+    limits: &OrientationLimits,
+    delta_time: f32,
+) -> (Quat, Vec3) {
+    if delta_time <= 0.0 {
+        return (rotation, angular_velocity);
+    }
+
+    let mut error = rotation.inverse() * desired_rotation;
+
+    // Make sure the agent turns the shorter way around
+    if error.w < 0.0 {
+        error = -error;
+    }
+
+    let error = error.to_scaled_axis();
+    let mut new_angular_velocity = Vec3::ZERO;
+
+    for axis in 0..3 {
+        let axis_error = error[axis];
+        let max_velocity = limits.max_angular_velocity[axis];
+        let max_acceleration = limits.max_angular_acceleration[axis];
+
+        let stopping_velocity = (2.0 * max_acceleration * axis_error.abs()).sqrt();
+        let desired_velocity = axis_error.signum()
+            * stopping_velocity
+                .min(max_velocity)
+                .min(axis_error.abs() / delta_time);
+
+        let max_change = max_acceleration * delta_time;
+        new_angular_velocity[axis] = angular_velocity[axis]
+            + (desired_velocity - angular_velocity[axis]).clamp(-max_change, max_change);
+    }
+
+    let new_rotation =
+        (rotation * Quat::from_scaled_axis(new_angular_velocity * delta_time)).normalize();
+
+    (new_rotation, new_angular_velocity)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use super::*;
+
+    #[test]
+    fn test_face_direction() {
+        let rotation = face_direction(Vec3::Z, Vec3::Y);
+
+        assert!((rotation.mul_vec3(Vec3::X) - Vec3::Z).length() < 1e-5);
+        assert!((rotation.mul_vec3(Vec3::Y) - Vec3::Y).length() < 1e-5);
+
+        // Facing straight up picks any perpendicular up direction
+        let rotation = face_direction(Vec3::Y, Vec3::Y);
+
+        assert!((rotation.mul_vec3(Vec3::X) - Vec3::Y).length() < 1e-5);
+        assert!(rotation.mul_vec3(Vec3::Y).dot(Vec3::Y).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_angular_velocity_is_limited() {
+        let limits = OrientationLimits::new(Vec3::new(0.0, 1.0, 0.0), Vec3::splat(2.0));
+
+        let (_, angular_velocity) = update_orientation(
+            Quat::IDENTITY,
+            Vec3::ZERO,
+            Quat::from_rotation_y(FRAC_PI_2),
+            &limits,
+            0.1,
+        );
+
+        assert!((angular_velocity.y - 0.2).abs() < 1e-5);
+
+        let mut rotation = Quat::IDENTITY;
+        let mut angular_velocity = Vec3::ZERO;
+
+        for _ in 0..10 {
+            (rotation, angular_velocity) = update_orientation(
+                rotation,
+                angular_velocity,
+                Quat::from_rotation_y(FRAC_PI_2),
+                &limits,
+                0.1,
+            );
+
+            assert!(angular_velocity.y <= 1.0 + 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_reaches_desired_orientation() {
+        let limits = OrientationLimits::uniform(2.0, 4.0);
+        let desired_rotation = Quat::from_rotation_z(1.0) * Quat::from_rotation_y(-0.5);
+        let mut rotation = Quat::IDENTITY;
+        let mut angular_velocity = Vec3::ZERO;
+
+        for _ in 0..200 {
+            (rotation, angular_velocity) =
+                update_orientation(rotation, angular_velocity, desired_rotation, &limits, 0.05);
+        }
+
+        assert!(rotation.angle_between(desired_rotation) < 1e-2);
+        assert!(angular_velocity.length() < 1e-2);
+    }
+}
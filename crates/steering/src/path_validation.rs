@@ -0,0 +1,231 @@
+use bevy_math::{Quat, Vec3};
+
+use crate::{minimum_turn_radius, TurnFeasibility, TurnPlane};
+
+/// The result of validating a single turn of a path.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TurnValidation {
+    /// The index of the waypoint at which the turn happens.
+    pub waypoint_index: usize,
+    /// The feasibility of the turn at the validated speed.
+    pub feasibility: TurnFeasibility,
+    /// The highest speed at which the turn is still feasible, capped by the validated speed.
+    pub advised_speed: f32,
+}
+
+/// Walks a path and tests every turn against the turning constraints of an agent.
+///
+/// # Arguments
+///
+/// * `path` - A slice of Vec3 that represents the path.
+/// * `speed` - A float that represents the speed at which the agent intends to follow the path.
+/// * `max_turn_speed` - A float that represents the maximum turning speed of the agent.
+///
+/// # Returns
+///
+/// * The validation of every inner waypoint of the path.
+///
+/// # Description
+///
+/// Segments between two turns are shared by both of them, so each turn may only use half of such
+/// a segment. A turn that reverses the direction of the path is never feasible.
+pub fn validate_path(path: &[Vec3], speed: f32, max_turn_speed: f32) -> Vec<TurnValidation> {
+    if path.len() < 3 {
+        return Vec::new();
+    }
+
+    (1..path.len() - 1)
+        .map(|i| {
+            let start = if i == 1 {
+                path[0]
+            } else {
+                path[i - 1].lerp(path[i], 0.5)
+            };
+
+            let end = if i == path.len() - 2 {
+                path[i + 1]
+            } else {
+                path[i].lerp(path[i + 1], 0.5)
+            };
+
+            let feasibility = turn_feasibility(start, path[i], end, speed, max_turn_speed);
+            let advised_speed = (feasibility.max_feasible_radius * max_turn_speed).min(speed);
+
+            TurnValidation {
+                waypoint_index: i,
+                feasibility,
+                advised_speed,
+            }
+        })
+        .collect()
+}
+
+/// Returns the advised speed for every segment of the path, the lower of the advised speeds of the
+/// turns at both of its ends.
+pub fn advised_segment_speeds(path: &[Vec3], speed: f32, max_turn_speed: f32) -> Vec<f32> {
+    let mut speeds = vec![speed; path.len().saturating_sub(1)];
+
+    for turn in validate_path(path, speed, max_turn_speed) {
+        let incoming = turn.waypoint_index - 1;
+        let outgoing = turn.waypoint_index;
+
+        speeds[incoming] = speeds[incoming].min(turn.advised_speed);
+        speeds[outgoing] = speeds[outgoing].min(turn.advised_speed);
+    }
+
+    speeds
+}
+
+/// Replaces every turn of the path with a circular arc the agent can follow.
+///
+/// # Arguments
+///
+/// * `path` - A slice of Vec3 that represents the path.
+/// * `speed` - A float that represents the speed at which the agent intends to follow the path.
+/// * `max_turn_speed` - A float that represents the maximum turning speed of the agent.
+/// * `arc_segments` - The number of segments each arc is made of.
+///
+/// # Returns
+///
+/// * The path with the fillet arcs inserted.
+///
+/// # Description
+///
+/// Feasible turns are replaced by an arc with the turn radius of the agent. Infeasible turns get the
+/// largest arc that fits, the agent has to slow down to the advised speed to follow it. Turns that
+/// reverse the direction of the path are kept as they are.
+pub fn fillet_path(
+    path: &[Vec3],
+    speed: f32,
+    max_turn_speed: f32,
+    arc_segments: usize,
+) -> Vec<Vec3> {
+    let validations = validate_path(path, speed, max_turn_speed);
+    let mut filleted = Vec::with_capacity(path.len() + validations.len() * arc_segments);
+
+    filleted.extend(path.first());
+
+    for validation in &validations {
+        let i = validation.waypoint_index;
+        let incoming = (path[i] - path[i - 1]).normalize_or_zero();
+        let outgoing = (path[i + 1] - path[i]).normalize_or_zero();
+        let axis = incoming.cross(outgoing);
+
+        if axis.length_squared() < f32::EPSILON || validation.advised_speed <= 0.0 {
+            filleted.push(path[i]);
+            continue;
+        }
+
+        let radius = validation
+            .feasibility
+            .turn_radius
+            .min(validation.feasibility.max_feasible_radius);
+
+        let turn_angle = incoming.angle_between(outgoing);
+        let tangent_length = radius * (turn_angle / 2.0).tan();
+        let entry_point = path[i] - incoming * tangent_length;
+        let center = entry_point + outgoing.reject_from(incoming).normalize() * radius;
+        let axis = axis.normalize();
+
+        for segment in 0..=arc_segments.max(1) {
+            let angle = turn_angle * segment as f32 / arc_segments.max(1) as f32;
+
+            filleted.push(center + Quat::from_axis_angle(axis, angle) * (entry_point - center));
+        }
+    }
+
+    if path.len() > 1 {
+        filleted.extend(path.last());
+    }
+
+    filleted
+}
+
+fn turn_feasibility(
+    start: Vec3,
+    turn_point: Vec3,
+    end: Vec3,
+    speed: f32,
+    max_turn_speed: f32,
+) -> TurnFeasibility {
+    let incoming = (turn_point - start).normalize_or_zero();
+    let outgoing = (end - turn_point).normalize_or_zero();
+
+    // The turn plane isn't defined for collinear points, those are handled separately
+    if incoming.cross(outgoing).length_squared() < f32::EPSILON {
+        let is_straight = incoming.dot(outgoing) >= 0.0;
+
+        return TurnFeasibility {
+            is_feasible: is_straight,
+            turn_radius: minimum_turn_radius(speed, max_turn_speed),
+            max_feasible_radius: if is_straight { f32::INFINITY } else { 0.0 },
+            entry_point: turn_point,
+            exit_point: turn_point,
+        };
+    }
+
+    TurnPlane::new(start, turn_point, end).turn_feasibility(speed, max_turn_speed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_path() {
+        let path = [
+            Vec3::ZERO,
+            Vec3::new(10.0, 0.0, 0.0),
+            Vec3::new(10.0, 10.0, 0.0),
+            Vec3::new(0.0, 10.0, 0.0),
+        ];
+
+        let validations = validate_path(&path, 2.0, 1.0);
+
+        assert_eq!(validations.len(), 2);
+        assert!(validations.iter().all(|v| v.feasibility.is_feasible));
+        assert_eq!(validations[1].waypoint_index, 2);
+
+        // The middle segment is shared by both turns, each of them may use half of it
+        let validations = validate_path(&path, 10.0, 1.0);
+
+        assert!(validations.iter().all(|v| !v.feasibility.is_feasible));
+        assert!((validations[0].advised_speed - 5.0).abs() < 1e-4);
+        assert_eq!(advised_segment_speeds(&path, 10.0, 1.0).len(), 3);
+        assert!(advised_segment_speeds(&path, 10.0, 1.0)
+            .iter()
+            .all(|speed| (speed - 5.0).abs() < 1e-4));
+    }
+
+    #[test]
+    fn test_reversal_is_never_feasible() {
+        let path = [Vec3::ZERO, Vec3::new(10.0, 0.0, 0.0), Vec3::ZERO];
+
+        let validations = validate_path(&path, 0.1, 1.0);
+
+        assert!(!validations[0].feasibility.is_feasible);
+        assert_eq!(validations[0].advised_speed, 0.0);
+        assert_eq!(fillet_path(&path, 0.1, 1.0, 4), path.to_vec());
+    }
+
+    #[test]
+    fn test_fillet_path() {
+        let path = [
+            Vec3::ZERO,
+            Vec3::new(10.0, 0.0, 0.0),
+            Vec3::new(10.0, 10.0, 0.0),
+        ];
+
+        let filleted = fillet_path(&path, 2.0, 1.0, 4);
+        let center = Vec3::new(8.0, 2.0, 0.0);
+
+        assert_eq!(filleted.len(), 7);
+        assert_eq!(filleted[0], path[0]);
+        assert_eq!(filleted[6], path[2]);
+        assert!((filleted[1] - Vec3::new(8.0, 0.0, 0.0)).length() < 1e-4);
+        assert!((filleted[5] - Vec3::new(10.0, 2.0, 0.0)).length() < 1e-4);
+        assert!(filleted[1..6]
+            .iter()
+            .all(|pt| (pt.distance(center) - 2.0).abs() < 1e-4));
+    }
+}
@@ -0,0 +1,134 @@
+use bevy_math::Vec3;
+
+/// Describes how the forces registered in a [`SteeringPipeline`] are combined.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SteeringCombination {
+    /// All forces are scaled by their weight, summed and the sum is truncated to the maximum force.
+    WeightedTruncatedSum,
+    /// Forces are visited from the highest priority to the lowest and the first weighted force
+    /// that is not negligible is used, truncated to the maximum force.
+    PriorityArbitration,
+}
+
+#[derive(Clone, Debug)]
+struct SteeringEntry {
+    force: Vec3,
+    weight: f32,
+    priority: u32,
+}
+
+/// Combines the forces of several steering behaviors into a single steering force.
+///
+/// The forces are registered every tick together with their weight and priority, combined
+/// with [`SteeringPipeline::calculate`] and cleared before the next tick.
+#[derive(Clone, Debug)]
+pub struct SteeringPipeline {
+    pub combination: SteeringCombination,
+    pub max_force: f32,
+    entries: Vec<SteeringEntry>,
+}
+
+impl SteeringPipeline {
+    #[must_use]
+    pub fn new(combination: SteeringCombination, max_force: f32) -> Self {
+        Self {
+            combination,
+            max_force,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Registers the force produced by a steering behavior.
+    ///
+    /// # Arguments
+    ///
+    /// * `force` - A Vec3 that represents the force produced by the behavior.
+    /// * `weight` - A float the force is scaled by.
+    /// * `priority` - The priority of the behavior. Higher values are evaluated first.
+    pub fn add(&mut self, force: Vec3, weight: f32, priority: u32) -> &mut Self {
+        self.entries.push(SteeringEntry {
+            force,
+            weight,
+            priority,
+        });
+
+        self
+    }
+
+    /// Removes all registered forces so the pipeline can be reused in the next tick.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Combines the registered forces into a single steering force clamped to `max_force`.
+    #[must_use]
+    pub fn calculate(&self) -> Vec3 {
+        match self.combination {
+            SteeringCombination::WeightedTruncatedSum => self
+                .entries
+                .iter()
+                .map(|entry| entry.force * entry.weight)
+                .sum::<Vec3>()
+                .clamp_length_max(self.max_force),
+            SteeringCombination::PriorityArbitration => {
+                let mut entries = self.entries.iter().collect::<Vec<_>>();
+
+                // Stable sort keeps the registration order for behaviors with the same priority
+                entries.sort_by_key(|entry| std::cmp::Reverse(entry.priority));
+
+                entries
+                    .into_iter()
+                    .map(|entry| entry.force * entry.weight)
+                    .find(|force| force.length_squared() > f32::EPSILON)
+                    .unwrap_or(Vec3::ZERO)
+                    .clamp_length_max(self.max_force)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_weighted_truncated_sum() {
+        let mut pipeline = SteeringPipeline::new(SteeringCombination::WeightedTruncatedSum, 10.0);
+        pipeline.add(Vec3::X, 2.0, 0).add(Vec3::Y, 3.0, 1);
+
+        assert!((pipeline.calculate() - Vec3::new(2.0, 3.0, 0.0)).length() < 1e-5);
+
+        pipeline.add(Vec3::X, 100.0, 0);
+
+        assert!((pipeline.calculate().length() - 10.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_priority_arbitration_skips_negligible_forces() {
+        let mut pipeline = SteeringPipeline::new(SteeringCombination::PriorityArbitration, 1.0);
+        pipeline
+            .add(Vec3::X, 1.0, 1)
+            .add(Vec3::Z, 0.0, 5)
+            .add(Vec3::Y * 4.0, 1.0, 3)
+            .add(Vec3::NEG_Y, 1.0, 3);
+
+        // The highest priority force is zero after weighting, the first registered force
+        // with the next priority wins and gets truncated
+        assert_eq!(pipeline.calculate(), Vec3::Y);
+    }
+
+    #[test]
+    fn test_clear() {
+        let mut pipeline = SteeringPipeline::new(SteeringCombination::WeightedTruncatedSum, 1.0);
+        pipeline.add(Vec3::X, 1.0, 0);
+        pipeline.clear();
+
+        assert!(pipeline.is_empty());
+        assert_eq!(pipeline.calculate(), Vec3::ZERO);
+    }
+}
@@ -0,0 +1,126 @@
+use std::{collections::HashMap, hash::Hash};
+
+/// Spreads expensive per-agent recomputation, like building ORCA planes or path lookahead,
+/// across multiple frames.
+///
+/// Every frame at most `agents_per_frame` agents are scheduled for an update. Agents that were never
+/// updated go first, followed by the agents that have been waiting the longest. Agents updated less than
+/// `min_interval` ago are never scheduled.
+#[derive(Clone, Debug)]
+pub struct SteeringScheduler<K> {
+    pub agents_per_frame: usize,
+    pub min_interval: f32,
+    last_updated: HashMap<K, f32>,
+}
+
+impl<K> SteeringScheduler<K>
+where
+    K: Copy + Eq + Hash,
+{
+    #[must_use]
+    pub fn new(agents_per_frame: usize, min_interval: f32) -> Self {
+        Self {
+            agents_per_frame,
+            min_interval,
+            last_updated: HashMap::new(),
+        }
+    }
+
+    /// Picks the agents that should be updated in the current frame and marks them as updated.
+    ///
+    /// # Arguments
+    ///
+    /// * `agents` - The keys of all agents that currently exist. Agents that aren't present are forgotten.
+    /// * `now` - A float that represents the current time.
+    ///
+    /// # Returns
+    ///
+    /// * The keys of the agents that should be updated, the stalest first.
+    pub fn schedule(&mut self, agents: impl IntoIterator<Item = K>, now: f32) -> Vec<K> {
+        let mut candidates = Vec::new();
+        let mut last_updated = HashMap::with_capacity(self.last_updated.len());
+
+        for agent in agents {
+            let agent_last_updated = self.last_updated.get(&agent).copied();
+
+            if let Some(time) = agent_last_updated {
+                last_updated.insert(agent, time);
+            }
+
+            let is_due = agent_last_updated.is_none_or(|time| now - time >= self.min_interval);
+
+            if is_due {
+                candidates.push((agent, agent_last_updated));
+            }
+        }
+
+        // Agents without any update yet compare as the stalest ones
+        candidates.sort_by(|(_, a), (_, b)| match (a, b) {
+            (None, None) => std::cmp::Ordering::Equal,
+            (None, Some(_)) => std::cmp::Ordering::Less,
+            (Some(_), None) => std::cmp::Ordering::Greater,
+            (Some(a), Some(b)) => a.total_cmp(b),
+        });
+
+        candidates.truncate(self.agents_per_frame);
+
+        for (agent, _) in &candidates {
+            last_updated.insert(*agent, now);
+        }
+
+        self.last_updated = last_updated;
+
+        candidates.into_iter().map(|(agent, _)| agent).collect()
+    }
+
+    /// Returns the time at which the agent was last scheduled.
+    #[must_use]
+    pub fn last_updated(&self, agent: K) -> Option<f32> {
+        self.last_updated.get(&agent).copied()
+    }
+
+    /// Forgets the agent, it'll be treated as never updated the next time it's scheduled.
+    pub fn remove(&mut self, agent: K) {
+        self.last_updated.remove(&agent);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schedule_stalest_agents_first() {
+        let mut scheduler = SteeringScheduler::new(2, 0.0);
+
+        assert_eq!(scheduler.schedule([1, 2, 3], 0.0), vec![1, 2]);
+        // Agent 3 was never updated so it goes first
+        assert_eq!(scheduler.schedule([1, 2, 3], 1.0), vec![3, 1]);
+        assert_eq!(scheduler.schedule([1, 2, 3], 2.0), vec![2, 1]);
+        assert_eq!(scheduler.last_updated(3), Some(1.0));
+    }
+
+    #[test]
+    fn test_min_interval() {
+        let mut scheduler = SteeringScheduler::new(10, 0.5);
+
+        assert_eq!(scheduler.schedule([1, 2], 0.0), vec![1, 2]);
+        assert!(scheduler.schedule([1, 2], 0.25).is_empty());
+        assert_eq!(scheduler.schedule([1, 2], 0.5), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_missing_agents_are_forgotten() {
+        let mut scheduler = SteeringScheduler::new(10, 1.0);
+
+        scheduler.schedule([1, 2], 0.0);
+        scheduler.schedule([2], 0.5);
+
+        assert_eq!(scheduler.last_updated(1), None);
+        assert_eq!(scheduler.schedule([1, 2], 0.5), vec![1]);
+
+        scheduler.remove(2);
+
+        assert_eq!(scheduler.schedule([1, 2], 0.5), vec![2]);
+    }
+}
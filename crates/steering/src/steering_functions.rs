@@ -1,10 +1,11 @@
-use bevy_math::Vec3;
-use geometry::{Ray3D, SecondTangentPointResult};
+use bevy_math::{Quat, Vec3};
+use geometry::{Ray3D, Ray3DIntersection, SecondTangentPointResult, Sphere, Vec3Operations};
 
 use crate::TurnPlane;
 
 const ARRIVE_MAX_FORCE_USAGE_MULTIPLIER: f32 = 0.75;
 const LOOKAHEAD_TURN_MULTIPLIER: f32 = 1.1;
+const CORRIDOR_CORRECTION_START: f32 = 0.5;
 
 /// Calculates the arrive steering force for an agent.
 ///
@@ -16,7 +17,7 @@ const LOOKAHEAD_TURN_MULTIPLIER: f32 = 1.1;
 /// * `agent_mass` - A float that represents the agent's mass.
 /// * `agent_max_force` - A float that represents the maximum force the agent can exert.
 /// * `tolerance` - A float that represents the distance within which the agent is considered to be
-/// at target
+///   at target
 ///
 /// # Returns
 ///
@@ -42,6 +43,130 @@ pub fn arrive(
     }
 }
 
+/// Calculates the velocity of an agent braking as hard as it can.
+///
+/// # Arguments
+///
+/// * `current_velocity` - A Vec3 that represents the agent's current velocity.
+/// * `max_deceleration` - A float that represents the maximum deceleration of the agent.
+/// * `delta_time` - A float that represents the time step.
+///
+/// # Returns
+///
+/// * A Vec3 that represents the agent's velocity after braking for `delta_time`. The agent never
+///   overshoots into the opposite direction.
+pub fn brake(current_velocity: Vec3, max_deceleration: f32, delta_time: f32) -> Vec3 {
+    let speed = current_velocity.length();
+    let new_speed = (speed - max_deceleration * delta_time).max(0.0);
+
+    current_velocity.clamp_length_max(new_speed)
+}
+
+/// Calculates the desired velocity of an agent that should stop at a given point.
+///
+/// # Arguments
+///
+/// * `point` - A Vec3 that represents the point the agent should stop at.
+/// * `agent_position` - A Vec3 that represents the agent's current position.
+/// * `agent_max_speed` - A float that represents the maximum speed of the agent.
+/// * `max_deceleration` - A float that represents the maximum deceleration of the agent.
+/// * `delta_time` - A float that represents the time step.
+/// * `tolerance` - A float that represents the distance within which the agent is considered to be
+///   at target
+///
+/// # Returns
+///
+/// * A Vec3 that represents the desired velocity of the agent.
+///
+/// # Description
+///
+/// Unlike [`arrive`], the desired speed is the highest speed from which the agent can still stop
+/// at the point with its maximum deceleration, including the distance traveled during the current
+/// time step. That keeps agents with high speeds and low deceleration from overshooting the point.
+pub fn stop_at(
+    point: Vec3,
+    agent_position: Vec3,
+    agent_max_speed: f32,
+    max_deceleration: f32,
+    delta_time: f32,
+    tolerance: f32,
+) -> Vec3 {
+    let displacement = point - agent_position;
+    let distance = displacement.length();
+
+    if distance <= tolerance {
+        return Vec3::ZERO;
+    }
+
+    // The speed v for which traveling a single time step and then braking covers the distance:
+    // v * dt + v^2 / (2 * a) = d
+    let a_dt = max_deceleration * delta_time;
+    let stopping_speed = (a_dt * a_dt + 2.0 * max_deceleration * distance).sqrt() - a_dt;
+
+    displacement / distance * stopping_speed.min(agent_max_speed)
+}
+
+/// Calculates the arrive steering force for an agent that should reach the target with a given terminal velocity.
+///
+/// # Arguments
+///
+/// * `target` - A Vec3 that represents the target position.
+/// * `target_velocity` - A Vec3 that represents the velocity the agent should have when reaching the target.
+/// * `agent_position` - A Vec3 that represents the agent's current position.
+/// * `agent_mass` - A float that represents the agent's mass.
+/// * `agent_max_force` - A float that represents the maximum force the agent can exert.
+/// * `tolerance` - A float that represents the distance within which the agent is considered to be
+///   at target
+///
+/// # Returns
+///
+/// * A Vec3 that represents the arrive steering force.
+///
+/// # Description
+///
+/// The target is treated as if it was moving with `target_velocity`. The agent arrives at it in the target's
+/// frame of reference, so once it gets there it moves along with the target. This is what docking and
+/// joining a moving formation need.
+pub fn arrive_with_velocity(
+    target: Vec3,
+    target_velocity: Vec3,
+    agent_position: Vec3,
+    agent_mass: f32,
+    agent_max_force: f32,
+    tolerance: f32,
+) -> Vec3 {
+    target_velocity
+        + arrive(
+            target,
+            agent_position,
+            agent_mass,
+            agent_max_force,
+            tolerance,
+        )
+}
+
+/// Calculates the velocity matching steering force for an agent.
+///
+/// # Arguments
+///
+/// * `target_velocity` - A Vec3 that represents the velocity the agent should match.
+/// * `current_velocity` - A Vec3 that represents the agent's current velocity.
+/// * `agent_max_force` - A float that represents the maximum force the agent can exert.
+/// * `agent_mass` - A float that represents the agent's mass.
+///
+/// # Returns
+///
+/// * A Vec3 that represents the force that would match the velocities within one second,
+///   clamped to the maximum force of the agent.
+pub fn match_velocity(
+    target_velocity: Vec3,
+    current_velocity: Vec3,
+    agent_max_force: f32,
+    agent_mass: f32,
+) -> Vec3 {
+    ((target_velocity - current_velocity) * agent_mass).clamp_length_max(agent_max_force)
+}
+
 /// Calculates the seek steering force for an agent.
 ///
 /// # Arguments
@@ -64,6 +189,228 @@ pub fn seek(target: Vec3, agent_position: Vec3, agent_max_force: f32, tolerance:
     displacement.normalize() * agent_max_force
 }
 
+/// Calculates the containment steering force for an agent.
+///
+/// # Arguments
+///
+/// * `agent_position` - A Vec3 that represents the agent's current position.
+/// * `agent_velocity` - A Vec3 that represents the agent's current velocity.
+/// * `volume` - The volume the agent should stay inside of.
+/// * `lookahead` - A float that represents how far ahead in time the agent's position is predicted.
+/// * `agent_max_force` - A float that represents the maximum force the agent can exert.
+///
+/// # Returns
+///
+/// * A Vec3 that represents the containment steering force. It's zero when the predicted
+///   position of the agent stays inside the volume.
+pub fn contain(
+    agent_position: Vec3,
+    agent_velocity: Vec3,
+    volume: &impl Vec3Operations,
+    lookahead: f32,
+    agent_max_force: f32,
+) -> Vec3 {
+    let predicted_position = agent_position + agent_velocity * lookahead;
+
+    if volume.contains(predicted_position) {
+        return Vec3::ZERO;
+    }
+
+    let inside_point = volume.constrain(predicted_position);
+
+    (inside_point - predicted_position).normalize_or_zero() * agent_max_force
+}
+
+/// Calculates a lightweight obstacle avoidance steering force by casting whiskers ahead of an agent.
+///
+/// # Arguments
+///
+/// * `agent_position` - A Vec3 that represents the agent's current position.
+/// * `agent_rotation` - A Quat that represents the agent's current rotation. The agent is expected
+///   to be facing along its local X axis.
+/// * `obstacles` - A slice of obstacles the whiskers are tested against.
+/// * `whisker_length` - A float that represents the length of each whisker.
+/// * `whisker_angle` - A float that represents the angle in radians between the forward whisker
+///   and the four side whiskers.
+/// * `agent_max_force` - A float that represents the maximum force the agent can exert.
+///
+/// # Returns
+///
+/// * A Vec3 that represents the avoidance steering force. It's perpendicular to the agent's heading
+///   and points away from the nearest hit. The closer the hit, the stronger the force. It's zero
+///   when no whisker hits an obstacle.
+pub fn avoid_obstacles<T>(
+    agent_position: Vec3,
+    agent_rotation: Quat,
+    obstacles: &[T],
+    whisker_length: f32,
+    whisker_angle: f32,
+    agent_max_force: f32,
+) -> Vec3
+where
+    T: Ray3DIntersection + Vec3Operations,
+{
+    let heading = agent_rotation.mul_vec3(Vec3::X).normalize();
+    let up = agent_rotation.mul_vec3(Vec3::Y).normalize();
+    let side = agent_rotation.mul_vec3(Vec3::Z).normalize();
+
+    let (sin, cos) = whisker_angle.sin_cos();
+    let whiskers = [
+        heading,
+        heading * cos + up * sin,
+        heading * cos - up * sin,
+        heading * cos + side * sin,
+        heading * cos - side * sin,
+    ];
+
+    let mut nearest_hit: Option<(f32, Vec3, &T)> = None;
+
+    for whisker in whiskers {
+        let ray = Ray3D::new(agent_position, whisker);
+
+        for obstacle in obstacles {
+            let Some(t) = obstacle.intersect_ray(&ray).first_hit() else {
+                continue;
+            };
+
+            if t > whisker_length {
+                continue;
+            }
+
+            if nearest_hit.is_none_or(|(nearest_t, _, _)| t < nearest_t) {
+                nearest_hit = Some((t, ray.at(t), obstacle));
+            }
+        }
+    }
+
+    let Some((t, hit_point, obstacle)) = nearest_hit else {
+        return Vec3::ZERO;
+    };
+
+    let (_, normal) = obstacle.closest_point_and_normal(hit_point);
+
+    // Only the lateral part of the normal is used, the agent should turn away from
+    // the obstacle rather than brake in front of it
+    let lateral = normal.reject_from(heading);
+    let lateral = if lateral.length_squared() > f32::EPSILON {
+        lateral.normalize()
+    } else {
+        // Head-on hit, any direction perpendicular to the heading will do
+        up
+    };
+
+    lateral * agent_max_force * (1.0 - t / whisker_length).clamp(0.0, 1.0)
+}
+
+/// Calculates the interpose steering force for an agent.
+///
+/// # Arguments
+///
+/// * `a_position` - A Vec3 that represents the current position of the first agent.
+/// * `a_velocity` - A Vec3 that represents the current velocity of the first agent.
+/// * `b_position` - A Vec3 that represents the current position of the second agent.
+/// * `b_velocity` - A Vec3 that represents the current velocity of the second agent.
+/// * `agent_position` - A Vec3 that represents the agent's current position.
+/// * `agent_max_speed` - A float that represents the maximum speed of the agent.
+/// * `agent_mass` - A float that represents the agent's mass.
+/// * `agent_max_force` - A float that represents the maximum force the agent can exert.
+/// * `tolerance` - A float that represents the distance within which the agent is considered to be
+///   at target
+///
+/// # Returns
+///
+/// * A Vec3 that represents the interpose steering force.
+///
+/// # Description
+///
+/// The agent estimates how long it would take to reach the current midpoint between the two agents,
+/// predicts where both agents will be after that time and arrives at the midpoint of the predicted positions.
+#[allow(clippy::too_many_arguments)]
+pub fn interpose(
+    a_position: Vec3,
+    a_velocity: Vec3,
+    b_position: Vec3,
+    b_velocity: Vec3,
+    agent_position: Vec3,
+    agent_max_speed: f32,
+    agent_mass: f32,
+    agent_max_force: f32,
+    tolerance: f32,
+) -> Vec3 {
+    let midpoint = (a_position + b_position) / 2.0;
+    let time_to_midpoint = if agent_max_speed > f32::EPSILON {
+        agent_position.distance(midpoint) / agent_max_speed
+    } else {
+        0.0
+    };
+
+    let predicted_a = a_position + a_velocity * time_to_midpoint;
+    let predicted_b = b_position + b_velocity * time_to_midpoint;
+
+    arrive(
+        (predicted_a + predicted_b) / 2.0,
+        agent_position,
+        agent_mass,
+        agent_max_force,
+        tolerance,
+    )
+}
+
+/// Calculates the hide steering force for an agent.
+///
+/// # Arguments
+///
+/// * `threat_position` - A Vec3 that represents the position of the threat the agent hides from.
+/// * `obstacles` - A slice of spheres the agent can hide behind.
+/// * `agent_position` - A Vec3 that represents the agent's current position.
+/// * `hide_distance` - A float that represents how far behind the obstacle's surface the agent hides.
+/// * `agent_mass` - A float that represents the agent's mass.
+/// * `agent_max_force` - A float that represents the maximum force the agent can exert.
+/// * `tolerance` - A float that represents the distance within which the agent is considered to be
+///   at target
+///
+/// # Returns
+///
+/// * A Vec3 that represents the hide steering force.
+///
+/// # Description
+///
+/// For every obstacle a hiding spot is computed on the far side of the obstacle as seen from the threat.
+/// The agent arrives at the hiding spot nearest to it. If there are no obstacles, the agent flees
+/// from the threat instead.
+pub fn hide(
+    threat_position: Vec3,
+    obstacles: &[Sphere],
+    agent_position: Vec3,
+    hide_distance: f32,
+    agent_mass: f32,
+    agent_max_force: f32,
+    tolerance: f32,
+) -> Vec3 {
+    let hiding_spot = obstacles
+        .iter()
+        .map(|obstacle| {
+            let direction = (obstacle.origin - threat_position).normalize_or_zero();
+
+            obstacle.origin + direction * (obstacle.radius + hide_distance)
+        })
+        .min_by(|a, b| {
+            a.distance_squared(agent_position)
+                .total_cmp(&b.distance_squared(agent_position))
+        });
+
+    match hiding_spot {
+        Some(hiding_spot) => arrive(
+            hiding_spot,
+            agent_position,
+            agent_mass,
+            agent_max_force,
+            tolerance,
+        ),
+        None => (agent_position - threat_position).normalize_or_zero() * agent_max_force,
+    }
+}
+
 #[derive(Debug)]
 pub enum FollowPathResult {
     CurrentSegment(Vec3),
@@ -71,11 +418,105 @@ pub enum FollowPathResult {
     EndOfPath(Vec3),
 }
 
+/// Describes how far an agent got along a path.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PathProgress {
+    /// The index of the segment the agent is following.
+    pub segment_index: usize,
+    /// The distance from the start of the segment to the projection of the agent on the segment.
+    pub segment_parameter: f32,
+    /// The length of the path that is still ahead of the agent.
+    pub remaining_distance: f32,
+    /// The fraction of the path length that is behind the agent, in range `0.0..=1.0`.
+    pub completion: f32,
+}
+
+/// Calculates the progress of an agent along a path.
+///
+/// # Arguments
+///
+/// * `path` - A slice of Vec3 that represents the path.
+/// * `path_index` - The index of the segment the agent is following.
+/// * `agent_position` - A Vec3 that represents the agent's current position.
+///
+/// # Returns
+///
+/// * The progress of the agent, measured by projecting its position onto the current segment.
+pub fn path_progress(path: &[Vec3], path_index: usize, agent_position: Vec3) -> PathProgress {
+    let total_length = path.windows(2).map(|w| w[0].distance(w[1])).sum::<f32>();
+
+    if path.len() < 2 || path_index >= path.len() - 1 {
+        return PathProgress {
+            segment_index: path_index,
+            segment_parameter: 0.0,
+            remaining_distance: 0.0,
+            completion: 1.0,
+        };
+    }
+
+    let segment = path[path_index + 1] - path[path_index];
+    let segment_length = segment.length();
+    let segment_parameter = if segment_length > f32::EPSILON {
+        Ray3D::new(path[path_index], segment)
+            .parameter_at_point(agent_position)
+            .clamp(0.0, segment_length)
+    } else {
+        0.0
+    };
+
+    let remaining_distance = segment_length - segment_parameter
+        + path[path_index + 1..]
+            .windows(2)
+            .map(|w| w[0].distance(w[1]))
+            .sum::<f32>();
+
+    let completion = if total_length > f32::EPSILON {
+        (1.0 - remaining_distance / total_length).clamp(0.0, 1.0)
+    } else {
+        1.0
+    };
+
+    PathProgress {
+        segment_index: path_index,
+        segment_parameter,
+        remaining_distance,
+        completion,
+    }
+}
+
+/// Describes what happens when an agent reaches the end of a path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PathMode {
+    /// The agent stops at the last point of the path.
+    #[default]
+    Once,
+    /// The path is closed, after the last point the agent continues to the first one.
+    Loop,
+    /// The agent turns around at both ends of the path. Segment indexes from `path.len() - 1`
+    /// up to `2 * (path.len() - 1)` denote the segments of the way back.
+    PingPong,
+}
+
+/// Returns the points of a single lap of the path, the last point is the one the lap ends at.
+fn path_lap(path: &[Vec3], path_mode: PathMode) -> Vec<Vec3> {
+    match path_mode {
+        PathMode::Once => path.to_vec(),
+        PathMode::Loop => path.iter().chain(path.first()).copied().collect(),
+        PathMode::PingPong => path
+            .iter()
+            .chain(path.iter().rev().skip(1))
+            .copied()
+            .collect(),
+    }
+}
+
 /// Follows a path defined by a sequence of points.
 ///
 /// # Arguments
 ///
 /// * `path` - A slice of Vec3 that represents the path to follow.
+/// * `path_index` - The index of the segment the agent is following.
+/// * `path_mode` - Describes what happens when the agent reaches the end of the path.
 /// * `agent_position` - A Vec3 that represents the agent's current position.
 /// * `agent_velocity` - A Vec3 that represents the agent's current velocity.
 /// * `agent_max_turning_speed` - A float that represents the maximum turning speed of the agent.
@@ -84,7 +525,9 @@ pub enum FollowPathResult {
 ///
 /// # Returns
 ///
-/// * A Vec3 that represents the steering force for the agent to follow the path.
+/// * A Vec3 that represents the steering force for the agent to follow the path, together with
+///   the progress of the agent along the path. For looping paths the progress is measured within
+///   the current lap.
 ///
 /// # Description
 ///
@@ -92,8 +535,200 @@ pub enum FollowPathResult {
 /// It first finds the closest point on the path to the agent. If the agent is at the end of the path, it uses the arrive behavior to stop at the last point.
 /// If the agent is not able to reach the next point on the path due to its turning radius, it uses the arrive behavior to stop at the next point.
 /// Otherwise, it calculates a lookahead point on the path based on the agent's turning radius and uses the seek behavior to steer towards this lookahead point.
+/// Looping and ping-pong paths never end, the lookahead wraps around the end of the path instead.
 #[allow(clippy::too_many_arguments)]
 pub fn follow_path(
+    path: &[Vec3],
+    path_index: usize,
+    path_mode: PathMode,
+    agent_position: Vec3,
+    agent_velocity: Vec3,
+    agent_max_turning_speed: f32,
+    agent_max_force: f32,
+    agent_mass: f32,
+    position_tolerance: f32,
+) -> (FollowPathResult, PathProgress) {
+    // There is no segment to follow
+    if path.len() < 2 {
+        return (
+            FollowPathResult::EndOfPath(Vec3::ZERO),
+            path_progress(path, 0, agent_position),
+        );
+    }
+
+    if path_mode == PathMode::Once {
+        let result = follow_path_force(
+            path,
+            path_index,
+            agent_position,
+            agent_velocity,
+            agent_max_turning_speed,
+            agent_max_force,
+            agent_mass,
+            position_tolerance,
+        );
+
+        let progress = match result {
+            FollowPathResult::CurrentSegment(_) => path_progress(path, path_index, agent_position),
+            FollowPathResult::NextSegment(_, index) => path_progress(path, index, agent_position),
+            FollowPathResult::EndOfPath(_) => path_progress(path, path.len() - 1, agent_position),
+        };
+
+        return (result, progress);
+    }
+
+    let lap = path_lap(path, path_mode);
+    let segment_count = lap.len() - 1;
+    let path_index = path_index % segment_count;
+
+    // The lap is unrolled twice starting at the current segment, that's more than enough
+    // for the lookahead to never reach the end of it
+    let unrolled_path = (0..=2 * segment_count + 1)
+        .map(|i| lap[(path_index + i) % segment_count])
+        .collect::<Vec<_>>();
+
+    let result = match follow_path_force(
+        &unrolled_path,
+        0,
+        agent_position,
+        agent_velocity,
+        agent_max_turning_speed,
+        agent_max_force,
+        agent_mass,
+        position_tolerance,
+    ) {
+        FollowPathResult::NextSegment(force, index) => {
+            FollowPathResult::NextSegment(force, (path_index + index) % segment_count)
+        }
+        FollowPathResult::CurrentSegment(force) | FollowPathResult::EndOfPath(force) => {
+            FollowPathResult::CurrentSegment(force)
+        }
+    };
+
+    let progress = match result {
+        FollowPathResult::NextSegment(_, index) => path_progress(&lap, index, agent_position),
+        _ => path_progress(&lap, path_index, agent_position),
+    };
+
+    (result, progress)
+}
+
+/// Follows a path like [`follow_path`] while keeping the agent inside a corridor around the path.
+///
+/// # Arguments
+///
+/// * `path` - A slice of Vec3 that represents the path to follow.
+/// * `corridor_radii` - A slice with the corridor radius of every segment of the path.
+/// * `path_index` - The index of the segment the agent is following.
+/// * `path_mode` - Describes what happens when the agent reaches the end of the path.
+/// * `agent_position` - A Vec3 that represents the agent's current position.
+/// * `agent_velocity` - A Vec3 that represents the agent's current velocity.
+/// * `agent_max_turning_speed` - A float that represents the maximum turning speed of the agent.
+/// * `agent_max_force` - A float that represents the maximum force the agent can exert.
+/// * `agent_mass` - A float that represents the agent's mass.
+///
+/// # Returns
+///
+/// * The result of [`follow_path`] together with a Vec3 that represents the corridor correction force.
+///
+/// # Description
+///
+/// The agent is tested against the corridor of the current and the previous segment, so cutting
+/// a corner doesn't count as leaving the corridor. The correction pulls the agent back towards the path
+/// once it drifts further than half of the corridor radius and reaches the maximum force at the corridor's wall.
+#[allow(clippy::too_many_arguments)]
+pub fn follow_path_in_corridor(
+    path: &[Vec3],
+    corridor_radii: &[f32],
+    path_index: usize,
+    path_mode: PathMode,
+    agent_position: Vec3,
+    agent_velocity: Vec3,
+    agent_max_turning_speed: f32,
+    agent_max_force: f32,
+    agent_mass: f32,
+    position_tolerance: f32,
+) -> (FollowPathResult, PathProgress, Vec3) {
+    let (result, progress) = follow_path(
+        path,
+        path_index,
+        path_mode,
+        agent_position,
+        agent_velocity,
+        agent_max_turning_speed,
+        agent_max_force,
+        agent_mass,
+        position_tolerance,
+    );
+
+    let lap = path_lap(path, path_mode);
+    let segment_count = lap.len().saturating_sub(1);
+
+    if segment_count == 0 || corridor_radii.is_empty() {
+        return (result, progress, Vec3::ZERO);
+    }
+
+    // Maps a segment of the lap back to the segment of the path it was created from
+    let corridor_radius = |segment: usize| {
+        let segment = if path_mode == PathMode::PingPong && segment >= path.len() - 1 {
+            segment_count - 1 - segment
+        } else {
+            segment
+        };
+
+        corridor_radii[segment.min(corridor_radii.len() - 1)]
+    };
+
+    let current_segment = progress.segment_index.min(segment_count - 1);
+    let previous_segment = match (current_segment, path_mode) {
+        (0, PathMode::Once) => None,
+        (0, _) => Some(segment_count - 1),
+        (segment, _) => Some(segment - 1),
+    };
+
+    let closest = std::iter::once(current_segment)
+        .chain(previous_segment)
+        .map(|segment| {
+            let closest_point =
+                closest_point_on_segment(lap[segment], lap[segment + 1], agent_position);
+
+            (closest_point, corridor_radius(segment))
+        })
+        .min_by(|(a, a_radius), (b, b_radius)| {
+            let a_distance = a.distance(agent_position) - a_radius;
+            let b_distance = b.distance(agent_position) - b_radius;
+
+            a_distance.total_cmp(&b_distance)
+        });
+
+    let correction = match closest {
+        Some((closest_point, radius)) if radius > 0.0 => {
+            let offset = closest_point - agent_position;
+            let correction_start = radius * CORRIDOR_CORRECTION_START;
+            let strength = ((offset.length() - correction_start) / (radius - correction_start))
+                .clamp(0.0, 1.0);
+
+            offset.normalize_or_zero() * agent_max_force * strength
+        }
+        _ => Vec3::ZERO,
+    };
+
+    (result, progress, correction)
+}
+
+fn closest_point_on_segment(start: Vec3, end: Vec3, pt: Vec3) -> Vec3 {
+    let segment = end - start;
+    let length_squared = segment.length_squared();
+
+    if length_squared < f32::EPSILON {
+        return start;
+    }
+
+    start + segment * ((pt - start).dot(segment) / length_squared).clamp(0.0, 1.0)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn follow_path_force(
     path: &[Vec3],
     path_index: usize,
     agent_position: Vec3,
@@ -239,3 +874,464 @@ pub fn separation(
     }
     separation_velocity
 }
+
+/// Calculates the queue velocity correction for an agent approaching a shared goal.
+///
+/// # Arguments
+///
+/// * `agent_position` - A Vec3 that represents the agent's current position.
+/// * `agent_velocity` - A Vec3 that represents the agent's current velocity.
+/// * `agent_radius` - A float that represents the agent's radius.
+/// * `agents` - A slice of positions and radii of the other agents.
+/// * `brake_distance` - A float that represents the length of the box ahead of the agent in which
+///   other agents make it slow down.
+/// * `lateral_nudge` - A float that represents the maximum sideways speed used to line up behind
+///   the agent ahead.
+///
+/// # Returns
+///
+/// * A Vec3 that represents the velocity correction that should be added to the agent's desired velocity.
+///
+/// # Description
+///
+/// The nearest agent inside a box ahead of the agent, as wide as both agents together and `brake_distance` long,
+/// is considered the agent in front. The closer it is, the more of the agent's speed is removed.
+/// The agent is also nudged sideways to line up directly behind the agent in front instead of pushing past it.
+pub fn queue(
+    agent_position: Vec3,
+    agent_velocity: Vec3,
+    agent_radius: f32,
+    agents: &[(Vec3, f32)],
+    brake_distance: f32,
+    lateral_nudge: f32,
+) -> Vec3 {
+    let Some(heading) = agent_velocity.try_normalize() else {
+        return Vec3::ZERO;
+    };
+
+    let agent_ahead = agents
+        .iter()
+        .filter_map(|(position, radius)| {
+            let relative_position = *position - agent_position;
+            let distance_ahead = relative_position.dot(heading);
+            let lateral_offset = relative_position - heading * distance_ahead;
+
+            let is_in_box = distance_ahead > 0.0
+                && distance_ahead <= brake_distance
+                && lateral_offset.length() < agent_radius + radius;
+
+            is_in_box.then_some((distance_ahead, lateral_offset))
+        })
+        .min_by(|(a, _), (b, _)| a.total_cmp(b));
+
+    let Some((distance_ahead, lateral_offset)) = agent_ahead else {
+        return Vec3::ZERO;
+    };
+
+    let urgency = 1.0 - distance_ahead / brake_distance;
+    let braking = -agent_velocity * urgency;
+    let nudge = lateral_offset.normalize_or_zero() * lateral_nudge * urgency;
+
+    braking + nudge
+}
+
+#[cfg(test)]
+mod tests {
+    use geometry::{Aabb, Sphere};
+
+    use super::*;
+
+    #[test]
+    fn test_contain_inside_volume() {
+        let volume = Aabb::new(Vec3::ZERO, Vec3::splat(10.0));
+
+        let force = contain(Vec3::ZERO, Vec3::X, &volume, 2.0, 1.0);
+
+        assert_eq!(force, Vec3::ZERO);
+    }
+
+    #[test]
+    fn test_contain_pushes_back_inside() {
+        let volume = Aabb::new(Vec3::ZERO, Vec3::splat(10.0));
+
+        let force = contain(Vec3::new(8.0, 0.0, 0.0), Vec3::X * 2.0, &volume, 2.0, 3.0);
+
+        assert!((force - Vec3::NEG_X * 3.0).length() < 1e-5);
+    }
+
+    #[test]
+    fn test_avoid_obstacles_without_hit() {
+        let obstacles = [Sphere::new(1.0, Vec3::new(20.0, 0.0, 0.0))];
+
+        let force = avoid_obstacles(Vec3::ZERO, Quat::IDENTITY, &obstacles, 10.0, 0.5, 1.0);
+
+        assert_eq!(force, Vec3::ZERO);
+    }
+
+    #[test]
+    fn test_avoid_obstacles_turns_away_from_hit() {
+        let obstacles = [
+            Sphere::new(2.0, Vec3::new(5.0, 1.0, 0.0)),
+            Sphere::new(2.0, Vec3::new(9.0, -1.0, 0.0)),
+        ];
+
+        let force = avoid_obstacles(Vec3::ZERO, Quat::IDENTITY, &obstacles, 10.0, 0.5, 1.0);
+
+        // The nearer sphere is above the heading, so the agent steers down
+        assert!(force.x.abs() < 1e-5);
+        assert!(force.y < 0.0);
+        assert!(force.length() < 1.0);
+    }
+
+    #[test]
+    fn test_avoid_obstacles_closer_hit_is_stronger() {
+        let near = [Sphere::new(1.0, Vec3::new(3.0, 0.5, 0.0))];
+        let far = [Sphere::new(1.0, Vec3::new(8.0, 0.5, 0.0))];
+
+        let near_force = avoid_obstacles(Vec3::ZERO, Quat::IDENTITY, &near, 10.0, 0.5, 1.0);
+        let far_force = avoid_obstacles(Vec3::ZERO, Quat::IDENTITY, &far, 10.0, 0.5, 1.0);
+
+        assert!(near_force.length() > far_force.length());
+        assert!(far_force.length() > 0.0);
+    }
+
+    #[test]
+    fn test_interpose_aims_at_predicted_midpoint() {
+        let force = interpose(
+            Vec3::new(-10.0, 0.0, 0.0),
+            Vec3::Y,
+            Vec3::new(10.0, 0.0, 0.0),
+            Vec3::Y,
+            Vec3::new(2.0, -10.0, 0.0),
+            5.0,
+            1.0,
+            1.0,
+            0.1,
+        );
+
+        // The midpoint will have moved up by the time the agent gets there
+        let expected = (Vec3::new(0.0, 2.0, 0.0) - Vec3::new(2.0, -10.0, 0.0)).normalize();
+        assert!(force.length() > 0.0);
+        assert!((force.normalize() - expected).length() < 1e-2);
+    }
+
+    #[test]
+    fn test_hide_behind_nearest_obstacle() {
+        let obstacles = [
+            Sphere::new(2.0, Vec3::new(10.0, 0.0, 0.0)),
+            Sphere::new(2.0, Vec3::new(-10.0, 0.0, 0.0)),
+        ];
+
+        let force = hide(
+            Vec3::ZERO,
+            &obstacles,
+            Vec3::new(13.0, 5.0, 0.0),
+            1.0,
+            1.0,
+            1.0,
+            0.1,
+        );
+
+        // The hiding spot is on the far side of the obstacle as seen from the threat
+        assert!((force.normalize() - Vec3::NEG_Y).length() < 1e-5);
+    }
+
+    #[test]
+    fn test_hide_without_obstacles_flees() {
+        let force = hide(
+            Vec3::ZERO,
+            &[],
+            Vec3::new(0.0, 0.0, 3.0),
+            1.0,
+            1.0,
+            2.0,
+            0.1,
+        );
+
+        assert!((force - Vec3::Z * 2.0).length() < 1e-5);
+    }
+
+    #[test]
+    fn test_arrive_with_velocity_moves_along_with_target() {
+        let force = arrive_with_velocity(Vec3::X, Vec3::Y * 3.0, Vec3::X, 1.0, 1.0, 0.1);
+
+        assert_eq!(force, Vec3::Y * 3.0);
+    }
+
+    #[test]
+    fn test_arrive_with_velocity_adds_arrive_force() {
+        let target = Vec3::new(10.0, 0.0, 0.0);
+
+        let force = arrive_with_velocity(target, Vec3::Y, Vec3::ZERO, 1.0, 1.0, 0.1);
+        let arrive_force = arrive(target, Vec3::ZERO, 1.0, 1.0, 0.1);
+
+        assert!((force - (Vec3::Y + arrive_force)).length() < 1e-5);
+        assert!(arrive_force.x > 0.0);
+    }
+
+    #[test]
+    fn test_match_velocity_is_clamped() {
+        let force = match_velocity(Vec3::X * 2.0, Vec3::X, 10.0, 2.0);
+        assert!((force - Vec3::X * 2.0).length() < 1e-5);
+
+        let force = match_velocity(Vec3::X * 20.0, Vec3::ZERO, 10.0, 2.0);
+        assert!((force - Vec3::X * 10.0).length() < 1e-5);
+    }
+
+    #[test]
+    fn test_path_progress() {
+        let path = [
+            Vec3::ZERO,
+            Vec3::new(10.0, 0.0, 0.0),
+            Vec3::new(10.0, 10.0, 0.0),
+        ];
+
+        let progress = path_progress(&path, 0, Vec3::new(4.0, 1.0, 0.0));
+
+        assert_eq!(progress.segment_index, 0);
+        assert!((progress.segment_parameter - 4.0).abs() < 1e-5);
+        assert!((progress.remaining_distance - 16.0).abs() < 1e-5);
+        assert!((progress.completion - 0.2).abs() < 1e-5);
+
+        let progress = path_progress(&path, 1, Vec3::new(12.0, 5.0, 0.0));
+
+        assert!((progress.remaining_distance - 5.0).abs() < 1e-5);
+        assert!((progress.completion - 0.75).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_path_progress_is_clamped_to_segment() {
+        let path = [Vec3::ZERO, Vec3::new(10.0, 0.0, 0.0)];
+
+        let behind = path_progress(&path, 0, Vec3::new(-5.0, 0.0, 0.0));
+        let past_end = path_progress(&path, 1, Vec3::new(15.0, 0.0, 0.0));
+
+        assert_eq!(behind.segment_parameter, 0.0);
+        assert_eq!(behind.completion, 0.0);
+        assert_eq!(past_end.remaining_distance, 0.0);
+        assert_eq!(past_end.completion, 1.0);
+    }
+
+    fn follow_triangle(
+        path_index: usize,
+        path_mode: PathMode,
+        agent_position: Vec3,
+        agent_velocity: Vec3,
+    ) -> (FollowPathResult, PathProgress) {
+        let path = [
+            Vec3::ZERO,
+            Vec3::new(20.0, 0.0, 0.0),
+            Vec3::new(20.0, 20.0, 0.0),
+        ];
+
+        follow_path(
+            &path,
+            path_index,
+            path_mode,
+            agent_position,
+            agent_velocity,
+            1.0,
+            1.0,
+            1.0,
+            0.5,
+        )
+    }
+
+    #[test]
+    fn test_follow_path_once_ends_at_last_point() {
+        let (result, progress) =
+            follow_triangle(1, PathMode::Once, Vec3::new(20.0, 19.9, 0.0), Vec3::Y);
+
+        assert!(matches!(result, FollowPathResult::EndOfPath(force) if force == Vec3::ZERO));
+        assert_eq!(progress.completion, 1.0);
+
+        let (result, _) = follow_triangle(0, PathMode::Once, Vec3::new(1.0, 0.0, 0.0), Vec3::X);
+
+        assert!(matches!(result, FollowPathResult::CurrentSegment(force) if force.x > 0.0));
+    }
+
+    #[test]
+    fn test_follow_path_loop_continues_to_first_point() {
+        // The closing segment goes from the last point back to the first one
+        let (result, progress) =
+            follow_triangle(2, PathMode::Loop, Vec3::new(20.0, 19.9, 0.0), Vec3::Y);
+
+        let force = match result {
+            FollowPathResult::CurrentSegment(force) | FollowPathResult::NextSegment(force, _) => {
+                force
+            }
+            FollowPathResult::EndOfPath(_) => panic!("looping path never ends"),
+        };
+
+        assert!(force.dot(Vec3::new(-1.0, -1.0, 0.0)) > 0.0);
+        assert_eq!(progress.segment_index, 2);
+
+        // Segment indexes wrap around the lap
+        let (_, progress) = follow_triangle(5, PathMode::Loop, Vec3::new(18.0, 18.0, 0.0), Vec3::X);
+
+        assert_eq!(progress.segment_index, 2);
+    }
+
+    #[test]
+    fn test_follow_path_ping_pong_turns_around() {
+        let (result, _) =
+            follow_triangle(1, PathMode::PingPong, Vec3::new(20.0, 19.9, 0.0), Vec3::Y);
+
+        assert!(!matches!(result, FollowPathResult::EndOfPath(_)));
+
+        // Segment 2 is the last segment of the path traveled backwards
+        let (result, progress) = follow_triangle(
+            2,
+            PathMode::PingPong,
+            Vec3::new(20.0, 19.0, 0.0),
+            Vec3::NEG_Y,
+        );
+
+        assert!(matches!(result, FollowPathResult::CurrentSegment(force) if force.y < 0.0));
+        assert_eq!(progress.segment_index, 2);
+    }
+
+    #[test]
+    fn test_follow_path_without_segments() {
+        for path_mode in [PathMode::Once, PathMode::Loop, PathMode::PingPong] {
+            for path in [&[][..], &[Vec3::X][..]] {
+                let (result, progress) =
+                    follow_path(path, 0, path_mode, Vec3::ZERO, Vec3::X, 1.0, 1.0, 1.0, 0.5);
+
+                assert!(
+                    matches!(result, FollowPathResult::EndOfPath(force) if force == Vec3::ZERO)
+                );
+                assert_eq!(progress.completion, 1.0);
+            }
+        }
+    }
+
+    fn corridor_correction(agent_position: Vec3, path_index: usize, path_mode: PathMode) -> Vec3 {
+        let path = [
+            Vec3::ZERO,
+            Vec3::new(10.0, 0.0, 0.0),
+            Vec3::new(20.0, 0.0, 0.0),
+        ];
+
+        let (_, _, correction) = follow_path_in_corridor(
+            &path,
+            &[2.0, 4.0],
+            path_index,
+            path_mode,
+            agent_position,
+            Vec3::X,
+            1.0,
+            1.0,
+            1.0,
+            0.5,
+        );
+
+        correction
+    }
+
+    #[test]
+    fn test_corridor_correction() {
+        // Inside half of the corridor radius the agent is left alone
+        let correction = corridor_correction(Vec3::new(5.0, 0.5, 0.0), 0, PathMode::Once);
+        assert_eq!(correction, Vec3::ZERO);
+
+        let correction = corridor_correction(Vec3::new(5.0, 1.5, 0.0), 0, PathMode::Once);
+        assert!((correction - Vec3::NEG_Y * 0.5).length() < 1e-5);
+
+        // At the wall and beyond the correction uses the maximum force
+        let correction = corridor_correction(Vec3::new(5.0, 0.0, -3.0), 0, PathMode::Once);
+        assert!((correction - Vec3::Z).length() < 1e-5);
+    }
+
+    #[test]
+    fn test_corridor_radius_on_the_way_back() {
+        // The way back along the second segment uses the radius of the second segment
+        let correction = corridor_correction(Vec3::new(15.0, 3.0, 0.0), 2, PathMode::PingPong);
+
+        assert!((correction - Vec3::NEG_Y * 0.5).length() < 1e-5);
+    }
+
+    #[test]
+    fn test_brake_never_reverses() {
+        assert!((brake(Vec3::X * 10.0, 4.0, 1.0) - Vec3::X * 6.0).length() < 1e-5);
+        assert_eq!(brake(Vec3::X * 10.0, 4.0, 5.0), Vec3::ZERO);
+    }
+
+    #[test]
+    fn test_stop_at_speed_allows_stopping_in_time() {
+        let velocity = stop_at(Vec3::X * 10.0, Vec3::ZERO, 100.0, 2.0, 0.5, 0.1);
+
+        // Traveling one time step and then braking covers exactly the distance
+        let speed = velocity.length();
+        assert!((speed * 0.5 + speed * speed / 4.0 - 10.0).abs() < 1e-4);
+
+        let velocity = stop_at(Vec3::X * 10.0, Vec3::ZERO, 3.0, 2.0, 0.5, 0.1);
+        assert!((velocity - Vec3::X * 3.0).length() < 1e-5);
+
+        assert_eq!(
+            stop_at(Vec3::X, Vec3::X * 0.95, 3.0, 2.0, 0.5, 0.1),
+            Vec3::ZERO
+        );
+    }
+
+    #[test]
+    fn test_stop_at_does_not_overshoot() {
+        let max_deceleration = 1.0;
+        let delta_time = 0.1;
+        let mut position = Vec3::ZERO;
+        let mut velocity = Vec3::ZERO;
+
+        for _ in 0..300 {
+            let desired_velocity = stop_at(
+                Vec3::X * 20.0,
+                position,
+                8.0,
+                max_deceleration,
+                delta_time,
+                0.01,
+            );
+
+            velocity +=
+                (desired_velocity - velocity).clamp_length_max(max_deceleration * delta_time);
+            position += velocity * delta_time;
+
+            assert!(position.x <= 20.0 + 0.1);
+        }
+
+        assert!(position.distance(Vec3::X * 20.0) < 0.1);
+    }
+
+    #[test]
+    fn test_queue_brakes_behind_agent_ahead() {
+        let agents = [
+            (Vec3::new(5.0, 0.5, 0.0), 1.0),
+            (Vec3::new(8.0, 0.0, 0.0), 1.0),
+            (Vec3::new(-2.0, 0.0, 0.0), 1.0),
+        ];
+
+        let correction = queue(Vec3::ZERO, Vec3::X * 2.0, 1.0, &agents, 10.0, 1.0);
+
+        // The nearest agent ahead is half way through the box
+        assert!((correction.x + 1.0).abs() < 1e-5);
+        assert!((correction.y - 0.5).abs() < 1e-5);
+        assert!(correction.z.abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_queue_ignores_agents_outside_box() {
+        let agents = [
+            (Vec3::new(-3.0, 0.0, 0.0), 1.0),
+            (Vec3::new(5.0, 3.0, 0.0), 1.0),
+            (Vec3::new(15.0, 0.0, 0.0), 1.0),
+        ];
+
+        assert_eq!(
+            queue(Vec3::ZERO, Vec3::X, 1.0, &agents, 10.0, 1.0),
+            Vec3::ZERO
+        );
+        assert_eq!(
+            queue(Vec3::ZERO, Vec3::ZERO, 1.0, &agents, 10.0, 1.0),
+            Vec3::ZERO
+        );
+    }
+}
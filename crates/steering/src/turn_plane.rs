@@ -3,6 +3,27 @@ use std::ops::Deref;
 use bevy_math::{Vec2, Vec3};
 use geometry::{Circle, LineSegment2D, Plane, SecondTangentPointResult};
 
+/// Returns the radius of the tightest turn an agent can make at the given speed.
+#[must_use]
+pub fn minimum_turn_radius(speed: f32, max_turn_speed: f32) -> f32 {
+    speed / max_turn_speed
+}
+
+/// Describes whether and how an agent can make a turn.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TurnFeasibility {
+    /// True if the agent can make the turn with its current speed and maximum turning speed.
+    pub is_feasible: bool,
+    /// The radius of the turn at the current speed and maximum turning speed.
+    pub turn_radius: f32,
+    /// The largest turn radius with which the turn is still feasible.
+    pub max_feasible_radius: f32,
+    /// The point at which the agent starts turning.
+    pub entry_point: Vec3,
+    /// The point at which the agent finishes turning.
+    pub exit_point: Vec3,
+}
+
 // When an agent wants to turn, we can calculate the plane at which the turn will happen.
 // This plane will be aligned with the agent's current position, the agent's current velocity, and the new direction.
 pub struct TurnPlane {
@@ -10,6 +31,7 @@ pub struct TurnPlane {
     direction_after_turn: Vec2,
     current_direction: Vec2,
     length_to_turn_point: f32,
+    length_after_turn: f32,
 }
 
 impl TurnPlane {
@@ -18,6 +40,7 @@ impl TurnPlane {
         let plane = Plane::from_points(agent_position, turn_point, end_point);
 
         let length_to_turn_point = (turn_point - agent_position).length();
+        let length_after_turn = (end_point - turn_point).length();
         let turn_point_2d = plane.project_2d(turn_point);
         let end_point_2d = plane.project_2d(end_point);
 
@@ -29,16 +52,25 @@ impl TurnPlane {
             direction_after_turn,
             current_direction,
             length_to_turn_point,
+            length_after_turn,
         }
     }
 
+    /// Returns the angle in radians by which the agent's direction changes at the turn point.
+    #[must_use]
+    pub fn turn_angle(&self) -> f32 {
+        self.current_direction
+            .angle_between(self.direction_after_turn)
+            .abs()
+    }
+
     pub fn turn_circle(&self, current_velocity: f32, max_turn_speed: f32) -> Circle {
         let mut perpendicular = self.current_direction.perp();
         if self.direction_after_turn.dot(perpendicular) < 0.0 {
             perpendicular = -perpendicular;
         }
 
-        let radius = current_velocity / max_turn_speed;
+        let radius = minimum_turn_radius(current_velocity, max_turn_speed);
         let origin = Vec2::ZERO + perpendicular * radius;
 
         Circle::new(radius, origin)
@@ -57,6 +89,37 @@ impl TurnPlane {
 
         turn_circle.find_tangent_on_line_segment(&segment, self.direction_after_turn)
     }
+
+    /// Tests whether the agent can turn from the segment towards the turn point onto the segment
+    /// after the turn point.
+    ///
+    /// The turn is an arc tangent to both segments. It's feasible when the arc starts before the turn
+    /// point and ends before the end point.
+    #[must_use]
+    pub fn turn_feasibility(&self, current_velocity: f32, max_turn_speed: f32) -> TurnFeasibility {
+        let turn_radius = minimum_turn_radius(current_velocity, max_turn_speed);
+        let half_tan = (self.turn_angle() / 2.0).tan();
+        let available_length = self.length_to_turn_point.min(self.length_after_turn);
+
+        let (max_feasible_radius, tangent_length) = if half_tan > f32::EPSILON {
+            (available_length / half_tan, turn_radius * half_tan)
+        } else {
+            // The agent doesn't need to turn at all
+            (f32::INFINITY, 0.0)
+        };
+
+        let turn_point = self.current_direction * self.length_to_turn_point;
+        let entry_point = turn_point - self.current_direction * tangent_length;
+        let exit_point = turn_point + self.direction_after_turn * tangent_length;
+
+        TurnFeasibility {
+            is_feasible: turn_radius <= max_feasible_radius,
+            turn_radius,
+            max_feasible_radius,
+            entry_point: self.plane.project_3d(entry_point),
+            exit_point: self.plane.project_3d(exit_point),
+        }
+    }
 }
 
 impl Deref for TurnPlane {
@@ -66,3 +129,117 @@ impl Deref for TurnPlane {
         &self.plane
     }
 }
+
+/// Tests whether an agent moving along `heading` can turn towards the target point.
+///
+/// The agent starts turning right away along the tightest turn it can make. The target is reachable
+/// when it lies outside of that turn circle, the agent then leaves the circle at the exit point heading
+/// straight for the target.
+#[must_use]
+pub fn turn_towards_point(
+    agent_position: Vec3,
+    heading: Vec3,
+    target: Vec3,
+    current_velocity: f32,
+    max_turn_speed: f32,
+) -> TurnFeasibility {
+    let turn_radius = minimum_turn_radius(current_velocity, max_turn_speed);
+    let heading = heading.normalize();
+    let relative_target = target - agent_position;
+
+    let side = relative_target
+        .reject_from(heading)
+        .try_normalize()
+        .unwrap_or_else(|| heading.any_orthonormal_vector());
+
+    // Work in the plane of the turn, x goes along the heading and y towards the target
+    let target_2d = Vec2::new(relative_target.dot(heading), relative_target.dot(side));
+
+    if relative_target.reject_from(heading).length_squared() < f32::EPSILON && target_2d.x >= 0.0 {
+        return TurnFeasibility {
+            is_feasible: true,
+            turn_radius,
+            max_feasible_radius: f32::INFINITY,
+            entry_point: agent_position,
+            exit_point: agent_position,
+        };
+    }
+
+    // The largest circle tangent to the heading at the agent's position that passes through the target
+    let max_feasible_radius = target_2d.length_squared() / (2.0 * target_2d.y);
+
+    let center = Vec2::new(0.0, turn_radius);
+    let to_target = target_2d - center;
+    let distance = to_target.length();
+
+    let exit_point_2d = if distance >= turn_radius {
+        // The agent moves counter-clockwise around the center, it leaves the circle at the tangent
+        // point from which the target is straight ahead
+        let angle = (turn_radius / distance).acos();
+        center + Vec2::from_angle(-angle).rotate(to_target / distance) * turn_radius
+    } else {
+        target_2d
+    };
+
+    TurnFeasibility {
+        is_feasible: distance >= turn_radius,
+        turn_radius,
+        max_feasible_radius,
+        entry_point: agent_position,
+        exit_point: agent_position + heading * exit_point_2d.x + side * exit_point_2d.y,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_minimum_turn_radius() {
+        assert!((minimum_turn_radius(6.0, 2.0) - 3.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_right_angle_turn_feasibility() {
+        let turn_plane = TurnPlane::new(
+            Vec3::ZERO,
+            Vec3::new(10.0, 0.0, 0.0),
+            Vec3::new(10.0, 0.0, 10.0),
+        );
+
+        assert!((turn_plane.turn_angle() - std::f32::consts::FRAC_PI_2).abs() < 1e-5);
+
+        let feasibility = turn_plane.turn_feasibility(2.0, 1.0);
+
+        assert!(feasibility.is_feasible);
+        assert!((feasibility.turn_radius - 2.0).abs() < 1e-5);
+        assert!((feasibility.max_feasible_radius - 10.0).abs() < 1e-4);
+        assert!((feasibility.entry_point - Vec3::new(8.0, 0.0, 0.0)).length() < 1e-4);
+        assert!((feasibility.exit_point - Vec3::new(10.0, 0.0, 2.0)).length() < 1e-4);
+
+        // Too fast to make the turn before the end of the next segment
+        assert!(!turn_plane.turn_feasibility(20.0, 1.0).is_feasible);
+    }
+
+    #[test]
+    fn test_turn_towards_point() {
+        // Straight ahead is always reachable
+        let feasibility = turn_towards_point(Vec3::ZERO, Vec3::X, Vec3::X * 5.0, 10.0, 1.0);
+        assert!(feasibility.is_feasible);
+
+        // Inside the turn circle the target can't be reached
+        let feasibility = turn_towards_point(Vec3::ZERO, Vec3::X, Vec3::Y * 2.0, 2.0, 1.0);
+        assert!(!feasibility.is_feasible);
+        assert!((feasibility.max_feasible_radius - 1.0).abs() < 1e-5);
+
+        // Outside of it the agent leaves the circle heading straight for the target
+        let target = Vec3::new(0.0, 10.0, 0.0);
+        let feasibility = turn_towards_point(Vec3::ZERO, Vec3::X, target, 2.0, 1.0);
+        let exit_point = feasibility.exit_point;
+        let center = Vec3::Y * 2.0;
+
+        assert!(feasibility.is_feasible);
+        assert!((exit_point.distance(center) - 2.0).abs() < 1e-4);
+        assert!((exit_point - center).dot(target - exit_point).abs() < 1e-3);
+    }
+}
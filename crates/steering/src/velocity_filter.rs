@@ -0,0 +1,187 @@
+use bevy_math::Vec3;
+
+/// Smooths the velocity chosen by collision avoidance, which jitters when its constraints flicker
+/// between frames.
+///
+/// The filtered velocity follows the raw one with exponential smoothing, which doesn't depend on the
+/// frame rate. The change of the velocity per second can be limited by `max_acceleration` and the
+/// change of the acceleration per second by `max_jerk`. Limiting the jerk makes the agent ease in
+/// and out of turns, but it can overshoot the raw velocity slightly when it changes abruptly.
+///
+/// The filter keeps the velocity and the acceleration of the agent, so every agent needs its own.
+#[derive(Clone, Debug)]
+pub struct VelocityFilter {
+    /// How many seconds it takes to cover 63% of the difference to the raw velocity, zero disables
+    /// the smoothing.
+    pub time_constant: f32,
+    /// The highest change of the velocity per second.
+    pub max_acceleration: f32,
+    /// The highest change of the acceleration per second.
+    pub max_jerk: f32,
+    velocity: Vec3,
+    acceleration: Vec3,
+}
+
+impl VelocityFilter {
+    #[must_use]
+    pub fn new(time_constant: f32) -> Self {
+        assert!(time_constant >= 0.0);
+
+        Self {
+            time_constant,
+            max_acceleration: f32::INFINITY,
+            max_jerk: f32::INFINITY,
+            velocity: Vec3::ZERO,
+            acceleration: Vec3::ZERO,
+        }
+    }
+
+    #[must_use]
+    pub fn with_max_acceleration(mut self, max_acceleration: f32) -> Self {
+        assert!(max_acceleration >= 0.0);
+
+        self.max_acceleration = max_acceleration;
+        self
+    }
+
+    #[must_use]
+    pub fn with_max_jerk(mut self, max_jerk: f32) -> Self {
+        assert!(max_jerk >= 0.0);
+
+        self.max_jerk = max_jerk;
+        self
+    }
+
+    /// Sets the velocity the filter starts from, e.g. the velocity of a newly spawned agent.
+    #[must_use]
+    pub fn with_velocity(mut self, velocity: Vec3) -> Self {
+        self.reset(velocity);
+        self
+    }
+
+    /// Returns the filtered velocity of the last update.
+    #[must_use]
+    pub fn velocity(&self) -> Vec3 {
+        self.velocity
+    }
+
+    /// Returns the acceleration of the last update.
+    #[must_use]
+    pub fn acceleration(&self) -> Vec3 {
+        self.acceleration
+    }
+
+    /// Continues from the given velocity without any acceleration, e.g. after the agent was
+    /// teleported or its velocity was set by something else than the filter.
+    pub fn reset(&mut self, velocity: Vec3) {
+        self.velocity = velocity;
+        self.acceleration = Vec3::ZERO;
+    }
+
+    /// Moves the filtered velocity towards the raw one.
+    ///
+    /// # Arguments
+    ///
+    /// * `raw_velocity` - A Vec3 that represents the velocity chosen for this frame, e.g. by ORCA.
+    /// * `delta_seconds` - A float that represents the time since the last update.
+    ///
+    /// # Returns
+    ///
+    /// * A Vec3 that represents the filtered velocity. It's the same as before when `delta_seconds`
+    ///   isn't positive.
+    pub fn update(&mut self, raw_velocity: Vec3, delta_seconds: f32) -> Vec3 {
+        if delta_seconds <= 0.0 {
+            return self.velocity;
+        }
+
+        let smoothing = if self.time_constant > 0.0 {
+            1.0 - (-delta_seconds / self.time_constant).exp()
+        } else {
+            1.0
+        };
+
+        let smoothed_velocity = self.velocity.lerp(raw_velocity, smoothing);
+        let acceleration = ((smoothed_velocity - self.velocity) / delta_seconds)
+            .clamp_length_max(self.max_acceleration);
+
+        self.acceleration +=
+            (acceleration - self.acceleration).clamp_length_max(self.max_jerk * delta_seconds);
+        self.velocity += self.acceleration * delta_seconds;
+
+        self.velocity
+    }
+}
+
+impl Default for VelocityFilter {
+    fn default() -> Self {
+        Self::new(0.1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::LN_2;
+
+    use super::*;
+
+    #[test]
+    fn test_without_smoothing_follows_raw_velocity() {
+        let mut filter = VelocityFilter::new(0.0);
+
+        assert!((filter.update(Vec3::X, 0.1) - Vec3::X).length() < 1e-5);
+        assert!((filter.update(Vec3::Y, 0.1) - Vec3::Y).length() < 1e-5);
+    }
+
+    #[test]
+    fn test_exponential_smoothing() {
+        let mut filter = VelocityFilter::new(1.0);
+
+        // After ln(2) time constants half of the difference is covered
+        let velocity = filter.update(Vec3::X * 2.0, LN_2);
+
+        assert!((velocity - Vec3::X).length() < 1e-5);
+    }
+
+    #[test]
+    fn test_smoothing_does_not_depend_on_frame_rate() {
+        let mut slow = VelocityFilter::new(0.5);
+        let mut fast = VelocityFilter::new(0.5);
+
+        slow.update(Vec3::new(3.0, 0.0, 4.0), 0.1);
+
+        for _ in 0..4 {
+            fast.update(Vec3::new(3.0, 0.0, 4.0), 0.025);
+        }
+
+        assert!((slow.velocity() - fast.velocity()).length() < 1e-5);
+    }
+
+    #[test]
+    fn test_acceleration_is_limited() {
+        let mut filter = VelocityFilter::new(0.0).with_max_acceleration(2.0);
+
+        let velocity = filter.update(Vec3::X * 10.0, 0.5);
+
+        assert!((velocity - Vec3::X).length() < 1e-5);
+    }
+
+    #[test]
+    fn test_jerk_is_limited() {
+        let mut filter = VelocityFilter::new(0.2)
+            .with_max_jerk(5.0)
+            .with_velocity(Vec3::Z);
+        let mut previous_acceleration = filter.acceleration();
+
+        for _ in 0..30 {
+            filter.update(Vec3::X * 4.0, 0.05);
+
+            let change = (filter.acceleration() - previous_acceleration).length();
+            assert!(change <= 5.0 * 0.05 + 1e-5);
+
+            previous_acceleration = filter.acceleration();
+        }
+
+        // The velocity still turns towards the raw one
+        assert!(filter.velocity().x > filter.velocity().z);
+    }
+}
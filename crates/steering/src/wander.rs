@@ -0,0 +1,147 @@
+use bevy_math::{Quat, Vec3};
+use rand::Rng;
+
+/// Persistent per-agent state of the wander behavior.
+///
+/// The wander target is stored as a point on the unit sphere in the agent's local space.
+/// Every update it is displaced by a small random jitter and projected back onto the sphere,
+/// which makes the resulting steering change smoothly from frame to frame.
+#[derive(Clone, Debug)]
+pub struct WanderState {
+    pub target: Vec3,
+}
+
+impl WanderState {
+    #[must_use]
+    pub fn new(target: Vec3) -> Self {
+        Self {
+            target: target.try_normalize().unwrap_or(Vec3::X),
+        }
+    }
+}
+
+impl Default for WanderState {
+    fn default() -> Self {
+        Self::new(Vec3::X)
+    }
+}
+
+/// Calculates the wander steering force for an agent.
+///
+/// # Arguments
+///
+/// * `state` - The persistent wander state of the agent. It is updated by this function.
+/// * `agent_position` - A Vec3 that represents the agent's current position.
+/// * `agent_rotation` - A Quat that represents the agent's current rotation. The agent is expected
+///   to be facing along its local X axis.
+/// * `wander_distance` - A float that represents how far ahead of the agent the wander sphere is.
+/// * `wander_radius` - A float that represents the radius of the wander sphere.
+/// * `wander_jitter` - A float that represents the maximum displacement of the wander target
+///   on the unit sphere per update.
+/// * `agent_max_force` - A float that represents the maximum force the agent can exert.
+/// * `rng` - A random number generator used to jitter the wander target.
+///
+/// # Returns
+///
+/// * A Vec3 that represents the wander steering force.
+#[allow(clippy::too_many_arguments)]
+pub fn wander(
+    state: &mut WanderState,
+    agent_position: Vec3,
+    agent_rotation: Quat,
+    wander_distance: f32,
+    wander_radius: f32,
+    wander_jitter: f32,
+    agent_max_force: f32,
+    rng: &mut impl Rng,
+) -> Vec3 {
+    if wander_jitter > 0.0 {
+        let jitter = Vec3::new(
+            rng.gen_range(-wander_jitter..=wander_jitter),
+            rng.gen_range(-wander_jitter..=wander_jitter),
+            rng.gen_range(-wander_jitter..=wander_jitter),
+        );
+
+        if let Some(target) = (state.target + jitter).try_normalize() {
+            state.target = target;
+        }
+    }
+
+    let heading = agent_rotation.mul_vec3(Vec3::X).normalize();
+    let wander_center = agent_position + heading * wander_distance;
+    let wander_target = wander_center + agent_rotation.mul_vec3(state.target) * wander_radius;
+
+    (wander_target - agent_position).normalize_or_zero() * agent_max_force
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use rand::rngs::mock::StepRng;
+
+    use super::*;
+
+    #[test]
+    fn test_without_jitter_agent_steers_towards_wander_target() {
+        let mut state = WanderState::new(Vec3::Z);
+        let mut rng = StepRng::new(0, 1);
+
+        let force = wander(
+            &mut state,
+            Vec3::ZERO,
+            Quat::IDENTITY,
+            1.0,
+            1.0,
+            0.0,
+            2.0,
+            &mut rng,
+        );
+
+        assert!((force - Vec3::new(1.0, 0.0, 1.0).normalize() * 2.0).length() < 1e-5);
+        assert_eq!(state.target, Vec3::Z);
+    }
+
+    #[test]
+    fn test_wander_target_follows_agent_rotation() {
+        let mut state = WanderState::default();
+        let mut rng = StepRng::new(0, 1);
+
+        let force = wander(
+            &mut state,
+            Vec3::ONE,
+            Quat::from_rotation_z(FRAC_PI_2),
+            2.0,
+            1.0,
+            0.0,
+            1.0,
+            &mut rng,
+        );
+
+        assert!((force - Vec3::Y).length() < 1e-5);
+    }
+
+    #[test]
+    fn test_jittered_target_stays_on_unit_sphere() {
+        let mut state = WanderState::default();
+        let mut rng = StepRng::new(0, 0x9E37_79B9_7F4A_7C15);
+
+        for _ in 0..100 {
+            let force = wander(
+                &mut state,
+                Vec3::ZERO,
+                Quat::IDENTITY,
+                2.0,
+                1.0,
+                0.3,
+                1.0,
+                &mut rng,
+            );
+
+            assert!((state.target.length() - 1.0).abs() < 1e-4);
+            assert!((force.length() - 1.0).abs() < 1e-4);
+            // The wander sphere is in front of the agent, so the agent never turns back
+            assert!(force.x > 0.0);
+        }
+    }
+}
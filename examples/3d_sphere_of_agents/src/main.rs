@@ -1,10 +1,11 @@
-use std::f32::consts::PI;
+use std::{collections::HashSet, f32::consts::PI};
 
 use bevy::{prelude::*, render::mesh::shape::UVSphere};
 use bevy_egui::EguiPlugin;
 use example_utils::{CameraTarget, UniversalCamera, UniversalCameraPlugin, UtilsPlugin};
 use geometry::{colliders::Collider, Plane};
 use orca::{optimize_velocity_3d, Agent3D, VelocityObstacle3D};
+use steering::{SteeringScheduler, VelocityFilter};
 
 #[derive(Debug, Clone, Copy, Resource, Default)]
 struct Statistics {
@@ -24,17 +25,27 @@ fn main() {
             EguiPlugin,
         ))
         .init_resource::<Statistics>()
+        .insert_resource(UpdateScheduler(SteeringScheduler::new(
+            AGENTS_UPDATED_PER_FRAME,
+            TIME_STEP,
+        )))
         .add_systems(Startup, setup)
         .add_systems(Update, update_agents)
         .run();
 }
 
+const AGENTS_UPDATED_PER_FRAME: usize = 300;
+const TIME_STEP: f32 = 0.1;
+
+#[derive(Resource)]
+struct UpdateScheduler(SteeringScheduler<Entity>);
+
 #[derive(Component)]
 struct Agent {
     shape: Collider,
     target_position: Vec3,
     velocity: Vec3,
-    last_updated: Option<f32>,
+    velocity_filter: VelocityFilter,
 }
 
 fn spawn_agent(
@@ -55,8 +66,8 @@ fn spawn_agent(
         .insert(Agent {
             shape: Collider::new_sphere(radius),
             velocity: Vec3::ZERO,
+            velocity_filter: VelocityFilter::new(0.05),
             target_position,
-            last_updated: None,
         });
 }
 
@@ -124,11 +135,11 @@ fn separation_velocity(
 fn update_agents(
     time: Res<Time>,
     mut agents: Query<(Entity, &mut Agent, &mut Transform)>,
+    mut scheduler: ResMut<UpdateScheduler>,
     mut statistics: ResMut<Statistics>,
 ) {
     const PRECISION: f32 = 0.01;
     const TIME_HORIZON: f32 = 12.0;
-    const TIME_STEP: f32 = 0.1;
 
     let agent_instances = agents
         .iter()
@@ -144,6 +155,12 @@ fn update_agents(
         })
         .collect::<Vec<_>>();
 
+    let scheduled_agents = scheduler
+        .0
+        .schedule(agents.iter().map(|a| a.0), time.elapsed_seconds())
+        .into_iter()
+        .collect::<HashSet<_>>();
+
     let mut number_of_collisions = 0;
     let mut minimum_distance = 100.0;
     for (entity, mut agent, mut transform) in agents.iter_mut() {
@@ -152,16 +169,13 @@ fn update_agents(
             round_to_precision(agent.velocity, PRECISION),
             agent.shape.clone(),
         );
-        let last_updated = agent.last_updated.unwrap_or(0.0);
         let other_agents = agent_instances
             .iter()
             .filter(|(e, _)| *e != entity)
             .map(|(_, a)| a)
             .collect::<Vec<&Agent3D>>();
 
-        if time.elapsed_seconds() - last_updated > TIME_STEP {
-            agent.last_updated = Some(time.elapsed_seconds());
-
+        if scheduled_agents.contains(&entity) {
             const NUMBER_OF_NEIGHBORS: usize = 15;
 
             // Get number of nearest neighbors
@@ -212,7 +226,9 @@ fn update_agents(
                     optimize_velocity_3d(desired_velocity, AGENT_SPEED, orca_planes.as_slice());
             }
 
-            agent.velocity = agent.velocity.lerp(optimal_velocity, 0.3);
+            agent.velocity = agent
+                .velocity_filter
+                .update(optimal_velocity, time.delta_seconds());
         }
 
         if number_of_collisions > statistics.number_of_collisions {
@@ -1,8 +1,11 @@
+use std::collections::HashSet;
+
 use bevy::{prelude::*, render::mesh::shape::UVSphere};
 use bevy_egui::EguiPlugin;
 use example_utils::{CameraTarget, UniversalCamera, UniversalCameraPlugin, UtilsPlugin};
 use geometry::{colliders::Collider, Plane};
 use orca::{optimize_velocity_3d, AccelerationVelocityObstacle3D, Agent3D};
+use steering::SteeringScheduler;
 
 fn main() {
     App::new()
@@ -16,17 +19,26 @@ fn main() {
             UniversalCameraPlugin,
             EguiPlugin,
         ))
+        .insert_resource(UpdateScheduler(SteeringScheduler::new(
+            AGENTS_UPDATED_PER_FRAME,
+            TIME_STEP,
+        )))
         .add_systems(Startup, setup)
         .add_systems(Update, update_agents)
         .run();
 }
 
+const AGENTS_UPDATED_PER_FRAME: usize = 2;
+const TIME_STEP: f32 = 0.1;
+
+#[derive(Resource)]
+struct UpdateScheduler(SteeringScheduler<Entity>);
+
 #[derive(Component)]
 struct Agent {
     shape: Collider,
     desired_velocity: Vec3,
     velocity: Vec3,
-    last_updated: Option<f32>,
 }
 
 fn spawn_agent(
@@ -48,7 +60,6 @@ fn spawn_agent(
             shape: Collider::new_sphere(radius * 2.0),
             velocity: Vec3::ZERO,
             desired_velocity: velocity,
-            last_updated: None,
         });
 }
 
@@ -131,10 +142,10 @@ fn separation_velocity(
 fn update_agents(
     time: Res<Time>,
     mut agents: Query<(Entity, &mut Agent, &mut Transform)>,
+    mut scheduler: ResMut<UpdateScheduler>,
     //mut gizmos: Gizmos,
 ) {
     const TIME_HORIZON: f32 = 6.0;
-    const TIME_STEP: f32 = 0.1;
 
     let agent_instances = agents
         .iter()
@@ -146,18 +157,21 @@ fn update_agents(
         })
         .collect::<Vec<_>>();
 
+    let scheduled_agents = scheduler
+        .0
+        .schedule(agents.iter().map(|a| a.0), time.elapsed_seconds())
+        .into_iter()
+        .collect::<HashSet<_>>();
+
     for (entity, mut agent, mut transform) in agents.iter_mut() {
         let self_agent = Agent3D::new(transform.translation, agent.velocity, agent.shape.clone());
-        let last_updated = agent.last_updated.unwrap_or(0.0);
         let other_agents = agent_instances
             .iter()
             .filter(|(e, _)| *e != entity)
             .map(|(_, a)| a)
             .collect::<Vec<&Agent3D>>();
 
-        if time.elapsed_seconds() - last_updated > TIME_STEP {
-            agent.last_updated = Some(time.elapsed_seconds());
-
+        if scheduled_agents.contains(&entity) {
             const NUMBER_OF_NEIGHBORS: usize = 100;
             // Get number of nearest neighbors
             let mut nearest_neighbors = other_agents
@@ -5,7 +5,7 @@ use bevy_egui::EguiPlugin;
 use example_utils::{CameraTarget, UniversalCamera, UniversalCameraPlugin, UtilsPlugin};
 use geometry::{colliders::Collider, Plane, Sphere, Vec3Operations};
 use orca::{optimize_velocity_3d, AccelerationVelocityObstacle3D, Agent3D};
-use steering::{follow_path, separation, update_agent_on_path, FollowPathResult};
+use steering::{follow_path, separation, update_agent_on_path, FollowPathResult, PathMode};
 
 #[derive(Component)]
 struct Velocity {
@@ -15,6 +15,7 @@ struct Velocity {
 #[derive(Component)]
 struct FollowPath {
     pub path: Vec<Vec3>,
+    pub segment: usize,
 }
 
 #[derive(Component)]
@@ -84,6 +85,7 @@ fn setup(
         .insert(Velocity { value: Vec3::ZERO })
         .insert(FollowPath {
             path: vec![initial_position, Vec3::new(1000.0, 0.0, 0.0)],
+            segment: 0,
         })
         .insert(Agent)
         .id();
@@ -157,9 +159,10 @@ fn draw_gizmos(
             }
         }
 
-        let follow_path_result = follow_path(
+        let (follow_path_result, _) = follow_path(
             &path.path,
-            0,
+            path.segment,
+            PathMode::Once,
             transform.translation,
             velocity.value,
             TURNING_SPEED,
@@ -171,12 +174,7 @@ fn draw_gizmos(
         let mut desired_velocity = match follow_path_result {
             FollowPathResult::CurrentSegment(velocity) => velocity.clamp_length_max(MAX_SPEED),
             FollowPathResult::NextSegment(velocity, segment) => {
-                path.path = path.path.split_off(segment);
-
-                if path.path.is_empty() {
-                    commands.entity(entity).remove::<FollowPath>();
-                }
-
+                path.segment = segment;
                 velocity.clamp_length_max(MAX_SPEED)
             }
             FollowPathResult::EndOfPath(velocity) => {
@@ -7,7 +7,27 @@ fn probability_density_function_of_gaussian(x: f64, mean: f64, std_dev: f64) ->
     a * b.exp()
 }
 
-pub fn expectation_maximization_1d(values: &[f64], n_gaussians: usize, max_steps: usize) {
+// The state of the expectation maximization after a single iteration
+pub struct ExpectationMaximization1DStep<'a> {
+    pub step: usize,
+    // Mean and standard deviation of each gaussian
+    pub gaussians: &'a [(f64, f64)],
+    pub prior_estimates: &'a [f64],
+}
+
+pub struct ExpectationMaximization1DResult {
+    pub gaussians: Vec<(f64, f64)>,
+    pub prior_estimates: Vec<f64>,
+    pub iterations: usize,
+    pub converged: bool,
+}
+
+pub fn expectation_maximization_1d(
+    values: &[f64],
+    n_gaussians: usize,
+    max_steps: usize,
+    mut trace: impl FnMut(&ExpectationMaximization1DStep),
+) -> ExpectationMaximization1DResult {
     // Initialization step
     let mut gaussians = Vec::new();
     let min_value = values.iter().cloned().fold(f64::INFINITY, f64::min);
@@ -24,6 +44,7 @@ pub fn expectation_maximization_1d(values: &[f64], n_gaussians: usize, max_steps
     let mut prior_estimates = vec![1.0 / n_gaussians as f64; n_gaussians];
 
     let mut steps = 0;
+    let mut converged = false;
     loop {
         let initial_gaussians = gaussians.clone();
 
@@ -85,10 +106,11 @@ pub fn expectation_maximization_1d(values: &[f64], n_gaussians: usize, max_steps
             gaussians[k].1 = (std_dev / denominator).sqrt();
         }
 
-        println!(
-            "Step: {}, Gaussians: {:?}, Probabilities: {:?}",
-            steps, gaussians, prior_estimates
-        );
+        trace(&ExpectationMaximization1DStep {
+            step: steps,
+            gaussians: &gaussians,
+            prior_estimates: &prior_estimates,
+        });
 
         steps += 1;
 
@@ -108,7 +130,15 @@ pub fn expectation_maximization_1d(values: &[f64], n_gaussians: usize, max_steps
             .expect("No gaussians");
 
         if gaussian_deltas < 1e-6 {
+            converged = true;
             break;
         }
     }
+
+    ExpectationMaximization1DResult {
+        gaussians,
+        prior_estimates,
+        iterations: steps,
+        converged,
+    }
 }
@@ -42,7 +42,17 @@ fn main() {
         y[p_i32] += 1;
     }
 
-    expectation_maximization_1d(&points, 3, 200);
+    let result = expectation_maximization_1d(&points, 3, 200, |step| {
+        println!(
+            "Step: {}, Gaussians: {:?}, Probabilities: {:?}",
+            step.step, step.gaussians, step.prior_estimates
+        );
+    });
+
+    println!(
+        "Iterations: {}, Converged: {}, Gaussians: {:?}, Probabilities: {:?}",
+        result.iterations, result.converged, result.gaussians, result.prior_estimates
+    );
 
     let bar = Bar::new(x, y);
     plot.add_trace(bar);
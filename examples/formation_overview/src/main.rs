@@ -7,7 +7,7 @@ use bevy_mod_picking::DefaultPickingPlugins;
 use bevy_transform_gizmo::TransformGizmoPlugin;
 use coordination::{
     formations::{CircleFormation, LineFormation, QueueFormation, VFormation},
-    Formation, FormationTemplate, FormationTemplateSet,
+    Formation, FormationEvaluationParams, FormationTemplate, FormationTemplateSet,
 };
 use example_utils::{
     CameraTarget, SkyboxPlugin, UniversalCamera, UniversalCameraPlugin, UtilsPlugin,
@@ -50,6 +50,7 @@ struct FormationSettings {
     obstacle_avoidance_time_horizon: f32,
     number_of_yaw_samples: u16,
     number_of_pitch_samples: u16,
+    number_of_heading_samples: u16,
     max_steps_for_em: usize,
     deformation_penalty_multiplier: f32,
 
@@ -86,6 +87,7 @@ impl Default for FormationSettings {
             obstacle_avoidance_time_horizon: 5.0,
             number_of_yaw_samples: 20,
             number_of_pitch_samples: 20,
+            number_of_heading_samples: 5,
             max_steps_for_em: 100,
             deformation_penalty_multiplier: 0.0,
         }
@@ -513,28 +515,33 @@ fn draw_formations(
         templates[3].as_ref(),
     ]);
 
-    let (best_formation, best_velocity) = formation_template_set.get_best_formation_and_velocity(
+    let params = FormationEvaluationParams::new(100.0)
+        .with_deformation_penalty_multiplier(formation_settings.deformation_penalty_multiplier)
+        .with_obstacle_avoidance_time_horizon(formation_settings.obstacle_avoidance_time_horizon)
+        .with_velocity_obstacle_samples(
+            formation_settings.number_of_yaw_samples,
+            formation_settings.number_of_pitch_samples,
+        )
+        .with_heading_samples(formation_settings.number_of_heading_samples)
+        .with_max_steps_for_em(formation_settings.max_steps_for_em);
+
+    let Ok(decision) = formation_template_set.get_best_formation(
         formation.get_positions(),
         Vec3::Z * 100.0,
-        100.0,
-        formation_settings.deformation_penalty_multiplier,
         &obstale_agents,
-        formation_settings.obstacle_avoidance_time_horizon,
-        formation_settings.number_of_yaw_samples,
-        formation_settings.number_of_pitch_samples,
-        formation_settings.max_steps_for_em,
-        &mut gizmos,
-    );
-
-    gizmos.line(aabb.center, aabb.center + best_velocity, Color::BLUE);
+        &[],
+        &params,
+    ) else {
+        return;
+    };
 
-    let rotation = Quat::from_rotation_arc(Vec3::Z, best_velocity.normalize());
+    gizmos.line(aabb.center, aabb.center + decision.velocity, Color::BLUE);
 
-    for position in best_formation.get_positions() {
-        let p = if rotation.is_near_identity() {
+    for position in decision.formation.get_positions() {
+        let p = if decision.rotation.is_near_identity() {
             *position
         } else {
-            rotation * *position
+            decision.rotation * *position
         };
 
         gizmos.sphere(
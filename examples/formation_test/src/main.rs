@@ -1,11 +1,10 @@
-use std::ops::Range;
+use std::{collections::HashMap, ops::Range};
 
 use bevy::{core_pipeline::clear_color::ClearColorConfig, prelude::*};
 use bevy_egui::EguiPlugin;
 use coordination::{
-    best_matching_indexes,
     formations::{CircleFormation, LineFormation, QueueFormation, VFormation},
-    Formation, FormationTemplate, FormationTemplateSet,
+    sticky_matching_indexes, Formation, FormationTemplate, FormationTemplateRegistry,
 };
 use example_utils::{
     CameraTarget, SkyboxPlugin, UniversalCamera, UniversalCameraPlugin, UtilsPlugin,
@@ -13,7 +12,7 @@ use example_utils::{
 use geometry::{colliders::Collider, Sphere};
 use orca::{optimize_velocity_3d, AccelerationVelocityObstacle3D, Agent3D};
 use rand::{thread_rng, Rng};
-use steering::{arrive, follow_path, update_agent_on_path, FollowPathResult};
+use steering::{arrive, follow_path, update_agent_on_path, FollowPathResult, PathMode};
 
 #[derive(Component)]
 struct Velocity {
@@ -24,6 +23,7 @@ struct Velocity {
 #[derive(Component)]
 struct FollowPath {
     pub path: Vec<Vec3>,
+    pub segment: usize,
 }
 
 #[derive(Component)]
@@ -41,13 +41,15 @@ struct Agent {
 struct FormationComponent {
     pub formation: Formation,
     pub agents: Vec<Entity>,
-    pub formation_templates: Vec<Box<dyn FormationTemplate + Send + Sync>>,
+    pub slot_assignment: HashMap<usize, usize>,
+    pub formation_templates: FormationTemplateRegistry<&'static str>,
 }
 
 const BOX_SIZE: f32 = 500.0;
 const NUMBER_OF_OBSTACLES: usize = 7;
 const TURNING_SPEED: f32 = 2.0;
 const MAX_SPEED: f32 = 80.0;
+const SLOT_SWITCHING_PENALTY: f32 = 100.0;
 const MAX_FORCE: f32 = 50.0;
 const AGENT_MASS: f32 = 2.0;
 const MAX_ACCELERATION: f32 = MAX_FORCE / AGENT_MASS;
@@ -133,12 +135,12 @@ fn setup(
         FormationComponent {
             formation: Formation::new(positions),
             agents: ships.clone(),
-            formation_templates: vec![
-                Box::new(CircleFormation::new(ORCA_RADIUS, 2.0, 9.0)),
-                Box::new(LineFormation::new(ORCA_RADIUS, 2.0, 3.0)),
-                Box::new(VFormation::new(ORCA_RADIUS, 2.0, 12.0)),
-                Box::new(QueueFormation::new(ORCA_RADIUS, 2.0, 1.0)),
-            ],
+            slot_assignment: HashMap::new(),
+            formation_templates: FormationTemplateRegistry::new()
+                .with_template("circle", CircleFormation::new(ORCA_RADIUS, 2.0, 9.0))
+                .with_template("line", LineFormation::new(ORCA_RADIUS, 2.0, 3.0))
+                .with_template("v", VFormation::new(ORCA_RADIUS, 2.0, 12.0))
+                .with_template("queue", QueueFormation::new(ORCA_RADIUS, 2.0, 1.0)),
         },
         Velocity { value: Vec3::ZERO },
     ));
@@ -239,22 +241,30 @@ fn generate_path(
             ));
         }
 
-        commands.entity(entity).insert(FollowPath { path });
+        commands
+            .entity(entity)
+            .insert(FollowPath { path, segment: 0 });
     }
 }
 
 fn move_formation_along_path(
     mut gizmos: Gizmos,
     time: Res<Time>,
-    mut formations: Query<(Entity, &mut FollowPath, &FormationComponent, &mut Velocity)>,
+    mut formations: Query<(
+        Entity,
+        &mut FollowPath,
+        &mut FormationComponent,
+        &mut Velocity,
+    )>,
     obstacles: Query<(&Transform, &Obstacle)>,
     mut commands: Commands,
 ) {
-    for (entity, mut path, formation, mut velocity) in formations.iter_mut() {
+    for (entity, mut path, mut formation, mut velocity) in formations.iter_mut() {
         let formation_center = formation.formation.get_bounds(ORCA_RADIUS).center;
-        let follow_path_result = follow_path(
+        let (follow_path_result, _) = follow_path(
             &path.path,
-            0,
+            path.segment,
+            PathMode::Once,
             formation_center,
             velocity.value,
             TURNING_SPEED,
@@ -266,12 +276,7 @@ fn move_formation_along_path(
         let desired_velocity = match follow_path_result {
             FollowPathResult::CurrentSegment(velocity) => velocity.clamp_length_max(MAX_SPEED),
             FollowPathResult::NextSegment(velocity, segment) => {
-                path.path = path.path.split_off(segment);
-
-                if path.path.is_empty() {
-                    commands.entity(entity).remove::<FollowPath>();
-                }
-
+                path.segment = segment;
                 velocity.clamp_length_max(MAX_SPEED)
             }
             FollowPathResult::EndOfPath(velocity) => {
@@ -280,12 +285,7 @@ fn move_formation_along_path(
             }
         };
 
-        let template_set =
-            FormationTemplateSet::from_iter(formation.formation_templates.iter().map(|e| {
-                let template: &dyn FormationTemplate = e.as_ref();
-
-                template
-            }));
+        let template_set = formation.formation_templates.template_set();
 
         //let (best_formation, best_velocity) = template_set.get_best_formation_and_velocity(
         //    formation.formation.get_positions(),
@@ -334,10 +334,14 @@ fn move_formation_along_path(
             })
             .collect::<Vec<_>>();
 
-        let best_matches =
-            best_matching_indexes(formation.formation.get_positions(), &new_positions);
+        let best_matches = sticky_matching_indexes(
+            formation.formation.get_positions(),
+            &new_positions,
+            &formation.slot_assignment,
+            SLOT_SWITCHING_PENALTY,
+        );
 
-        for (agent_index, new_position) in best_matches {
+        for (&agent_index, &new_position) in &best_matches {
             let agent = formation.agents[agent_index];
             let agent_position = new_positions[new_position];
 
@@ -353,6 +357,8 @@ fn move_formation_along_path(
 
             //gizmos.sphere(agent_position, Quat::IDENTITY, 1.0, Color::WHITE);
         }
+
+        formation.slot_assignment = best_matches;
     }
 }
 
@@ -8,7 +8,7 @@ use example_utils::{
 use geometry::{colliders::Collider, Plane, Sphere, Vec3Operations};
 use orca::{optimize_velocity_3d, AccelerationVelocityObstacle3D, Agent3D};
 use rand::{thread_rng, Rng};
-use steering::{follow_path, separation, update_agent_on_path, FollowPathResult};
+use steering::{follow_path, separation, update_agent_on_path, FollowPathResult, PathMode};
 
 #[derive(Component)]
 struct Velocity {
@@ -18,6 +18,7 @@ struct Velocity {
 #[derive(Component)]
 struct FollowPath {
     pub path: Vec<Vec3>,
+    pub segment: usize,
 }
 
 #[derive(Component)]
@@ -181,7 +182,9 @@ fn generate_path(
             ));
         }
 
-        commands.entity(entity).insert(FollowPath { path });
+        commands
+            .entity(entity)
+            .insert(FollowPath { path, segment: 0 });
     }
 }
 
@@ -205,9 +208,10 @@ fn draw_gizmos(
             }
         }
 
-        let follow_path_result = follow_path(
+        let (follow_path_result, _) = follow_path(
             &path.path,
-            0,
+            path.segment,
+            PathMode::Once,
             transform.translation,
             velocity.value,
             TURNING_SPEED,
@@ -221,12 +225,7 @@ fn draw_gizmos(
         let mut desired_velocity = match follow_path_result {
             FollowPathResult::CurrentSegment(velocity) => velocity.clamp_length_max(MAX_SPEED),
             FollowPathResult::NextSegment(velocity, segment) => {
-                path.path = path.path.split_off(segment);
-
-                if path.path.is_empty() {
-                    commands.entity(entity).remove::<FollowPath>();
-                }
-
+                path.segment = segment;
                 velocity.clamp_length_max(MAX_SPEED)
             }
             FollowPathResult::EndOfPath(velocity) => {